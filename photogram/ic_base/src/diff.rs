@@ -0,0 +1,152 @@
+//a Documentation
+//! Line-based differences between two texts, in the unified diff
+//! format
+//!
+//! This is used to show what writing a file would change, and is
+//! intended for JSON files of modest size; if the region of the texts
+//! that differs is too large then it is shown as a single replacement
+
+//a Constants
+//ci MAX_DIFF_CELLS
+/// The largest (old lines x new lines) of the differing region of two
+/// texts for which the longest common subsequence is determined
+const MAX_DIFF_CELLS: usize = 1 << 24;
+
+//a Edit
+//tp Edit
+/// An edit of a single line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    Keep,
+    Delete,
+    Insert,
+}
+
+//fi edits
+/// Determine the edits that convert 'old' to 'new', keeping a
+/// longest common subsequence of the lines
+fn edits<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Edit, &'a str)> {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(o, n)| o == n)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(o, n)| o == n)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+
+    let mut result: Vec<_> = old[..prefix].iter().map(|l| (Edit::Keep, *l)).collect();
+    if (a.len() + 1) * (b.len() + 1) > MAX_DIFF_CELLS {
+        result.extend(a.iter().map(|l| (Edit::Delete, *l)));
+        result.extend(b.iter().map(|l| (Edit::Insert, *l)));
+    } else {
+        // lcs[i * w + j] is the length of the LCS of a[i..] and b[j..]
+        let w = b.len() + 1;
+        let mut lcs = vec![0_u32; (a.len() + 1) * w];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i * w + j] = if a[i] == b[j] {
+                    lcs[(i + 1) * w + j + 1] + 1
+                } else {
+                    lcs[(i + 1) * w + j].max(lcs[i * w + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                result.push((Edit::Keep, a[i]));
+                i += 1;
+                j += 1;
+            } else if j >= b.len() || (i < a.len() && lcs[(i + 1) * w + j] >= lcs[i * w + j + 1]) {
+                result.push((Edit::Delete, a[i]));
+                i += 1;
+            } else {
+                result.push((Edit::Insert, b[j]));
+                j += 1;
+            }
+        }
+    }
+    result.extend(old[old.len() - suffix..].iter().map(|l| (Edit::Keep, *l)));
+    result
+}
+
+//fi hunk_range
+/// Format the range of a hunk, given the number of lines before it
+/// and the number of lines in it
+fn hunk_range(before: usize, count: usize) -> String {
+    match count {
+        0 => format!("{before},0"),
+        1 => format!("{}", before + 1),
+        _ => format!("{},{count}", before + 1),
+    }
+}
+
+//fp unified
+/// Generate the hunks of a unified diff between two texts, with
+/// 'context' unchanged lines around each change
+///
+/// The result is empty if the texts have the same lines; it does not
+/// include the '---' and '+++' file header lines
+pub fn unified(old: &str, new: &str, context: usize) -> String {
+    let old_lines: Vec<_> = old.lines().collect();
+    let new_lines: Vec<_> = new.lines().collect();
+    let edits = edits(&old_lines, &new_lines);
+
+    // The number of old and new lines before each edit
+    let mut positions = Vec::with_capacity(edits.len() + 1);
+    let (mut o, mut n) = (0, 0);
+    for (edit, _) in &edits {
+        positions.push((o, n));
+        match edit {
+            Edit::Keep => {
+                o += 1;
+                n += 1;
+            }
+            Edit::Delete => o += 1,
+            Edit::Insert => n += 1,
+        }
+    }
+    positions.push((o, n));
+
+    let changes: Vec<usize> = (0..edits.len())
+        .filter(|i| edits[*i].0 != Edit::Keep)
+        .collect();
+    let mut result = String::new();
+    let mut c = 0;
+    while c < changes.len() {
+        // Extend the hunk while the next change is within its context
+        let first = changes[c];
+        let mut last = first;
+        c += 1;
+        while c < changes.len() && changes[c] - last <= 2 * context + 1 {
+            last = changes[c];
+            c += 1;
+        }
+        let start = first.saturating_sub(context);
+        let end = (last + context + 1).min(edits.len());
+        let (o0, n0) = positions[start];
+        let (o1, n1) = positions[end];
+        result += &format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(o0, o1 - o0),
+            hunk_range(n0, n1 - n0)
+        );
+        for (edit, line) in &edits[start..end] {
+            let prefix = match edit {
+                Edit::Keep => ' ',
+                Edit::Delete => '-',
+                Edit::Insert => '+',
+            };
+            result.push(prefix);
+            result += line;
+            result.push('\n');
+        }
+    }
+    result
+}
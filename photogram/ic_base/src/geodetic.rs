@@ -0,0 +1,396 @@
+//a Documentation
+//! Geodetic coordinates on the WGS84 ellipsoid, and their conversion
+//! to Cartesian frames
+//!
+//! A [Geodetic] position is a latitude and longitude in degrees and
+//! an ellipsoidal height in metres (as given by GPS). It may be
+//! converted to and from:
+//!
+//! * earth-centred earth-fixed (ECEF) coordinates, in metres
+//!
+//! * local east-north-up (ENU) coordinates about an origin, in metres
+//!
+//! * universal transverse Mercator (UTM) easting, northing and
+//!   height, in metres, for a [UtmZone]
+//!
+//! The UTM projection uses the Krüger series to sixth order in the
+//! third flattening, which is accurate to well under a millimetre
+//! within a zone; the zone of a position is its 6 degree longitude
+//! band (the Norway and Svalbard exceptions are not applied).
+
+//a Imports
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Point3D, Result};
+
+//a Constants
+/// WGS84 semi-major axis in metres
+pub const WGS84_A: f64 = 6_378_137.0;
+
+/// WGS84 flattening
+pub const WGS84_F: f64 = 1.0 / 298.257_223_563;
+
+/// WGS84 first eccentricity squared
+const WGS84_E2: f64 = WGS84_F * (2.0 - WGS84_F);
+
+/// UTM scale factor on the central meridian
+const UTM_K0: f64 = 0.9996;
+
+/// UTM false easting in metres
+const UTM_FALSE_EASTING: f64 = 500_000.0;
+
+/// UTM false northing in metres for the southern hemisphere
+const UTM_FALSE_NORTHING_SOUTH: f64 = 10_000_000.0;
+
+//a Geodetic
+//tp Geodetic
+/// A position on the WGS84 ellipsoid: latitude and longitude in
+/// degrees (north and east positive), and ellipsoidal height in metres
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Geodetic {
+    /// Latitude in degrees
+    lat: f64,
+    /// Longitude in degrees
+    lon: f64,
+    /// Height above the ellipsoid in metres
+    #[serde(default)]
+    alt: f64,
+}
+
+//ip Display for Geodetic
+impl std::fmt::Display for Geodetic {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(
+            fmt,
+            "{:.8}{} {:.8}{} {:.3}m",
+            self.lat.abs(),
+            if self.lat < 0. { 'S' } else { 'N' },
+            self.lon.abs(),
+            if self.lon < 0. { 'W' } else { 'E' },
+            self.alt
+        )
+    }
+}
+
+//ip Geodetic
+impl Geodetic {
+    //cp new
+    pub fn new(lat: f64, lon: f64, alt: f64) -> Self {
+        Self { lat, lon, alt }
+    }
+
+    //cp of_str
+    /// Parse a position given as 'lat,lon' or 'lat,lon,alt'
+    pub fn of_str(s: &str) -> Result<Self> {
+        let coords: Vec<_> = s.split(',').map(|c| c.trim()).collect();
+        if coords.len() != 2 && coords.len() != 3 {
+            return Err(
+                format!("Expected 'lat,lon' or 'lat,lon,alt' for a position, got '{s}'").into(),
+            );
+        }
+        let lat = coords[0].parse::<f64>()?;
+        let lon = coords[1].parse::<f64>()?;
+        let alt = {
+            if coords.len() == 3 {
+                coords[2].parse::<f64>()?
+            } else {
+                0.
+            }
+        };
+        if !(-90.0..=90.0).contains(&lat) {
+            return Err(format!("Latitude {lat} must be in the range -90 to 90 degrees").into());
+        }
+        Ok(Self::new(lat, lon, alt))
+    }
+
+    //ap lat
+    pub fn lat(&self) -> f64 {
+        self.lat
+    }
+
+    //ap lon
+    pub fn lon(&self) -> f64 {
+        self.lon
+    }
+
+    //ap alt
+    pub fn alt(&self) -> f64 {
+        self.alt
+    }
+
+    //mp to_ecef
+    /// Convert to earth-centred earth-fixed coordinates in metres
+    pub fn to_ecef(&self) -> Point3D {
+        let (sin_lat, cos_lat) = self.lat.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = self.lon.to_radians().sin_cos();
+        let n = WGS84_A / (1.0 - WGS84_E2 * sin_lat * sin_lat).sqrt();
+        [
+            (n + self.alt) * cos_lat * cos_lon,
+            (n + self.alt) * cos_lat * sin_lon,
+            (n * (1.0 - WGS84_E2) + self.alt) * sin_lat,
+        ]
+        .into()
+    }
+
+    //cp of_ecef
+    /// Convert from earth-centred earth-fixed coordinates in metres
+    ///
+    /// The latitude is found by fixed-point iteration, which converges
+    /// to well below a micrometre in a few steps for positions near
+    /// the surface of the earth
+    pub fn of_ecef(ecef: &Point3D) -> Self {
+        let (x, y, z) = (ecef[0], ecef[1], ecef[2]);
+        let p = x.hypot(y);
+        let lon = y.atan2(x);
+        let mut lat = z.atan2(p * (1.0 - WGS84_E2));
+        for _ in 0..10 {
+            let sin_lat = lat.sin();
+            let n = WGS84_A / (1.0 - WGS84_E2 * sin_lat * sin_lat).sqrt();
+            let next_lat = (z + WGS84_E2 * n * sin_lat).atan2(p);
+            let done = (next_lat - lat).abs() < 1.0E-14;
+            lat = next_lat;
+            if done {
+                break;
+            }
+        }
+        let (sin_lat, cos_lat) = lat.sin_cos();
+        let alt = p * cos_lat + z * sin_lat - WGS84_A * (1.0 - WGS84_E2 * sin_lat * sin_lat).sqrt();
+        Self::new(lat.to_degrees(), lon.to_degrees(), alt)
+    }
+
+    //mi enu_axes
+    /// The unit east, north and up vectors (in ECEF) at this position
+    fn enu_axes(&self) -> [Point3D; 3] {
+        let (sin_lat, cos_lat) = self.lat.to_radians().sin_cos();
+        let (sin_lon, cos_lon) = self.lon.to_radians().sin_cos();
+        [
+            [-sin_lon, cos_lon, 0.].into(),
+            [-sin_lat * cos_lon, -sin_lat * sin_lon, cos_lat].into(),
+            [cos_lat * cos_lon, cos_lat * sin_lon, sin_lat].into(),
+        ]
+    }
+
+    //mp to_enu
+    /// Convert to local east-north-up coordinates in metres about an
+    /// origin
+    pub fn to_enu(&self, origin: &Geodetic) -> Point3D {
+        let d = self.to_ecef() - origin.to_ecef();
+        let [e, n, u] = origin.enu_axes();
+        [dot(&e, &d), dot(&n, &d), dot(&u, &d)].into()
+    }
+
+    //cp of_enu
+    /// Convert from local east-north-up coordinates in metres about an
+    /// origin
+    pub fn of_enu(origin: &Geodetic, enu: &Point3D) -> Self {
+        let [e, n, u] = origin.enu_axes();
+        let ecef = origin.to_ecef() + e * enu[0] + n * enu[1] + u * enu[2];
+        Self::of_ecef(&ecef)
+    }
+}
+
+//fi dot
+fn dot(a: &Point3D, b: &Point3D) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+//a UtmZone
+//tp UtmZone
+/// A universal transverse Mercator zone, 1 to 60, and hemisphere
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct UtmZone {
+    /// Zone number, 1 to 60
+    zone: u8,
+    /// True for the northern hemisphere
+    north: bool,
+}
+
+//ip Display for UtmZone
+impl std::fmt::Display for UtmZone {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(fmt, "{}{}", self.zone, if self.north { 'N' } else { 'S' })
+    }
+}
+
+//ip UtmZone
+impl UtmZone {
+    //cp new
+    pub fn new(zone: u8, north: bool) -> Result<Self> {
+        if !(1..=60).contains(&zone) {
+            return Err(format!("UTM zone {zone} must be in the range 1 to 60").into());
+        }
+        Ok(Self { zone, north })
+    }
+
+    //cp of_str
+    /// Parse a zone given as its number and hemisphere, such as '31N'
+    /// or '56S'
+    pub fn of_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let north = match s.chars().last() {
+            Some('N') | Some('n') => true,
+            Some('S') | Some('s') => false,
+            _ => {
+                return Err(format!("Expected a UTM zone such as '31N' or '56S', got '{s}'").into())
+            }
+        };
+        let zone = s[..s.len() - 1]
+            .parse::<u8>()
+            .map_err(|_| format!("Bad UTM zone number in '{s}'"))?;
+        Self::new(zone, north)
+    }
+
+    //cp of_geodetic
+    /// The zone that contains a position
+    pub fn of_geodetic(geodetic: &Geodetic) -> Self {
+        let lon = (geodetic.lon + 180.0).rem_euclid(360.0);
+        let zone = ((lon / 6.0).floor() as u8).min(59) + 1;
+        Self {
+            zone,
+            north: geodetic.lat >= 0.,
+        }
+    }
+
+    //ap zone
+    pub fn zone(&self) -> u8 {
+        self.zone
+    }
+
+    //ap north
+    pub fn north(&self) -> bool {
+        self.north
+    }
+
+    //mi central_meridian
+    /// The longitude of the central meridian of the zone, in radians
+    fn central_meridian(&self) -> f64 {
+        (self.zone as f64 * 6.0 - 183.0).to_radians()
+    }
+
+    //mi false_northing
+    fn false_northing(&self) -> f64 {
+        if self.north {
+            0.
+        } else {
+            UTM_FALSE_NORTHING_SOUTH
+        }
+    }
+
+    //mp to_utm
+    /// Convert a position to easting, northing and height in metres
+    /// in this zone
+    pub fn to_utm(&self, geodetic: &Geodetic) -> Point3D {
+        let k = Kruger::wgs84();
+        let lat = geodetic.lat.to_radians();
+        let dlon = geodetic.lon.to_radians() - self.central_meridian();
+        let c = 2.0 * k.n.sqrt() / (1.0 + k.n);
+        let t = (lat.sin().atanh() - c * (c * lat.sin()).atanh()).sinh();
+        let xi_p = t.atan2(dlon.cos());
+        let eta_p = (dlon.sin() / (1.0 + t * t).sqrt()).atanh();
+        let mut xi = xi_p;
+        let mut eta = eta_p;
+        for (j, alpha) in k.alpha.iter().enumerate() {
+            let j2 = 2.0 * (j + 1) as f64;
+            xi += alpha * (j2 * xi_p).sin() * (j2 * eta_p).cosh();
+            eta += alpha * (j2 * xi_p).cos() * (j2 * eta_p).sinh();
+        }
+        [
+            UTM_FALSE_EASTING + UTM_K0 * k.a * eta,
+            self.false_northing() + UTM_K0 * k.a * xi,
+            geodetic.alt,
+        ]
+        .into()
+    }
+
+    //mp to_geodetic
+    /// Convert an easting, northing and height in metres in this zone
+    /// to a position
+    pub fn to_geodetic(&self, utm: &Point3D) -> Geodetic {
+        let k = Kruger::wgs84();
+        let xi = (utm[1] - self.false_northing()) / (UTM_K0 * k.a);
+        let eta = (utm[0] - UTM_FALSE_EASTING) / (UTM_K0 * k.a);
+        let mut xi_p = xi;
+        let mut eta_p = eta;
+        for (j, beta) in k.beta.iter().enumerate() {
+            let j2 = 2.0 * (j + 1) as f64;
+            xi_p -= beta * (j2 * xi).sin() * (j2 * eta).cosh();
+            eta_p -= beta * (j2 * xi).cos() * (j2 * eta).sinh();
+        }
+        let chi = (xi_p.sin() / eta_p.cosh()).asin();
+        let mut lat = chi;
+        for (j, delta) in k.delta.iter().enumerate() {
+            let j2 = 2.0 * (j + 1) as f64;
+            lat += delta * (j2 * chi).sin();
+        }
+        let lon = self.central_meridian() + eta_p.sinh().atan2(xi_p.cos());
+        Geodetic::new(lat.to_degrees(), lon.to_degrees(), utm[2])
+    }
+}
+
+//a Kruger
+//ti Kruger
+/// Coefficients of the Krüger series for the transverse Mercator
+/// projection, to sixth order in the third flattening (after Karney,
+/// "Transverse Mercator with an accuracy of a few nanometers"), which
+/// is accurate to well under a millimetre within a UTM zone
+struct Kruger {
+    /// Third flattening
+    n: f64,
+    /// Rectifying radius
+    a: f64,
+    alpha: [f64; 6],
+    beta: [f64; 6],
+    delta: [f64; 6],
+}
+
+//ii Kruger
+impl Kruger {
+    //fi wgs84
+    fn wgs84() -> Self {
+        let n = WGS84_F / (2.0 - WGS84_F);
+        let n2 = n * n;
+        let n3 = n2 * n;
+        let n4 = n3 * n;
+        let n5 = n4 * n;
+        let n6 = n5 * n;
+        let a = WGS84_A / (1.0 + n) * (1.0 + n2 / 4.0 + n4 / 64.0 + n6 / 256.0);
+        Self {
+            n,
+            a,
+            alpha: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 5.0 * n3 / 16.0 + 41.0 * n4 / 180.0 - 127.0 * n5 / 288.0
+                    + 7891.0 * n6 / 37800.0,
+                13.0 * n2 / 48.0 - 3.0 * n3 / 5.0 + 557.0 * n4 / 1440.0 + 281.0 * n5 / 630.0
+                    - 1983433.0 * n6 / 1935360.0,
+                61.0 * n3 / 240.0 - 103.0 * n4 / 140.0
+                    + 15061.0 * n5 / 26880.0
+                    + 167603.0 * n6 / 181440.0,
+                49561.0 * n4 / 161280.0 - 179.0 * n5 / 168.0 + 6601661.0 * n6 / 7257600.0,
+                34729.0 * n5 / 80640.0 - 3418889.0 * n6 / 1995840.0,
+                212378941.0 * n6 / 319334400.0,
+            ],
+            beta: [
+                n / 2.0 - 2.0 * n2 / 3.0 + 37.0 * n3 / 96.0 - n4 / 360.0 - 81.0 * n5 / 512.0
+                    + 96199.0 * n6 / 604800.0,
+                n2 / 48.0 + n3 / 15.0 - 437.0 * n4 / 1440.0 + 46.0 * n5 / 105.0
+                    - 1118711.0 * n6 / 3870720.0,
+                17.0 * n3 / 480.0 - 37.0 * n4 / 840.0 - 209.0 * n5 / 4480.0 + 5569.0 * n6 / 90720.0,
+                4397.0 * n4 / 161280.0 - 11.0 * n5 / 504.0 - 830251.0 * n6 / 7257600.0,
+                4583.0 * n5 / 161280.0 - 108847.0 * n6 / 3991680.0,
+                20648693.0 * n6 / 638668800.0,
+            ],
+            delta: [
+                2.0 * n - 2.0 * n2 / 3.0 - 2.0 * n3 + 116.0 * n4 / 45.0 + 26.0 * n5 / 45.0
+                    - 2854.0 * n6 / 675.0,
+                7.0 * n2 / 3.0 - 8.0 * n3 / 5.0 - 227.0 * n4 / 45.0
+                    + 2704.0 * n5 / 315.0
+                    + 2323.0 * n6 / 945.0,
+                56.0 * n3 / 15.0 - 136.0 * n4 / 35.0 - 1262.0 * n5 / 105.0 + 73814.0 * n6 / 2835.0,
+                4279.0 * n4 / 630.0 - 332.0 * n5 / 35.0 - 399572.0 * n6 / 14175.0,
+                4174.0 * n5 / 315.0 - 144838.0 * n6 / 6237.0,
+                601676.0 * n6 / 22275.0,
+            ],
+        }
+    }
+}
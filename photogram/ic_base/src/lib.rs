@@ -4,15 +4,19 @@
 !*/
 
 //a Modules
+pub mod diff;
 mod error;
 pub use error::Error;
 pub type Result<T> = std::result::Result<T, Error>;
 
+pub mod geodetic;
+pub use geodetic::{Geodetic, UtmZone};
 pub mod json;
 mod path_set;
 pub use path_set::PathSet;
 mod plane;
 mod quadtree;
+pub mod schema;
 pub mod types;
 pub mod utils;
 mod word_xy;
@@ -24,6 +28,9 @@ pub use types::{Mat3x3, Point2D, Point3D, Point4D, Quat, RollYaw, TanXTanY};
 mod ray;
 pub use ray::Ray;
 
+mod similarity;
+pub use similarity::Similarity;
+
 mod mesh;
 pub use mesh::Mesh;
 pub use utils::Rrc;
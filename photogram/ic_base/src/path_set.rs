@@ -1,11 +1,10 @@
 //a Imports
 use std::path::{Path, PathBuf};
 
-use serde::de::DeserializeOwned;
 use serde::Deserialize;
 
-use crate::json::json_error;
 use crate::json::remove_comments;
+use crate::schema::{self, Versioned};
 use crate::{Error, Result};
 
 //a PathSet
@@ -56,7 +55,9 @@ impl PathSet {
     }
 
     //mp load_from_json_file
-    pub fn load_from_json_file<P: AsRef<Path> + std::fmt::Display, T: DeserializeOwned>(
+    /// Find and load a versioned JSON file, upgrading it to the
+    /// current version of its schema
+    pub fn load_from_json_file<P: AsRef<Path> + std::fmt::Display, T: Versioned>(
         &self,
         reason: &str,
         path: P,
@@ -64,10 +65,7 @@ impl PathSet {
         let (pathname, json) = self
             .read_json_file(&path)
             .map_err(|e| (e, reason.to_owned()))?;
-        Ok((
-            pathname,
-            serde_json::from_str(&json)
-                .map_err(|e| json_error(&format!("{reason} '{path}'"), &json, e))?,
-        ))
+        let value = schema::from_json(&json).map_err(|e| (e, format!("{reason} '{path}'")))?;
+        Ok((pathname, value))
     }
 }
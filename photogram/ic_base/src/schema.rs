@@ -0,0 +1,242 @@
+//a Documentation
+//! Schema versions, migration and JSON Schema documents for JSON files
+//!
+//! Each type that is persisted as a JSON file implements [Versioned],
+//! giving the name and current version of its schema. A file carries
+//! its version as a 'version' key: for a type that serializes as an
+//! object this is added to the object; for any other type (such as
+//! the lists of named points or point mappings) the file is an object
+//! with the version and the contents under a type-specific key.
+//!
+//! Files without a version (as written before versions were added)
+//! are version 0. When a file is read its contents are upgraded one
+//! version at a time, using [Versioned::upgrade], to the current
+//! version before being deserialized; files with a version later than
+//! the current version are rejected.
+//!
+//! The JSON Schema document (draft 2020-12) for the current version of
+//! a file is generated from the serde types with [json_schema], so
+//! that files may be validated by other tools.
+
+//a Imports
+use std::marker::PhantomData;
+
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+
+use crate::json::json_error;
+use crate::{Error, Result};
+
+//a Versioned
+//tt Versioned
+/// A type that is persisted as a versioned JSON file
+pub trait Versioned: Serialize + DeserializeOwned + JsonSchema {
+    /// The name of the schema, in snake case
+    const SCHEMA_NAME: &'static str;
+
+    /// The current version of the schema
+    const SCHEMA_VERSION: usize;
+
+    /// The key of the file holding the contents, if the type does not
+    /// serialize as an object
+    const CONTENTS_KEY: Option<&'static str> = None;
+
+    //fp upgrade
+    /// Upgrade the contents of a file from a version to the next
+    /// version
+    ///
+    /// Version 0 files may be in any of the forms that were written
+    /// before versions were added; upgrades from version 0 must
+    /// therefore also accept contents that are already in the form of
+    /// version 1
+    fn upgrade(version: usize, contents: Value) -> Result<Value> {
+        let _ = version;
+        Ok(contents)
+    }
+}
+
+//a Support
+//fi description
+/// The schema name as a description for errors
+fn description<T: Versioned>() -> String {
+    T::SCHEMA_NAME.replace('_', " ")
+}
+
+//tp VersionedRef
+/// A reference to a value, serialized as a versioned file of the
+/// schema of S
+struct VersionedRef<'a, S: Versioned, T: Serialize>(&'a T, PhantomData<S>);
+
+//ip Serialize for VersionedRef
+impl<S: Versioned, T: Serialize> Serialize for VersionedRef<'_, S, T> {
+    fn serialize<SER: Serializer>(
+        &self,
+        serializer: SER,
+    ) -> std::result::Result<SER::Ok, SER::Error> {
+        #[derive(Serialize)]
+        struct Flattened<'a, T> {
+            version: usize,
+            #[serde(flatten)]
+            contents: &'a T,
+        }
+        if let Some(key) = S::CONTENTS_KEY {
+            use serde::ser::SerializeMap;
+            let mut map = serializer.serialize_map(Some(2))?;
+            map.serialize_entry("version", &S::SCHEMA_VERSION)?;
+            map.serialize_entry(key, self.0)?;
+            map.end()
+        } else {
+            Flattened {
+                version: S::SCHEMA_VERSION,
+                contents: self.0,
+            }
+            .serialize(serializer)
+        }
+    }
+}
+
+//a Public functions
+//fp split_version
+/// Split the JSON of a file of a type into its version and contents
+///
+/// A file without a version is version 0, and its contents are the
+/// whole file
+pub fn split_version<T: Versioned>(value: Value) -> Result<(usize, Value)> {
+    let Value::Object(mut map) = value else {
+        return Ok((0, value));
+    };
+    let Some(version) = map.get("version") else {
+        return Ok((0, Value::Object(map)));
+    };
+    let Some(version) = version.as_u64() else {
+        return Err(format!("Bad version '{version}' in {} json", description::<T>()).into());
+    };
+    let version = version as usize;
+    map.remove("version");
+    let contents = {
+        if let Some(key) = T::CONTENTS_KEY {
+            map.remove(key).ok_or_else(|| {
+                Error::from(format!(
+                    "Missing '{key}' in version {version} {} json",
+                    description::<T>()
+                ))
+            })?
+        } else {
+            Value::Object(map)
+        }
+    };
+    Ok((version, contents))
+}
+
+//fp upgrade
+/// Upgrade the contents of a file of a type from a version to the
+/// current version
+///
+/// This may be used by a type to upgrade the contents of another type
+/// that it contains, which carry no version of their own
+pub fn upgrade<T: Versioned>(version: usize, mut contents: Value) -> Result<Value> {
+    if version > T::SCHEMA_VERSION {
+        return Err(format!(
+            "{} json is version {version}, which is newer than the supported version {}",
+            description::<T>(),
+            T::SCHEMA_VERSION
+        )
+        .into());
+    }
+    for v in version..T::SCHEMA_VERSION {
+        contents = T::upgrade(v, contents)
+            .map_err(|e| (e, format!("Failed to upgrade {} json", description::<T>())))?;
+    }
+    Ok(contents)
+}
+
+//fp from_json
+/// Read a file of a type from its JSON, upgrading it to the current
+/// version
+pub fn from_json<T: Versioned>(json: &str) -> Result<T> {
+    let what = description::<T>();
+    let value: Value = serde_json::from_str(json).map_err(|e| json_error(&what, json, e))?;
+    let (version, contents) = split_version::<T>(value)?;
+    if version == T::SCHEMA_VERSION && T::CONTENTS_KEY.is_none() {
+        // Deserialize from the text so that errors give the location;
+        // the version is ignored as an unknown field
+        return serde_json::from_str(json).map_err(|e| json_error(&what, json, e));
+    }
+    let contents = upgrade::<T>(version, contents)?;
+    serde_json::from_value(contents)
+        .map_err(|e| Error::JsonCtxt(format!("Error in version {version} {what} json"), e))
+}
+
+//fp to_json
+/// Write a value as a file of its type, with the current version
+pub fn to_json<T: Versioned>(value: &T, pretty: bool) -> Result<String> {
+    to_json_as::<T, T>(value, pretty)
+}
+
+//fp to_json_as
+/// Write a value as a file of the schema of another type, which it
+/// must serialize compatibly with (such as a camera instance, which is
+/// read as a camera instance descriptor)
+pub fn to_json_as<S: Versioned, T: Serialize>(value: &T, pretty: bool) -> Result<String> {
+    let versioned = VersionedRef::<S, T>(value, PhantomData);
+    if pretty {
+        Ok(serde_json::to_string_pretty(&versioned)?)
+    } else {
+        Ok(serde_json::to_string(&versioned)?)
+    }
+}
+
+//fp version_of_json
+/// Get the version of a file of a type from its JSON
+pub fn version_of_json<T: Versioned>(json: &str) -> Result<usize> {
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| json_error(&description::<T>(), json, e))?;
+    Ok(split_version::<T>(value)?.0)
+}
+
+//fp json_schema
+/// Generate the JSON Schema document for the current version of a
+/// file of a type
+pub fn json_schema<T: Versioned>() -> Value {
+    let mut schema = schemars::schema_for!(T).to_value();
+    let version = serde_json::json!({
+        "description": "The version of the schema of the file",
+        "const": T::SCHEMA_VERSION,
+    });
+    let Value::Object(root) = &mut schema else {
+        unreachable!("A schema document is an object");
+    };
+    if let Some(key) = T::CONTENTS_KEY {
+        // Move the schema of the contents (but not the metadata or
+        // definitions) under the contents key
+        let mut contents = serde_json::Map::new();
+        for k in root.keys().cloned().collect::<Vec<_>>() {
+            if !k.starts_with('$') {
+                contents.insert(k.clone(), root.remove(&k).unwrap());
+            }
+        }
+        root.insert("type".into(), "object".into());
+        root.insert(
+            "properties".into(),
+            serde_json::json!({ "version": version, key: contents }),
+        );
+        root.insert("required".into(), serde_json::json!(["version", key]));
+    } else {
+        let properties = root
+            .entry("properties")
+            .or_insert_with(|| Value::Object(Default::default()));
+        if let Value::Object(properties) = properties {
+            properties.insert("version".into(), version);
+        }
+        let required = root
+            .entry("required")
+            .or_insert_with(|| Value::Array(vec![]));
+        if let Value::Array(required) = required {
+            required.insert(0, "version".into());
+        }
+    }
+    root.insert("title".into(), T::SCHEMA_NAME.into());
+    schema
+}
@@ -0,0 +1,174 @@
+//a Imports
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::Point3D;
+
+//a Similarity
+//tp Similarity
+/// A similarity transform: a uniform scale, a rotation and a
+/// translation, mapping p to scale * rotation * p + translation
+///
+/// The rotation is a 3x3 matrix in row-major order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Similarity {
+    scale: f64,
+    rotation: [f64; 9],
+    #[schemars(with = "[f64; 3]")]
+    translation: Point3D,
+}
+
+//ip Default for Similarity
+impl std::default::Default for Similarity {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            rotation: [1., 0., 0., 0., 1., 0., 0., 0., 1.],
+            translation: Point3D::default(),
+        }
+    }
+}
+
+//ip Display for Similarity
+impl std::fmt::Display for Similarity {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        write!(
+            fmt,
+            "scale {:.6e} rotation {:.6?} translation {}",
+            self.scale, self.rotation, self.translation
+        )
+    }
+}
+
+//ip Similarity
+impl Similarity {
+    //cp fit
+    /// Find the similarity transform that best maps each 'from' point
+    /// to its 'to' point, in the least squares sense
+    ///
+    /// This uses Umeyama's method: the rotation is found from the
+    /// singular value decomposition of the cross-covariance of the
+    /// centred points, and then the scale and translation follow.
+    ///
+    /// At least three points that are not in a line are required;
+    /// None is returned if the points are degenerate
+    pub fn fit(pairs: &[(Point3D, Point3D)]) -> Option<Self> {
+        if pairs.len() < 3 {
+            return None;
+        }
+        let n = pairs.len() as f64;
+        let mut from_mean = Point3D::default();
+        let mut to_mean = Point3D::default();
+        for (f, t) in pairs {
+            from_mean += *f;
+            to_mean += *t;
+        }
+        from_mean /= n;
+        to_mean /= n;
+
+        let mut from_var = 0.0;
+        let mut cov = nalgebra::Matrix3::<f64>::zeros();
+        for (f, t) in pairs {
+            let f = *f - from_mean;
+            let t = *t - to_mean;
+            from_var += f[0] * f[0] + f[1] * f[1] + f[2] * f[2];
+            let f = nalgebra::Vector3::new(f[0], f[1], f[2]);
+            let t = nalgebra::Vector3::new(t[0], t[1], t[2]);
+            cov += t * f.transpose();
+        }
+        from_var /= n;
+        cov /= n;
+        if from_var < 1.0E-20 {
+            return None;
+        }
+
+        let svd = cov.svd(true, true);
+        let u = svd.u?;
+        let v_t = svd.v_t?;
+        let d = svd.singular_values;
+        let mut order = [0, 1, 2];
+        order.sort_by(|a, b| d[*b].total_cmp(&d[*a]));
+        let [i_max, i_mid, i_min] = order;
+        // All but the smallest singular value must be significant,
+        // else the points are in a line (or coincide)
+        if d[i_mid] <= d[i_max] * 1.0E-9 {
+            return None;
+        }
+        // Choose a rotation rather than a reflection
+        let mut s = nalgebra::Matrix3::<f64>::identity();
+        if u.determinant() * v_t.determinant() < 0. {
+            s[(i_min, i_min)] = -1.;
+        }
+        let r = u * s * v_t;
+        let scale = (0..3).map(|i| d[i] * s[(i, i)]).sum::<f64>() / from_var;
+
+        let mut rotation = [0.; 9];
+        for i in 0..3 {
+            for j in 0..3 {
+                rotation[i * 3 + j] = r[(i, j)];
+            }
+        }
+        let mut similarity = Self {
+            scale,
+            rotation,
+            translation: Point3D::default(),
+        };
+        similarity.translation = to_mean - similarity.apply(&from_mean);
+        Some(similarity)
+    }
+
+    //ap scale
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    //ap rotation
+    pub fn rotation(&self) -> &[f64; 9] {
+        &self.rotation
+    }
+
+    //ap translation
+    pub fn translation(&self) -> &Point3D {
+        &self.translation
+    }
+
+    //mp apply
+    /// Apply the transform to a point
+    pub fn apply(&self, p: &Point3D) -> Point3D {
+        let r = &self.rotation;
+        let rotated: Point3D = [
+            r[0] * p[0] + r[1] * p[1] + r[2] * p[2],
+            r[3] * p[0] + r[4] * p[1] + r[5] * p[2],
+            r[6] * p[0] + r[7] * p[1] + r[8] * p[2],
+        ]
+        .into();
+        rotated * self.scale + self.translation
+    }
+
+    //mp apply_inverse
+    /// Apply the inverse of the transform to a point
+    pub fn apply_inverse(&self, p: &Point3D) -> Point3D {
+        let r = &self.rotation;
+        let d = *p - self.translation;
+        let rotated: Point3D = [
+            r[0] * d[0] + r[3] * d[1] + r[6] * d[2],
+            r[1] * d[0] + r[4] * d[1] + r[7] * d[2],
+            r[2] * d[0] + r[5] * d[1] + r[8] * d[2],
+        ]
+        .into();
+        rotated / self.scale
+    }
+
+    //mp residuals
+    /// The distance of each transformed 'from' point from its 'to'
+    /// point
+    pub fn residuals(&self, pairs: &[(Point3D, Point3D)]) -> Vec<f64> {
+        pairs
+            .iter()
+            .map(|(f, t)| {
+                let d = self.apply(f) - *t;
+                (d[0] * d[0] + d[1] * d[1] + d[2] * d[2]).sqrt()
+            })
+            .collect()
+    }
+}
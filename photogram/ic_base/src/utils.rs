@@ -61,6 +61,24 @@ impl<T> Rrc<T> {
     pub fn borrow_mut(&self) -> RefMut<T> {
         self.0.borrow_mut()
     }
+    /// Return true if the two refer to the same data
+    pub fn ptr_eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+impl<T: schemars::JsonSchema> schemars::JsonSchema for Rrc<T> {
+    fn inline_schema() -> bool {
+        T::inline_schema()
+    }
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        T::schema_name()
+    }
+    fn schema_id() -> std::borrow::Cow<'static, str> {
+        T::schema_id()
+    }
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        T::json_schema(generator)
+    }
 }
 impl<T> std::default::Default for Rrc<T>
 where
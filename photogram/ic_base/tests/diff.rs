@@ -0,0 +1,36 @@
+//a Imports
+use ic_base::diff::unified;
+
+//a Tests
+//ti same
+#[test]
+fn same() {
+    assert_eq!(unified("", "", 3), "");
+    assert_eq!(unified("a\nb\n", "a\nb\n", 3), "");
+}
+
+//ti change
+#[test]
+fn change() {
+    let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n";
+    let new = "1\n2\n3\n4\nfive\n6\n7\n8\n9\n";
+    assert_eq!(unified(old, new, 1), "@@ -4,3 +4,3 @@\n 4\n-5\n+five\n 6\n");
+    let new = "0\n1\n2\n3\n4\n5\n6\n7\n8\n";
+    assert_eq!(
+        unified(old, new, 1),
+        "@@ -1 +1,2 @@\n+0\n 1\n@@ -8,2 +9 @@\n 8\n-9\n"
+    );
+    // Changes closer than twice the context are in one hunk
+    let new = "1\n2\nthree\n4\nfive\n6\n7\n8\n9\n";
+    assert_eq!(
+        unified(old, new, 1),
+        "@@ -2,5 +2,5 @@\n 2\n-3\n+three\n 4\n-5\n+five\n 6\n"
+    );
+}
+
+//ti create
+#[test]
+fn create() {
+    assert_eq!(unified("", "a\nb\n", 3), "@@ -0,0 +1,2 @@\n+a\n+b\n");
+    assert_eq!(unified("a\n", "", 3), "@@ -1 +0,0 @@\n-a\n");
+}
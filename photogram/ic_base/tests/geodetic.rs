@@ -0,0 +1,130 @@
+//a Imports
+use geo_nd::Vector;
+use ic_base::{Geodetic, Point3D, Similarity, UtmZone};
+
+//a Support
+//fi assert_close
+fn assert_close(a: &Point3D, b: &Point3D, tol: f64) {
+    assert!((*a - *b).length() < tol, "{a} != {b}");
+}
+
+//fi assert_geodetic_close
+fn assert_geodetic_close(a: &Geodetic, b: &Geodetic) {
+    assert!((a.lat() - b.lat()).abs() < 1.0E-9, "{a} != {b}");
+    assert!((a.lon() - b.lon()).abs() < 1.0E-9, "{a} != {b}");
+    assert!((a.alt() - b.alt()).abs() < 1.0E-4, "{a} != {b}");
+}
+
+//a Tests
+//ti of_str
+#[test]
+fn of_str() {
+    let g = Geodetic::of_str("51.5, -0.1").unwrap();
+    assert_eq!((g.lat(), g.lon(), g.alt()), (51.5, -0.1, 0.));
+    let g = Geodetic::of_str("-33.9,151.2,40").unwrap();
+    assert_eq!((g.lat(), g.lon(), g.alt()), (-33.9, 151.2, 40.));
+    assert!(Geodetic::of_str("51.5").is_err());
+    assert!(Geodetic::of_str("91,0").is_err());
+
+    let z = UtmZone::of_str("31N").unwrap();
+    assert_eq!((z.zone(), z.north()), (31, true));
+    let z = UtmZone::of_str("56s").unwrap();
+    assert_eq!((z.zone(), z.north()), (56, false));
+    assert!(UtmZone::of_str("61N").is_err());
+    assert!(UtmZone::of_str("31").is_err());
+}
+
+//ti ecef
+#[test]
+fn ecef() {
+    let g = Geodetic::new(0., 0., 0.);
+    assert_close(&g.to_ecef(), &[6_378_137.0, 0., 0.].into(), 1.0E-6);
+    let g = Geodetic::new(90., 0., 0.);
+    assert_close(&g.to_ecef(), &[0., 0., 6_356_752.314_245].into(), 1.0E-3);
+
+    for g in [
+        Geodetic::new(51.5, -0.1, 45.),
+        Geodetic::new(-33.9, 151.2, 1200.),
+        Geodetic::new(89.9, 10., -20.),
+    ] {
+        assert_geodetic_close(&Geodetic::of_ecef(&g.to_ecef()), &g);
+    }
+}
+
+//ti enu
+#[test]
+fn enu() {
+    let origin = Geodetic::new(51.5, -0.1, 45.);
+    assert_close(&origin.to_enu(&origin), &Point3D::default(), 1.0E-6);
+
+    // A point slightly north and above is north and up
+    let g = Geodetic::new(51.501, -0.1, 55.);
+    let enu = g.to_enu(&origin);
+    assert!(enu[0].abs() < 1.0E-3);
+    assert!((enu[1] - 111.26).abs() < 0.1, "{enu}");
+    assert!((enu[2] - 10.).abs() < 0.01, "{enu}");
+
+    let enu: Point3D = [120., -35., 7.5].into();
+    let g = Geodetic::of_enu(&origin, &enu);
+    assert_close(&g.to_enu(&origin), &enu, 1.0E-6);
+}
+
+//ti utm
+#[test]
+fn utm() {
+    // The central meridian of zone 31 is 3 degrees east
+    let zone = UtmZone::of_geodetic(&Geodetic::new(0., 3., 0.));
+    assert_eq!((zone.zone(), zone.north()), (31, true));
+    let utm = zone.to_utm(&Geodetic::new(0., 3., 10.));
+    assert_close(&utm, &[500_000., 0., 10.].into(), 1.0E-6);
+
+    // On the central meridian the northing is the scaled meridian arc
+    let utm = zone.to_utm(&Geodetic::new(45., 3., 0.));
+    assert!((utm[0] - 500_000.).abs() < 1.0E-6);
+    assert!((utm[1] - 4_982_950.4).abs() < 0.1, "{utm}");
+
+    let zone = UtmZone::of_geodetic(&Geodetic::new(-33.9, 151.2, 0.));
+    assert_eq!((zone.zone(), zone.north()), (56, false));
+    for g in [
+        Geodetic::new(-33.9, 151.2, 40.),
+        Geodetic::new(-34.5, 149.5, 0.),
+    ] {
+        let utm = zone.to_utm(&g);
+        assert_geodetic_close(&zone.to_geodetic(&utm), &g);
+    }
+}
+
+//ti similarity
+#[test]
+fn similarity() {
+    let from: Vec<Point3D> = vec![
+        [0., 0., 0.].into(),
+        [1000., 0., 0.].into(),
+        [0., 2000., 0.].into(),
+        [300., 400., 500.].into(),
+    ];
+    // Scale by 1/1000, rotate 90 degrees about z, translate
+    let to: Vec<Point3D> = from
+        .iter()
+        .map(|p| [-p[1] / 1000. + 10., p[0] / 1000. + 20., p[2] / 1000. + 30.].into())
+        .collect();
+    let pairs: Vec<_> = from.iter().copied().zip(to.iter().copied()).collect();
+    let s = Similarity::fit(&pairs).unwrap();
+    assert!((s.scale() - 0.001).abs() < 1.0E-12);
+    assert_close(s.translation(), &[10., 20., 30.].into(), 1.0E-9);
+    for (f, t) in &pairs {
+        assert_close(&s.apply(f), t, 1.0E-9);
+        assert_close(&s.apply_inverse(t), f, 1.0E-6);
+    }
+    assert!(s.residuals(&pairs).iter().all(|r| *r < 1.0E-9));
+
+    // Points in a line do not fix the rotation
+    let pairs: Vec<(Point3D, Point3D)> = (0..4)
+        .map(|i| {
+            let x = i as f64;
+            ([x, 0., 0.].into(), [0., x, 0.].into())
+        })
+        .collect();
+    assert!(Similarity::fit(&pairs).is_none());
+    assert!(Similarity::fit(&pairs[0..2]).is_none());
+}
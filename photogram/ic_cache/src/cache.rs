@@ -5,8 +5,59 @@ use std::hash::Hash;
 
 use crate::{CacheEntry, CacheRef, Cacheable};
 
+//a EvictionPolicy, CacheStats
+//tp EvictionPolicy
+/// The policy used to choose which entries to evict from a cache
+/// when it exceeds its budget
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    /// Evict the least recently used entries first
+    #[default]
+    Lru,
+    /// Evict the least frequently used entries first; entries used
+    /// equally often are evicted least recently used first
+    Lfu,
+}
+
+//tp CacheStats
+/// Statistics of the use of a cache
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Number of 'get's that found a filled entry
+    pub hits: usize,
+    /// Number of 'get's that did not find a filled entry
+    pub misses: usize,
+    /// Number of entries inserted
+    pub insertions: usize,
+    /// Number of entries evicted, whether to keep to the budget or by
+    /// an explicit shrink
+    pub evictions: usize,
+    /// Total size of the filled entries
+    pub total_size: usize,
+    /// Number of filled entries
+    pub num_entries: usize,
+}
+
+//ip std::ops::AddAssign for CacheStats
+impl std::ops::AddAssign for CacheStats {
+    fn add_assign(&mut self, other: Self) {
+        self.hits += other.hits;
+        self.misses += other.misses;
+        self.insertions += other.insertions;
+        self.evictions += other.evictions;
+        self.total_size += other.total_size;
+        self.num_entries += other.num_entries;
+    }
+}
+
 //a Cache
 //tp Cache
+/// A cache of [Cacheable] values
+///
+/// If the cache has a budget then entries are evicted (using its
+/// [EvictionPolicy]) whenever an insertion takes its total size over
+/// the budget. Entries with outstanding [CacheRef]s are never
+/// evicted, so the cache may exceed its budget while they are in use.
 #[derive(Debug)]
 pub struct Cache<Key>
 where
@@ -14,6 +65,9 @@ where
 {
     use_count: usize,
     total_size: usize,
+    budget: Option<usize>,
+    policy: EvictionPolicy,
+    stats: CacheStats,
     entries: Vec<CacheEntry>,
     index: HashMap<Key, usize>,
 }
@@ -27,6 +81,9 @@ where
         Cache {
             use_count: 0,
             total_size: 0,
+            budget: None,
+            policy: EvictionPolicy::default(),
+            stats: CacheStats::default(),
             entries: vec![],
             index: HashMap::default(),
         }
@@ -38,11 +95,43 @@ impl<Key> Cache<Key>
 where
     Key: Hash + Ord + Sized + Eq + 'static,
 {
+    //cp with_budget
+    /// Set the maximum total size of the entries of the cache
+    pub fn with_budget(mut self, budget: usize) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    //cp with_policy
+    pub fn with_policy(mut self, policy: EvictionPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
     //ap total_size
     pub fn total_size(&self) -> usize {
         self.total_size
     }
 
+    //ap budget
+    pub fn budget(&self) -> Option<usize> {
+        self.budget
+    }
+
+    //ap policy
+    pub fn policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    //ap stats
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            total_size: self.total_size,
+            num_entries: self.entries.iter().filter(|e| !e.is_empty()).count(),
+            ..self.stats
+        }
+    }
+
     //mp contains
     pub fn contains<Q>(&self, k: &Q) -> bool
     where
@@ -57,26 +146,51 @@ where
     }
 
     //mp insert
+    /// Insert an entry into the cache, returning it if the cache
+    /// already has a filled entry for the key
+    ///
+    /// If the cache then exceeds its budget then other entries are
+    /// evicted
     pub fn insert<C: Cacheable>(&mut self, k: Key, e: C) -> Option<C> {
-        if let Some(idx) = self.index.get(&k) {
-            if self.entries[*idx].is_empty() {
-                let size = e.size();
+        let size = e.size();
+        let idx = {
+            if let Some(idx) = self.index.get(&k) {
+                if !self.entries[*idx].is_empty() {
+                    return Some(e);
+                }
                 self.entries[*idx].fill(e.into(), self.use_count);
-                self.use_count += 1;
-                self.total_size += size;
-                None
+                *idx
             } else {
-                Some(e)
+                let n = self.entries.len();
+                self.entries.push(CacheEntry::new(e.into(), self.use_count));
+                self.index.insert(k, n);
+                n
             }
-        } else {
-            let size = e.size();
-            let n = self.entries.len();
-            self.entries.push(CacheEntry::new(e.into(), self.use_count));
-            self.index.insert(k, n);
-            self.use_count += 1;
-            self.total_size += size;
-            None
+        };
+        self.use_count += 1;
+        self.total_size += size;
+        self.stats.insertions += 1;
+        if let Some(budget) = self.budget.filter(|b| self.total_size > *b) {
+            self.evict_to(budget, Some(idx));
         }
+        None
+    }
+
+    //mp insert_and_get
+    /// Insert an entry into the cache (dropping the new value if the
+    /// cache already has a filled entry for the key), and return a
+    /// reference to the entry for the key
+    ///
+    /// This counts as neither a hit nor a miss
+    pub fn insert_and_get<C: Cacheable>(&mut self, k: Key, e: C) -> CacheRef
+    where
+        Key: Clone,
+    {
+        self.insert(k.clone(), e);
+        let idx = self.index[&k];
+        let cr = self.entries[idx].take_copy(self.use_count).unwrap();
+        self.use_count += 1;
+        cr
     }
 
     //mp get
@@ -85,41 +199,71 @@ where
         Key: Borrow<Q>,
         Q: Hash + Eq + ?Sized,
     {
-        if let Some(idx) = self.index.get(k) {
-            let opt_e = self.entries[*idx].take_copy(self.use_count);
+        let opt_e = self
+            .index
+            .get(k)
+            .and_then(|idx| self.entries[*idx].take_copy(self.use_count));
+        if opt_e.is_some() {
             self.use_count += 1;
-            opt_e
+            self.stats.hits += 1;
         } else {
-            None
+            self.stats.misses += 1;
         }
+        opt_e
     }
 
     //mp indices_by_age
+    /// The indices of the filled entries, least recently used first
     pub fn indices_by_age(&self) -> Vec<usize> {
-        let mut indices: Vec<usize> = self.index.values().copied().collect();
-        indices.sort_by(|a, b| {
-            self.entries[*a]
-                .last_use()
-                .cmp(&self.entries[*b].last_use())
-        });
+        let mut indices: Vec<usize> = self
+            .index
+            .values()
+            .copied()
+            .filter(|i| !self.entries[*i].is_empty())
+            .collect();
+        indices.sort_by_key(|i| self.entries[*i].last_use());
+        indices
+    }
+
+    //mp indices_by_eviction_order
+    /// The indices of the filled entries in the order in which the
+    /// eviction policy would evict them
+    pub fn indices_by_eviction_order(&self) -> Vec<usize> {
+        let mut indices = self.indices_by_age();
+        if self.policy == EvictionPolicy::Lfu {
+            // A stable sort keeps the least recently used first
+            indices.sort_by_key(|i| self.entries[*i].use_count());
+        }
         indices
     }
 
+    //mi evict_to
+    /// Evict entries that have no outstanding references, in eviction
+    /// order, until the total size is at most `size`
+    fn evict_to(&mut self, size: usize, keep: Option<usize>) -> bool {
+        for i in self.indices_by_eviction_order() {
+            if self.total_size <= size {
+                break;
+            }
+            if Some(i) == keep || !self.entries[i].can_empty() {
+                continue;
+            }
+            self.total_size -= self.entries[i].empty();
+            self.stats.evictions += 1;
+        }
+        self.total_size <= size
+    }
+
     //mp shrink_to
+    /// Evict entries until the total size is below `size`, if
+    /// possible; entries with outstanding references are not evicted
+    ///
+    /// Returns true if the total size is then below `size`
     pub fn shrink_to(&mut self, size: usize) -> bool {
-        eprintln!("Shrink to {size} when at {}", self.total_size);
         if self.total_size < size {
             return true;
         }
-        let indices = self.indices_by_age();
-        eprintln!("indices {indices:?}");
-        for i in indices.into_iter() {
-            eprintln!("Index {i}, {}", self.total_size);
-            if self.total_size < size {
-                return true;
-            }
-            self.total_size -= self.entries[i].empty();
-        }
+        self.evict_to(size.saturating_sub(1), None);
         self.total_size < size
     }
 
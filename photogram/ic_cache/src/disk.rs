@@ -0,0 +1,258 @@
+//a Imports
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::{CacheStats, ContentHash};
+
+//a Constants
+//ci ENTRY_EXTENSION
+/// Extension of the files holding the entries of a [DiskCache]
+const ENTRY_EXTENSION: &str = "bin";
+
+//a DiskEntry, DiskState
+//ti DiskEntry
+#[derive(Debug, Clone, Copy)]
+struct DiskEntry {
+    size: u64,
+    last_use: usize,
+}
+
+//ti DiskState
+#[derive(Debug, Default)]
+struct DiskState {
+    use_count: usize,
+    total_size: u64,
+    entries: HashMap<ContentHash, DiskEntry>,
+    stats: CacheStats,
+}
+
+//ii DiskState
+impl DiskState {
+    //mi touch
+    fn touch(&mut self, key: &ContentHash) -> bool {
+        let use_count = self.use_count;
+        if let Some(e) = self.entries.get_mut(key) {
+            e.last_use = use_count;
+            self.use_count += 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+//a DiskCache
+//tp DiskCache
+/// A persistent cache of byte buffers in a directory, keyed by
+/// [ContentHash]
+///
+/// This is intended as a second tier below an in-memory [crate::Cache]
+/// for artefacts that are expensive to derive (such as thumbnails or
+/// tiles of images), so that they survive a restart. Each entry is a
+/// file in the directory named by its key; entries are written to a
+/// temporary file that is then renamed, so a partially written entry
+/// is never read.
+///
+/// When the total size of the entries exceeds the budget the least
+/// recently used are removed. The times of use are kept in memory,
+/// and recovered from the modification times of the files when the
+/// cache is opened; using an entry updates the modification time of
+/// its file.
+///
+/// Since keys are content hashes entries are never out of date; when
+/// a source changes the key of its derived artefacts changes, and the
+/// old entries are eventually evicted.
+#[derive(Debug)]
+pub struct DiskCache {
+    root: PathBuf,
+    budget: u64,
+    state: Mutex<DiskState>,
+    temp_count: AtomicUsize,
+}
+
+//ip DiskCache
+impl DiskCache {
+    //cp open
+    /// Open a cache in a directory, creating the directory if required,
+    /// and evicting entries if it exceeds the budget
+    pub fn open<P: AsRef<Path>>(root: P, budget: u64) -> std::io::Result<Self> {
+        let root = root.as_ref().to_owned();
+        std::fs::create_dir_all(&root)?;
+        let mut found = vec![];
+        for d in root.read_dir()? {
+            let d = d?;
+            let path = d.path();
+            if path.extension().is_none_or(|x| x != ENTRY_EXTENSION) {
+                // Remove any temporary files left by a failed write
+                if path.extension().is_some_and(|x| x == "tmp") {
+                    let _ = std::fs::remove_file(&path);
+                }
+                continue;
+            }
+            let Some(key) = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .and_then(ContentHash::of_hex)
+            else {
+                continue;
+            };
+            let metadata = d.metadata()?;
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            found.push((modified, key, metadata.len()));
+        }
+        found.sort();
+        let mut state = DiskState::default();
+        for (_, key, size) in found {
+            let last_use = state.use_count;
+            state.entries.insert(key, DiskEntry { size, last_use });
+            state.use_count += 1;
+            state.total_size += size;
+        }
+        let cache = Self {
+            root,
+            budget,
+            state: Mutex::new(state),
+            temp_count: AtomicUsize::new(0),
+        };
+        cache.evict_to(budget);
+        Ok(cache)
+    }
+
+    //ap root
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    //ap budget
+    pub fn budget(&self) -> u64 {
+        self.budget
+    }
+
+    //mi lock
+    fn lock(&self) -> std::sync::MutexGuard<'_, DiskState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    //mi path_of
+    fn path_of(&self, key: &ContentHash) -> PathBuf {
+        self.root.join(format!("{key}.{ENTRY_EXTENSION}"))
+    }
+
+    //ap stats
+    pub fn stats(&self) -> CacheStats {
+        let state = self.lock();
+        CacheStats {
+            total_size: state.total_size as usize,
+            num_entries: state.entries.len(),
+            ..state.stats
+        }
+    }
+
+    //mp contains
+    pub fn contains(&self, key: &ContentHash) -> bool {
+        self.lock().entries.contains_key(key)
+    }
+
+    //mp get
+    /// Read the entry for a key, if it is in the cache
+    ///
+    /// If the file for the entry cannot be read then the entry is
+    /// removed from the cache
+    pub fn get(&self, key: &ContentHash) -> Option<Vec<u8>> {
+        if !self.lock().touch(key) {
+            self.lock().stats.misses += 1;
+            return None;
+        }
+        let path = self.path_of(key);
+        match std::fs::read(&path) {
+            Ok(data) => {
+                // Recording the use in the file is not essential
+                if let Ok(file) = std::fs::File::options().append(true).open(&path) {
+                    let _ = file.set_modified(SystemTime::now());
+                }
+                self.lock().stats.hits += 1;
+                Some(data)
+            }
+            Err(_) => {
+                let mut state = self.lock();
+                if let Some(e) = state.entries.remove(key) {
+                    state.total_size -= e.size;
+                }
+                state.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    //mp insert
+    /// Write the entry for a key, replacing any existing entry, and
+    /// evict entries if the cache is then over budget
+    pub fn insert(&self, key: &ContentHash, data: &[u8]) -> std::io::Result<()> {
+        let n = self.temp_count.fetch_add(1, Ordering::Relaxed);
+        let temp_path = self
+            .root
+            .join(format!("{key}.{}.{n}.tmp", std::process::id()));
+        std::fs::write(&temp_path, data)?;
+        if let Err(e) = std::fs::rename(&temp_path, self.path_of(key)) {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(e);
+        }
+        let over_budget = {
+            let mut state = self.lock();
+            let size = data.len() as u64;
+            let last_use = state.use_count;
+            state.use_count += 1;
+            if let Some(e) = state.entries.insert(*key, DiskEntry { size, last_use }) {
+                state.total_size -= e.size;
+            }
+            state.total_size += size;
+            state.stats.insertions += 1;
+            state.total_size > self.budget
+        };
+        if over_budget {
+            self.evict_to(self.budget);
+        }
+        Ok(())
+    }
+
+    //mp remove
+    pub fn remove(&self, key: &ContentHash) -> bool {
+        let mut state = self.lock();
+        let Some(e) = state.entries.remove(key) else {
+            return false;
+        };
+        state.total_size -= e.size;
+        let _ = std::fs::remove_file(self.path_of(key));
+        true
+    }
+
+    //mp evict_to
+    /// Remove the least recently used entries until the total size is
+    /// at most `size`
+    pub fn evict_to(&self, size: u64) {
+        let mut state = self.lock();
+        if state.total_size <= size {
+            return;
+        }
+        let mut by_age: Vec<(usize, ContentHash)> = state
+            .entries
+            .iter()
+            .map(|(k, e)| (e.last_use, *k))
+            .collect();
+        by_age.sort();
+        for (_, key) in by_age {
+            if state.total_size <= size {
+                break;
+            }
+            let e = state.entries.remove(&key).unwrap();
+            state.total_size -= e.size;
+            state.stats.evictions += 1;
+            let _ = std::fs::remove_file(self.path_of(&key));
+        }
+    }
+
+    //zz All done
+}
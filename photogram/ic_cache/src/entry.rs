@@ -17,7 +17,7 @@ impl std::fmt::Debug for CacheRef {
     }
 }
 impl CacheRef {
-    fn ref_cnt(&self) -> usize {
+    pub(crate) fn ref_cnt(&self) -> usize {
         Arc::strong_count(&self.data)
     }
     #[inline]
@@ -62,6 +62,8 @@ impl std::convert::AsRef<Arc<dyn Cacheable>> for CacheRef {
 pub struct CacheEntry {
     data: Option<CacheRef>,
     last_use: usize,
+    /// Number of times the entry has been used since it was filled
+    use_count: usize,
     size: usize,
 }
 
@@ -75,6 +77,7 @@ impl CacheEntry {
         Self {
             data,
             last_use,
+            use_count: 1,
             size,
         }
     }
@@ -84,13 +87,19 @@ impl CacheEntry {
         self.last_use
     }
 
+    //ap use_count
+    pub fn use_count(&self) -> usize {
+        self.use_count
+    }
+
     //mp is_empty
     pub fn is_empty(&self) -> bool {
         self.data.is_none()
     }
 
     //mp can_empty
-    #[allow(dead_code)]
+    /// Return true if the entry is filled and there are no
+    /// outstanding [CacheRef]s to it
     pub fn can_empty(&self) -> bool {
         if let Some(rc_e) = self.data.as_ref() {
             rc_e.ref_cnt() == 1
@@ -117,6 +126,7 @@ impl CacheEntry {
     pub fn take_copy(&mut self, use_time: usize) -> Option<CacheRef> {
         if let Some(rc_e) = self.data.as_ref() {
             self.last_use = use_time;
+            self.use_count += 1;
             Some(rc_e.clone())
         } else {
             None
@@ -126,8 +136,10 @@ impl CacheEntry {
     //mp fill
     pub fn fill(&mut self, e: CacheRef, use_time: usize) -> Option<CacheRef> {
         if self.is_empty() {
+            self.size = e.size();
             self.data = Some(e);
             self.last_use = use_time;
+            self.use_count = 1;
             None
         } else {
             Some(e)
@@ -0,0 +1,141 @@
+//a Imports
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+//a Constants
+//ci FNV_OFFSET
+/// Offset basis of the 128-bit FNV-1a hash
+const FNV_OFFSET: u128 = 0x6c62272e07bb014262b821756295c58d;
+
+//ci FNV_PRIME
+/// Prime of the 128-bit FNV-1a hash
+const FNV_PRIME: u128 = 0x0000000001000000000000000000013b;
+
+//a ContentHash
+//tp ContentHash
+/// A 128-bit hash of some content, used as the key of a persistent
+/// cache entry
+///
+/// This is an FNV-1a hash, which (unlike the hashers of the standard
+/// library) is the same on every platform and in every release, so
+/// it can be used for data that outlives the process. It is not a
+/// cryptographic hash.
+///
+/// The key of a derived artefact is formed from the hash of its
+/// sources and the parameters of its derivation, using
+/// [ContentHash::with_str] and [ContentHash::with_hash]; if a source
+/// changes then so does the key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ContentHash(u128);
+
+//ip Default for ContentHash
+impl std::default::Default for ContentHash {
+    fn default() -> Self {
+        Self(FNV_OFFSET)
+    }
+}
+
+//ip Display for ContentHash
+impl std::fmt::Display for ContentHash {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{:032x}", self.0)
+    }
+}
+
+//ip ContentHash
+impl ContentHash {
+    //cp of_bytes
+    pub fn of_bytes(data: &[u8]) -> Self {
+        Self::default().with_bytes(data)
+    }
+
+    //cp of_file
+    /// Hash the contents of a file
+    pub fn of_file<P: AsRef<Path>>(path: P) -> std::io::Result<Self> {
+        let mut file = std::fs::File::open(path)?;
+        let mut hash = Self::default();
+        let mut buffer = vec![0_u8; 65536];
+        loop {
+            let n = file.read(&mut buffer)?;
+            if n == 0 {
+                return Ok(hash);
+            }
+            hash = hash.with_bytes(&buffer[0..n]);
+        }
+    }
+
+    //cp of_hex
+    /// Parse a hash from the form produced by Display, which is exactly
+    /// 32 hex digits
+    pub fn of_hex(s: &str) -> Option<Self> {
+        if s.len() != 32 || !s.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        u128::from_str_radix(s, 16).ok().map(Self)
+    }
+
+    //cp with_bytes
+    pub fn with_bytes(mut self, data: &[u8]) -> Self {
+        for b in data {
+            self.0 ^= *b as u128;
+            self.0 = self.0.wrapping_mul(FNV_PRIME);
+        }
+        self
+    }
+
+    //cp with_str
+    /// Add a string to the hash; the length is included so that
+    /// successive strings are not ambiguous
+    pub fn with_str(self, s: &str) -> Self {
+        self.with_bytes(&(s.len() as u64).to_le_bytes())
+            .with_bytes(s.as_bytes())
+    }
+
+    //cp with_hash
+    pub fn with_hash(self, other: &ContentHash) -> Self {
+        self.with_bytes(&other.0.to_le_bytes())
+    }
+}
+
+//a FileHashes
+//ti FileRecord
+/// The size, modification time and content hash of a file
+type FileRecord = (u64, Option<SystemTime>, ContentHash);
+
+//tp FileHashes
+/// A record of the content hashes of files, so that a file is only
+/// hashed again if its size or modification time changes
+#[derive(Debug, Default)]
+pub struct FileHashes {
+    hashes: Mutex<HashMap<PathBuf, FileRecord>>,
+}
+
+//ip FileHashes
+impl FileHashes {
+    //mp hash_of
+    /// Get the content hash of a file
+    pub fn hash_of<P: AsRef<Path>>(&self, path: P) -> std::io::Result<ContentHash> {
+        let path = path.as_ref();
+        let metadata = std::fs::metadata(path)?;
+        let len = metadata.len();
+        let modified = metadata.modified().ok();
+        let known = self.lock().get(path).copied();
+        if let Some((_, _, hash)) =
+            known.filter(|(l, m, _)| *l == len && *m == modified && modified.is_some())
+        {
+            return Ok(hash);
+        }
+        // Do not hold the lock while the file is read
+        let hash = ContentHash::of_file(path)?;
+        self.lock().insert(path.to_owned(), (len, modified, hash));
+        Ok(hash)
+    }
+
+    //mi lock
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, FileRecord>> {
+        self.hashes.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
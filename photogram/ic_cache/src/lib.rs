@@ -7,4 +7,13 @@ pub(crate) use entry::CacheEntry;
 pub use entry::CacheRef;
 
 mod cache;
-pub use cache::Cache;
+pub use cache::{Cache, CacheStats, EvictionPolicy};
+
+mod shared;
+pub use shared::SharedCache;
+
+mod hash;
+pub use hash::{ContentHash, FileHashes};
+
+mod disk;
+pub use disk::DiskCache;
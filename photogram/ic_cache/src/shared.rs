@@ -0,0 +1,233 @@
+//a Imports
+use std::borrow::Borrow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::sync::{Condvar, Mutex, MutexGuard};
+
+use crate::{Cache, CacheRef, CacheStats, Cacheable, EvictionPolicy};
+
+//a Constants
+//ci DEFAULT_NUM_SHARDS
+/// Default number of shards of a [SharedCache]
+const DEFAULT_NUM_SHARDS: usize = 16;
+
+//a Shard
+//ti Shard
+/// A part of a [SharedCache], holding the entries for a subset of the
+/// keys
+#[derive(Debug)]
+struct Shard<Key>
+where
+    Key: Hash + Ord + Sized + Eq + 'static,
+{
+    cache: Mutex<ShardState<Key>>,
+    /// Notified when a key is no longer being created
+    created: Condvar,
+}
+
+//ti ShardState
+#[derive(Debug)]
+struct ShardState<Key>
+where
+    Key: Hash + Ord + Sized + Eq + 'static,
+{
+    cache: Cache<Key>,
+    /// Keys whose entries are being created by a thread
+    creating: HashSet<Key>,
+}
+
+//ti Creating
+/// A guard for a key whose entry is being created by a thread
+struct Creating<'a, Key>
+where
+    Key: Hash + Ord + Sized + Eq + 'static,
+{
+    shard: &'a Shard<Key>,
+    key: &'a Key,
+}
+
+//ii Drop for Creating
+impl<Key> Drop for Creating<'_, Key>
+where
+    Key: Hash + Ord + Sized + Eq + 'static,
+{
+    fn drop(&mut self) {
+        let mut state = self.shard.cache.lock().unwrap_or_else(|e| e.into_inner());
+        state.creating.remove(self.key);
+        self.shard.created.notify_all();
+    }
+}
+
+//a SharedCache
+//tp SharedCache
+/// A cache that may be shared between threads
+///
+/// The keys are split between a number of shards, each a [Cache]
+/// with its own lock (and a share of the budget), so that threads
+/// using different keys rarely contend for a lock.
+///
+/// Values that are expensive to create should be created with
+/// [SharedCache::get_or_insert_with], which creates a value at most
+/// once even if many threads request it at the same time, and which
+/// does not hold a lock while the value is created.
+#[derive(Debug)]
+pub struct SharedCache<Key>
+where
+    Key: Hash + Ord + Sized + Eq + 'static,
+{
+    shards: Vec<Shard<Key>>,
+}
+
+//ip Default for SharedCache
+impl<Key> std::default::Default for SharedCache<Key>
+where
+    Key: Hash + Ord + Sized + Eq + Clone + 'static,
+{
+    fn default() -> Self {
+        Self::new(DEFAULT_NUM_SHARDS, None, EvictionPolicy::default())
+    }
+}
+
+//ip SharedCache
+impl<Key> SharedCache<Key>
+where
+    Key: Hash + Ord + Sized + Eq + Clone + 'static,
+{
+    //cp new
+    /// Create a cache with a number of shards, and an optional budget
+    /// for the total size of the entries that is split evenly between
+    /// the shards
+    pub fn new(num_shards: usize, budget: Option<usize>, policy: EvictionPolicy) -> Self {
+        let num_shards = num_shards.max(1);
+        let shards = (0..num_shards)
+            .map(|_| {
+                let mut cache = Cache::default().with_policy(policy);
+                if let Some(budget) = budget {
+                    cache = cache.with_budget(budget / num_shards);
+                }
+                Shard {
+                    cache: Mutex::new(ShardState {
+                        cache,
+                        creating: HashSet::new(),
+                    }),
+                    created: Condvar::new(),
+                }
+            })
+            .collect();
+        Self { shards }
+    }
+
+    //mi shard
+    fn shard<Q>(&self, k: &Q) -> &Shard<Key>
+    where
+        Q: Hash + ?Sized,
+    {
+        let mut hasher = DefaultHasher::new();
+        k.hash(&mut hasher);
+        &self.shards[(hasher.finish() as usize) % self.shards.len()]
+    }
+
+    //mi lock
+    fn lock(shard: &Shard<Key>) -> MutexGuard<'_, ShardState<Key>> {
+        // A panic while the lock was held cannot leave a cache that is
+        // not usable
+        shard.cache.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    //ap stats
+    /// Get the statistics summed over all the shards
+    pub fn stats(&self) -> CacheStats {
+        let mut stats = CacheStats::default();
+        for s in &self.shards {
+            stats += Self::lock(s).cache.stats();
+        }
+        stats
+    }
+
+    //mp contains
+    pub fn contains<Q>(&self, k: &Q) -> bool
+    where
+        Key: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Self::lock(self.shard(k)).cache.contains(k)
+    }
+
+    //mp get
+    pub fn get<Q>(&self, k: &Q) -> Option<CacheRef>
+    where
+        Key: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        Self::lock(self.shard(k)).cache.get(k)
+    }
+
+    //mp insert
+    /// Insert an entry, returning a reference to the entry for the key
+    ///
+    /// If there is already an entry for the key then the new value is
+    /// dropped and the existing entry is returned
+    pub fn insert<C: Cacheable>(&self, k: Key, e: C) -> CacheRef {
+        let mut state = Self::lock(self.shard(&k));
+        state.cache.insert_and_get(k, e)
+    }
+
+    //mp get_or_insert_with
+    /// Get the entry for a key, creating it if it is not in the cache
+    ///
+    /// The shard of the key is not locked while the entry is created,
+    /// so `create` may use the cache; other threads requesting the
+    /// same key wait for it to be created rather than creating it
+    /// again. If `create` fails then the error is returned, and
+    /// another waiting thread will attempt to create the entry.
+    ///
+    /// Each call counts as one hit, or one miss if the entry is created
+    pub fn get_or_insert_with<C, E, F>(&self, k: &Key, create: F) -> Result<CacheRef, E>
+    where
+        C: Cacheable,
+        F: FnOnce() -> Result<C, E>,
+    {
+        let shard = self.shard(k);
+        let mut state = Self::lock(shard);
+        while state.creating.contains(k) {
+            state = shard.created.wait(state).unwrap_or_else(|e| e.into_inner());
+        }
+        if let Some(cr) = state.cache.get(k) {
+            return Ok(cr);
+        }
+        state.creating.insert(k.clone());
+        drop(state);
+
+        // The guard marks the key as no longer being created when
+        // dropped, even if 'create' panics
+        let _creating = Creating { shard, key: k };
+        let e = create()?;
+        let mut state = Self::lock(shard);
+        Ok(state.cache.insert_and_get(k.clone(), e))
+    }
+
+    //mp shrink_to
+    /// Evict entries until the total size is below `size` if
+    /// possible, splitting `size` evenly between the shards
+    ///
+    /// Returns true if every shard is then within its share
+    pub fn shrink_to(&self, size: usize) -> bool {
+        let shard_size = size / self.shards.len();
+        let mut okay = true;
+        for s in &self.shards {
+            okay &= Self::lock(s).cache.shrink_to(shard_size);
+        }
+        okay
+    }
+
+    //ap total_size
+    pub fn total_size(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|s| Self::lock(s).cache.total_size())
+            .sum()
+    }
+
+    //zz All done
+}
@@ -141,3 +141,143 @@ fn test_cache() -> Result<(), ()> {
     assert!(!cache.contains("3"), "Should not contain 3");
     Ok(())
 }
+
+//tp test_budget
+#[test]
+fn test_budget() {
+    let mut cache = Cache::default().with_budget(100);
+    for i in 0..10 {
+        assert!(cache.insert(i, CacheThing::huge(20)).is_none());
+    }
+    // Only the last five fit in the budget
+    assert_eq!(cache.total_size(), 100);
+    assert!(!cache.contains(&4));
+    assert!(cache.contains(&5));
+
+    // Using 5 makes 6 the least recently used
+    let five = cache.get(&5).unwrap();
+    cache.insert(10, CacheThing::huge(20));
+    assert!(cache.contains(&5));
+    assert!(!cache.contains(&6));
+
+    // Entries in use are not evicted, even to keep to the budget
+    cache.insert(11, CacheThing::huge(90));
+    assert!(cache.contains(&5));
+    assert!(cache.contains(&11));
+    assert_eq!(cache.total_size(), 110);
+    drop(five);
+    assert!(cache.shrink_to(100));
+    assert!(!cache.contains(&5));
+
+    let stats = cache.stats();
+    assert_eq!(stats.insertions, 12);
+    assert_eq!(stats.evictions, 11);
+    assert_eq!(stats.hits, 1);
+    assert_eq!(stats.num_entries, 1);
+}
+
+//tp test_lfu
+#[test]
+fn test_lfu() {
+    use ic_cache::EvictionPolicy;
+
+    let mut cache = Cache::default()
+        .with_budget(60)
+        .with_policy(EvictionPolicy::Lfu);
+    for i in 0..3 {
+        cache.insert(i, CacheThing::huge(20));
+    }
+    for _ in 0..3 {
+        cache.get(&0);
+    }
+    cache.get(&1);
+    assert!(cache.get(&7).is_none());
+    // 2 is the least frequently used, although 0 and 1 are older
+    cache.insert(3, CacheThing::huge(20));
+    assert!(cache.contains(&0));
+    assert!(cache.contains(&1));
+    assert!(!cache.contains(&2));
+    let stats = cache.stats();
+    assert_eq!((stats.hits, stats.misses, stats.evictions), (4, 1, 1));
+}
+
+//tp test_shared
+#[test]
+fn test_shared() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use ic_cache::{EvictionPolicy, SharedCache};
+
+    let cache: Arc<SharedCache<usize>> = Arc::new(SharedCache::new(4, None, EvictionPolicy::Lru));
+    let created = Arc::new(AtomicUsize::new(0));
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let cache = cache.clone();
+            let created = created.clone();
+            std::thread::spawn(move || {
+                for k in 0..16 {
+                    let cr = cache
+                        .get_or_insert_with(&k, || {
+                            created.fetch_add(1, Ordering::Relaxed);
+                            std::thread::sleep(std::time::Duration::from_millis(1));
+                            Ok::<_, ()>(CacheThing::int(k))
+                        })
+                        .unwrap();
+                    assert_eq!(cr.downcast::<CacheThing>().unwrap().thing, Thing::Int(k));
+                }
+            })
+        })
+        .collect();
+    for t in threads {
+        t.join().unwrap();
+    }
+    // Each entry is created exactly once
+    assert_eq!(created.load(Ordering::Relaxed), 16);
+    let stats = cache.stats();
+    assert_eq!(stats.num_entries, 16);
+    assert_eq!(stats.total_size, 16 * 8);
+    // Each entry is a miss for the thread that created it, and a hit
+    // for every other thread
+    assert_eq!((stats.hits, stats.misses), (7 * 16, 16));
+    assert_eq!(
+        cache
+            .get_or_insert_with(&99, || Err::<CacheThing, _>("failed"))
+            .unwrap_err(),
+        "failed"
+    );
+    assert!(!cache.contains(&99));
+}
+
+//tp test_shared_stats
+#[test]
+fn test_shared_stats() {
+    use ic_cache::{EvictionPolicy, SharedCache};
+
+    let counts = |cache: &SharedCache<usize>| {
+        let stats = cache.stats();
+        (stats.hits, stats.misses, stats.insertions)
+    };
+    let cache: SharedCache<usize> = SharedCache::new(4, None, EvictionPolicy::Lru);
+
+    // Inserting is neither a hit nor a miss
+    let cr = cache.insert(1, CacheThing::int(1));
+    assert_eq!(cr.downcast::<CacheThing>().unwrap().thing, Thing::Int(1));
+    assert_eq!(counts(&cache), (0, 0, 1));
+    let cr = cache.insert(1, CacheThing::int(2));
+    assert_eq!(cr.downcast::<CacheThing>().unwrap().thing, Thing::Int(1));
+    assert_eq!(counts(&cache), (0, 0, 1));
+
+    assert!(cache.get(&1).is_some());
+    assert!(cache.get(&2).is_none());
+    assert_eq!(counts(&cache), (1, 1, 1));
+
+    // Creating an entry is one miss, and getting it again one hit
+    for _ in 0..2 {
+        let cr = cache
+            .get_or_insert_with(&3, || Ok::<_, ()>(CacheThing::int(3)))
+            .unwrap();
+        assert_eq!(cr.downcast::<CacheThing>().unwrap().thing, Thing::Int(3));
+    }
+    assert_eq!(counts(&cache), (2, 2, 2));
+}
@@ -0,0 +1,73 @@
+//a Imports
+use ic_cache::{ContentHash, DiskCache, FileHashes};
+
+//fi temp_dir
+fn temp_dir(name: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("ic_cache_{name}_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+//a Tests
+//tp test_hash
+#[test]
+fn test_hash() {
+    let h = ContentHash::of_bytes(b"hello");
+    assert_eq!(h, ContentHash::of_bytes(b"hello"));
+    assert_ne!(h, ContentHash::of_bytes(b"hellp"));
+    assert_eq!(ContentHash::of_hex(&h.to_string()), Some(h));
+    // Only exactly 32 hex digits are a hash
+    let hex = "0123456789abcdef0123456789ABCDEF";
+    assert!(ContentHash::of_hex(hex).is_some());
+    assert!(ContentHash::of_hex(&hex[1..]).is_none());
+    assert!(ContentHash::of_hex(&format!("+{}", &hex[1..])).is_none());
+    assert!(ContentHash::of_hex(&format!("-{}", &hex[1..])).is_none());
+    assert!(ContentHash::of_hex(&format!("{}g", &hex[1..])).is_none());
+    // Successive strings are not ambiguous
+    assert_ne!(
+        h.with_str("ab").with_str("c"),
+        h.with_str("a").with_str("bc")
+    );
+
+    let dir = temp_dir("hash");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("source");
+    std::fs::write(&path, b"hello").unwrap();
+    let hashes = FileHashes::default();
+    assert_eq!(hashes.hash_of(&path).unwrap(), h);
+    std::fs::write(&path, b"hello world").unwrap();
+    assert_eq!(
+        hashes.hash_of(&path).unwrap(),
+        ContentHash::of_bytes(b"hello world")
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+//tp test_disk
+#[test]
+fn test_disk() {
+    let dir = temp_dir("disk");
+    let keys: Vec<_> = (0..4_u8).map(|i| ContentHash::of_bytes(&[i])).collect();
+    {
+        let cache = DiskCache::open(&dir, 250).unwrap();
+        assert!(cache.get(&keys[0]).is_none());
+        cache.insert(&keys[0], &[0; 100]).unwrap();
+        cache.insert(&keys[1], &[1; 100]).unwrap();
+        assert_eq!(cache.get(&keys[0]), Some(vec![0; 100]));
+        // keys[1] is least recently used
+        cache.insert(&keys[2], &[2; 100]).unwrap();
+        assert!(cache.contains(&keys[0]));
+        assert!(!cache.contains(&keys[1]));
+        assert!(cache.contains(&keys[2]));
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses), (1, 1));
+        assert_eq!((stats.insertions, stats.evictions), (3, 1));
+        assert_eq!((stats.num_entries, stats.total_size), (2, 200));
+    }
+    // Entries persist, and a smaller budget evicts on opening
+    let cache = DiskCache::open(&dir, 150).unwrap();
+    assert_eq!(cache.stats().num_entries, 1);
+    assert!(cache.remove(&keys[2]) || cache.remove(&keys[0]));
+    assert_eq!(cache.stats().total_size, 0);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -1,4 +1,5 @@
 //a Imports
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use ic_base::Point2D;
@@ -18,7 +19,7 @@ pub fn serialize_body_name<S: serde::Serializer>(
 //tp CameraBody
 /// A rectangular camera sensor
 ////// This provides an implementation of [CameraSensor], which allows mapping from a known point on an image (captured by the sensor) to relative positions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CameraBody {
     /// Name
     name: String,
@@ -1,7 +1,7 @@
 //a Imports
 use serde::{Deserialize, Serialize};
 
-use ic_base::json;
+use ic_base::schema::{self, Versioned};
 use ic_base::{Point2D, Point3D, Result, TanXTanY};
 
 use crate::{CameraInstance, CameraProjection};
@@ -41,6 +41,23 @@ impl<'de> Deserialize<'de> for CalibrationMapping {
     }
 }
 
+//ip JsonSchema for CalibrationMapping
+impl schemars::JsonSchema for CalibrationMapping {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "CalibrationMapping".into()
+    }
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        <Vec<(f64, f64, f64, usize, usize)>>::json_schema(generator)
+    }
+}
+
+//ip Versioned for CalibrationMapping
+impl Versioned for CalibrationMapping {
+    const SCHEMA_NAME: &'static str = "calibration_mapping";
+    const SCHEMA_VERSION: usize = 1;
+    const CONTENTS_KEY: Option<&'static str> = Some("mappings");
+}
+
 //ip CalibrationMapping
 impl CalibrationMapping {
     //cp new
@@ -56,16 +73,12 @@ impl CalibrationMapping {
 
     //cp from_json
     pub fn from_json(json: &str) -> Result<Self> {
-        json::from_json("calibration mapping", json)
+        schema::from_json(json)
     }
 
     //mp to_json
     pub fn to_json(&self, pretty: bool) -> Result<String> {
-        if pretty {
-            Ok(serde_json::to_string_pretty(self)?)
-        } else {
-            Ok(serde_json::to_string(self)?)
-        }
+        schema::to_json(self, pretty)
     }
 
     //ap len
@@ -1,14 +1,16 @@
 //a Imports
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use ic_base::{json, Error, Result};
+use ic_base::schema::{self, Versioned};
+use ic_base::{Error, Result};
 
 use crate::{CameraBody, CameraLens, CameraSensor};
 
 //a CameraDatabase
 //tp CameraDatabase
 /// A database of camera bodies and lenses
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CameraDatabase {
     bodies: Vec<CameraBody>,
     lenses: Vec<CameraLens>,
@@ -29,18 +31,24 @@ impl std::fmt::Display for CameraDatabase {
     }
 }
 
+//ip Versioned for CameraDatabase
+impl Versioned for CameraDatabase {
+    const SCHEMA_NAME: &'static str = "camera_database";
+    const SCHEMA_VERSION: usize = 1;
+}
+
 //ip CameraDatabase
 impl CameraDatabase {
     //cp from_json
     pub fn from_json(json: &str) -> Result<Self> {
-        let mut cdb: Self = json::from_json("camera database", json)?;
+        let mut cdb: Self = schema::from_json(json)?;
         cdb.derive();
         Ok(cdb)
     }
 
     //mp to_json
     pub fn to_json(&self) -> Result<String> {
-        Ok(serde_json::to_string(self)?)
+        schema::to_json(self, false)
     }
 
     //mp derive
@@ -3,7 +3,7 @@ use serde::Serialize;
 
 use geo_nd::quat;
 
-use ic_base::json;
+use ic_base::schema;
 use ic_base::{Point2D, Point3D, Quat, Result, RollYaw, TanXTanY};
 
 use crate::{serialize_body_name, serialize_lens_name};
@@ -141,7 +141,7 @@ impl CameraInstance {
 
     //cp from_json`
     pub fn from_json(cdb: &CameraDatabase, json: &str) -> Result<Self> {
-        let desc: CameraInstanceDesc = json::from_json("camera instance descriptor", json)?;
+        let desc = CameraInstanceDesc::from_json(json)?;
         Self::from_desc(cdb, desc)
     }
 
@@ -162,12 +162,10 @@ impl CameraInstance {
     }
 
     //fp to_json
+    /// Write the camera as a camera instance descriptor file (which
+    /// includes the derived lens-sensor distance)
     pub fn to_json(&self, pretty: bool) -> Result<String> {
-        if pretty {
-            Ok(serde_json::to_string_pretty(self)?)
-        } else {
-            Ok(serde_json::to_string(self)?)
-        }
+        schema::to_json_as::<CameraInstanceDesc, _>(self, pretty)
     }
 }
 
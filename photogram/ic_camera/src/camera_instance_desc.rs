@@ -1,16 +1,19 @@
 //a Imports
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use geo_nd::quat;
 
-use ic_base::json;
+use ic_base::schema::{self, Versioned};
 use ic_base::{Point3D, Quat, Result};
 
 use crate::utils;
 
 //a CameraInstanceDesc
 //tp CameraInstanceDesc
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+///
+/// This is the form in which a camera is stored in a JSON file
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
 pub struct CameraInstanceDesc {
     /// Name of the camera body
     body: String,
@@ -19,9 +22,11 @@ pub struct CameraInstanceDesc {
     /// The distance the lens if focussed on - make it 1E6*mm_focal_length  for infinity
     mm_focus_distance: f64,
     /// Position in world coordinates of the camera
+    #[schemars(with = "[f64; 3]")]
     position: Point3D,
     /// Orientation to be applied to camera-relative world coordinates
     /// to convert to camera-space coordinates
+    #[schemars(with = "[f64; 4]")]
     orientation: Quat,
 }
 
@@ -39,6 +44,12 @@ impl std::fmt::Display for CameraInstanceDesc {
     }
 }
 
+//ip Versioned for CameraInstanceDesc
+impl Versioned for CameraInstanceDesc {
+    const SCHEMA_NAME: &'static str = "camera_instance";
+    const SCHEMA_VERSION: usize = 1;
+}
+
 //ip CameraInstanceDesc - Accessors
 impl CameraInstanceDesc {
     //ap lens
@@ -99,12 +110,12 @@ impl CameraInstanceDesc {
 
     //cp from_json`
     pub fn from_json(json: &str) -> Result<Self> {
-        json::from_json("camera instance descriptor", json)
+        schema::from_json(json)
     }
 
     //dp to_json
     pub fn to_json(self) -> Result<String> {
-        Ok(serde_json::to_string_pretty(&self)?)
+        schema::to_json(&self, true)
     }
 }
 
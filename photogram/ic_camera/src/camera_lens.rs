@@ -104,9 +104,10 @@
 !*/
 
 //a Imports
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use ic_base::json;
+use ic_base::schema::{self, Versioned};
 use ic_base::{Error, Result};
 
 use crate::polynomial;
@@ -259,7 +260,7 @@ pub const LP_ORTHOGRAPHIC: ([f64; 9], [f64; 9]) = (
 ///    r0 = p1-1, r1 = p3, r2 = p5, r3 = p7, ...
 ///
 /// The calibration could take advantage of this
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct LensPolys {
     /// Function of fractional X-offset (0 center, 1 RH of sensor) to angle
     ///
@@ -291,6 +292,12 @@ impl std::fmt::Display for LensPolys {
     }
 }
 
+//ip Versioned for LensPolys
+impl Versioned for LensPolys {
+    const SCHEMA_NAME: &'static str = "lens_polys";
+    const SCHEMA_VERSION: usize = 1;
+}
+
 //ip LensPolys
 impl LensPolys {
     //cp stereographic
@@ -328,16 +335,12 @@ impl LensPolys {
 
     //cp from_json`
     pub fn from_json(json: &str) -> Result<Self> {
-        json::from_json("lens polynomials", json)
+        schema::from_json(json)
     }
 
     //mp to_json
     pub fn to_json(&self, pretty: bool) -> Result<String> {
-        if pretty {
-            Ok(serde_json::to_string_pretty(self)?)
-        } else {
-            Ok(serde_json::to_string(self)?)
-        }
+        schema::to_json(self, pretty)
     }
 
     //cp set_stw_poly
@@ -487,7 +490,7 @@ impl LensPolys {
 /// Hence mm_focal_length =  S / (2tan(N/2)) = 2.1515mm
 ///
 /// e.g. for N=55 degrees, S=2.24mm we have mm_focal_length =
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct CameraLens {
     /// Name
     name: String,
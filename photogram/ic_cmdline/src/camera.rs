@@ -177,7 +177,7 @@ pub fn add_arg_camera<C, F, G, H>(
         Box::new(move |args, matches| {
             let polys = matches.get_one::<String>("use_polys").unwrap();
             let json = json::read_file(polys)?;
-            let lens_polys: LensPolys = json::from_json("lens polynomials", &json)?;
+            let lens_polys = LensPolys::from_json(&json)?;
             let camera = borrow_mut(args);
             let mut lens = camera.lens().clone();
             lens.set_polys(lens_polys);
@@ -204,9 +204,7 @@ where
 pub fn get_camera_database(matches: &ArgMatches) -> Result<CameraDatabase> {
     let camera_db_filename = matches.get_one::<String>("camera_db").unwrap();
     let camera_db_json = json::read_file(camera_db_filename)?;
-    let mut camera_db: CameraDatabase = json::from_json("camera database", &camera_db_json)?;
-    camera_db.derive();
-    Ok(camera_db)
+    CameraDatabase::from_json(&camera_db_json)
 }
 
 //fp set_opt_camera_database
@@ -1,5 +1,5 @@
 //a Modules
-use clap::{Arg, ArgAction, ArgMatches, Command};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, Command};
 
 //a File system
 //fp add_file_root_arg
@@ -17,7 +17,10 @@ pub fn add_file_root_arg(cmd: Command, required: bool) -> Command {
 
 //fp get_file_root
 pub fn get_file_root(matches: &ArgMatches) -> Result<String, String> {
-    let file_root = matches.get_one::<String>("file_root").unwrap().to_owned();
+    let file_root = matches
+        .get_one::<String>("file_root")
+        .ok_or("No file root given")?
+        .to_owned();
     Ok(file_root)
 }
 
@@ -36,7 +39,10 @@ pub fn add_image_root_arg(cmd: Command, required: bool) -> Command {
 
 //fp get_image_root
 pub fn get_image_root(matches: &ArgMatches) -> Result<String, String> {
-    let image_root = matches.get_one::<String>("image_root").unwrap().to_owned();
+    let image_root = matches
+        .get_one::<String>("image_root")
+        .ok_or("No image root given")?
+        .to_owned();
     Ok(image_root)
 }
 
@@ -57,7 +63,95 @@ pub fn add_project_root_arg(cmd: Command, required: bool) -> Command {
 pub fn get_project_root(matches: &ArgMatches) -> Result<String, String> {
     let project_root = matches
         .get_one::<String>("project_root")
-        .unwrap()
+        .ok_or("No project root given")?
         .to_owned();
     Ok(project_root)
 }
+
+//fp add_cache_dir_arg
+pub fn add_cache_dir_arg(cmd: Command, required: bool) -> Command {
+    cmd.arg(
+        Arg::new("cache_dir")
+            .long("cache_dir")
+            .required(required)
+            .help("Directory for the persistent cache of derived images")
+            .long_help(
+                "Directory in which derived images (such as thumbnails and tiles) are kept so that they survive a restart; if not given then derived images are only cached in memory",
+            )
+            .action(ArgAction::Set),
+    )
+}
+
+//fp get_cache_dir
+pub fn get_cache_dir(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.get_one::<String>("cache_dir").cloned())
+}
+
+//fp add_cache_size_arg
+pub fn add_cache_size_arg(cmd: Command) -> Command {
+    cmd.arg(
+        Arg::new("cache_size")
+            .long("cache_size")
+            .default_value("1024")
+            .help("Maximum size in MB of the persistent cache")
+            .value_parser(value_parser!(u64))
+            .action(ArgAction::Set),
+    )
+}
+
+//fp get_cache_size
+/// Get the maximum size of the persistent cache in bytes
+pub fn get_cache_size(matches: &ArgMatches) -> Result<u64, String> {
+    let cache_size = *matches.get_one::<u64>("cache_size").unwrap();
+    Ok(cache_size << 20)
+}
+
+//fp add_config_arg
+pub fn add_config_arg(cmd: Command) -> Command {
+    cmd.arg(
+        Arg::new("config")
+            .long("config")
+            .help("JSON configuration file")
+            .long_help(
+                "JSON file providing the configuration; options given on the command line override those in the file",
+            )
+            .action(ArgAction::Set),
+    )
+}
+
+//fp get_config
+pub fn get_config(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.get_one::<String>("config").cloned())
+}
+
+//fp add_tls_args
+pub fn add_tls_args(cmd: Command) -> Command {
+    cmd.arg(
+        Arg::new("tls_cert")
+            .long("tls_cert")
+            .requires("tls_key")
+            .help("PEM file of the TLS certificate chain")
+            .long_help("PEM file of the certificate chain for the server; if given (with a key) then the server uses HTTPS")
+            .action(ArgAction::Set),
+    )
+    .arg(
+        Arg::new("tls_key")
+            .long("tls_key")
+            .requires("tls_cert")
+            .help("PEM file of the TLS private key")
+            .action(ArgAction::Set),
+    )
+}
+
+//fp get_tls_files
+/// Get the certificate chain and private key files for TLS, if given
+pub fn get_tls_files(matches: &ArgMatches) -> Result<Option<(String, String)>, String> {
+    match (
+        matches.get_one::<String>("tls_cert"),
+        matches.get_one::<String>("tls_key"),
+    ) {
+        (Some(cert), Some(key)) => Ok(Some((cert.clone(), key.clone()))),
+        (None, None) => Ok(None),
+        _ => Err("Both a TLS certificate and key must be given".into()),
+    }
+}
@@ -132,3 +132,32 @@ pub fn get_flags(matches: &ArgMatches) -> Result<usize, String> {
     let flags = *matches.get_one::<usize>("kernel_flags").unwrap();
     Ok(flags)
 }
+
+//fp add_accelerator_args
+pub fn add_accelerator_args(cmd: Command) -> Command {
+    cmd.arg(
+        Arg::new("accelerator")
+            .long("accelerator")
+            .help("Accelerator for kernels (auto, cpu, wgpu, vulkan, metal, dx12, gl or software)")
+            .action(ArgAction::Set),
+    )
+    .arg(
+        Arg::new("shader_path")
+            .long("shader_path")
+            .help("Add a directory to search for kernel shaders (before the embedded shaders)")
+            .action(ArgAction::Append),
+    )
+}
+
+//fp get_accelerator
+pub fn get_accelerator(matches: &ArgMatches) -> Result<Option<String>, String> {
+    Ok(matches.get_one::<String>("accelerator").cloned())
+}
+
+//fp get_shader_paths
+pub fn get_shader_paths(matches: &ArgMatches) -> Result<Vec<String>, String> {
+    Ok(matches
+        .get_many::<String>("shader_path")
+        .map(|paths| paths.cloned().collect())
+        .unwrap_or_default())
+}
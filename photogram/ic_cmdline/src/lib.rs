@@ -49,6 +49,13 @@ pub fn get_error_fn(
 }
 
 //a General
+//fp is_given
+/// Return true if an argument was given on the command line, rather
+/// than taking its default value (if any)
+pub fn is_given(matches: &ArgMatches, id: &str) -> bool {
+    matches.value_source(id) == Some(clap::parser::ValueSource::CommandLine)
+}
+
 //fp add_verbose_arg
 pub fn add_verbose_arg(cmd: Command) -> Command {
     cmd.arg(
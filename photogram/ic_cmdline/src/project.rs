@@ -23,8 +23,7 @@ pub fn add_project_arg(cmd: Command, required: bool) -> Command {
 pub fn get_project(matches: &ArgMatches) -> Result<Project> {
     if let Some(project_filename) = matches.get_one::<String>("project") {
         let project_json = json::read_file(project_filename)?;
-        let project: Project = json::from_json("project", &project_json)?;
-        Ok(project)
+        Project::from_json(&project_json)
     } else {
         let mut project = Project::default();
         let cdb = camera::get_camera_database(matches)?;
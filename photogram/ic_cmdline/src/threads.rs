@@ -30,6 +30,22 @@ pub fn add_port_arg(cmd: Command) -> Command {
     )
 }
 
+//fp add_bind_arg
+pub fn add_bind_arg(cmd: Command) -> Command {
+    cmd.arg(
+        Arg::new("bind")
+            .long("bind")
+            .short('b')
+            .default_value("127.0.0.1")
+            .help("Address to listen on")
+            .long_help(
+                "The IP address of the interface for the server to listen on; 0.0.0.0 listens on all IPv4 interfaces",
+            )
+            .value_parser(value_parser!(std::net::IpAddr))
+            .action(ArgAction::Set),
+    )
+}
+
 //fp get_threads
 pub fn get_threads(matches: &ArgMatches) -> Result<usize, String> {
     let num_threads = *matches.get_one::<usize>("threads").unwrap();
@@ -41,3 +57,9 @@ pub fn get_port(matches: &ArgMatches) -> Result<usize, String> {
     let port = *matches.get_one::<usize>("port").unwrap();
     Ok(port)
 }
+
+//fp get_bind
+pub fn get_bind(matches: &ArgMatches) -> Result<std::net::IpAddr, String> {
+    let bind = *matches.get_one::<std::net::IpAddr>("bind").unwrap();
+    Ok(bind)
+}
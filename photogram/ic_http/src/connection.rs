@@ -0,0 +1,183 @@
+//a Imports
+use std::io::{Read, Write};
+
+use crate::HttpRequest;
+
+//a Constants
+//ci MAX_HEADER_SIZE
+/// Maximum size of the header of a request
+const MAX_HEADER_SIZE: usize = 64 * 1024;
+
+//ci MAX_LINE_SIZE
+/// Maximum size of a chunk size or trailer line of a chunked body
+const MAX_LINE_SIZE: usize = 4096;
+
+//a ConnectionError
+//tp ConnectionError
+/// Reason for abandoning a connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionError {
+    /// The connection was closed, timed out or failed; no response
+    /// can be sent
+    Closed,
+    /// The request was bad; a response with the status code should be
+    /// sent before closing the connection
+    Reply(u16),
+}
+
+//a HttpConnection
+//tp HttpConnection
+/// A connection to a client, from which requests are read
+///
+/// Bytes that have been read beyond the end of a request are kept
+/// for the next request, so that pipelined requests are handled
+pub struct HttpConnection<S: Read + Write> {
+    stream: S,
+    pending: Vec<u8>,
+}
+
+//ip HttpConnection
+impl<S: Read + Write> HttpConnection<S> {
+    //cp new
+    pub fn new(stream: S) -> Self {
+        Self {
+            stream,
+            pending: vec![],
+        }
+    }
+
+    //ap stream
+    pub fn stream(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    //mi fill
+    /// Read more data from the stream into the pending buffer
+    ///
+    /// Returns Closed if the stream is at its end or has failed
+    /// (including a read timeout)
+    fn fill(&mut self) -> Result<(), ConnectionError> {
+        let mut buffer = [0_u8; 65536];
+        match self.stream.read(&mut buffer) {
+            Ok(0) | Err(_) => Err(ConnectionError::Closed),
+            Ok(n) => {
+                self.pending.extend_from_slice(&buffer[0..n]);
+                Ok(())
+            }
+        }
+    }
+
+    //mi fill_to
+    /// Fill the pending buffer until it has at least `n` bytes
+    fn fill_to(&mut self, n: usize) -> Result<(), ConnectionError> {
+        while self.pending.len() < n {
+            self.fill()?;
+        }
+        Ok(())
+    }
+
+    //fi find_crlf
+    fn find_crlf(buffer: &[u8], from: usize) -> Option<usize> {
+        buffer
+            .get(from..)?
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .map(|n| n + from)
+    }
+
+    //mi read_line
+    /// Read a line terminated by CRLF, returning it without the CRLF
+    fn read_line(&mut self) -> Result<String, ConnectionError> {
+        loop {
+            if let Some(n) = Self::find_crlf(&self.pending, 0) {
+                let line = std::str::from_utf8(&self.pending[0..n])
+                    .map_err(|_| ConnectionError::Reply(400))?
+                    .to_string();
+                self.pending.drain(0..n + 2);
+                return Ok(line);
+            }
+            if self.pending.len() > MAX_LINE_SIZE {
+                return Err(ConnectionError::Reply(400));
+            }
+            self.fill()?;
+        }
+    }
+
+    //mp read_request
+    /// Read the header of the next request
+    pub fn read_request(&mut self) -> Result<HttpRequest, ConnectionError> {
+        let mut from = 0;
+        loop {
+            // Empty lines before a request are ignored
+            while self.pending.starts_with(b"\r\n") {
+                self.pending.drain(0..2);
+                from = 0;
+            }
+            while let Some(n) = Self::find_crlf(&self.pending, from) {
+                if self.pending[n..].starts_with(b"\r\n\r\n") {
+                    let header_len = n + 4;
+                    let Some((request, _)) =
+                        HttpRequest::parse_request(&self.pending[0..header_len])
+                    else {
+                        return Err(ConnectionError::Reply(400));
+                    };
+                    self.pending.drain(0..header_len);
+                    return Ok(request);
+                }
+                from = n + 2;
+            }
+            if self.pending.len() > MAX_HEADER_SIZE {
+                return Err(ConnectionError::Reply(431));
+            }
+            from = self.pending.len().saturating_sub(3);
+            self.fill()?;
+        }
+    }
+
+    //mp read_body
+    /// Read the body of a request, which may use chunked transfer
+    /// encoding, up to a maximum size
+    pub fn read_body(
+        &mut self,
+        request: &HttpRequest,
+        max_size: usize,
+    ) -> Result<Vec<u8>, ConnectionError> {
+        if request.chunked {
+            return self.read_chunked(max_size);
+        }
+        let n = request.content_length;
+        if n > max_size {
+            return Err(ConnectionError::Reply(413));
+        }
+        self.fill_to(n)?;
+        Ok(self.pending.drain(0..n).collect())
+    }
+
+    //mi read_chunked
+    fn read_chunked(&mut self, max_size: usize) -> Result<Vec<u8>, ConnectionError> {
+        let mut body = vec![];
+        loop {
+            let line = self.read_line()?;
+            let size = line.split(';').next().unwrap().trim();
+            let size = usize::from_str_radix(size, 16).map_err(|_| ConnectionError::Reply(400))?;
+            if size == 0 {
+                // Skip any trailers up to the terminating empty line
+                while !self.read_line()?.is_empty() {}
+                return Ok(body);
+            }
+            // The size is given by the client, so guard against overflow
+            if body.len().checked_add(size).is_none_or(|n| n > max_size) {
+                return Err(ConnectionError::Reply(413));
+            }
+            let Some(chunk_end) = size.checked_add(2) else {
+                return Err(ConnectionError::Reply(413));
+            };
+            self.fill_to(chunk_end)?;
+            if &self.pending[size..chunk_end] != b"\r\n" {
+                return Err(ConnectionError::Reply(400));
+            }
+            body.extend_from_slice(&self.pending[0..size]);
+            self.pending.drain(0..chunk_end);
+        }
+    }
+}
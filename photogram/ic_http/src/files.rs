@@ -0,0 +1,531 @@
+//a Imports
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::{
+    HttpRequest, HttpRequestType, HttpResponse, HttpResponseType, HttpServer, HttpServerExt,
+};
+
+//a Constants
+//ci DAY_NAMES
+const DAY_NAMES: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+//ci MONTH_NAMES
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+//ci ENCODINGS
+/// Content encodings of precompressed files, in order of preference,
+/// with the extension of the precompressed file
+const ENCODINGS: [(&str, &str); 2] = [("br", "br"), ("gzip", "gz")];
+
+//ci SNIFF_SIZE
+/// Number of bytes of a file examined to determine its MIME type if
+/// its extension is not known
+const SNIFF_SIZE: usize = 512;
+
+//a HTTP dates
+//fi days_from_civil
+/// Number of days from 1970-01-01 to a date in the proleptic
+/// Gregorian calendar
+fn days_from_civil(y: i64, m: i64, d: i64) -> i64 {
+    let y = if m <= 2 { y - 1 } else { y };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (m + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + d - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+//fi civil_from_days
+/// The year, month and day of a number of days from 1970-01-01
+fn civil_from_days(z: i64) -> (i64, i64, i64) {
+    let z = z + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
+//fp http_date
+/// Format a time as an HTTP date (such as 'Sun, 06 Nov 1994 08:49:37
+/// GMT'), truncated to the second
+pub fn http_date(time: SystemTime) -> String {
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    let days = secs.div_euclid(86400);
+    let s = secs.rem_euclid(86400);
+    let (y, m, d) = civil_from_days(days);
+    format!(
+        "{}, {d:02} {} {y:04} {:02}:{:02}:{:02} GMT",
+        DAY_NAMES[(days + 4).rem_euclid(7) as usize],
+        MONTH_NAMES[(m - 1) as usize],
+        s / 3600,
+        (s / 60) % 60,
+        s % 60
+    )
+}
+
+//fp parse_http_date
+/// Parse an HTTP date in the preferred (IMF-fixdate) format
+pub fn parse_http_date(date: &str) -> Option<SystemTime> {
+    let fields: Vec<&str> = date.split_ascii_whitespace().collect();
+    let [_day, d, month, y, time, "GMT"] = fields.as_slice() else {
+        return None;
+    };
+    let d: i64 = d.parse().ok()?;
+    let m = MONTH_NAMES.iter().position(|n| n == month)? as i64 + 1;
+    let y: i64 = y.parse().ok()?;
+    let mut hms = time.split(':').map(|x| x.parse::<i64>().ok());
+    let (Some(Some(h)), Some(Some(mi)), Some(Some(s)), None) =
+        (hms.next(), hms.next(), hms.next(), hms.next())
+    else {
+        return None;
+    };
+    // The year is limited to four digits so that the seconds cannot
+    // overflow
+    if !(1..=31).contains(&d)
+        || !(1..=9999).contains(&y)
+        || !(0..=23).contains(&h)
+        || !(0..=59).contains(&mi)
+        || !(0..=60).contains(&s)
+    {
+        return None;
+    }
+    let secs = days_from_civil(y, m, d) * 86400 + h * 3600 + mi * 60 + s;
+    let secs: u64 = secs.try_into().ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+//a Header parsing
+//fi parse_range
+/// Parse the value of a Range header for a resource of `total` bytes
+///
+/// Returns None if the header should be ignored (it is not a single
+/// byte range, or is not valid), in which case the whole resource is
+/// returned; Some(Err) if the range cannot be satisfied; and
+/// Some(Ok((start, end))) for bytes start..end
+fn parse_range(range: &str, total: u64) -> Option<Result<(u64, u64), ()>> {
+    let (unit, spec) = range.trim().split_once('=')?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") || spec.contains(',') {
+        return None;
+    }
+    let (first, last) = spec.trim().split_once('-')?;
+    if first.is_empty() {
+        let n: u64 = last.parse().ok()?;
+        if n == 0 || total == 0 {
+            return Some(Err(()));
+        }
+        return Some(Ok((total.saturating_sub(n), total)));
+    }
+    let start: u64 = first.parse().ok()?;
+    let end = {
+        if last.is_empty() {
+            total
+        } else {
+            let last: u64 = last.parse().ok()?;
+            if last < start {
+                return None;
+            }
+            last.saturating_add(1).min(total)
+        }
+    };
+    if start >= total || end <= start {
+        return Some(Err(()));
+    }
+    Some(Ok((start, end)))
+}
+
+//fi etag_matches
+/// Return true if an If-None-Match or If-Match header value matches
+/// an entity tag, using the weak comparison
+fn etag_matches(header: &str, etag: &str) -> bool {
+    let etag = etag.trim_start_matches("W/");
+    header
+        .split(',')
+        .map(|t| t.trim())
+        .any(|t| t == "*" || t.trim_start_matches("W/") == etag)
+}
+
+//fi accepts_encoding
+/// Return true if an Accept-Encoding header value accepts an encoding
+fn accepts_encoding(header: &str, encoding: &str) -> bool {
+    header.split(',').any(|e| {
+        let mut params = e.split(';');
+        let name = params.next().unwrap_or("").trim();
+        if !name.eq_ignore_ascii_case(encoding) {
+            return false;
+        }
+        // An encoding with q=0 is not acceptable
+        params.all(|p| {
+            let Some((k, v)) = p.split_once('=') else {
+                return true;
+            };
+            k.trim() != "q" || v.trim().parse::<f64>().is_ok_and(|q| q > 0.0)
+        })
+    })
+}
+
+//fp sniff_mime_type
+/// Determine the MIME type of some content (the start of a file) from
+/// its bytes, for files whose extension does not give the type
+pub fn sniff_mime_type(bytes: &[u8]) -> &'static str {
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"\x89PNG\r\n\x1a\n", "image/png"),
+        (b"\xff\xd8\xff", "image/jpeg"),
+        (b"GIF87a", "image/gif"),
+        (b"GIF89a", "image/gif"),
+        (b"II*\0", "image/tiff"),
+        (b"MM\0*", "image/tiff"),
+        (b"%PDF-", "application/pdf"),
+        (b"\0asm", "application/wasm"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"PK\x03\x04", "application/zip"),
+    ];
+    for (signature, mime_type) in SIGNATURES {
+        if bytes.starts_with(signature) {
+            return mime_type;
+        }
+    }
+    if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+        return "image/webp";
+    }
+    // Text must be UTF-8, although the sample may end mid-character
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => {
+            std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap()
+        }
+        Err(_) => return "application/octet-stream",
+    };
+    if text.contains('\0') {
+        return "application/octet-stream";
+    }
+    let start = text.trim_start().to_ascii_lowercase();
+    if start.starts_with("<!doctype html") || start.starts_with("<html") {
+        "text/html"
+    } else if start.starts_with("<?xml") || start.starts_with("<svg") {
+        if start.contains("<svg") {
+            "image/svg+xml"
+        } else {
+            "application/xml"
+        }
+    } else if start.starts_with('{') || start.starts_with('[') {
+        "application/json"
+    } else {
+        "text/plain"
+    }
+}
+
+//fi json_string
+/// Quote a string for JSON
+fn json_string(s: &str) -> String {
+    let mut r = String::with_capacity(s.len() + 2);
+    r.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => r.push_str("\\\""),
+            '\\' => r.push_str("\\\\"),
+            '\n' => r.push_str("\\n"),
+            '\r' => r.push_str("\\r"),
+            '\t' => r.push_str("\\t"),
+            c if (c as u32) < 0x20 => r.push_str(&format!("\\u{:04x}", c as u32)),
+            c => r.push(c),
+        }
+    }
+    r.push('"');
+    r
+}
+
+//a HttpServer file methods
+//ip HttpServer
+impl<T: HttpServerExt> HttpServer<T> {
+    //fi set_file_response
+    /// Respond to a GET request for a file below the file root
+    ///
+    /// The path of the request has already been canonicalized so that
+    /// it cannot refer outside the file root. A directory is served as
+    /// its 'index.html' if it has one, else as a JSON listing if
+    /// directory listings are enabled.
+    ///
+    /// Responses carry an ETag and Last-Modified, and conditional
+    /// requests (If-None-Match, If-Modified-Since) are answered with
+    /// 304 when the file has not changed. A single byte range may be
+    /// requested (honouring If-Range). If precompressed files are
+    /// enabled and the client accepts it, 'file.br' or 'file.gz' is
+    /// served in place of 'file'.
+    pub fn set_file_response(
+        &self,
+        request: &HttpRequest,
+        _content: &[u8],
+        response: &mut HttpResponse,
+    ) -> bool {
+        let Some(path) = request.uri.path() else {
+            response.resp_type = HttpResponseType::MalformedRequest;
+            return false;
+        };
+        if request.req_type != HttpRequestType::Get {
+            response.set_error(405, "Files may only be read");
+            response.set_header("Allow", "GET, HEAD, OPTIONS");
+            return true;
+        }
+        let mut path = Path::join(&self.file_root, path);
+        if path.is_dir() {
+            let index = path.join("index.html");
+            if index.is_file() {
+                path = index;
+            } else if self.directory_listing {
+                self.set_directory_response(&path, response);
+                return true;
+            } else {
+                response.set_error(404, "File not found");
+                return true;
+            }
+        }
+        self.verbose().then(|| eprintln!("Fetching path {path:?}"));
+        if let Err(e) = self.set_file_content(request, &path, response) {
+            response.set_error(404, "File not found");
+            eprintln!("Failed to open {path:?}: {e}");
+        }
+        true
+    }
+
+    //mi select_encoding
+    /// Select the precompressed variant of a file to serve, if any
+    fn select_encoding(
+        &self,
+        request: &HttpRequest,
+        path: &Path,
+    ) -> (PathBuf, Option<&'static str>) {
+        let accept = request
+            .header("Accept-Encoding")
+            .filter(|_| self.precompressed)
+            .unwrap_or("");
+        for (encoding, extension) in ENCODINGS {
+            if !accepts_encoding(accept, encoding) {
+                continue;
+            }
+            let mut variant = path.as_os_str().to_owned();
+            variant.push(".");
+            variant.push(extension);
+            let variant = PathBuf::from(variant);
+            if variant.is_file() {
+                return (variant, Some(encoding));
+            }
+        }
+        (path.to_owned(), None)
+    }
+
+    //mi set_file_content
+    fn set_file_content(
+        &self,
+        request: &HttpRequest,
+        path: &Path,
+        response: &mut HttpResponse,
+    ) -> std::io::Result<()> {
+        let (file_path, encoding) = self.select_encoding(request, path);
+        let mut file = fs::File::open(&file_path)?;
+        let metadata = file.metadata()?;
+        let total = metadata.len();
+        let modified = metadata.modified().ok();
+
+        let mime_type = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| self.mime_type(&ext.to_ascii_lowercase()));
+        response.mime_type = match (mime_type, encoding) {
+            (Some(mime_type), _) => Some(mime_type),
+            (None, Some(_)) => Some("application/octet-stream".into()),
+            (None, None) => {
+                let mut sample = vec![0; SNIFF_SIZE];
+                let n = file.read(&mut sample)?;
+                file.seek(SeekFrom::Start(0))?;
+                Some(sniff_mime_type(&sample[0..n]).into())
+            }
+        };
+
+        let since_epoch = modified.and_then(|m| m.duration_since(UNIX_EPOCH).ok());
+        let etag = {
+            let (secs, nanos) = since_epoch
+                .map(|d| (d.as_secs(), d.subsec_nanos()))
+                .unwrap_or_default();
+            match encoding {
+                Some(encoding) => format!("\"{total:x}-{secs:x}.{nanos:x}-{encoding}\""),
+                None => format!("\"{total:x}-{secs:x}.{nanos:x}\""),
+            }
+        };
+        let last_modified = modified.map(http_date);
+        response.set_header("ETag", etag.as_str());
+        if let Some(last_modified) = &last_modified {
+            response.set_header("Last-Modified", last_modified.as_str());
+        }
+        response.set_header("Accept-Ranges", "bytes");
+        response.set_header("X-Content-Type-Options", "nosniff");
+        if self.precompressed {
+            response.set_header("Vary", "Accept-Encoding");
+        }
+        if let Some(encoding) = encoding {
+            response.set_header("Content-Encoding", encoding);
+        }
+
+        // If-None-Match takes precedence over If-Modified-Since
+        let not_modified = {
+            if let Some(if_none_match) = request.header("If-None-Match") {
+                etag_matches(if_none_match, &etag)
+            } else if let (Some(since), Some(modified)) = (
+                request
+                    .header("If-Modified-Since")
+                    .and_then(parse_http_date),
+                since_epoch,
+            ) {
+                modified.as_secs() <= since.duration_since(UNIX_EPOCH).unwrap().as_secs()
+            } else {
+                false
+            }
+        };
+        if not_modified {
+            response.set_status(304);
+            return Ok(());
+        }
+
+        // A range applies only if the If-Range (if any) is the current
+        // entity tag or modification date
+        let range_applies = match request.header("If-Range") {
+            None => true,
+            Some(if_range) if if_range.trim().starts_with('"') => if_range.trim() == etag,
+            Some(if_range) => last_modified.as_deref() == Some(if_range.trim()),
+        };
+        let range = request
+            .header("Range")
+            .filter(|_| range_applies)
+            .and_then(|r| parse_range(r, total));
+        match range {
+            Some(Err(())) => {
+                response.set_error(416, "Range not satisfiable");
+                response.set_header("Content-Range", format!("bytes */{total}"));
+            }
+            Some(Ok((start, end))) => {
+                let mut content = vec![0; (end - start) as usize];
+                file.seek(SeekFrom::Start(start))?;
+                file.read_exact(&mut content)?;
+                response.content = content;
+                response.is_utf8 = false;
+                response.set_content_range(start as usize, end as usize, total as usize);
+            }
+            None => {
+                let mut content = Vec::with_capacity(total as usize);
+                file.read_to_end(&mut content)?;
+                response.is_utf8 = encoding.is_none() && std::str::from_utf8(&content).is_ok();
+                response.content = content;
+                response.resp_type = HttpResponseType::FileRead;
+            }
+        }
+        Ok(())
+    }
+
+    //mi set_directory_response
+    /// Respond with a JSON listing of a directory
+    ///
+    /// Each entry has a 'name', a 'type' ('file' or 'dir'), and for
+    /// files a 'size' and 'modified' (as an HTTP date). Hidden entries
+    /// (starting with '.') are not listed.
+    fn set_directory_response(&self, path: &Path, response: &mut HttpResponse) {
+        let Ok(read_dir) = path.read_dir() else {
+            response.set_error(404, "Directory not found");
+            return;
+        };
+        let mut entries = vec![];
+        for d in read_dir.flatten() {
+            let Some(name) = d.file_name().to_str().map(|s| s.to_string()) else {
+                continue;
+            };
+            if name.starts_with('.') {
+                continue;
+            }
+            let Ok(metadata) = d.metadata() else {
+                continue;
+            };
+            let entry = {
+                if metadata.is_dir() {
+                    format!("{{\"name\":{},\"type\":\"dir\"}}", json_string(&name))
+                } else {
+                    let modified = metadata
+                        .modified()
+                        .map(|m| format!(",\"modified\":\"{}\"", http_date(m)))
+                        .unwrap_or_default();
+                    format!(
+                        "{{\"name\":{},\"type\":\"file\",\"size\":{}{modified}}}",
+                        json_string(&name),
+                        metadata.len()
+                    )
+                }
+            };
+            entries.push((name, entry));
+        }
+        entries.sort();
+        let entries: Vec<String> = entries.into_iter().map(|(_, e)| e).collect();
+        response.content = format!("[{}]", entries.join(",")).into_bytes();
+        response.mime_type = self.mime_type("json");
+        response.is_utf8 = true;
+        response.set_cache_control("no-cache");
+        response.resp_type = HttpResponseType::FileRead;
+    }
+}
+
+//a Tests
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_dates() {
+        let t = UNIX_EPOCH + Duration::from_secs(784111777);
+        assert_eq!(http_date(t), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(t));
+        assert_eq!(http_date(UNIX_EPOCH), "Thu, 01 Jan 1970 00:00:00 GMT");
+        let t = UNIX_EPOCH + Duration::from_secs(951782400);
+        assert_eq!(http_date(t), "Tue, 29 Feb 2000 00:00:00 GMT");
+        assert_eq!(parse_http_date(&http_date(t)), Some(t));
+        assert_eq!(parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), None);
+    }
+
+    #[test]
+    fn test_ranges() {
+        assert_eq!(parse_range("bytes=0-9", 100), Some(Ok((0, 10))));
+        assert_eq!(parse_range("bytes=90-", 100), Some(Ok((90, 100))));
+        assert_eq!(parse_range("bytes=90-200", 100), Some(Ok((90, 100))));
+        assert_eq!(parse_range("bytes=-10", 100), Some(Ok((90, 100))));
+        assert_eq!(parse_range("bytes=-200", 100), Some(Ok((0, 100))));
+        assert_eq!(parse_range("bytes=100-", 100), Some(Err(())));
+        assert_eq!(parse_range("bytes=-0", 100), Some(Err(())));
+        assert_eq!(parse_range("bytes=5-2", 100), None);
+        assert_eq!(parse_range("bytes=0-1,5-6", 100), None);
+        assert_eq!(parse_range("lines=0-1", 100), None);
+    }
+
+    #[test]
+    fn test_headers() {
+        assert!(etag_matches("\"a\", W/\"b\"", "\"b\""));
+        assert!(etag_matches("*", "\"b\""));
+        assert!(!etag_matches("\"a\"", "\"b\""));
+        assert!(accepts_encoding("gzip, deflate, br", "br"));
+        assert!(!accepts_encoding("gzip, br;q=0", "br"));
+        assert!(accepts_encoding("GZIP;q=0.5", "gzip"));
+        assert_eq!(sniff_mime_type(b"\x89PNG\r\n\x1a\n...."), "image/png");
+        assert_eq!(sniff_mime_type(b"  <!DOCTYPE html><html>"), "text/html");
+        assert_eq!(sniff_mime_type(b"hello \xe2\x82"), "text/plain");
+        assert_eq!(sniff_mime_type(b"\x00\x01\x02"), "application/octet-stream");
+        assert_eq!(json_string("a\"b\n"), "\"a\\\"b\\n\"");
+    }
+}
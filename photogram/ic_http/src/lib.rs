@@ -1,11 +1,20 @@
 //a Imports
 use std::collections::HashMap;
-use std::fs;
 use std::io::{Read, Write};
 use std::net::TcpStream;
 use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
+mod connection;
+pub use connection::{ConnectionError, HttpConnection};
+
+mod files;
+pub use files::{http_date, parse_http_date, sniff_mime_type};
+
+mod tls;
+pub use tls::load_tls_config;
+
 //a MimeTypes
 //ci MIME_TYPES
 pub const MIME_TYPES: &[(&str, &str)] = &[
@@ -13,6 +22,7 @@ pub const MIME_TYPES: &[(&str, &str)] = &[
     ("htm", "text/html"),
     ("html", "text/html"),
     ("js", "text/javascript"),
+    ("mjs", "text/javascript"),
     ("txt", "text/plain"),
     ("gif", "image/gif"),
     ("ico", "image/vnd.microsoft.icon"),
@@ -22,12 +32,44 @@ pub const MIME_TYPES: &[(&str, &str)] = &[
     ("svg", "image/svg+xml"),
     ("tif", "image/tiff"),
     ("tiff", "image/tiff"),
+    ("webp", "image/webp"),
     ("json", "application/json"),
+    ("map", "application/json"),
     ("pdf", "application/pdf"),
     ("wasm", "application/wasm"),
     ("xml", "application/xml"),
 ];
 
+//a Base64
+//fi base64_decode
+/// Decode standard base64 (with optional padding), as used in an
+/// 'Authorization: Basic' header
+fn base64_decode(s: &str) -> Option<Vec<u8>> {
+    let s = s.trim_end_matches('=');
+    let mut result = Vec::with_capacity(s.len() * 3 / 4);
+    let mut acc = 0_u32;
+    let mut bits = 0;
+    for c in s.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        };
+        acc = (acc << 6) | (v as u32);
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    // A single character left over is not valid base64
+    (bits < 6).then_some(result)
+}
+
 //a UriArgValue
 //ti UriArgValue
 // trait UriArgValueKind = std::any::Any  + 'static
@@ -62,7 +104,7 @@ impl UriArgValue {
 
 //a UriDecode
 //tp UriDecode
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct UriDecode {
     uri: Option<String>,
     path: Option<PathBuf>,
@@ -163,24 +205,63 @@ impl UriDecode {
         Some(pb)
     }
 
+    //fp percent_decode
+    /// Decode the '%XX' escapes of a part of a URI
+    ///
+    /// Returns None if an escape is malformed or the result is not
+    /// UTF-8; '+' is not decoded
+    pub fn percent_decode(s: &str) -> Option<String> {
+        if !s.contains('%') {
+            return Some(s.into());
+        }
+        let mut bytes = Vec::with_capacity(s.len());
+        let mut iter = s.bytes();
+        while let Some(b) = iter.next() {
+            if b != b'%' {
+                bytes.push(b);
+                continue;
+            }
+            let hi = (iter.next()? as char).to_digit(16)?;
+            let lo = (iter.next()? as char).to_digit(16)?;
+            bytes.push((hi * 16 + lo) as u8);
+        }
+        String::from_utf8(bytes).ok()
+    }
+
     //cp decode_uri
     /// Parse a URI as a path optionally followed by ? action [& k=v]*
     ///
+    /// The path, action, and argument names and values are
+    /// percent-decoded; the path is decoded before it is canonicalized
+    ///
     /// If the decode fails, produce a plain Uri
     pub fn decode_uri(uri: &str) -> UriDecode {
         let mut split = uri.splitn(2, '?');
-        let Some(uri) = Self::canonicalize_path(split.next().unwrap()) else {
+        let Some(path) = Self::percent_decode(split.next().unwrap()) else {
+            return UriDecode::of_uri(uri);
+        };
+        let Some(path) = Self::canonicalize_path(&path) else {
             return UriDecode::of_uri(uri);
         };
 
-        let mut ud = UriDecode::of_path(uri);
+        let mut ud = UriDecode::of_path(path);
         if let Some(action_args) = split.next() {
             let mut aa_split = action_args.split('&');
-            ud.set_action(aa_split.next());
+            let Some(action) = Self::percent_decode(aa_split.next().unwrap()) else {
+                return UriDecode::of_uri(uri);
+            };
+            ud.set_action(Some(&action));
             for args in aa_split {
                 let mut arg_split = args.splitn(2, '=');
-                let arg = arg_split.next().unwrap();
-                ud.add_arg(arg, arg_split.next());
+                let arg = Self::percent_decode(arg_split.next().unwrap());
+                let value = arg_split.next().map(Self::percent_decode);
+                match (arg, value) {
+                    (Some(arg), None) => ud.add_arg(&arg, None),
+                    (Some(arg), Some(Some(value))) => ud.add_arg(&arg, Some(&value)),
+                    _ => {
+                        return UriDecode::of_uri(uri);
+                    }
+                }
             }
         }
         ud
@@ -211,13 +292,97 @@ impl UriDecode {
 }
 
 //a HttpResponse
+//fp reason_phrase
+/// The standard reason phrase for an HTTP status code
+pub fn reason_phrase(code: u16) -> &'static str {
+    match code {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        206 => "Partial Content",
+        301 => "Moved Permanently",
+        302 => "Found",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        411 => "Length Required",
+        412 => "Precondition Failed",
+        413 => "Content Too Large",
+        416 => "Range Not Satisfiable",
+        422 => "Unprocessable Content",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        503 => "Service Unavailable",
+        505 => "HTTP Version Not Supported",
+        _ => "Unknown",
+    }
+}
+
 //tp HttpResponseType
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum HttpResponseType {
+    /// 200 OK, with the content
     FileRead,
+    /// 404 Not Found
     FileNotFound,
+    /// 400 Bad Request
     #[default]
     MalformedRequest,
+    /// Any other status code, with the content (if any)
+    Status(u16),
+}
+
+//ip HttpResponseType
+impl HttpResponseType {
+    //cp of_status_code
+    pub fn of_status_code(code: u16) -> Self {
+        match code {
+            200 => Self::FileRead,
+            400 => Self::MalformedRequest,
+            404 => Self::FileNotFound,
+            code => Self::Status(code),
+        }
+    }
+
+    //ap status_code
+    pub fn status_code(&self) -> u16 {
+        match self {
+            Self::FileRead => 200,
+            Self::FileNotFound => 404,
+            Self::MalformedRequest => 400,
+            Self::Status(code) => *code,
+        }
+    }
+
+    //ap has_body
+    /// Returns true if a response with this status may have a body
+    pub fn has_body(&self) -> bool {
+        let code = self.status_code();
+        !((100..200).contains(&code) || code == 204 || code == 304)
+    }
+}
+
+//tp HttpChunks
+/// A source of the body of a response that is sent with chunked
+/// transfer encoding, each item being sent (and flushed) as one chunk
+pub struct HttpChunks(Box<dyn Iterator<Item = Vec<u8>>>);
+
+//ip Debug for HttpChunks
+impl std::fmt::Debug for HttpChunks {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "HttpChunks")
+    }
 }
 
 //tp HttpResponse
@@ -227,6 +392,222 @@ pub struct HttpResponse {
     pub content: Vec<u8>,
     pub mime_type: Option<String>,
     pub is_utf8: bool,
+    /// Additional headers; Content-Type, Content-Length,
+    /// Transfer-Encoding and Connection are added when the response
+    /// is sent
+    pub headers: Vec<(String, String)>,
+    /// If Some then the body is sent with chunked transfer encoding,
+    /// with the content (if not empty) as the first chunk
+    pub chunks: Option<HttpChunks>,
+}
+
+//ip HttpResponse
+impl HttpResponse {
+    //cp error
+    /// Create a response with a status code and a plain text message
+    pub fn error(code: u16, message: &str) -> Self {
+        let mut response = Self::default();
+        response.set_error(code, message);
+        response
+    }
+
+    //ap status_code
+    pub fn status_code(&self) -> u16 {
+        self.resp_type.status_code()
+    }
+
+    //mp set_status
+    pub fn set_status(&mut self, code: u16) {
+        self.resp_type = HttpResponseType::of_status_code(code);
+    }
+
+    //mp set_error
+    /// Set the status code, with a plain text message as the content
+    pub fn set_error(&mut self, code: u16, message: &str) {
+        self.set_status(code);
+        self.content = message.as_bytes().to_vec();
+        self.mime_type = Some("text/plain".into());
+        self.is_utf8 = true;
+        self.chunks = None;
+    }
+
+    //ap header
+    /// Get the value of a header that has been added
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    //mp add_header
+    pub fn add_header<K: Into<String>, V: Into<String>>(&mut self, name: K, value: V) {
+        self.headers.push((name.into(), value.into()));
+    }
+
+    //mp set_header
+    /// Set a header, replacing any of the same name
+    pub fn set_header<K: Into<String>, V: Into<String>>(&mut self, name: K, value: V) {
+        let name = name.into();
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(&name));
+        self.headers.push((name, value.into()));
+    }
+
+    //mp set_etag
+    /// Set the ETag of the response; the tag is quoted
+    pub fn set_etag(&mut self, tag: &str) {
+        self.set_header("ETag", format!("\"{tag}\""));
+    }
+
+    //mp set_cache_control
+    /// Set the Cache-Control header, such as 'no-cache' or 'max-age=3600'
+    pub fn set_cache_control(&mut self, cache_control: &str) {
+        self.set_header("Cache-Control", cache_control);
+    }
+
+    //mp set_content_range
+    /// Mark the response as the bytes `start..end` of a resource of
+    /// `total` bytes; the content should be just those bytes
+    pub fn set_content_range(&mut self, start: usize, end: usize, total: usize) {
+        self.set_status(206);
+        self.set_header(
+            "Content-Range",
+            format!("bytes {start}-{}/{total}", end.saturating_sub(1)),
+        );
+    }
+
+    //mp set_chunks
+    /// Send the body with chunked transfer encoding, taking the chunks
+    /// from an iterator as they become available
+    pub fn set_chunks<I: Iterator<Item = Vec<u8>> + 'static>(&mut self, chunks: I) {
+        self.chunks = Some(HttpChunks(Box::new(chunks)));
+    }
+
+    //mp set_event_stream
+    /// Send a stream of server-sent events as the body of the response
+    ///
+    /// Each event is sent as it is produced by the iterator, and the
+    /// response completes when the iterator does
+    pub fn set_event_stream<I: Iterator<Item = ServerSentEvent> + 'static>(&mut self, events: I) {
+        self.set_status(200);
+        self.content.clear();
+        self.mime_type = Some("text/event-stream".into());
+        self.is_utf8 = true;
+        self.set_cache_control("no-cache");
+        self.set_chunks(events.map(|e| e.to_bytes()));
+    }
+
+    //ap is_event_stream
+    /// Return true if the response is a stream of server-sent events
+    pub fn is_event_stream(&self) -> bool {
+        self.chunks.is_some() && self.mime_type.as_deref() == Some("text/event-stream")
+    }
+
+    //mp write_to
+    /// Write the response to a stream
+    ///
+    /// If `head_only` is true then only the headers are written (as
+    /// for a HEAD request)
+    pub fn write_to<W: Write>(
+        self,
+        stream: &mut W,
+        head_only: bool,
+        keep_alive: bool,
+    ) -> Result<(), std::io::Error> {
+        let code = self.status_code();
+        let mut hdr = format!("HTTP/1.1 {code} {}\r\n", reason_phrase(code));
+        if let Some(mt) = &self.mime_type {
+            let charset = if self.is_utf8 { "; charset=utf-8" } else { "" };
+            hdr += &format!("Content-Type: {mt}{charset}\r\n");
+        }
+        for (k, v) in &self.headers {
+            hdr += &format!("{k}: {v}\r\n");
+        }
+        if keep_alive {
+            hdr += "Connection: keep-alive\r\n";
+        } else {
+            hdr += "Connection: close\r\n";
+        }
+        let has_body = self.resp_type.has_body();
+        if has_body {
+            if self.chunks.is_some() {
+                hdr += "Transfer-Encoding: chunked\r\n";
+            } else {
+                hdr += &format!("Content-Length: {}\r\n", self.content.len());
+            }
+        }
+        hdr += "\r\n";
+        stream.write_all(hdr.as_bytes())?;
+        if head_only || !has_body {
+            return stream.flush();
+        }
+        if let Some(chunks) = self.chunks {
+            let first = (!self.content.is_empty()).then_some(self.content);
+            for chunk in first.into_iter().chain(chunks.0) {
+                if chunk.is_empty() {
+                    continue;
+                }
+                stream.write_all(format!("{:x}\r\n", chunk.len()).as_bytes())?;
+                stream.write_all(&chunk)?;
+                stream.write_all(b"\r\n")?;
+                stream.flush()?;
+            }
+            stream.write_all(b"0\r\n\r\n")?;
+        } else {
+            stream.write_all(&self.content)?;
+        }
+        stream.flush()
+    }
+}
+
+//a ServerSentEvent
+//tp ServerSentEvent
+/// An event sent in a 'text/event-stream' response
+///
+/// An event with no type and no data is sent as a comment, which can
+/// be used to keep a connection alive
+#[derive(Debug, Default, Clone)]
+pub struct ServerSentEvent {
+    pub event: Option<String>,
+    pub id: Option<String>,
+    pub data: String,
+}
+
+//ip ServerSentEvent
+impl ServerSentEvent {
+    //cp new
+    pub fn new<S: Into<String>>(event: &str, data: S) -> Self {
+        Self {
+            event: Some(event.into()),
+            id: None,
+            data: data.into(),
+        }
+    }
+
+    //cp keep_alive
+    pub fn keep_alive() -> Self {
+        Self::default()
+    }
+
+    //mp to_bytes
+    /// Encode the event as it is sent on the stream
+    pub fn to_bytes(&self) -> Vec<u8> {
+        if self.event.is_none() && self.data.is_empty() {
+            return b": keep-alive\n\n".to_vec();
+        }
+        let mut s = String::new();
+        if let Some(event) = &self.event {
+            s += &format!("event: {event}\n");
+        }
+        if let Some(id) = &self.id {
+            s += &format!("id: {id}\n");
+        }
+        for line in self.data.split('\n') {
+            s += &format!("data: {line}\n");
+        }
+        s += "\n";
+        s.into_bytes()
+    }
 }
 
 //a HttpRequest
@@ -234,19 +615,33 @@ pub struct HttpResponse {
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 pub enum HttpRequestType {
     Get,
+    Head,
     Put,
     Post,
+    Delete,
+    Options,
     #[default]
     Unknown,
 }
+
 //tp HttpRequest
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct HttpRequest {
     pub req_type: HttpRequestType,
     pub uri: UriDecode,
     pub content_type: String,
     pub content_length: usize,
+    /// All of the headers of the request, in order
+    pub headers: Vec<(String, String)>,
+    /// True if the body is sent with chunked transfer encoding
+    pub chunked: bool,
+    /// True if the connection should be kept open after the response
+    ///
+    /// This is the default for HTTP/1.1, unless the client sends
+    /// 'Connection: close'
+    pub keep_alive: bool,
 }
+
 //ip HttpRequest
 impl HttpRequest {
     //fi split_at_crlf
@@ -271,6 +666,59 @@ impl HttpRequest {
         self.uri.action_is(action)
     }
 
+    //ap header
+    /// Get the value of a header of the request (the name is not case
+    /// sensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    //ap basic_auth
+    /// Get the user name and password of an 'Authorization: Basic'
+    /// header, if the request has a valid one
+    pub fn basic_auth(&self) -> Option<(String, String)> {
+        let (scheme, credentials) = self.header("Authorization")?.trim().split_once(' ')?;
+        if !scheme.eq_ignore_ascii_case("Basic") {
+            return None;
+        }
+        let credentials = String::from_utf8(base64_decode(credentials.trim())?).ok()?;
+        let (user, password) = credentials.split_once(':')?;
+        Some((user.to_string(), password.to_string()))
+    }
+
+    //ap bearer_token
+    /// Get the token of an 'Authorization: Bearer' header, if the
+    /// request has one
+    pub fn bearer_token(&self) -> Option<&str> {
+        let (scheme, token) = self.header("Authorization")?.trim().split_once(' ')?;
+        scheme
+            .eq_ignore_ascii_case("Bearer")
+            .then_some(token.trim())
+    }
+
+    //ap has_body
+    /// Returns true if the request has a body to be read
+    pub fn has_body(&self) -> bool {
+        self.chunked || self.content_length > 0
+    }
+
+    //ap expects_continue
+    /// Returns true if the client is waiting for a '100 Continue'
+    /// before sending the body
+    pub fn expects_continue(&self) -> bool {
+        self.header("Expect")
+            .is_some_and(|e| e.eq_ignore_ascii_case("100-continue"))
+    }
+
+    //mp has_arg
+    /// Returns true if the URI has the argument, with or without a value
+    pub fn has_arg(&self, id: &str) -> bool {
+        self.uri.args.iter().any(|(k, _)| k == id)
+    }
+
     //mp get_one
     pub fn get_one<T>(&self, id: &str) -> Option<Result<T, String>>
     where
@@ -311,42 +759,82 @@ impl HttpRequest {
         let b_req_type = req_fields.next()?;
         let b_uri = req_fields.next()?;
         let b_http = req_fields.next()?;
-        if b_http != b"HTTP/1.1" {
+        if b_http == b"HTTP/1.1" {
+            self.keep_alive = true;
+        } else if b_http == b"HTTP/1.0" {
+            self.keep_alive = false;
+        } else {
             return None;
         }
-        if b_req_type == b"GET" {
-            self.req_type = HttpRequestType::Get;
-        } else if b_req_type == b"PUT" {
-            self.req_type = HttpRequestType::Put;
-        } else if b_req_type == b"POST" {
-            self.req_type = HttpRequestType::Post;
-        }
+        self.req_type = match b_req_type {
+            b"GET" => HttpRequestType::Get,
+            b"HEAD" => HttpRequestType::Head,
+            b"PUT" => HttpRequestType::Put,
+            b"POST" => HttpRequestType::Post,
+            b"DELETE" => HttpRequestType::Delete,
+            b"OPTIONS" => HttpRequestType::Options,
+            _ => HttpRequestType::Unknown,
+        };
         self.uri = UriDecode::decode_uri(std::str::from_utf8(b_uri).unwrap());
         Some(b_rest)
     }
 
+    //mp add_header
+    /// Add a header to the request, returning false if it is invalid
+    ///
+    /// A Content-Length that is not a number, or that differs from an
+    /// earlier one, makes the end of the body ambiguous, so is invalid
+    fn add_header(&mut self, k: &str, v: &str) -> bool {
+        let k = k.trim();
+        let v = v.trim();
+        if k.eq_ignore_ascii_case("Content-Length") {
+            if !v.bytes().all(|b| b.is_ascii_digit()) {
+                return false;
+            }
+            let Ok(n) = v.parse::<usize>() else {
+                return false;
+            };
+            if self.header("Content-Length").is_some() && self.content_length != n {
+                return false;
+            }
+            self.content_length = n;
+        } else if k.eq_ignore_ascii_case("Content-Type") {
+            self.content_type = v.into();
+        } else if k.eq_ignore_ascii_case("Transfer-Encoding") {
+            self.chunked = v
+                .rsplit(',')
+                .next()
+                .is_some_and(|te| te.trim().eq_ignore_ascii_case("chunked"));
+        } else if k.eq_ignore_ascii_case("Connection") {
+            for token in v.split(',') {
+                if token.trim().eq_ignore_ascii_case("close") {
+                    self.keep_alive = false;
+                } else if token.trim().eq_ignore_ascii_case("keep-alive") {
+                    self.keep_alive = true;
+                }
+            }
+        }
+        self.headers.push((k.into(), v.into()));
+        true
+    }
+
     //cp parse_request
     pub fn parse_request(buffer: &[u8]) -> Option<(HttpRequest, &[u8])> {
         let mut request = HttpRequest::default();
         let mut rest = request.parse_req_hdr(buffer)?;
-        loop {
-            let Some((b_req, b_rest)) = Self::split_at_crlf(rest) else {
-                break;
-            };
+        while let Some((b_req, b_rest)) = Self::split_at_crlf(rest) {
             if b_req.is_empty() {
+                if request.chunked {
+                    request.content_length = 0;
+                }
                 return Some((request, b_rest));
             }
             let Ok(line) = std::str::from_utf8(b_req) else {
                 break;
             };
-            if let Some((k, v)) = line.split_once(": ") {
-                if k == "Content-Length" {
-                    if let Ok(n) = v.parse::<usize>() {
-                        request.content_length = n;
-                    }
-                }
-                if k == "Content-Type" {
-                    request.content_type = v.into();
+            if let Some((k, v)) = line.split_once(':') {
+                if !request.add_header(k, v) {
+                    return None;
                 }
             }
             rest = b_rest;
@@ -375,13 +863,34 @@ pub trait HttpServerExt: Sized {
 //ip HttpServerExt for ()
 impl HttpServerExt for () {}
 
+//ci ALLOWED_METHODS
+/// The methods reported in response to an OPTIONS request
+const ALLOWED_METHODS: &str = "GET, HEAD, PUT, POST, DELETE, OPTIONS";
+
 //tp HttpServer
 pub struct HttpServer<T: HttpServerExt> {
     verbose: bool,
     file_root: PathBuf,
     mime_types: HashMap<&'static str, &'static str>,
+    /// Time to wait for a request on a connection before closing it
+    keep_alive_timeout: Duration,
+    /// Maximum number of requests handled on one connection
+    max_requests: usize,
+    /// Maximum number of connections that may be held open between
+    /// requests; each occupies a thread while it waits
+    max_held_connections: usize,
+    /// Number of connections currently held open
+    held_connections: AtomicUsize,
+    /// Maximum size of a request body; larger requests get a 413
+    max_body_size: usize,
+    /// Serve a JSON listing for a directory without an 'index.html'
+    directory_listing: bool,
+    /// Serve precompressed '.br' or '.gz' variants of files if the
+    /// client accepts them
+    precompressed: bool,
     data: T,
 }
+
 //ip HttpServer
 impl<T: HttpServerExt> HttpServer<T> {
     //cp new
@@ -392,140 +901,228 @@ impl<T: HttpServerExt> HttpServer<T> {
             verbose,
             mime_types,
             file_root,
+            keep_alive_timeout: Duration::from_secs(5),
+            max_requests: 100,
+            max_held_connections: usize::MAX,
+            held_connections: AtomicUsize::new(0),
+            max_body_size: 16 * 1024 * 1024,
+            directory_listing: false,
+            precompressed: true,
             data,
         }
     }
 
+    //cp with_keep_alive_timeout
+    /// Set the time an idle connection is kept open for
+    ///
+    /// A connection occupies a thread while it is open, so this
+    /// should be short
+    pub fn with_keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.keep_alive_timeout = timeout;
+        self
+    }
+
+    //cp with_max_requests
+    /// Set the maximum number of requests handled on a single
+    /// connection; 1 disables persistent connections
+    pub fn with_max_requests(mut self, max_requests: usize) -> Self {
+        self.max_requests = max_requests.max(1);
+        self
+    }
+
+    //cp with_max_held_connections
+    /// Set the maximum number of connections that may be held open
+    /// between requests (by keep-alive) or by event streams; once
+    /// this many are held, further connections are closed after each
+    /// response, and event streams are refused with a 503
+    ///
+    /// This should be less than the number of threads handling
+    /// connections, so that idle clients cannot occupy them all; it
+    /// is unlimited by default
+    pub fn with_max_held_connections(mut self, max_held_connections: usize) -> Self {
+        self.max_held_connections = max_held_connections;
+        self
+    }
+
+    //cp with_max_body_size
+    pub fn with_max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    //cp with_directory_listing
+    /// Enable (or disable) JSON listings of directories that have no
+    /// 'index.html'; they are disabled by default
+    pub fn with_directory_listing(mut self, directory_listing: bool) -> Self {
+        self.directory_listing = directory_listing;
+        self
+    }
+
+    //cp with_precompressed
+    /// Enable (or disable) serving 'file.br' or 'file.gz' for
+    /// 'file'; they are enabled by default
+    pub fn with_precompressed(mut self, precompressed: bool) -> Self {
+        self.precompressed = precompressed;
+        self
+    }
+
     //ap verbose
     #[inline]
     pub fn verbose(&self) -> bool {
         self.verbose
     }
 
+    //ap data
+    pub fn data(&self) -> &T {
+        &self.data
+    }
+
     //mp mime_type
     pub fn mime_type(&self, extension: &str) -> Option<String> {
         self.mime_types.get(extension).map(|mt| mt.to_string())
     }
 
-    //fi set_file_response
-    pub fn set_file_response(
-        &self,
-        request: &HttpRequest,
-        _content: &[u8],
-        response: &mut HttpResponse,
-    ) -> bool {
-        let Some(path) = request.uri.path() else {
-            response.resp_type = HttpResponseType::MalformedRequest;
-            return false;
-        };
-        let mut path = Path::join(&self.file_root, path);
-        if path.is_dir() {
-            path.push("index.html");
-        }
-        self.verbose().then(|| eprintln!("Fetching path {path:?}"));
-        if let Some(ext) = path.extension() {
-            response.mime_type = self.mime_type(ext.to_str().unwrap());
-            if let Ok(bytes) = fs::read(&path) {
-                response.is_utf8 = std::str::from_utf8(&bytes).is_ok();
-                response.content = bytes;
-                response.resp_type = HttpResponseType::FileRead;
-            } else {
-                response.resp_type = HttpResponseType::FileNotFound;
-                eprintln!("Failed to open {path:?}");
-            }
-        }
-        true
-    }
-
     //mp send_response
-    pub fn send_response(
+    pub fn send_response<W: Write>(
         &self,
-        stream: &mut TcpStream,
+        stream: &mut W,
         response: HttpResponse,
+        head_only: bool,
+        keep_alive: bool,
     ) -> Result<(), std::io::Error> {
-        match response.resp_type {
-            HttpResponseType::MalformedRequest => {
-                stream.write_all("HTTP/1.1 400 BAD REQUEST\r\n\r\n".as_bytes())
+        response.write_to(stream, head_only, keep_alive)
+    }
+
+    //mp respond
+    /// Generate the response to a request
+    ///
+    /// A HEAD request is handled as a GET (the body being dropped
+    /// when the response is sent); OPTIONS requests are answered by
+    /// the server itself
+    pub fn respond(&self, request: &mut HttpRequest, content: &[u8]) -> HttpResponse {
+        let mut response = HttpResponse::default();
+        match request.req_type {
+            HttpRequestType::Unknown => {
+                response.set_error(501, "Method not implemented");
+                return response;
             }
-            HttpResponseType::FileNotFound => {
-                stream.write_all("HTTP/1.1 404 NOT FOUND\r\n\r\n".as_bytes())
+            HttpRequestType::Options => {
+                response.set_status(204);
+                response.set_header("Allow", ALLOWED_METHODS);
+                return response;
             }
-            HttpResponseType::FileRead => {
-                let length = response.content.len();
-                let charset = if response.is_utf8 {
-                    "; charset=utf-8"
-                } else {
-                    ""
-                };
-                let mime_type = response
-                    .mime_type
-                    .map(|mt| format!("Content-Type: {mt}{charset}\r\n")) // text/html; charset=utf-8
-                    .unwrap_or_default();
-                stream.write_all(
-                    format!("HTTP/1.1 200 OK\r\n{mime_type}Content-Length: {length}\r\n\r\n")
-                        .as_bytes(),
-                )?;
-                stream.write_all(&response.content)
+            HttpRequestType::Head => {
+                request.req_type = HttpRequestType::Get;
             }
+            _ => (),
+        }
+        if !(self
+            .data
+            .set_http_response(self, request, content, &mut response)
+            || self.set_file_response(request, content, &mut response))
+        {
+            eprintln!("Request failed: send {response:?}");
         }
+        response
     }
 
     //fp handle_connection
-    pub fn handle_connection(&self, mut stream: TcpStream) {
-        let mut buffer = vec![0_u8; 65536];
-        let mut ofs = 0;
-        stream
-            .set_read_timeout(Some(Duration::from_secs(3)))
-            .unwrap();
-        let (request, mut content) = {
-            loop {
-                let Ok(n) = stream.read(buffer.as_mut_slice().split_at_mut(ofs).1) else {
-                    return;
-                };
-                ofs += n;
-                if let Some(r_cs) = HttpRequest::parse_request(&buffer[0..ofs]) {
-                    break r_cs;
-                }
-                if n == 0 {
-                    // Connection shut down without a full header
-                    return;
-                }
-            }
-        };
-        if request.content_length > 16 * 1024 * 1024 {
+    /// Handle requests on a connection until the client closes it,
+    /// it is idle for the keep-alive timeout, or a request asks for
+    /// it to be closed
+    pub fn handle_connection(&self, stream: TcpStream) {
+        if stream
+            .set_read_timeout(Some(self.keep_alive_timeout))
+            .is_err()
+        {
             return;
         }
-        let mut response = HttpResponse::default();
-        let mut content_buffer;
-        if request.content_length > content.len() {
-            let mut extra_bytes = request.content_length - content.len();
-            content_buffer = Vec::with_capacity(request.content_length);
-            content_buffer.extend_from_slice(content);
-            while extra_bytes > 0 {
-                let max_n = extra_bytes.min(buffer.len());
-                let Ok(n) = stream.read(&mut buffer[0..max_n]) else {
+        self.serve(stream);
+    }
+
+    //mi hold_connection
+    /// Try to take one of the slots for connections held open
+    fn hold_connection(&self) -> Option<HeldConnection<'_>> {
+        self.held_connections
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < self.max_held_connections).then_some(n + 1)
+            })
+            .ok()?;
+        Some(HeldConnection(&self.held_connections))
+    }
+
+    //fp serve
+    /// Handle requests on a stream (see [Self::handle_connection])
+    ///
+    /// The connection is kept open after a response (and an event
+    /// stream is sent) only if a held connection slot is available
+    pub fn serve<S: Read + Write>(&self, stream: S) {
+        let mut connection = HttpConnection::new(stream);
+        let mut held = None;
+        for n in 0..self.max_requests {
+            let mut request = match connection.read_request() {
+                Ok(request) => request,
+                Err(ConnectionError::Closed) => return,
+                Err(ConnectionError::Reply(code)) => {
+                    let response = HttpResponse::error(code, reason_phrase(code));
+                    let _ = self.send_response(connection.stream(), response, false, false);
                     return;
-                };
-                content_buffer.extend_from_slice(&buffer[0..n]);
-                extra_bytes -= n;
-                if n == 0 && extra_bytes > 0 {
-                    // Connection shut down without full content
+                }
+            };
+            if request.content_length > self.max_body_size {
+                let response = HttpResponse::error(413, "Request body too large");
+                let _ = self.send_response(connection.stream(), response, false, false);
+                return;
+            }
+            if request.has_body() && request.expects_continue() {
+                let _ = connection
+                    .stream()
+                    .write_all(b"HTTP/1.1 100 Continue\r\n\r\n");
+            }
+            let content = match connection.read_body(&request, self.max_body_size) {
+                Ok(content) => content,
+                Err(ConnectionError::Closed) => return,
+                Err(ConnectionError::Reply(code)) => {
+                    let response = HttpResponse::error(code, reason_phrase(code));
+                    let _ = self.send_response(connection.stream(), response, false, false);
                     return;
                 }
+            };
+            let head_only = request.req_type == HttpRequestType::Head;
+            let keep_alive = request.keep_alive && n + 1 < self.max_requests;
+            let mut response = self.respond(&mut request, &content);
+            // An event stream occupies the thread for as long as it
+            // runs, so it also needs a held connection slot
+            let event_stream = response.is_event_stream();
+            if (keep_alive || event_stream) && held.is_none() {
+                held = self.hold_connection();
+            }
+            let keep_alive = keep_alive && held.is_some();
+            if event_stream && held.is_none() {
+                response = HttpResponse::error(503, "Too many open connections");
+                response.set_header("Retry-After", "5");
+            }
+            if self
+                .send_response(connection.stream(), response, head_only, keep_alive)
+                .is_err()
+                || !keep_alive
+            {
+                return;
             }
-            content = &content_buffer;
-        }
-        if self
-            .data
-            .set_http_response(self, &request, content, &mut response)
-            || self.set_file_response(&request, content, &mut response)
-        {
-            let _ = self.send_response(&mut stream, response);
-        } else {
-            eprintln!("Request failed: send {response:?}");
-            let _ = self.send_response(&mut stream, response);
         }
     }
 
     //zz All done
 }
+
+//tp HeldConnection
+/// A slot for a connection held open between requests, released
+/// when dropped
+struct HeldConnection<'a>(&'a AtomicUsize);
+
+//ip Drop for HeldConnection
+impl Drop for HeldConnection<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
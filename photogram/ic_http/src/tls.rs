@@ -0,0 +1,62 @@
+//a Imports
+use std::io::Write;
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::Arc;
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+
+use crate::{HttpServer, HttpServerExt};
+
+//a TLS configuration
+//fp load_tls_config
+/// Create a TLS server configuration from a certificate chain and a
+/// private key, each held in a PEM file
+pub fn load_tls_config<P: AsRef<Path>, Q: AsRef<Path>>(
+    cert: P,
+    key: Q,
+) -> Result<Arc<ServerConfig>, String> {
+    let cert = cert.as_ref();
+    let key = key.as_ref();
+    let certs = CertificateDer::pem_file_iter(cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read certificates from {}: {e}", cert.display()))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates found in {}", cert.display()));
+    }
+    let key = PrivateKeyDer::from_pem_file(key)
+        .map_err(|e| format!("Failed to read private key from {}: {e}", key.display()))?;
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("Bad TLS certificate or key: {e}"))?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+//a HttpServer TLS methods
+//ip HttpServer
+impl<T: HttpServerExt> HttpServer<T> {
+    //mp handle_tls_connection
+    /// Handle a connection that uses TLS, as [Self::handle_connection]
+    ///
+    /// The TLS handshake is performed as the first request is read; if
+    /// it fails the connection is closed
+    pub fn handle_tls_connection(&self, stream: TcpStream, tls_config: &Arc<ServerConfig>) {
+        if stream
+            .set_read_timeout(Some(self.keep_alive_timeout))
+            .is_err()
+        {
+            return;
+        }
+        let Ok(connection) = ServerConnection::new(tls_config.clone()) else {
+            return;
+        };
+        let mut stream = StreamOwned::new(connection, stream);
+        self.serve(&mut stream);
+        stream.conn.send_close_notify();
+        let _ = stream.flush();
+    }
+}
@@ -259,6 +259,20 @@ impl Serialize for Color {
     }
 }
 
+//ip JsonSchema for Color
+impl schemars::JsonSchema for Color {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Color".into()
+    }
+    fn json_schema(_generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "A color as #RGB, #ARGB, #RRGGBB or #AARRGGBB, or None",
+            "type": "string",
+            "pattern": "^(None|#([0-9a-fA-F]{3,4}|[0-9a-fA-F]{6}|[0-9a-fA-F]{8}))$",
+        })
+    }
+}
+
 //ip Deserialize for Color
 impl<'de> Deserialize<'de> for Color {
     fn deserialize<DE>(deserializer: DE) -> Result<Self, DE::Error>
@@ -22,6 +22,14 @@ impl ImageRgb8 {
         Ok(Self(img.into()))
     }
 
+    //fp read_size
+    /// Read the width and height of an image file, without decoding
+    /// the image
+    pub fn read_size<P: AsRef<Path>>(path: P) -> Result<(u32, u32)> {
+        let size = ImageReader::open(path)?.into_dimensions()?;
+        Ok(size)
+    }
+
     //fp read_or_create_image
     pub fn read_or_create_image<P: AsRef<Path>>(
         width: usize,
@@ -80,6 +88,40 @@ impl ImageRgb8 {
         let image = image.buffer().to_rgb8();
         Self(image.into())
     }
+
+    //cp half_size
+    /// Create an image of half the width and height (rounded up),
+    /// each pixel being the average of (up to) four source pixels
+    pub fn half_size(&self) -> Self {
+        let img = self.0.to_rgb8();
+        let (w, h) = img.dimensions();
+        let (hw, hh) = (w.div_ceil(2), h.div_ceil(2));
+        let mut result = image::RgbImage::new(hw, hh);
+        for y in 0..hh {
+            let sy0 = 2 * y;
+            let sy1 = (2 * y + 1).min(h - 1);
+            for x in 0..hw {
+                let sx0 = 2 * x;
+                let sx1 = (2 * x + 1).min(w - 1);
+                let mut sum = [0_u32; 3];
+                for (sx, sy) in [(sx0, sy0), (sx1, sy0), (sx0, sy1), (sx1, sy1)] {
+                    let p = img[(sx, sy)];
+                    for (s, c) in sum.iter_mut().zip(p.0.iter()) {
+                        *s += *c as u32;
+                    }
+                }
+                result[(x, y)] = image::Rgb(sum.map(|s| ((s + 2) / 4) as u8));
+            }
+        }
+        Self(result.into())
+    }
+
+    //cp sub_image
+    /// Create a copy of a region of the image; the region is clipped
+    /// to the image
+    pub fn sub_image(&self, x: u32, y: u32, width: u32, height: u32) -> Self {
+        Self(self.0.crop_imm(x, y, width, height))
+    }
 }
 
 //ip Image for ImageRgb8
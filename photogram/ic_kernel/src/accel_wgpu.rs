@@ -1,11 +1,10 @@
 //a Imports
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::path::PathBuf;
 
 use serde::{Deserialize, Serialize};
 
-use crate::{Accelerate, KernelArgs};
+use crate::{Accelerate, KernelArgs, KernelPipeline};
 use ic_base::utils::rtc::run_to_completion as rtc;
 
 //a Support types
@@ -19,19 +18,30 @@ pub struct ShaderDesc {
 }
 
 //tp ShaderFileDesc
-#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+/// A WGSL shader file and the description of its kernels
+#[derive(Debug, Default, Clone)]
 pub struct ShaderFileDesc {
-    path: PathBuf,
+    /// Where the shader came from (for error messages)
+    source_name: String,
+    source: String,
     shader_descs: Vec<ShaderDesc>,
 }
 
 //ip ShaderFileDesc
 impl ShaderFileDesc {
     //cp from_json
-    pub fn from_json(path: PathBuf, json: &str) -> Result<Self, serde_json::Error> {
+    pub fn from_json(
+        source_name: String,
+        source: String,
+        json: &str,
+    ) -> Result<Self, serde_json::Error> {
         // Change to ic_base::json::from_json
         let shader_descs = serde_json::from_str::<Vec<ShaderDesc>>(json)?;
-        Ok(Self { path, shader_descs })
+        Ok(Self {
+            source_name,
+            source,
+            shader_descs,
+        })
     }
 }
 
@@ -85,7 +95,6 @@ pub enum BufferType {
 pub struct AccelWgpu {
     #[allow(dead_code)]
     instance: wgpu::Instance,
-    #[allow(dead_code)]
     adapter: wgpu::Adapter,
     device: wgpu::Device,
     queue: wgpu::Queue,
@@ -96,13 +105,28 @@ pub struct AccelWgpu {
 //ip AccelWgpu
 impl AccelWgpu {
     //cp new
-    pub fn new() -> Self {
+    /// Create an accelerator using an adapter from one of the backends
+    ///
+    /// If force_fallback is true then only a software adapter is used
+    pub fn new(backends: wgpu::Backends, force_fallback: bool) -> Result<Self, String> {
         // Instantiates instance of WebGPU
-        let instance = wgpu::Instance::default();
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
 
         // `request_adapter` instantiates the general connection to the GPU
-        let adapter =
-            rtc(instance.request_adapter(&wgpu::RequestAdapterOptions::default())).unwrap();
+        let adapter = rtc(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            force_fallback_adapter: force_fallback,
+            ..Default::default()
+        }))
+        .ok_or_else(|| {
+            if force_fallback {
+                format!("No software wgpu adapter found for backends {backends:?}")
+            } else {
+                format!("No wgpu adapter found for backends {backends:?}")
+            }
+        })?;
 
         // `request_device` instantiates the feature specific connection to the GPU, defining some parameters,
         //  `features` being the available features.
@@ -114,17 +138,22 @@ impl AccelWgpu {
             },
             None,
         ))
-        .unwrap();
+        .map_err(|e| format!("Failed to create wgpu device: {e}"))?;
         let shaders = vec![];
         let pipelines = vec![];
-        Self {
+        Ok(Self {
             instance,
             adapter,
             device,
             queue,
             shaders,
             pipelines,
-        }
+        })
+    }
+
+    //ap adapter_info
+    pub fn adapter_info(&self) -> wgpu::AdapterInfo {
+        self.adapter.get_info()
     }
 
     //mp add_shader
@@ -257,6 +286,7 @@ impl AccelWgpu {
 pub struct ImageAccelerator {
     accelerator: AccelWgpu,
     buffer_size: usize,
+    bind_group_layout: wgpu::BindGroupLayout,
     pipeline_layout: wgpu::PipelineLayout,
     pipelines: HashMap<String, (Pipeline, ShaderDesc)>,
     /// Buffer for data that gets copied TO the GPU shader_in_out_data_buffer
@@ -381,6 +411,7 @@ impl ImageAccelerator {
         Ok(Self {
             accelerator,
             buffer_size,
+            bind_group_layout: bgl,
             pipeline_layout,
             pipelines,
             input_buffer,
@@ -395,18 +426,33 @@ impl ImageAccelerator {
         })
     }
 
+    //ap description
+    /// Describe the adapter in use
+    pub fn description(&self) -> String {
+        let info = self.accelerator.adapter_info();
+        format!(
+            "wgpu {} ({:?}, {:?})",
+            info.name, info.backend, info.device_type
+        )
+    }
+
+    //ap max_elements
+    /// The maximum number of f32 that can be passed to or from a kernel
+    pub fn max_elements(&self) -> usize {
+        self.buffer_size / std::mem::size_of::<f32>()
+    }
+
     //mp create_pipelines
     pub fn create_pipelines(&mut self, shader_file: ShaderFileDesc) -> Result<(), String> {
-        let file_text = std::fs::read_to_string(&shader_file.path)
-            .map_err(|e| format!("Error reading WGSL shader file {e}"))?;
-        let cs_module = self.accelerator.add_shader(&file_text, None)?;
+        let cs_module = self
+            .accelerator
+            .add_shader(&shader_file.source, None)
+            .map_err(|e| format!("{}: {e}", shader_file.source_name))?;
         for sd in shader_file.shader_descs.into_iter() {
-            let pipeline = self.accelerator.create_pipeline(
-                cs_module,
-                &sd.shader,
-                Some(&self.pipeline_layout),
-                1,
-            )?;
+            let pipeline = self
+                .accelerator
+                .create_pipeline(cs_module, &sd.shader, Some(&self.pipeline_layout), 1)
+                .map_err(|e| format!("{}: {e}", shader_file.source_name))?;
             self.pipelines.insert(sd.kernel.clone(), (pipeline, sd));
         }
         Ok(())
@@ -503,6 +549,164 @@ impl ImageAccelerator {
         Ok(())
     }
 
+    //mp run_pipeline
+    /// Run a [KernelPipeline] keeping all of its buffers on the GPU
+    ///
+    /// The buffers are copied to the GPU at the start, and back at
+    /// the end; all of the steps are run from a single command
+    /// buffer, with the in-place data for each step copied on the
+    /// GPU to the shader input buffers
+    ///
+    /// Returns Ok(false) if any step uses a kernel that is not
+    /// supported, or if the buffers are too large
+    pub fn run_pipeline(
+        &self,
+        pipeline: &KernelPipeline,
+        args: &KernelArgs,
+        buffers: &mut [Vec<f32>],
+    ) -> Result<bool, String> {
+        let mut step_pipelines = vec![];
+        for step in pipeline.steps() {
+            let Some((p, sd)) = self.pipelines.get(step.kernel()) else {
+                return Ok(false);
+            };
+            step_pipelines.push((*p, sd));
+        }
+        let byte_sizes: Vec<usize> = buffers
+            .iter()
+            .map(|b| std::mem::size_of_val(b.as_slice()))
+            .collect();
+        let max_byte_size = byte_sizes.iter().copied().max().unwrap_or(0);
+        if max_byte_size == 0 || max_byte_size > self.buffer_size {
+            return Ok(false);
+        }
+
+        let device = self.accelerator.device();
+        let queue = self.accelerator.queue();
+        let mut gpu_buffers = vec![];
+        for b in buffers.iter() {
+            let gpu_buffer = self.accelerator.create_buffer(
+                BufferType::GpuData,
+                std::mem::size_of_val(b.as_slice()).max(4),
+                Some("Pipeline Data"),
+            );
+            queue.write_buffer(&gpu_buffer, 0, bytemuck::cast_slice(b));
+            gpu_buffers.push(gpu_buffer);
+        }
+        let in_buffer =
+            self.accelerator
+                .create_buffer(BufferType::GpuData, max_byte_size, Some("Pipeline In"));
+        let in_b_buffer = self.accelerator.create_buffer(
+            BufferType::GpuData,
+            max_byte_size,
+            Some("Pipeline In B"),
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Pipeline"),
+        });
+        // The uniform buffers must live until the command buffer is submitted
+        let mut uniform_buffers = vec![];
+        for (step, (p, sd)) in pipeline.steps().iter().zip(step_pipelines) {
+            let step_args = step.kernel_args(args);
+            let out = step.out();
+            let work_items = step.work_items(&step_args, buffers[out].len());
+            let uniform_buffer = self.accelerator.create_buffer(
+                BufferType::GpuUniform,
+                std::mem::size_of::<KernelArgs>(),
+                Some("Pipeline Uniform"),
+            );
+            queue.write_buffer(&uniform_buffer, 0, bytemuck::cast_slice(&[step_args]));
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &self.bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: uniform_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: gpu_buffers[out].as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 2,
+                        resource: in_buffer.as_entire_binding(),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 3,
+                        resource: in_b_buffer.as_entire_binding(),
+                    },
+                ],
+            });
+            uniform_buffers.push(uniform_buffer);
+
+            // As for run_shader, in_data is the source (or the
+            // current output data if there is no source), and for
+            // binary kernels in_data_b is the current output data
+            let src = step.src().unwrap_or(out);
+            encoder.copy_buffer_to_buffer(
+                &gpu_buffers[src],
+                0,
+                &in_buffer,
+                0,
+                byte_sizes[src] as u64,
+            );
+            if sd.binary {
+                encoder.copy_buffer_to_buffer(
+                    &gpu_buffers[out],
+                    0,
+                    &in_b_buffer,
+                    0,
+                    byte_sizes[out] as u64,
+                );
+            }
+            let mut cpass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: None,
+                timestamp_writes: None,
+            });
+            cpass.set_bind_group(0, &bind_group, &[]);
+            cpass.set_pipeline(self.accelerator.pipeline_err(p)?);
+            cpass.dispatch_workgroups(work_items.div_ceil(sd.x_worksize) as u32, 1, 1);
+        }
+
+        let total_byte_size: usize = byte_sizes.iter().sum();
+        let output_buffer = self.accelerator.create_buffer(
+            BufferType::HostDst,
+            total_byte_size,
+            Some("Pipeline Output"),
+        );
+        let mut ofs = 0;
+        for (b, size) in gpu_buffers.iter().zip(byte_sizes.iter()) {
+            if *size > 0 {
+                encoder.copy_buffer_to_buffer(b, 0, &output_buffer, ofs as u64, *size as u64);
+            }
+            ofs += size;
+        }
+        queue.submit(Some(encoder.finish()));
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let buffer_slice = output_buffer.slice(..);
+        buffer_slice.map_async(wgpu::MapMode::Read, move |v| sender.send(v).unwrap());
+        self.accelerator.block();
+        let Ok(Ok(())) = receiver.recv() else {
+            return Err("Failed to run pipeline on gpu!".into());
+        };
+        {
+            let data = buffer_slice.get_mapped_range();
+            let data: &[f32] = bytemuck::cast_slice(&data);
+            let mut ofs = 0;
+            for b in buffers.iter_mut() {
+                let n = b.len();
+                b.copy_from_slice(&data[ofs..ofs + n]);
+                ofs += n;
+            }
+        }
+        output_buffer.unmap();
+        drop(uniform_buffers);
+        Ok(true)
+    }
+
     //zz All done
 }
 
@@ -521,7 +725,7 @@ impl Accelerate for ImageAccelerator {
             match sd.binary {
                 false => {
                     let cmd_buffer = self.create_cmd_buffer(
-                        [work_items / sd.x_worksize, 1, 1],
+                        [work_items.div_ceil(sd.x_worksize), 1, 1],
                         *p,
                         &[
                             (
@@ -549,7 +753,7 @@ impl Accelerate for ImageAccelerator {
                 }
                 true => {
                     let cmd_buffer = self.create_cmd_buffer(
-                        [work_items / sd.x_worksize, 1, 1],
+                        [work_items.div_ceil(sd.x_worksize), 1, 1],
                         *p,
                         &[
                             (
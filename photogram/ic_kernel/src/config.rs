@@ -0,0 +1,173 @@
+//a Imports
+use std::path::PathBuf;
+
+//a Accelerator
+//tp Accelerator
+/// Selection of the accelerator to use for [crate::Kernels]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Accelerator {
+    /// Use wgpu if an adapter is available, else the CPU
+    ///
+    /// The wgpu backends may be restricted with the WGPU_BACKEND
+    /// environment variable
+    #[default]
+    Auto,
+    /// Use only the CPU
+    Cpu,
+    /// Use wgpu with any backend (failing if there is no adapter)
+    Wgpu,
+    /// Use wgpu with the Vulkan backend
+    Vulkan,
+    /// Use wgpu with the Metal backend
+    Metal,
+    /// Use wgpu with the DirectX 12 backend
+    Dx12,
+    /// Use wgpu with the OpenGL backend
+    Gl,
+    /// Use a software wgpu adapter (such as llvmpipe, lavapipe or WARP)
+    Software,
+}
+
+//ip Accelerator
+impl Accelerator {
+    //ap wgpu_options
+    /// The wgpu backends to use, and whether to force a fallback
+    /// (software) adapter; None if wgpu is not to be used
+    pub(crate) fn wgpu_options(&self) -> Option<(wgpu::Backends, bool)> {
+        let env_backends = || wgpu::util::backend_bits_from_env().unwrap_or(wgpu::Backends::all());
+        match self {
+            Self::Cpu => None,
+            Self::Auto | Self::Wgpu => Some((env_backends(), false)),
+            Self::Vulkan => Some((wgpu::Backends::VULKAN, false)),
+            Self::Metal => Some((wgpu::Backends::METAL, false)),
+            Self::Dx12 => Some((wgpu::Backends::DX12, false)),
+            Self::Gl => Some((wgpu::Backends::GL, false)),
+            Self::Software => Some((env_backends(), true)),
+        }
+    }
+
+    //ap is_required
+    /// Returns true if failing to create the accelerator is an error
+    /// (rather than falling back to the CPU)
+    pub fn is_required(&self) -> bool {
+        !matches!(self, Self::Auto | Self::Cpu)
+    }
+}
+
+//ip FromStr for Accelerator
+impl std::str::FromStr for Accelerator {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s.to_lowercase().as_str() {
+            "auto" => Ok(Self::Auto),
+            "cpu" => Ok(Self::Cpu),
+            "wgpu" | "gpu" => Ok(Self::Wgpu),
+            "vulkan" => Ok(Self::Vulkan),
+            "metal" => Ok(Self::Metal),
+            "dx12" => Ok(Self::Dx12),
+            "gl" => Ok(Self::Gl),
+            "software" => Ok(Self::Software),
+            _ => Err(format!(
+                "Unknown accelerator '{s}': expected one of auto, cpu, wgpu, vulkan, metal, dx12, gl or software"
+            )),
+        }
+    }
+}
+
+//ip Display for Accelerator
+impl std::fmt::Display for Accelerator {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            Self::Auto => "auto",
+            Self::Cpu => "cpu",
+            Self::Wgpu => "wgpu",
+            Self::Vulkan => "vulkan",
+            Self::Metal => "metal",
+            Self::Dx12 => "dx12",
+            Self::Gl => "gl",
+            Self::Software => "software",
+        };
+        s.fmt(fmt)
+    }
+}
+
+//a KernelsConfig
+//tp KernelsConfig
+/// Configuration for creating [crate::Kernels]
+///
+/// Shaders are looked for (as '<name>.json' and '<name>.wgsl') in
+/// each of the shader paths in turn; if a shader is not found then
+/// the copy embedded in the binary is used
+#[derive(Debug, Clone)]
+pub struct KernelsConfig {
+    accelerator: Accelerator,
+    shader_paths: Vec<PathBuf>,
+    buffer_size: usize,
+}
+
+//ip Default for KernelsConfig
+impl Default for KernelsConfig {
+    fn default() -> Self {
+        Self {
+            accelerator: Accelerator::Auto,
+            shader_paths: vec!["shaders".into()],
+            buffer_size: 16 * 1024 * 1024,
+        }
+    }
+}
+
+//ip KernelsConfig
+impl KernelsConfig {
+    //cp with_accelerator
+    pub fn with_accelerator(mut self, accelerator: Accelerator) -> Self {
+        self.accelerator = accelerator;
+        self
+    }
+
+    //cp with_shader_path
+    /// Add a directory to search for shaders, ahead of any already
+    /// present
+    pub fn with_shader_path<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.shader_paths.insert(0, path.into());
+        self
+    }
+
+    //cp with_embedded_shaders
+    /// Use only the shaders embedded in the binary
+    pub fn with_embedded_shaders(mut self) -> Self {
+        self.shader_paths.clear();
+        self
+    }
+
+    //cp with_buffer_size
+    /// Set the size in bytes of the accelerator buffers
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    //mp set_accelerator
+    pub fn set_accelerator(&mut self, accelerator: Accelerator) {
+        self.accelerator = accelerator;
+    }
+
+    //mp add_shader_path
+    pub fn add_shader_path<P: Into<PathBuf>>(&mut self, path: P) {
+        self.shader_paths.insert(0, path.into());
+    }
+
+    //ap accelerator
+    pub fn accelerator(&self) -> Accelerator {
+        self.accelerator
+    }
+
+    //ap shader_paths
+    pub fn shader_paths(&self) -> &[PathBuf] {
+        &self.shader_paths
+    }
+
+    //ap buffer_size
+    pub fn buffer_size(&self) -> usize {
+        self.buffer_size
+    }
+}
@@ -1,6 +1,42 @@
 //a Imports
 use crate::{Accelerate, KernelArgs};
 
+//a Support functions
+//fi at
+/// Read data at x,y, clamping to the edges of the image
+fn at(data: &[f32], width: usize, height: usize, x: isize, y: isize) -> f32 {
+    let x = x.clamp(0, width as isize - 1) as usize;
+    let y = y.clamp(0, height as isize - 1) as usize;
+    data[x + y * width]
+}
+
+//fi gaussian_weight
+/// Unnormalized Gaussian weight at a squared distance of d2
+fn gaussian_weight(d2: f32, sigma: f32) -> f32 {
+    let s = sigma.max(0.01);
+    (-d2 / (2.0 * s * s)).exp()
+}
+
+//fi gradient
+/// Gradient at x,y using a 3x3 kernel with the given edge and centre
+/// weights (1,2 for Sobel, 3,10 for Scharr), normalized so that a
+/// unit ramp has a unit gradient
+fn gradient(
+    data: &[f32],
+    width: usize,
+    height: usize,
+    x: isize,
+    y: isize,
+    edge: f32,
+    centre: f32,
+) -> (f32, f32) {
+    let d = |dx: isize, dy: isize| at(data, width, height, x + dx, y + dy);
+    let gx = (d(1, -1) + d(1, 1) - d(-1, -1) - d(-1, 1)) * edge + (d(1, 0) - d(-1, 0)) * centre;
+    let gy = (d(-1, 1) + d(1, 1) - d(-1, -1) - d(1, -1)) * edge + (d(0, 1) - d(0, -1)) * centre;
+    let norm = 2.0 * (2.0 * edge + centre);
+    (gx / norm, gy / norm)
+}
+
 //tp ImageAccelerator
 #[derive(Debug, Default)]
 pub struct ImageAccelerator();
@@ -121,6 +157,412 @@ impl ImageAccelerator {
         }
     }
 
+    //mp gaussian
+    /// Separable Gaussian blur in X (or Y), normalized over the window
+    pub fn gaussian(
+        &self,
+        args: &KernelArgs,
+        src_data: Option<&[f32]>,
+        out_data: &mut [f32],
+        in_y: bool,
+    ) {
+        let (width, height) = args.dims();
+        let scale = args.scale();
+        let sigma = args.sigma();
+        let r = args.radius_for_sigma(sigma) as isize;
+        let weights: Vec<f32> = (-r..=r)
+            .map(|i| gaussian_weight((i * i) as f32, sigma))
+            .collect();
+        let total: f32 = weights.iter().sum();
+        let src = src_data.unwrap_or(out_data).to_vec();
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let mut sum = 0.0;
+                for (i, w) in (-r..=r).zip(weights.iter()) {
+                    let v = {
+                        if in_y {
+                            at(&src, width, height, x, y + i)
+                        } else {
+                            at(&src, width, height, x + i, y)
+                        }
+                    };
+                    sum += w * v;
+                }
+                out_data[x as usize + y as usize * width] = sum / total * scale;
+            }
+        }
+    }
+
+    //mp gradient
+    /// Sobel or Scharr gradient magnitude or angle
+    pub fn gradient(
+        &self,
+        args: &KernelArgs,
+        src_data: Option<&[f32]>,
+        out_data: &mut [f32],
+        scharr: bool,
+        angle: bool,
+    ) {
+        let (width, height) = args.dims();
+        let scale = args.scale();
+        let (edge, centre) = if scharr { (3.0, 10.0) } else { (1.0, 2.0) };
+        let src = src_data.unwrap_or(out_data).to_vec();
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let (gx, gy) = gradient(&src, width, height, x, y, edge, centre);
+                let v = {
+                    if angle {
+                        gy.atan2(gx)
+                    } else {
+                        (gx * gx + gy * gy).sqrt()
+                    }
+                };
+                out_data[x as usize + y as usize * width] = v * scale;
+            }
+        }
+    }
+
+    //mp log
+    /// Scale-normalized negated Laplacian of Gaussian, so that bright
+    /// blobs of about sigma radius give a positive peak
+    ///
+    /// The mean of the (truncated) kernel is removed so that flat
+    /// regions give zero
+    pub fn log(&self, args: &KernelArgs, src_data: Option<&[f32]>, out_data: &mut [f32]) {
+        let scale = args.scale();
+        let sigma = args.sigma().max(0.01);
+        let s2 = sigma * sigma;
+        let r = args.radius_for_sigma(sigma) as isize;
+        let mut weights = vec![];
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let d2 = (dx * dx + dy * dy) as f32;
+                weights.push(
+                    (1.0 - d2 / (2.0 * s2)) * gaussian_weight(d2, sigma)
+                        / (std::f32::consts::PI * s2),
+                );
+            }
+        }
+        let mean = weights.iter().sum::<f32>() / (weights.len() as f32);
+        weights.iter_mut().for_each(|w| *w -= mean);
+        self.convolve(args, src_data, out_data, r, &weights, scale);
+    }
+
+    //mp dog
+    /// Difference of Gaussians (sigma minus sigma_b), each normalized
+    /// over the window; with sigma < sigma_b bright blobs are positive
+    pub fn dog(&self, args: &KernelArgs, src_data: Option<&[f32]>, out_data: &mut [f32]) {
+        let scale = args.scale();
+        let sigma_a = args.sigma();
+        let sigma_b = args.sigma_b();
+        let r = args.radius_for_sigma(sigma_a.max(sigma_b)) as isize;
+        let mut weights_a = vec![];
+        let mut weights_b = vec![];
+        for dy in -r..=r {
+            for dx in -r..=r {
+                let d2 = (dx * dx + dy * dy) as f32;
+                weights_a.push(gaussian_weight(d2, sigma_a));
+                weights_b.push(gaussian_weight(d2, sigma_b));
+            }
+        }
+        let total_a: f32 = weights_a.iter().sum();
+        let total_b: f32 = weights_b.iter().sum();
+        let weights: Vec<f32> = weights_a
+            .iter()
+            .zip(weights_b.iter())
+            .map(|(a, b)| a / total_a - b / total_b)
+            .collect();
+        self.convolve(args, src_data, out_data, r, &weights, scale);
+    }
+
+    //mi convolve
+    /// Convolve with a square (2r+1)*(2r+1) kernel, clamping at the edges
+    fn convolve(
+        &self,
+        args: &KernelArgs,
+        src_data: Option<&[f32]>,
+        out_data: &mut [f32],
+        r: isize,
+        weights: &[f32],
+        scale: f32,
+    ) {
+        let (width, height) = args.dims();
+        let src = src_data.unwrap_or(out_data).to_vec();
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let mut sum = 0.0;
+                let mut w = weights.iter();
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        sum += w.next().unwrap() * at(&src, width, height, x + dx, y + dy);
+                    }
+                }
+                out_data[x as usize + y as usize * width] = sum * scale;
+            }
+        }
+    }
+
+    //mp nms
+    /// Non-maximum suppression in a window of radius size (at least 1)
+    ///
+    /// A value is kept if it is positive and no other value in the
+    /// window is larger; otherwise it is set to zero
+    pub fn nms(&self, args: &KernelArgs, src_data: Option<&[f32]>, out_data: &mut [f32]) {
+        let (width, height) = args.dims();
+        let scale = args.scale();
+        let r = args.size().max(1) as isize;
+        let src = src_data.unwrap_or(out_data).to_vec();
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let v = src[x as usize + y as usize * width];
+                let mut is_max = v > 0.0;
+                for dy in -r..=r {
+                    for dx in -r..=r {
+                        is_max = is_max && at(&src, width, height, x + dx, y + dy) <= v;
+                    }
+                }
+                out_data[x as usize + y as usize * width] = if is_max { v * scale } else { 0.0 };
+            }
+        }
+    }
+
+    //mp nms_edge
+    /// Non-maximum suppression of an edge magnitude along the gradient direction
+    ///
+    /// The source data is the gradient angle, and must be provided;
+    /// the out data is the gradient magnitude on input, and the
+    /// suppressed magnitude on output
+    pub fn nms_edge(
+        &self,
+        args: &KernelArgs,
+        src_data: Option<&[f32]>,
+        out_data: &mut [f32],
+    ) -> Result<(), String> {
+        let (width, height) = args.dims();
+        let scale = args.scale();
+        let Some(src_data) = src_data else {
+            return Err("Shader nms_edge requires the gradient angles as source data".into());
+        };
+        let mag = out_data.to_vec();
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let i = x as usize + y as usize * width;
+                let angle = src_data[i];
+                let dx = angle.cos().round() as isize;
+                let dy = angle.sin().round() as isize;
+                let v = mag[i];
+                let is_max = v >= at(&mag, width, height, x + dx, y + dy)
+                    && v >= at(&mag, width, height, x - dx, y - dy);
+                out_data[i] = if is_max { v * scale } else { 0.0 };
+            }
+        }
+        Ok(())
+    }
+
+    //mp integral
+    /// Running sum along each row (or down each column) of the
+    /// image; applying both yields a summed-area table
+    pub fn integral(
+        &self,
+        args: &KernelArgs,
+        src_data: Option<&[f32]>,
+        out_data: &mut [f32],
+        in_y: bool,
+    ) {
+        let (width, height) = args.dims();
+        let scale = args.scale();
+        let src = src_data.unwrap_or(out_data).to_vec();
+        let (n, len, step, stride) = {
+            if in_y {
+                (width, height, 1, width)
+            } else {
+                (height, width, width, 1)
+            }
+        };
+        for i in 0..n {
+            let mut sum = 0.0;
+            for j in 0..len {
+                let ofs = i * step + j * stride;
+                sum += src[ofs];
+                out_data[ofs] = sum * scale;
+            }
+        }
+    }
+
+    //mp window_mean_sat
+    /// Mean over a window of size*size centred on each pixel, using a
+    /// summed-area table as the input; the window is clipped to the
+    /// image
+    pub fn window_mean_sat(
+        &self,
+        args: &KernelArgs,
+        src_data: Option<&[f32]>,
+        out_data: &mut [f32],
+    ) {
+        let (width, height) = args.dims();
+        let scale = args.scale();
+        let half_ws = (args.size() / 2) as isize;
+        let sat = src_data.unwrap_or(out_data).to_vec();
+        let sat_at = |x: isize, y: isize| {
+            if x < 0 || y < 0 {
+                0.0
+            } else {
+                sat[x as usize + y as usize * width]
+            }
+        };
+        for y in 0..height as isize {
+            for x in 0..width as isize {
+                let x0 = (x - half_ws - 1).max(-1);
+                let y0 = (y - half_ws - 1).max(-1);
+                let x1 = (x + half_ws).min(width as isize - 1);
+                let y1 = (y + half_ws).min(height as isize - 1);
+                let n = ((x1 - x0) * (y1 - y0)) as f32;
+                let sum = sat_at(x1, y1) - sat_at(x1, y0) - sat_at(x0, y1) + sat_at(x0, y0);
+                out_data[x as usize + y as usize * width] = sum / n * scale;
+            }
+        }
+    }
+
+    //mp downsample
+    /// Halve a source image (src_width by src_height) by averaging 2x2 blocks
+    pub fn downsample(&self, args: &KernelArgs, src_data: Option<&[f32]>, out_data: &mut [f32]) {
+        let (width, height) = args.dims();
+        let (src_width, src_height) = args.src_dims();
+        let scale = args.scale();
+        if let Some(src) = src_data {
+            for y in 0..height as isize {
+                for x in 0..width as isize {
+                    let d = |dx: isize, dy: isize| {
+                        at(src, src_width, src_height, x * 2 + dx, y * 2 + dy)
+                    };
+                    let sum = d(0, 0) + d(1, 0) + d(0, 1) + d(1, 1);
+                    out_data[x as usize + y as usize * width] = sum * 0.25 * scale;
+                }
+            }
+        }
+    }
+
+    //mp upsample
+    /// Bilinearly resample a source image (src_width by src_height) to width by height
+    pub fn upsample(&self, args: &KernelArgs, src_data: Option<&[f32]>, out_data: &mut [f32]) {
+        let (width, height) = args.dims();
+        let (src_width, src_height) = args.src_dims();
+        let scale = args.scale();
+        if let Some(src) = src_data {
+            for y in 0..height {
+                let sy = ((y as f32 + 0.5) * src_height as f32 / height as f32 - 0.5).max(0.0);
+                let y0 = sy.floor();
+                let fy = sy - y0;
+                let y0 = y0 as isize;
+                for x in 0..width {
+                    let sx = ((x as f32 + 0.5) * src_width as f32 / width as f32 - 0.5).max(0.0);
+                    let x0 = sx.floor();
+                    let fx = sx - x0;
+                    let x0 = x0 as isize;
+                    let d = |dx: isize, dy: isize| at(src, src_width, src_height, x0 + dx, y0 + dy);
+                    let top = d(0, 0) + (d(1, 0) - d(0, 0)) * fx;
+                    let bottom = d(0, 1) + (d(1, 1) - d(0, 1)) * fx;
+                    out_data[x + y * width] = (top + (bottom - top) * fy) * scale;
+                }
+            }
+        }
+    }
+
+    //mp window_corr
+    /// Correlate a window of the source image centred on cx,cy with
+    /// the window centred on every pixel of the out data, as the
+    /// 'window_corr' shader does
+    ///
+    /// The result is the cube of (n.AB - A.B) / (n.A2 - A.A), or zero
+    /// if that is negative or the windows are not wholly within the
+    /// images
+    pub fn window_corr(&self, args: &KernelArgs, src_data: Option<&[f32]>, out_data: &mut [f32]) {
+        let (width, height) = args.dims();
+        let (src_width, src_height) = args.src_dims();
+        let scale = args.scale();
+        let half_ws = args.size() / 2;
+        let ws = half_ws * 2;
+        let (src_x, src_y) = (args.cx as usize, args.cy as usize);
+        let Some(src) = src_data else {
+            return;
+        };
+        let img_b = out_data.to_vec();
+        let src_out_of_bounds = src_x < half_ws
+            || src_x + half_ws > src_width
+            || src_y < half_ws
+            || src_y + half_ws > src_height;
+        let src_ofs = {
+            if src_out_of_bounds {
+                0
+            } else {
+                (src_x - half_ws) + (src_y - half_ws) * src_width
+            }
+        };
+        let n = (ws * ws) as f32;
+        for y in 0..height {
+            for x in 0..width {
+                let out_of_bounds =
+                    x < half_ws || x + half_ws > width || y < half_ws || y + half_ws > height;
+                if out_of_bounds || src_out_of_bounds {
+                    out_data[x + y * width] = 0.0;
+                    continue;
+                }
+                let cmp_ofs = (x - half_ws) + (y - half_ws) * width;
+                let (mut a, mut b, mut a2, mut ab) = (0.0_f32, 0.0_f32, 0.0_f32, 0.0_f32);
+                for dy in 0..ws {
+                    for dx in 0..ws {
+                        let i_a = src[src_ofs + dy * src_width + dx];
+                        let i_b = img_b[cmp_ofs + dy * width + dx];
+                        a += i_a;
+                        b += i_b;
+                        a2 += i_a * i_a;
+                        ab += i_a * i_b;
+                    }
+                }
+                let value = (n * ab - a * b) / (a2 * n - a * a);
+                let value = if value > 0.0 { value } else { 0.0 };
+                out_data[x + y * width] = value * value * value * scale;
+            }
+        }
+    }
+
+    //mp subtended_err2
+    /// For each point (x, y, z, err2) of out_data, set err2 to the sum
+    /// of the squared errors in the angles subtended by the lines of
+    /// src_data, each of which is (p0.xyz, angle, p1.xyz, unused)
+    ///
+    /// The width of args is the number of points, and the size is the
+    /// number of lines; the calculation is performed in f64
+    pub fn subtended_err2(
+        &self,
+        args: &KernelArgs,
+        src_data: Option<&[f32]>,
+        out_data: &mut [f32],
+    ) {
+        let Some(src_data) = src_data else {
+            return;
+        };
+        let lines = &src_data[0..args.size() * 8];
+        for pt in out_data.chunks_exact_mut(4).take(args.width()) {
+            let p = [pt[0] as f64, pt[1] as f64, pt[2] as f64];
+            let mut err2 = 0.0_f64;
+            for l in lines.chunks_exact(8) {
+                let a = [l[0] as f64 - p[0], l[1] as f64 - p[1], l[2] as f64 - p[2]];
+                let b = [l[4] as f64 - p[0], l[5] as f64 - p[1], l[6] as f64 - p[2]];
+                let cross = [
+                    a[1] * b[2] - a[2] * b[1],
+                    a[2] * b[0] - a[0] * b[2],
+                    a[0] * b[1] - a[1] * b[0],
+                ];
+                let sin = (cross[0] * cross[0] + cross[1] * cross[1] + cross[2] * cross[2]).sqrt();
+                let cos = a[0] * b[0] + a[1] * b[1] + a[2] * b[2];
+                let err = sin.atan2(cos) - l[3] as f64;
+                err2 += err * err;
+            }
+            pt[3] = err2 as f32;
+        }
+    }
+
     //mp circle_fft16
     /*
     pub fn circle_fft16(&self, args: &KernelArgs, src_data: Option<&[f32]>, out_data: &mut [f32]) {
@@ -193,6 +635,74 @@ impl Accelerate for ImageAccelerator {
                 self.sqrt(args, src_data, out_data);
                 Ok(true)
             }
+            "gaussian_x" => {
+                self.gaussian(args, src_data, out_data, false);
+                Ok(true)
+            }
+            "gaussian_y" => {
+                self.gaussian(args, src_data, out_data, true);
+                Ok(true)
+            }
+            "sobel_mag" => {
+                self.gradient(args, src_data, out_data, false, false);
+                Ok(true)
+            }
+            "sobel_angle" => {
+                self.gradient(args, src_data, out_data, false, true);
+                Ok(true)
+            }
+            "scharr_mag" => {
+                self.gradient(args, src_data, out_data, true, false);
+                Ok(true)
+            }
+            "scharr_angle" => {
+                self.gradient(args, src_data, out_data, true, true);
+                Ok(true)
+            }
+            "log" => {
+                self.log(args, src_data, out_data);
+                Ok(true)
+            }
+            "dog" => {
+                self.dog(args, src_data, out_data);
+                Ok(true)
+            }
+            "nms" => {
+                self.nms(args, src_data, out_data);
+                Ok(true)
+            }
+            "nms_edge" => {
+                self.nms_edge(args, src_data, out_data)?;
+                Ok(true)
+            }
+            "integral_x" => {
+                self.integral(args, src_data, out_data, false);
+                Ok(true)
+            }
+            "integral_y" => {
+                self.integral(args, src_data, out_data, true);
+                Ok(true)
+            }
+            "window_mean_sat" => {
+                self.window_mean_sat(args, src_data, out_data);
+                Ok(true)
+            }
+            "downsample" => {
+                self.downsample(args, src_data, out_data);
+                Ok(true)
+            }
+            "upsample" => {
+                self.upsample(args, src_data, out_data);
+                Ok(true)
+            }
+            "window_corr" => {
+                self.window_corr(args, src_data, out_data);
+                Ok(true)
+            }
+            "subtended_err2" => {
+                self.subtended_err2(args, src_data, out_data);
+                Ok(true)
+            }
             _ => Err(format!("Unimplemented shader {shader}")),
         }
     }
@@ -0,0 +1,141 @@
+//a Imports
+use crate::{KernelArgs, Kernels};
+
+//a IntegralImage
+//tp IntegralImage
+/// A summed-area table for an image
+///
+/// Each entry is the sum of all of the image values above and to the
+/// left of it (inclusive), so the sum of any rectangle of the image
+/// can be found with four lookups.
+///
+/// The values are f32 (as used by the accelerators), so for large
+/// images the data should have a mean near zero if accurate sums of
+/// small windows are required
+#[derive(Debug, Default, Clone)]
+pub struct IntegralImage {
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+}
+
+//ip IntegralImage
+impl IntegralImage {
+    //cp of_data
+    /// Create an integral image from image data on the CPU
+    ///
+    /// The sums are accumulated in f64
+    pub fn of_data((width, height): (usize, usize), data: &[f32]) -> Self {
+        let mut sat = vec![0.0_f32; width * height];
+        let mut col_sums = vec![0.0_f64; width];
+        for y in 0..height {
+            let mut row_sum = 0.0_f64;
+            for x in 0..width {
+                row_sum += data[x + y * width] as f64;
+                col_sums[x] += row_sum;
+                sat[x + y * width] = col_sums[x] as f32;
+            }
+        }
+        Self {
+            width,
+            height,
+            data: sat,
+        }
+    }
+
+    //cp of_sat
+    /// Create an integral image from an already-generated summed-area table
+    pub fn of_sat((width, height): (usize, usize), data: Vec<f32>) -> Self {
+        assert_eq!(data.len(), width * height);
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    //ap dims
+    pub fn dims(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    //ap data
+    pub fn data(&self) -> &[f32] {
+        &self.data
+    }
+
+    //mp sum
+    /// Sum the image over the rectangle x0 <= x < x1, y0 <= y < y1
+    ///
+    /// The rectangle is clipped to the image
+    pub fn sum(&self, x0: usize, y0: usize, x1: usize, y1: usize) -> f32 {
+        let x1 = x1.min(self.width);
+        let y1 = y1.min(self.height);
+        if x0 >= x1 || y0 >= y1 {
+            return 0.0;
+        }
+        let at = |x: usize, y: usize| {
+            if x == 0 || y == 0 {
+                0.0
+            } else {
+                self.data[(x - 1) + (y - 1) * self.width]
+            }
+        };
+        at(x1, y1) - at(x0, y1) - at(x1, y0) + at(x0, y0)
+    }
+
+    //mp window_mean
+    /// Mean of the image in a window of size*size centred on cx, cy,
+    /// clipped to the image
+    pub fn window_mean(&self, (cx, cy): (usize, usize), size: usize) -> f32 {
+        let half_ws = size / 2;
+        let x0 = cx.saturating_sub(half_ws);
+        let y0 = cy.saturating_sub(half_ws);
+        let x1 = (cx + half_ws + 1).min(self.width);
+        let y1 = (cy + half_ws + 1).min(self.height);
+        let n = (x1.saturating_sub(x0) * y1.saturating_sub(y0)).max(1);
+        self.sum(x0, y0, x1, y1) / (n as f32)
+    }
+
+    //zz All done
+}
+
+//a Kernels
+//ip Kernels - integral images
+impl Kernels {
+    //mp integral_image
+    /// Create an integral image using the accelerator
+    ///
+    /// The row sums are generated with one work item per row, and
+    /// then the column sums with one work item per column
+    pub fn integral_image(
+        &self,
+        size: (usize, usize),
+        data: &[f32],
+    ) -> Result<IntegralImage, String> {
+        let (width, height) = size;
+        let args: KernelArgs = size.into();
+        let mut sat = data.to_vec();
+        self.run_shader("integral_x", &args, height, None, &mut sat)?;
+        self.run_shader("integral_y", &args, width, None, &mut sat)?;
+        Ok(IntegralImage::of_sat(size, sat))
+    }
+
+    //mp window_mean_sat
+    /// Generate the mean over a window of size*size around every
+    /// pixel of an image using its integral image
+    pub fn window_mean_sat(&self, sat: &IntegralImage, size: usize) -> Result<Vec<f32>, String> {
+        let (width, height) = sat.dims();
+        let args: KernelArgs = sat.dims().into();
+        let args = args.with_size(size);
+        let mut out = vec![0.0; width * height];
+        self.run_shader(
+            "window_mean_sat",
+            &args,
+            width * height,
+            Some(sat.data()),
+            &mut out,
+        )?;
+        Ok(out)
+    }
+}
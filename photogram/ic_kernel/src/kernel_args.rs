@@ -26,6 +26,11 @@ pub struct KernelArgs {
     pub src_width: u32,
     /// Height of the source 'image'
     pub src_height: u32,
+    /// Standard deviation for Gaussian-derived kernels
+    pub sigma: f32,
+    /// Second standard deviation (difference-of-Gaussians); if not
+    /// positive then 1.6 times sigma is used
+    pub sigma_b: f32,
 }
 
 //ip Default for KernelArgs
@@ -42,6 +47,8 @@ impl std::default::Default for KernelArgs {
             sin_a: 0.0,
             src_width: 0,
             src_height: 0,
+            sigma: 1.0,
+            sigma_b: 0.0,
         }
     }
 }
@@ -86,6 +93,15 @@ impl KernelArgs {
         self.sin_a = sin_a;
         self
     }
+    pub fn with_sigma(mut self, sigma: f32) -> Self {
+        self.sigma = sigma;
+        self
+    }
+    pub fn with_sigmas(mut self, sigma: f32, sigma_b: f32) -> Self {
+        self.sigma = sigma;
+        self.sigma_b = sigma_b;
+        self
+    }
     pub fn with_xy(mut self, (x, y): (usize, usize)) -> Self {
         self.cx = x as u32;
         self.cy = y as u32;
@@ -106,4 +122,30 @@ impl KernelArgs {
     pub fn dims(&self) -> (usize, usize) {
         (self.width as usize, self.height as usize)
     }
+    pub fn src_dims(&self) -> (usize, usize) {
+        (self.src_width as usize, self.src_height as usize)
+    }
+    pub fn sigma(&self) -> f32 {
+        self.sigma
+    }
+    pub fn sigma_b(&self) -> f32 {
+        if self.sigma_b > 0.0 {
+            self.sigma_b
+        } else {
+            self.sigma * 1.6
+        }
+    }
+
+    //mp radius_for_sigma
+    /// The radius of a Gaussian-derived kernel for a given sigma
+    ///
+    /// If the size is nonzero then it is used as the radius;
+    /// otherwise the kernel extends to three standard deviations
+    pub fn radius_for_sigma(&self, sigma: f32) -> usize {
+        if self.size > 0 {
+            self.size as usize
+        } else {
+            (3.0 * sigma).ceil() as usize
+        }
+    }
 }
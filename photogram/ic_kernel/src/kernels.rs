@@ -1,7 +1,6 @@
 //a Imports
-use std::path::Path;
-
-use crate::{accel_wgpu, cpu, Accelerate, KernelArgs};
+use crate::shaders::{self, SHADER_NAMES};
+use crate::{accel_wgpu, cpu, tiling, Accelerate, KernelArgs, KernelPipeline, KernelsConfig};
 
 //a Kernels
 //tp Kernels
@@ -11,6 +10,9 @@ pub struct Kernels {
     wgpu: Option<accel_wgpu::ImageAccelerator>,
     cpu: cpu::ImageAccelerator,
     verbose: bool,
+    /// If set, the maximum number of elements to pass to the wgpu
+    /// accelerator in one go (which must be less than its buffer size)
+    max_tile_elements: Option<usize>,
 }
 
 //ip Default for Kernels
@@ -21,44 +23,78 @@ impl Default for Kernels {
 }
 //ip Kernels
 impl Kernels {
-    //ci read_json
-    /// TODO: move me to accel_wgpu
-    fn read_json<P: AsRef<Path>>(
-        root: P,
-        names: &[&str],
+    //ci create_wgpu
+    /// Create the wgpu accelerator and load all of the shaders into it
+    fn create_wgpu(
+        config: &KernelsConfig,
+        backends: wgpu::Backends,
+        force_fallback: bool,
     ) -> Result<accel_wgpu::ImageAccelerator, String> {
-        let accelerator = accel_wgpu::AccelWgpu::new();
-        let mut wgpu = accel_wgpu::ImageAccelerator::new(accelerator, 16 * 1024 * 1024)?;
-        for n in names {
-            let mut root = root.as_ref().to_owned();
-            root.push(n);
-            let mut x = root.clone();
-            x.set_extension("json");
-            let json = std::fs::read_to_string(&x)
-                .map_err(|e| format!("Error reading Json describing shader file {e}"))?;
-            root.set_extension("wgsl");
-            let x = root.clone();
-            let shader_desc_file = accel_wgpu::ShaderFileDesc::from_json(x, &json)
-                .map_err(|e| format!("Error parsing JSON file {}: {e}", root.display()))?;
+        let accelerator = accel_wgpu::AccelWgpu::new(backends, force_fallback)?;
+        let mut wgpu = accel_wgpu::ImageAccelerator::new(accelerator, config.buffer_size())?;
+        for n in SHADER_NAMES {
+            let shader_desc_file = shaders::find_shader_file(config.shader_paths(), n)?;
             wgpu.create_pipelines(shader_desc_file)?;
         }
         Ok(wgpu)
     }
 
     //cp new
+    /// Create with the default configuration - using wgpu if
+    /// possible, else the CPU
     pub fn new() -> Self {
+        Self::of_config(&KernelsConfig::default())
+            .expect("Automatic accelerator selection always falls back to the CPU")
+    }
+
+    //cp of_config
+    /// Create using a configuration
+    ///
+    /// If the configuration requires a particular wgpu accelerator
+    /// and it cannot be created then an error is returned; with
+    /// automatic selection the CPU is used instead
+    pub fn of_config(config: &KernelsConfig) -> Result<Self, String> {
         let cpu = cpu::ImageAccelerator::default();
+        let accelerator = config.accelerator();
         let wgpu = {
-            match Self::read_json("shaders", &["statistical", "extract"]) {
-                Err(e) => {
-                    eprintln!("Wgpu acceleration failed, not using that : {e}");
-                    None
+            if let Some((backends, force_fallback)) = accelerator.wgpu_options() {
+                match Self::create_wgpu(config, backends, force_fallback) {
+                    Err(e) => {
+                        if accelerator.is_required() {
+                            return Err(format!("Failed to create {accelerator} accelerator: {e}"));
+                        }
+                        eprintln!("Wgpu acceleration failed, not using that : {e}");
+                        None
+                    }
+                    Ok(s) => Some(s),
                 }
-                Ok(s) => Some(s),
+            } else {
+                None
             }
         };
         let verbose = false;
-        Self { wgpu, cpu, verbose }
+        let max_tile_elements = None;
+        Ok(Self {
+            wgpu,
+            cpu,
+            verbose,
+            max_tile_elements,
+        })
+    }
+
+    //ap has_wgpu
+    /// Returns true if a wgpu accelerator is in use
+    pub fn has_wgpu(&self) -> bool {
+        self.wgpu.is_some()
+    }
+
+    //ap description
+    /// Describe the accelerator in use
+    pub fn description(&self) -> String {
+        match &self.wgpu {
+            Some(wgpu) => wgpu.description(),
+            None => "cpu".into(),
+        }
     }
 
     //mp set_verbose
@@ -67,7 +103,21 @@ impl Kernels {
         self.verbose = verbose;
     }
 
+    //mp set_max_tile_elements
+    /// Limit the number of elements passed to the wgpu accelerator in
+    /// one go; larger images are processed in strips
+    pub fn set_max_tile_elements(&mut self, max_tile_elements: Option<usize>) {
+        self.max_tile_elements = max_tile_elements;
+    }
+
     //mp run_shader
+    /// Run a kernel on the data, using wgpu if possible and the CPU
+    /// if not
+    ///
+    /// If the data is too large for the wgpu accelerator then the
+    /// kernel is run in horizontal strips (with enough overlap for
+    /// the kernel's window); if the kernel cannot be run in strips
+    /// then the CPU is used
     pub fn run_shader(
         &self,
         shader: &str,
@@ -79,8 +129,31 @@ impl Kernels {
         if self.verbose {
             eprintln!("Run shader {shader} with {work_items} items");
         }
+        if shader == "nms_edge" && src_data.is_none() {
+            return Err("Shader nms_edge requires the gradient angles as source data".into());
+        }
         if let Some(wgpu) = &self.wgpu {
-            if wgpu.run_shader(shader, args, work_items, src_data, out_data)? {
+            let max_elements = self
+                .max_tile_elements
+                .map_or(wgpu.max_elements(), |n| n.min(wgpu.max_elements()));
+            let too_large =
+                out_data.len() > max_elements || src_data.is_some_and(|s| s.len() > max_elements);
+            if too_large {
+                if tiling::run_tiled(
+                    wgpu,
+                    max_elements,
+                    shader,
+                    args,
+                    work_items,
+                    src_data,
+                    out_data,
+                )? {
+                    return Ok(());
+                }
+                if self.verbose {
+                    eprintln!("Shader {shader} cannot be tiled, using the CPU");
+                }
+            } else if wgpu.run_shader(shader, args, work_items, src_data, out_data)? {
                 return Ok(());
             }
         }
@@ -89,6 +162,86 @@ impl Kernels {
             .map(|_| ())
     }
 
+    //mp run_pipeline_wgpu
+    /// Run a pipeline using the wgpu accelerator, if there is one and
+    /// it supports all of the kernels of the pipeline
+    pub(crate) fn run_pipeline_wgpu(
+        &self,
+        pipeline: &KernelPipeline,
+        args: &KernelArgs,
+        buffers: &mut [Vec<f32>],
+    ) -> Result<bool, String> {
+        if self.verbose {
+            eprintln!("Run pipeline of {} steps", pipeline.steps().len());
+        }
+        if let Some(max_elements) = self.max_tile_elements
+            && buffers.iter().any(|b| b.len() > max_elements)
+        {
+            return Ok(false);
+        }
+        match &self.wgpu {
+            Some(wgpu) => wgpu.run_pipeline(pipeline, args, buffers),
+            None => Ok(false),
+        }
+    }
+
+    //mp gaussian_blur
+    /// Blur an image in-place with a separable Gaussian of the given
+    /// sigma; the radius is taken from args (or 3 sigma if zero)
+    pub fn gaussian_blur(&self, args: &KernelArgs, data: &mut [f32]) -> Result<(), String> {
+        let (width, height) = args.dims();
+        self.run_shader("gaussian_x", args, width * height, None, data)?;
+        self.run_shader("gaussian_y", args, width * height, None, data)
+    }
+
+    //mp edges
+    /// Generate the gradient magnitude of an image after non-maximum
+    /// suppression along the gradient direction
+    ///
+    /// If scharr is true then the Scharr kernel is used rather than Sobel
+    pub fn edges(&self, args: &KernelArgs, data: &[f32], scharr: bool) -> Result<Vec<f32>, String> {
+        let (width, height) = args.dims();
+        let (mag, angle) = {
+            if scharr {
+                ("scharr_mag", "scharr_angle")
+            } else {
+                ("sobel_mag", "sobel_angle")
+            }
+        };
+        let unit_args = args.with_scale(1.0);
+        let mut angles = data.to_vec();
+        self.run_shader(angle, &unit_args, width * height, None, &mut angles)?;
+        let mut edges = data.to_vec();
+        self.run_shader(mag, &unit_args, width * height, None, &mut edges)?;
+        self.run_shader("nms_edge", args, width * height, Some(&angles), &mut edges)?;
+        Ok(edges)
+    }
+
+    //mp subtended_err2
+    /// Evaluate the error in the angles subtended by a set of lines at
+    /// a batch of points
+    ///
+    /// Each line is 8 values (p0.xyz, angle subtended, p1.xyz,
+    /// unused); each point is 4 values (x, y, z, err2), and err2 is
+    /// set to the sum over the lines of the square of the difference
+    /// between the angle the line subtends at the point and its
+    /// expected angle
+    ///
+    /// The coordinates should be relative to some nearby origin, as
+    /// the accelerators use f32
+    pub fn subtended_err2(&self, lines: &[f32], points: &mut [f32]) -> Result<(), String> {
+        if !lines.len().is_multiple_of(8) || !points.len().is_multiple_of(4) {
+            return Err("Lines must be 8 values each, and points 4 values each".into());
+        }
+        let n = points.len() / 4;
+        if n == 0 {
+            return Ok(());
+        }
+        let args: KernelArgs = (n, 1).into();
+        let args = args.with_size(lines.len() / 8);
+        self.run_shader("subtended_err2", &args, n, Some(lines), points)
+    }
+
     //mp find_best_n_above_value
     pub fn find_best_n_above_value(
         &self,
@@ -2,11 +2,25 @@
 
 // Rename to kernel
 mod accel_wgpu;
+mod config;
 mod cpu;
+mod integral;
 mod kernel_args;
 mod kernels;
+mod pipeline;
+mod pyramid;
+mod shaders;
+mod tiling;
+pub use config::{Accelerator, KernelsConfig};
+pub use integral::IntegralImage;
 pub use kernel_args::KernelArgs;
 pub use kernels::Kernels;
+pub use pipeline::{
+    BufferDesc, KernelPipeline, PipelineBuffers, PipelineDesc, PipelineStep, StepArgs, StepDesc,
+    WorkItems,
+};
+pub use pyramid::{ImagePyramid, PyramidLevel};
+pub use shaders::SHADER_NAMES;
 
 mod traits;
 pub use traits::Accelerate;
@@ -0,0 +1,415 @@
+//a Imports
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{KernelArgs, Kernels};
+
+//a Builtin pipelines
+//ci BUILTIN_PIPELINES
+/// Pipelines that are compiled in, by name
+const BUILTIN_PIPELINES: &[(&str, &str)] = &[(
+    "window_std_dev",
+    include_str!("../pipelines/window_std_dev.json"),
+)];
+
+//ci SIGMA_KERNELS
+/// Kernels whose radius may be derived from their sigma
+const SIGMA_KERNELS: &[&str] = &["gaussian_x", "gaussian_y", "log", "dog"];
+
+//a Description types
+//tp WorkItems
+/// The number of work items for a pipeline step
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WorkItems {
+    /// One work item per element of the output buffer
+    #[default]
+    Pixels,
+    /// One work item per row of the image
+    Rows,
+    /// One work item per column of the image
+    Columns,
+}
+
+//tp StepArgs
+/// Overrides of the pipeline [KernelArgs] for a single step
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct StepArgs {
+    /// Window size, radius etc
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<usize>,
+    /// Scale factor
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scale: Option<f32>,
+    /// If true then the scale is divided by the size
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    scale_by_inv_size: bool,
+    /// Angle in degrees
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    angle: Option<f32>,
+    /// Standard deviation for Gaussian-derived kernels
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sigma: Option<f32>,
+    /// Second standard deviation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sigma_b: Option<f32>,
+}
+
+//ip StepArgs
+impl StepArgs {
+    //mp apply
+    /// Apply the overrides to a set of [KernelArgs]
+    fn apply(&self, args: &KernelArgs) -> KernelArgs {
+        let mut args = *args;
+        if let Some(size) = self.size {
+            args = args.with_size(size);
+        }
+        if let Some(scale) = self.scale {
+            args = args.with_scale(scale);
+        }
+        if self.scale_by_inv_size {
+            args = args.with_scale(args.scale() / (args.size().max(1) as f32));
+        }
+        if let Some(angle) = self.angle {
+            args = args.with_angle(angle.to_radians());
+        }
+        if let Some(sigma) = self.sigma {
+            args = args.with_sigma(sigma);
+        }
+        if let Some(sigma_b) = self.sigma_b {
+            args.sigma_b = sigma_b;
+        }
+        args
+    }
+}
+
+//tp BufferDesc
+/// Description of a named buffer of a pipeline
+///
+/// If `from` is None then the buffer is an input to the pipeline;
+/// otherwise it starts as a copy of the named (earlier) buffer
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct BufferDesc {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    from: Option<String>,
+}
+
+//tp StepDesc
+/// Description of a step of a pipeline
+///
+/// The kernel is run on the buffer `out` (in-place), with `src` as
+/// the source data if given
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct StepDesc {
+    kernel: String,
+    out: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    src: Option<String>,
+    #[serde(default)]
+    args: StepArgs,
+    #[serde(default)]
+    work_items: WorkItems,
+}
+
+//tp PipelineDesc
+/// The JSON description of a [KernelPipeline]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct PipelineDesc {
+    buffers: Vec<BufferDesc>,
+    steps: Vec<StepDesc>,
+    /// The buffer that is the result of the pipeline; if not given,
+    /// then the last buffer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+}
+
+//a PipelineStep
+//tp PipelineStep
+/// A step of a [KernelPipeline] with the buffers resolved to indices
+#[derive(Debug, Clone)]
+pub struct PipelineStep {
+    kernel: String,
+    out: usize,
+    src: Option<usize>,
+    args: StepArgs,
+    work_items: WorkItems,
+}
+
+//ip PipelineStep
+impl PipelineStep {
+    //ap kernel
+    pub fn kernel(&self) -> &str {
+        &self.kernel
+    }
+
+    //ap out
+    pub fn out(&self) -> usize {
+        self.out
+    }
+
+    //ap src
+    pub fn src(&self) -> Option<usize> {
+        self.src
+    }
+
+    //mp kernel_args
+    /// The [KernelArgs] for this step, given those for the pipeline
+    pub fn kernel_args(&self, args: &KernelArgs) -> KernelArgs {
+        self.args.apply(args)
+    }
+
+    //mp work_items
+    /// The number of work items for this step, given the number of
+    /// elements in the output buffer
+    pub fn work_items(&self, args: &KernelArgs, out_len: usize) -> usize {
+        match self.work_items {
+            WorkItems::Pixels => out_len,
+            WorkItems::Rows => args.height(),
+            WorkItems::Columns => args.width(),
+        }
+    }
+}
+
+//a KernelPipeline
+//tp KernelPipeline
+/// A sequence of kernels run on a set of named buffers
+///
+/// With a wgpu accelerator all of the buffers are kept on the GPU
+/// for the whole pipeline; otherwise each step is run as a separate
+/// [Kernels::run_shader]
+#[derive(Debug, Clone)]
+pub struct KernelPipeline {
+    buffers: Vec<BufferDesc>,
+    /// For each buffer, the index of the buffer it is a copy of (or
+    /// None if it is an input)
+    buffer_from: Vec<Option<usize>>,
+    steps: Vec<PipelineStep>,
+    output: usize,
+}
+
+//ip KernelPipeline
+impl KernelPipeline {
+    //cp of_desc
+    /// Create a pipeline from its description, resolving the buffer names
+    pub fn of_desc(desc: PipelineDesc) -> Result<Self, String> {
+        let mut buffer_from = vec![];
+        for (i, b) in desc.buffers.iter().enumerate() {
+            if desc.buffers[0..i].iter().any(|o| o.name == b.name) {
+                return Err(format!("Duplicate pipeline buffer '{}'", b.name));
+            }
+            let from = match &b.from {
+                None => None,
+                Some(f) => Some(Self::find_buffer(&desc.buffers[0..i], f)?),
+            };
+            buffer_from.push(from);
+        }
+        if desc.buffers.is_empty() {
+            return Err("A pipeline must have at least one buffer".into());
+        }
+        let mut steps = vec![];
+        for s in desc.steps {
+            let out = Self::find_buffer(&desc.buffers, &s.out)?;
+            let src = match &s.src {
+                None => None,
+                Some(src) => Some(Self::find_buffer(&desc.buffers, src)?),
+            };
+            steps.push(PipelineStep {
+                kernel: s.kernel,
+                out,
+                src,
+                args: s.args,
+                work_items: s.work_items,
+            });
+        }
+        let output = match &desc.output {
+            None => desc.buffers.len() - 1,
+            Some(o) => Self::find_buffer(&desc.buffers, o)?,
+        };
+        Ok(Self {
+            buffers: desc.buffers,
+            buffer_from,
+            steps,
+            output,
+        })
+    }
+
+    //cp from_json
+    pub fn from_json(json: &str) -> Result<Self, String> {
+        let desc = serde_json::from_str::<PipelineDesc>(json)
+            .map_err(|e| format!("Error parsing pipeline description: {e}"))?;
+        Self::of_desc(desc)
+    }
+
+    //cp read_file
+    pub fn read_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Error reading pipeline file {}: {e}", path.display()))?;
+        Self::from_json(&json)
+    }
+
+    //cp builtin
+    /// Get one of the pipelines that are compiled in
+    pub fn builtin(name: &str) -> Result<Self, String> {
+        let Some((_, json)) = BUILTIN_PIPELINES.iter().find(|(n, _)| *n == name) else {
+            return Err(format!("Unknown builtin pipeline '{name}'"));
+        };
+        Self::from_json(json)
+    }
+
+    //cp of_kernels
+    /// Create a pipeline that runs a sequence of kernels in-place on
+    /// a single buffer 'image'
+    pub fn of_kernels<S: AsRef<str>>(kernels: &[S]) -> Self {
+        let buffers = vec![BufferDesc {
+            name: "image".into(),
+            from: None,
+        }];
+        let steps = kernels
+            .iter()
+            .map(|k| PipelineStep {
+                kernel: k.as_ref().into(),
+                out: 0,
+                src: None,
+                args: StepArgs::default(),
+                work_items: WorkItems::Pixels,
+            })
+            .collect();
+        Self {
+            buffers,
+            buffer_from: vec![None],
+            steps,
+            output: 0,
+        }
+    }
+
+    //cp with_sigma_radius
+    /// Make the steps of Gaussian-derived kernels (Gaussian blur, LoG
+    /// and DoG) that do not set their own size use a size of 0, so that
+    /// their radius is derived from their sigma rather than taken from
+    /// the size of the pipeline [KernelArgs]
+    pub fn with_sigma_radius(mut self) -> Self {
+        for step in &mut self.steps {
+            if step.args.size.is_none() && SIGMA_KERNELS.contains(&step.kernel.as_str()) {
+                step.args.size = Some(0);
+            }
+        }
+        self
+    }
+
+    //fi find_buffer
+    fn find_buffer(buffers: &[BufferDesc], name: &str) -> Result<usize, String> {
+        buffers
+            .iter()
+            .position(|b| b.name == name)
+            .ok_or_else(|| format!("Unknown pipeline buffer '{name}'"))
+    }
+
+    //ap steps
+    pub fn steps(&self) -> &[PipelineStep] {
+        &self.steps
+    }
+
+    //ap output
+    /// The name of the output buffer
+    pub fn output(&self) -> &str {
+        &self.buffers[self.output].name
+    }
+
+    //mp create_buffers
+    /// Create the initial buffers for the pipeline from the inputs
+    pub fn create_buffers(&self, inputs: &[(&str, &[f32])]) -> Result<PipelineBuffers, String> {
+        let mut data: Vec<Vec<f32>> = vec![];
+        for (b, from) in self.buffers.iter().zip(self.buffer_from.iter()) {
+            if let Some(from) = from {
+                data.push(data[*from].clone());
+            } else if let Some((_, d)) = inputs.iter().find(|(n, _)| *n == b.name) {
+                data.push(d.to_vec());
+            } else {
+                return Err(format!("Pipeline input '{}' not provided", b.name));
+            }
+        }
+        Ok(PipelineBuffers {
+            names: self.buffers.iter().map(|b| b.name.clone()).collect(),
+            data,
+            output: self.output,
+        })
+    }
+
+    //zz All done
+}
+
+//a PipelineBuffers
+//tp PipelineBuffers
+/// The buffers of a [KernelPipeline], by name
+#[derive(Debug, Default, Clone)]
+pub struct PipelineBuffers {
+    names: Vec<String>,
+    data: Vec<Vec<f32>>,
+    output: usize,
+}
+
+//ip PipelineBuffers
+impl PipelineBuffers {
+    //ap get
+    pub fn get(&self, name: &str) -> Option<&[f32]> {
+        let n = self.names.iter().position(|b| b == name)?;
+        Some(&self.data[n])
+    }
+
+    //ap data_mut
+    pub(crate) fn data_mut(&mut self) -> &mut [Vec<f32>] {
+        &mut self.data
+    }
+
+    //mp take
+    /// Take the data of a buffer, leaving it empty
+    pub fn take(&mut self, name: &str) -> Option<Vec<f32>> {
+        let n = self.names.iter().position(|b| b == name)?;
+        Some(std::mem::take(&mut self.data[n]))
+    }
+
+    //mp take_output
+    /// Take the data of the pipeline's output buffer
+    pub fn take_output(&mut self) -> Vec<f32> {
+        std::mem::take(&mut self.data[self.output])
+    }
+}
+
+//a Kernels
+//ip Kernels - pipelines
+impl Kernels {
+    //mp run_pipeline
+    /// Run a pipeline, providing data for all of its input buffers
+    ///
+    /// The args are for the whole pipeline; each step may override
+    /// them
+    pub fn run_pipeline(
+        &self,
+        pipeline: &KernelPipeline,
+        args: &KernelArgs,
+        inputs: &[(&str, &[f32])],
+    ) -> Result<PipelineBuffers, String> {
+        let mut buffers = pipeline.create_buffers(inputs)?;
+        if self.run_pipeline_wgpu(pipeline, args, buffers.data_mut())? {
+            return Ok(buffers);
+        }
+        let data = buffers.data_mut();
+        for step in pipeline.steps() {
+            let step_args = step.kernel_args(args);
+            let mut out = std::mem::take(&mut data[step.out()]);
+            let work_items = step.work_items(&step_args, out.len());
+            let src = match step.src() {
+                Some(s) if s == step.out() => Some(out.clone()),
+                _ => None,
+            };
+            let src = src.as_deref().or(step.src().map(|s| data[s].as_slice()));
+            let result = self.run_shader(step.kernel(), &step_args, work_items, src, &mut out);
+            data[step.out()] = out;
+            result?;
+        }
+        Ok(buffers)
+    }
+}
@@ -0,0 +1,311 @@
+//a Imports
+use crate::{KernelArgs, Kernels};
+
+//a PyramidLevel
+//tp PyramidLevel
+/// A single level of an [ImagePyramid]
+#[derive(Debug, Default, Clone)]
+pub struct PyramidLevel {
+    width: usize,
+    height: usize,
+    data: Vec<f32>,
+}
+
+//ip PyramidLevel
+impl PyramidLevel {
+    //cp new
+    pub fn new((width, height): (usize, usize), data: Vec<f32>) -> Self {
+        assert_eq!(data.len(), width * height);
+        Self {
+            width,
+            height,
+            data,
+        }
+    }
+
+    //ap dims
+    pub fn dims(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    //ap data
+    pub fn data(&self) -> &[f32] {
+        &self.data
+    }
+
+    //mp crop
+    /// Copy out a rectangle of the level, clipped to the level
+    ///
+    /// Returns the actual size of the rectangle and its data
+    pub fn crop(
+        &self,
+        (x0, y0): (usize, usize),
+        (w, h): (usize, usize),
+    ) -> ((usize, usize), Vec<f32>) {
+        let w = w.min(self.width.saturating_sub(x0));
+        let h = h.min(self.height.saturating_sub(y0));
+        let mut data = Vec::with_capacity(w * h);
+        for y in y0..(y0 + h) {
+            let ofs = y * self.width + x0;
+            data.extend_from_slice(&self.data[ofs..ofs + w]);
+        }
+        ((w, h), data)
+    }
+}
+
+//a ImagePyramid
+//tp ImagePyramid
+/// A Gaussian or Laplacian image pyramid
+///
+/// Level 0 is the full-resolution image; each subsequent level is
+/// blurred and half the size (rounded up) of the previous level.
+///
+/// For a Laplacian pyramid each level (bar the last) holds the
+/// difference between that level of the Gaussian pyramid and the
+/// upsampled next level; the last level is the same as the Gaussian
+/// pyramid's last level.
+#[derive(Debug, Default, Clone)]
+pub struct ImagePyramid {
+    laplacian: bool,
+    levels: Vec<PyramidLevel>,
+}
+
+//ip ImagePyramid
+impl ImagePyramid {
+    //cp gaussian
+    /// Build a Gaussian pyramid of up to `num_levels` levels
+    ///
+    /// Each level is blurred with a Gaussian of standard deviation
+    /// sigma before being halved; levels stop being added when either
+    /// dimension would become less than 8
+    pub fn gaussian(
+        kernels: &Kernels,
+        size: (usize, usize),
+        data: &[f32],
+        num_levels: usize,
+        sigma: f32,
+    ) -> Result<Self, String> {
+        let mut levels = vec![PyramidLevel::new(size, data.to_vec())];
+        while levels.len() < num_levels {
+            let last = levels.last().unwrap();
+            let (width, height) = last.dims();
+            let next_size = (width.div_ceil(2), height.div_ceil(2));
+            if next_size.0 < 8 || next_size.1 < 8 {
+                break;
+            }
+            let mut blurred = last.data.clone();
+            let args: KernelArgs = last.dims().into();
+            let args = args.with_size(0).with_sigma(sigma);
+            kernels.gaussian_blur(&args, &mut blurred)?;
+
+            let args: KernelArgs = next_size.into();
+            let args = args.with_src(last.dims());
+            let mut next = vec![0.0; next_size.0 * next_size.1];
+            kernels.run_shader(
+                "downsample",
+                &args,
+                next_size.0 * next_size.1,
+                Some(&blurred),
+                &mut next,
+            )?;
+            levels.push(PyramidLevel::new(next_size, next));
+        }
+        Ok(Self {
+            laplacian: false,
+            levels,
+        })
+    }
+
+    //cp laplacian
+    /// Build a Laplacian pyramid of up to `num_levels` levels
+    pub fn laplacian(
+        kernels: &Kernels,
+        size: (usize, usize),
+        data: &[f32],
+        num_levels: usize,
+        sigma: f32,
+    ) -> Result<Self, String> {
+        let mut gaussian = Self::gaussian(kernels, size, data, num_levels, sigma)?;
+        let n = gaussian.levels.len();
+        for i in 0..n.saturating_sub(1) {
+            let upsampled = gaussian.upsample_level(kernels, i + 1, gaussian.levels[i].dims())?;
+            for (d, u) in gaussian.levels[i].data.iter_mut().zip(upsampled.iter()) {
+                *d -= u;
+            }
+        }
+        gaussian.laplacian = true;
+        Ok(gaussian)
+    }
+
+    //ap is_laplacian
+    pub fn is_laplacian(&self) -> bool {
+        self.laplacian
+    }
+
+    //ap len
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    //ap is_empty
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    //ap level
+    pub fn level(&self, n: usize) -> Option<&PyramidLevel> {
+        self.levels.get(n)
+    }
+
+    //ap levels
+    pub fn levels(&self) -> &[PyramidLevel] {
+        &self.levels
+    }
+
+    //mi upsample_level
+    /// Bilinearly upsample level `n` to the given size
+    fn upsample_level(
+        &self,
+        kernels: &Kernels,
+        n: usize,
+        size: (usize, usize),
+    ) -> Result<Vec<f32>, String> {
+        let level = &self.levels[n];
+        let args: KernelArgs = size.into();
+        let args = args.with_src(level.dims());
+        let mut out = vec![0.0; size.0 * size.1];
+        kernels.run_shader(
+            "upsample",
+            &args,
+            size.0 * size.1,
+            Some(&level.data),
+            &mut out,
+        )?;
+        Ok(out)
+    }
+
+    //mp reconstruct
+    /// Collapse a Laplacian pyramid back to a full-resolution image
+    ///
+    /// For a Gaussian pyramid this just returns level 0
+    pub fn reconstruct(&self, kernels: &Kernels) -> Result<Vec<f32>, String> {
+        if self.levels.is_empty() {
+            return Ok(vec![]);
+        }
+        if !self.laplacian {
+            return Ok(self.levels[0].data.clone());
+        }
+        let n = self.levels.len();
+        let mut image = self.levels[n - 1].clone();
+        for i in (0..n - 1).rev() {
+            let size = self.levels[i].dims();
+            let args: KernelArgs = size.into();
+            let args = args.with_src(image.dims());
+            let mut out = vec![0.0; size.0 * size.1];
+            kernels.run_shader(
+                "upsample",
+                &args,
+                size.0 * size.1,
+                Some(&image.data),
+                &mut out,
+            )?;
+            for (o, l) in out.iter_mut().zip(self.levels[i].data.iter()) {
+                *o += l;
+            }
+            image = PyramidLevel::new(size, out);
+        }
+        Ok(image.data)
+    }
+
+    //zz All done
+}
+
+//a Kernels
+//ip Kernels - pyramid search
+impl Kernels {
+    //mp window_corr_coarse_to_fine
+    /// Find the best match in `dst` for the window of `src` centred
+    /// on `src_xy`, using the 'window_corr' kernel coarse-to-fine
+    ///
+    /// At the coarsest level the whole of the `dst` level is
+    /// searched; at each finer level only the region within
+    /// `search_radius` pixels of the (doubled) best match from the
+    /// previous level is searched.
+    ///
+    /// The window size is the same number of pixels at every level
+    /// (hence it covers more of the image at coarser levels).
+    ///
+    /// Returns the x, y of the best match at full resolution, and the
+    /// 'window_corr' value there
+    pub fn window_corr_coarse_to_fine(
+        &self,
+        src: &ImagePyramid,
+        dst: &ImagePyramid,
+        src_xy: (usize, usize),
+        window_size: usize,
+        search_radius: usize,
+    ) -> Result<(usize, usize, f32), String> {
+        let num_levels = src.len().min(dst.len());
+        if num_levels == 0 {
+            return Err("Cannot search empty image pyramids".into());
+        }
+        let half_ws = window_size / 2;
+        let mut best: Option<(usize, usize, f32)> = None;
+        for l in (0..num_levels).rev() {
+            let src_level = src.level(l).unwrap();
+            let dst_level = dst.level(l).unwrap();
+            let (x0, y0, region) = {
+                if let Some((bx, by, _)) = best {
+                    let r = search_radius + half_ws;
+                    let (cx, cy) = (bx * 2, by * 2);
+                    (
+                        cx.saturating_sub(r),
+                        cy.saturating_sub(r),
+                        (
+                            cx + r + 1 - cx.saturating_sub(r),
+                            cy + r + 1 - cy.saturating_sub(r),
+                        ),
+                    )
+                } else {
+                    (0, 0, dst_level.dims())
+                }
+            };
+            let ((w, h), mut data) = dst_level.crop((x0, y0), region);
+            if w <= window_size || h <= window_size {
+                best = best.map(|(bx, by, v)| (bx * 2, by * 2, v));
+                continue;
+            }
+            let args: KernelArgs = (w, h).into();
+            let args = args
+                .with_size(window_size)
+                .with_src(src_level.dims())
+                .with_xy((src_xy.0 >> l, src_xy.1 >> l));
+            self.run_shader(
+                "window_corr",
+                &args,
+                w * h,
+                Some(src_level.data()),
+                &mut data,
+            )?;
+            // The kernel does not write pixels whose window would
+            // leave the image, so only consider those that it does
+            let mut level_best = (0, 0.0_f32);
+            for y in half_ws..(h - half_ws) {
+                for x in half_ws..(w - half_ws) {
+                    let i = x + y * w;
+                    if data[i] > level_best.1 {
+                        level_best = (i, data[i]);
+                    }
+                }
+            }
+            if level_best.1 > 0.0 {
+                best = Some((x0 + level_best.0 % w, y0 + level_best.0 / w, level_best.1));
+            } else {
+                // Nothing found at this level, so just carry any
+                // previous best match down a level
+                best = best.map(|(bx, by, v)| (bx * 2, by * 2, v));
+            }
+        }
+        Ok(best.unwrap_or_default())
+    }
+}
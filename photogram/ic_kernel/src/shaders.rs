@@ -0,0 +1,76 @@
+//a Imports
+use std::path::Path;
+
+use crate::accel_wgpu::ShaderFileDesc;
+
+//a Embedded shaders
+//ci SHADER_NAMES
+/// The names of the shader files used by the kernels
+pub const SHADER_NAMES: &[&str] = &[
+    "statistical",
+    "extract",
+    "features",
+    "multiscale",
+    "geometry",
+];
+
+//ci EMBEDDED_SHADERS
+/// The shader files compiled into the binary, as (name, json, wgsl)
+const EMBEDDED_SHADERS: &[(&str, &str, &str)] = &[
+    (
+        "statistical",
+        include_str!("../../shaders/statistical.json"),
+        include_str!("../../shaders/statistical.wgsl"),
+    ),
+    (
+        "extract",
+        include_str!("../../shaders/extract.json"),
+        include_str!("../../shaders/extract.wgsl"),
+    ),
+    (
+        "features",
+        include_str!("../../shaders/features.json"),
+        include_str!("../../shaders/features.wgsl"),
+    ),
+    (
+        "multiscale",
+        include_str!("../../shaders/multiscale.json"),
+        include_str!("../../shaders/multiscale.wgsl"),
+    ),
+    (
+        "geometry",
+        include_str!("../../shaders/geometry.json"),
+        include_str!("../../shaders/geometry.wgsl"),
+    ),
+];
+
+//a Shader lookup
+//fp find_shader_file
+/// Find a shader file by name; the first of the search paths that
+/// contains '<name>.json' is used (and it must also contain
+/// '<name>.wgsl'), else the embedded copy is used
+pub(crate) fn find_shader_file<P: AsRef<Path>>(
+    search_paths: &[P],
+    name: &str,
+) -> Result<ShaderFileDesc, String> {
+    for root in search_paths {
+        let mut json_path = root.as_ref().to_owned();
+        json_path.push(name);
+        json_path.set_extension("json");
+        if !json_path.is_file() {
+            continue;
+        }
+        let json = std::fs::read_to_string(&json_path)
+            .map_err(|e| format!("Error reading Json describing shader file {e}"))?;
+        let wgsl_path = json_path.with_extension("wgsl");
+        let wgsl = std::fs::read_to_string(&wgsl_path)
+            .map_err(|e| format!("Error reading WGSL shader file {e}"))?;
+        return ShaderFileDesc::from_json(wgsl_path.display().to_string(), wgsl, &json)
+            .map_err(|e| format!("Error parsing JSON file {}: {e}", json_path.display()));
+    }
+    let Some((_, json, wgsl)) = EMBEDDED_SHADERS.iter().find(|(n, _, _)| *n == name) else {
+        return Err(format!("Could not find shader file '{name}'"));
+    };
+    ShaderFileDesc::from_json(format!("<embedded {name}>"), wgsl.to_string(), json)
+        .map_err(|e| format!("Error parsing embedded JSON for {name}: {e}"))
+}
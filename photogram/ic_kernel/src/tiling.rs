@@ -0,0 +1,174 @@
+//a Imports
+use crate::{Accelerate, KernelArgs};
+
+//a Halos
+//fi halo_rows
+/// The number of rows above and below a horizontal strip of an image
+/// that a kernel needs to generate the correct results for the strip
+///
+/// Returns None if the kernel cannot be run in strips (for example,
+/// if it uses a source image of a different size, or works on whole
+/// columns)
+fn halo_rows(shader: &str, args: &KernelArgs) -> Option<usize> {
+    match shader {
+        "copy" | "sqrt" | "square" | "add_scaled" | "sub_scaled" | "integral_x" => Some(0),
+        // Horizontal windows may wrap to the adjacent rows
+        "window_sum_x" | "gaussian_x" => Some(1),
+        "window_sum_y" | "window_mean" | "window_var" | "window_var_scaled" | "window_mean_sat" => {
+            Some(args.size() + 1)
+        }
+        "gaussian_y" | "log" => Some(args.radius_for_sigma(args.sigma()) + 1),
+        "dog" => Some(args.radius_for_sigma(args.sigma().max(args.sigma_b())) + 1),
+        "sobel_mag" | "sobel_angle" | "scharr_mag" | "scharr_angle" | "nms_edge" => Some(2),
+        "nms" => Some(args.size().max(1) + 1),
+        _ => None,
+    }
+}
+
+//a Tiling
+//fp run_tiled
+/// Run a kernel on an accelerator in horizontal strips of at most
+/// `max_elements`, each with a halo of rows above and below
+///
+/// Each strip is run on a copy of the original data (including the
+/// halo), and only the rows of the strip itself are written back
+///
+/// Returns Ok(false) if the kernel cannot be tiled or if the
+/// accelerator does not support it, in which case the data is unchanged
+pub(crate) fn run_tiled<A: Accelerate + ?Sized>(
+    accel: &A,
+    max_elements: usize,
+    shader: &str,
+    args: &KernelArgs,
+    work_items: usize,
+    src_data: Option<&[f32]>,
+    out_data: &mut [f32],
+) -> Result<bool, String> {
+    let (width, height) = args.dims();
+    let npix = width * height;
+    if width == 0 || out_data.len() != npix {
+        return Ok(false);
+    }
+    if let Some(src_data) = src_data
+        && (args.src_dims() != (width, height) || src_data.len() != npix)
+    {
+        return Ok(false);
+    }
+    let Some(halo) = halo_rows(shader, args) else {
+        return Ok(false);
+    };
+    // Kernels are invoked either per pixel or per row
+    let items_per_row = {
+        if work_items == npix {
+            width
+        } else if work_items == height {
+            1
+        } else {
+            return Ok(false);
+        }
+    };
+    let max_rows = max_elements / width;
+    if max_rows <= 2 * halo {
+        return Ok(false);
+    }
+    let strip_rows = max_rows - 2 * halo;
+
+    let original = (halo > 0).then(|| out_data.to_vec());
+    let mut y0 = 0;
+    while y0 < height {
+        let y1 = (y0 + strip_rows).min(height);
+        let t0 = y0.saturating_sub(halo);
+        let t1 = (y1 + halo).min(height);
+        let tile_rows = t1 - t0;
+        let mut tile = match &original {
+            Some(original) => original[t0 * width..t1 * width].to_vec(),
+            None => out_data[t0 * width..t1 * width].to_vec(),
+        };
+        let tile_src = src_data.map(|s| &s[t0 * width..t1 * width]);
+        let mut tile_args = *args;
+        tile_args.height = tile_rows as u32;
+        tile_args.src_height = tile_rows as u32;
+        if !accel.run_shader(
+            shader,
+            &tile_args,
+            tile_rows * items_per_row,
+            tile_src,
+            &mut tile,
+        )? {
+            return Ok(false);
+        }
+        out_data[y0 * width..y1 * width]
+            .copy_from_slice(&tile[(y0 - t0) * width..(y1 - t0) * width]);
+        y0 = y1;
+    }
+    Ok(true)
+}
+
+//a Tests
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::cpu;
+
+    fn image(width: usize, height: usize) -> Vec<f32> {
+        (0..width * height)
+            .map(|i| ((i * 7919) % 251) as f32 / 251.0)
+            .collect()
+    }
+
+    fn check_tiled(shader: &str, args: &KernelArgs, max_elements: usize) {
+        let accel = cpu::ImageAccelerator::default();
+        let (width, height) = args.dims();
+        let mut expected = image(width, height);
+        accel
+            .run_shader(shader, args, width * height, None, &mut expected)
+            .unwrap();
+        let mut tiled = image(width, height);
+        assert!(run_tiled(
+            &accel,
+            max_elements,
+            shader,
+            args,
+            width * height,
+            None,
+            &mut tiled,
+        )
+        .unwrap());
+        for (i, (e, t)) in expected.iter().zip(tiled.iter()).enumerate() {
+            assert!(
+                (e - t).abs() < 1E-4,
+                "Mismatch for {shader} at {i}: {e} != {t}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_tiled() {
+        let args: KernelArgs = (37, 53).into();
+        check_tiled("square", &args, 37 * 5);
+        check_tiled("window_sum_y", &args.with_size(4), 37 * 16);
+        check_tiled("gaussian_y", &args.with_sigma(1.5), 37 * 20);
+        check_tiled("sobel_mag", &args, 37 * 8);
+    }
+
+    #[test]
+    fn test_not_tiled() {
+        let accel = cpu::ImageAccelerator::default();
+        let args: KernelArgs = (37, 53).into();
+        let mut data = image(37, 53);
+        // Halo too large for the tile size
+        assert!(!run_tiled(
+            &accel,
+            37 * 4,
+            "nms",
+            &args.with_size(4),
+            37 * 53,
+            None,
+            &mut data
+        )
+        .unwrap());
+        // Kernel that cannot be tiled
+        assert!(!run_tiled(&accel, 37 * 4, "integral_y", &args, 37, None, &mut data).unwrap());
+        assert_eq!(data, image(37, 53));
+    }
+}
@@ -0,0 +1,85 @@
+//a Imports
+use ic_kernel::{Accelerator, KernelArgs, KernelPipeline, Kernels, KernelsConfig};
+
+//a Tests
+//ft accelerator
+#[test]
+fn accelerator() -> Result<(), String> {
+    for s in [
+        "auto", "cpu", "wgpu", "vulkan", "metal", "dx12", "gl", "software",
+    ] {
+        let a: Accelerator = s.parse()?;
+        assert_eq!(a.to_string(), s);
+    }
+    assert_eq!("GPU".parse::<Accelerator>()?, Accelerator::Wgpu);
+    assert!("tpu".parse::<Accelerator>().is_err());
+    assert!(!Accelerator::Auto.is_required());
+    assert!(!Accelerator::Cpu.is_required());
+    assert!(Accelerator::Software.is_required());
+    Ok(())
+}
+
+//ft cpu_kernels
+#[test]
+fn cpu_kernels() -> Result<(), String> {
+    let config = KernelsConfig::default()
+        .with_accelerator(Accelerator::Cpu)
+        .with_embedded_shaders();
+    assert!(config.shader_paths().is_empty());
+    let kernels = Kernels::of_config(&config)?;
+    assert!(!kernels.has_wgpu());
+    assert_eq!(kernels.description(), "cpu");
+
+    let data: Vec<f32> = (0..64).map(|i| i as f32 / 8.0).collect();
+    let args: KernelArgs = (8, 8).into();
+    let mut squared = data.clone();
+    kernels.run_shader("square", &args, 64, None, &mut squared)?;
+    for (d, s) in data.iter().zip(squared.iter()) {
+        assert_eq!(d * d, *s);
+    }
+
+    let pipeline = KernelPipeline::of_kernels(&["square", "sqrt"]);
+    let result = kernels
+        .run_pipeline(&pipeline, &args, &[("image", &data)])?
+        .take_output();
+    for (d, r) in data.iter().zip(result.iter()) {
+        assert!((d - r).abs() < 1E-5);
+    }
+    Ok(())
+}
+
+//ft subtended_err2
+#[test]
+fn subtended_err2() -> Result<(), String> {
+    let config = KernelsConfig::default().with_accelerator(Accelerator::Cpu);
+    let kernels = Kernels::of_config(&config)?;
+    let right_angle = std::f32::consts::FRAC_PI_2;
+    // Lines from (-1,0,0) to (1,0,0) and (0,0,-1) to (0,0,1), each subtending 90 degrees
+    let lines = [
+        -1.0,
+        0.0,
+        0.0,
+        right_angle,
+        1.0,
+        0.0,
+        0.0,
+        0.0, //
+        0.0,
+        0.0,
+        -1.0,
+        right_angle,
+        0.0,
+        0.0,
+        1.0,
+        0.0,
+    ];
+    let mut points = [0.0, 1.0, 0.0, -1.0, 0.0, 3.0_f32.sqrt(), 0.0, -1.0];
+    kernels.subtended_err2(&lines, &mut points)?;
+    // (0,1,0) subtends 90 degrees for both lines
+    assert!(points[3].abs() < 1E-10);
+    // (0,sqrt(3),0) subtends 60 degrees for both lines
+    let err = std::f32::consts::FRAC_PI_6;
+    assert!((points[7] - 2.0 * err * err).abs() < 1E-6);
+    assert!(kernels.subtended_err2(&lines[0..7], &mut points).is_err());
+    Ok(())
+}
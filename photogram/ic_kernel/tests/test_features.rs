@@ -0,0 +1,172 @@
+//a Imports
+use ic_kernel::{Accelerator, KernelArgs, KernelPipeline, Kernels, KernelsConfig};
+
+//a Support
+//fi kernels
+fn kernels() -> Kernels {
+    let config = KernelsConfig::default().with_accelerator(Accelerator::Cpu);
+    Kernels::of_config(&config).unwrap()
+}
+
+//fi impulse
+/// A 9x9 image that is zero except for a one at its centre
+fn impulse() -> Vec<f32> {
+    let mut data = vec![0.0; 81];
+    data[40] = 1.0;
+    data
+}
+
+//fi assert_near
+fn assert_near(a: f32, b: f32) {
+    assert!((a - b).abs() < 1E-5, "{a} != {b}");
+}
+
+//a Tests
+//ft gaussian
+#[test]
+fn gaussian() -> Result<(), String> {
+    let kernels = kernels();
+    let args: KernelArgs = (9, 9).into();
+
+    // With a size of 0 the radius is 3 sigma; the 1D weights for sigma
+    // of 1 are 1, 0.606531, 0.135335 and 0.011109, totalling 2.505950
+    let mut data = impulse();
+    kernels.gaussian_blur(&args.with_sigma(1.0), &mut data)?;
+    assert_near(data[40], 0.159240);
+    assert_near(data[41], 0.159240 * 0.606531);
+    assert_near(data[50], 0.159240 * 0.606531 * 0.606531);
+    assert_near(data[43], 0.159240 * 0.011109);
+    assert_eq!(data[44], 0.0);
+    assert_eq!(data[0], 0.0);
+    assert_near(data.iter().sum(), 1.0);
+
+    // An explicit size is the radius
+    let mut data = impulse();
+    kernels.gaussian_blur(&args.with_sigma(1.0).with_size(1), &mut data)?;
+    assert_near(data[40], 0.204180);
+    assert_eq!(data[42], 0.0);
+
+    // A flat image is unchanged, including at the edges
+    let mut data = vec![0.5; 81];
+    kernels.gaussian_blur(&args.with_sigma(2.0), &mut data)?;
+    assert!(data.iter().all(|d| (d - 0.5).abs() < 1E-5));
+    Ok(())
+}
+
+//ft gradient
+#[test]
+fn gradient() -> Result<(), String> {
+    let kernels = kernels();
+    let args: KernelArgs = (8, 8).into();
+    let ramp_x: Vec<f32> = (0..64).map(|i| (i % 8) as f32).collect();
+    let ramp_y: Vec<f32> = (0..64).map(|i| (i / 8) as f32 * 2.0).collect();
+
+    // A unit ramp has a unit gradient away from the edges
+    for (kernel, ramp, mag, angle) in [
+        ("sobel", &ramp_x, 1.0, 0.0),
+        ("scharr", &ramp_x, 1.0, 0.0),
+        ("sobel", &ramp_y, 2.0, std::f32::consts::FRAC_PI_2),
+        ("scharr", &ramp_y, 2.0, std::f32::consts::FRAC_PI_2),
+    ] {
+        let mut data = ramp.clone();
+        kernels.run_shader(&format!("{kernel}_mag"), &args, 64, None, &mut data)?;
+        assert_near(data[3 + 4 * 8], mag);
+        let mut data = ramp.clone();
+        kernels.run_shader(&format!("{kernel}_angle"), &args, 64, None, &mut data)?;
+        assert_near(data[3 + 4 * 8], angle);
+    }
+    Ok(())
+}
+
+//ft log_dog
+#[test]
+fn log_dog() -> Result<(), String> {
+    let kernels = kernels();
+    let args: KernelArgs = (9, 9).into();
+    for kernel in ["log", "dog"] {
+        // Flat regions give zero
+        let mut data = vec![0.5; 81];
+        kernels.run_shader(kernel, &args, 81, None, &mut data)?;
+        assert!(data.iter().all(|d| d.abs() < 1E-5), "{kernel}");
+
+        // A bright spot gives a positive peak surrounded by negatives
+        let mut data = impulse();
+        kernels.run_shader(kernel, &args, 81, None, &mut data)?;
+        assert!(data[40] > 0.0, "{kernel}");
+        assert!(data.iter().all(|d| *d <= data[40]), "{kernel}");
+        assert!(data[43] < 0.0, "{kernel}");
+    }
+
+    // The LoG at the centre of an impulse is the kernel weight, 1/(pi
+    // sigma^2) less the mean of the kernel
+    let mut data = impulse();
+    kernels.run_shader("log", &args.with_sigma(1.0), 81, None, &mut data)?;
+    assert!((data[40] - std::f32::consts::FRAC_1_PI).abs() < 0.01);
+    Ok(())
+}
+
+//ft nms
+#[test]
+fn nms() -> Result<(), String> {
+    let kernels = kernels();
+    let args: KernelArgs = (7, 1).into();
+    let mut data = vec![0.0, 1.0, 3.0, 2.0, 0.0, 1.0, -1.0];
+    kernels.run_shader("nms", &args.with_size(1), 7, None, &mut data)?;
+    assert_eq!(data, vec![0.0, 0.0, 3.0, 0.0, 0.0, 1.0, 0.0]);
+
+    let mut data = vec![0.0, 1.0, 3.0, 2.0, 0.0, 1.0, -1.0];
+    kernels.run_shader("nms", &args.with_size(3), 7, None, &mut data)?;
+    assert_eq!(data, vec![0.0, 0.0, 3.0, 0.0, 0.0, 0.0, 0.0]);
+    Ok(())
+}
+
+//ft edges
+#[test]
+fn edges() -> Result<(), String> {
+    let kernels = kernels();
+    let args: KernelArgs = (8, 8).into();
+    let step: Vec<f32> = (0..64)
+        .map(|i| match i % 8 {
+            0..=2 => 0.0,
+            3 => 0.5,
+            _ => 1.0,
+        })
+        .collect();
+
+    // The magnitudes are 0.25, 0.5 and 0.25 in columns 2 to 4, and only
+    // the maximum remains
+    let edges = kernels.edges(&args, &step, false)?;
+    for (i, e) in edges.iter().enumerate() {
+        let expected = if i % 8 == 3 { 0.5 } else { 0.0 };
+        assert_near(*e, expected);
+    }
+
+    // The gradient angles must be provided to nms_edge
+    let mut data = step.clone();
+    assert!(kernels
+        .run_shader("nms_edge", &args, 64, None, &mut data)
+        .is_err());
+    Ok(())
+}
+
+//ft sigma_radius
+#[test]
+fn sigma_radius() {
+    let args: KernelArgs = (8, 8).into();
+    let args = args.with_size(8);
+    let pipeline = KernelPipeline::of_kernels(&["gaussian_x", "window_sum_x", "log", "nms"]);
+    let sizes: Vec<usize> = pipeline
+        .steps()
+        .iter()
+        .map(|s| s.kernel_args(&args).size())
+        .collect();
+    assert_eq!(sizes, vec![8, 8, 8, 8]);
+
+    let pipeline = pipeline.with_sigma_radius();
+    let sizes: Vec<usize> = pipeline
+        .steps()
+        .iter()
+        .map(|s| s.kernel_args(&args).size())
+        .collect();
+    assert_eq!(sizes, vec![0, 8, 0, 8]);
+}
@@ -0,0 +1,59 @@
+//a Imports
+use ic_kernel::{KernelArgs, KernelPipeline};
+
+//a Tests
+//ft builtin
+#[test]
+fn builtin() -> Result<(), String> {
+    let pipeline = KernelPipeline::builtin("window_std_dev")?;
+    assert_eq!(pipeline.output(), "std_dev");
+    assert_eq!(pipeline.steps().len(), 8);
+    assert!(KernelPipeline::builtin("not_a_pipeline").is_err());
+
+    let buffers = pipeline.create_buffers(&[("image", &[1.0, 2.0][..])])?;
+    assert_eq!(buffers.get("image"), Some(&[1.0, 2.0][..]));
+    assert_eq!(buffers.get("std_dev"), Some(&[1.0, 2.0][..]));
+    assert!(pipeline.create_buffers(&[]).is_err());
+    Ok(())
+}
+
+//ft step_args
+#[test]
+fn step_args() -> Result<(), String> {
+    let pipeline = KernelPipeline::from_json(
+        r#"{ "buffers": [ {"name": "a"}, {"name": "b", "from": "a"} ],
+             "steps": [ {"kernel": "window_sum_x", "out": "b", "src": "a",
+                         "args": {"size": 4, "scale_by_inv_size": true},
+                         "work_items": "rows"} ] }"#,
+    )?;
+    assert_eq!(pipeline.output(), "b");
+    let step = &pipeline.steps()[0];
+    assert_eq!(step.out(), 1);
+    assert_eq!(step.src(), Some(0));
+
+    let args: KernelArgs = (10, 20).into();
+    let args = args.with_scale(2.0);
+    let step_args = step.kernel_args(&args);
+    assert_eq!(step_args.size(), 4);
+    assert_eq!(step_args.scale(), 0.5);
+    assert_eq!(step.work_items(&step_args, 200), 20);
+    Ok(())
+}
+
+//ft bad_buffers
+#[test]
+fn bad_buffers() {
+    assert!(KernelPipeline::from_json(r#"{ "buffers": [], "steps": [] }"#).is_err());
+    assert!(KernelPipeline::from_json(
+        r#"{ "buffers": [ {"name": "a"}, {"name": "a"} ], "steps": [] }"#
+    )
+    .is_err());
+    assert!(KernelPipeline::from_json(
+        r#"{ "buffers": [ {"name": "a", "from": "b"}, {"name": "b"} ], "steps": [] }"#
+    )
+    .is_err());
+    assert!(KernelPipeline::from_json(
+        r#"{ "buffers": [ {"name": "a"} ], "steps": [ {"kernel": "sqrt", "out": "c"} ] }"#
+    )
+    .is_err());
+}
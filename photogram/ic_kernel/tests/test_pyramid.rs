@@ -0,0 +1,138 @@
+//a Imports
+use ic_kernel::{Accelerator, ImagePyramid, IntegralImage, KernelArgs, Kernels, KernelsConfig};
+
+//a Support
+//fi kernels
+fn kernels() -> Kernels {
+    let config = KernelsConfig::default().with_accelerator(Accelerator::Cpu);
+    Kernels::of_config(&config).unwrap()
+}
+
+//fi blobs
+/// A 128x128 image of Gaussian blobs of different sizes and
+/// brightnesses, offset by dx, dy
+fn blobs(dx: f32, dy: f32) -> Vec<f32> {
+    let blobs = [
+        (30.0, 40.0, 5.0, 1.0),
+        (70.0, 60.0, 8.0, 0.7),
+        (90.0, 30.0, 4.0, 0.9),
+        (50.0, 95.0, 10.0, 0.5),
+        (100.0, 100.0, 6.0, 0.8),
+        (60.0, 70.0, 3.0, 0.6),
+    ];
+    let mut data = vec![0.0; 128 * 128];
+    for y in 0..128 {
+        for x in 0..128 {
+            let (px, py) = (x as f32 - dx, y as f32 - dy);
+            data[x + y * 128] = blobs
+                .iter()
+                .map(|(bx, by, s, b)| {
+                    let d2 = (px - bx) * (px - bx) + (py - by) * (py - by);
+                    b * (-d2 / (2.0 * s * s)).exp()
+                })
+                .sum();
+        }
+    }
+    data
+}
+
+//a Tests
+//ft integral_image
+#[test]
+fn integral_image() -> Result<(), String> {
+    let (width, height) = (7, 5);
+    let data: Vec<f32> = (0..width * height).map(|i| ((i * 7) % 11) as f32).collect();
+    let sat = IntegralImage::of_data((width, height), &data);
+    assert_eq!(sat.dims(), (width, height));
+
+    // The accelerated table matches the CPU one
+    let kernels = kernels();
+    let sat_k = kernels.integral_image((width, height), &data)?;
+    assert_eq!(sat_k.data(), sat.data());
+
+    // Every rectangle sums to the brute force sum
+    for y0 in 0..height {
+        for y1 in y0..=height {
+            for x0 in 0..width {
+                for x1 in x0..=width {
+                    let mut sum = 0.0;
+                    for y in y0..y1 {
+                        for x in x0..x1 {
+                            sum += data[x + y * width];
+                        }
+                    }
+                    assert_eq!(sat.sum(x0, y0, x1, y1), sum, "{x0},{y0} to {x1},{y1}");
+                }
+            }
+        }
+    }
+
+    // Rectangles are clipped to the image, as are windows
+    assert_eq!(sat.sum(0, 0, 100, 100), data.iter().sum::<f32>());
+    assert_eq!(sat.sum(3, 3, 2, 4), 0.0);
+    let corner = (data[0] + data[1] + data[width] + data[width + 1]) / 4.0;
+    assert_eq!(sat.window_mean((0, 0), 3), corner);
+
+    let means = kernels.window_mean_sat(&sat, 3)?;
+    assert_eq!(means[0], corner);
+    assert_eq!(means[8], sat.window_mean((1, 1), 3));
+    Ok(())
+}
+
+//ft pyramid_levels
+#[test]
+fn pyramid_levels() -> Result<(), String> {
+    let kernels = kernels();
+    let data: Vec<f32> = (0..100 * 60).map(|i| ((i * 13) % 17) as f32).collect();
+
+    // Levels halve (rounding up) until a dimension would be below 8
+    let pyramid = ImagePyramid::gaussian(&kernels, (100, 60), &data, 10, 1.0)?;
+    let dims: Vec<_> = pyramid.levels().iter().map(|l| l.dims()).collect();
+    assert_eq!(dims, vec![(100, 60), (50, 30), (25, 15), (13, 8)]);
+    assert!(!pyramid.is_laplacian());
+    assert_eq!(pyramid.level(0).unwrap().data(), &data[..]);
+
+    let pyramid = ImagePyramid::gaussian(&kernels, (100, 60), &data, 2, 1.0)?;
+    assert_eq!(pyramid.len(), 2);
+
+    // A Laplacian pyramid reconstructs the original image
+    let pyramid = ImagePyramid::laplacian(&kernels, (100, 60), &data, 10, 1.0)?;
+    assert!(pyramid.is_laplacian());
+    assert_eq!(pyramid.len(), 4);
+    let image = pyramid.reconstruct(&kernels)?;
+    for (i, d) in image.iter().zip(data.iter()) {
+        assert!((i - d).abs() < 1E-3, "{i} != {d}");
+    }
+    Ok(())
+}
+
+//ft coarse_to_fine
+#[test]
+fn coarse_to_fine() -> Result<(), String> {
+    let kernels = kernels();
+    let src = blobs(0.0, 0.0);
+    let dst = blobs(11.0, -7.0);
+    let (src_xy, window_size) = ((30, 40), 16);
+
+    // Full search of the whole destination image
+    let args: KernelArgs = (128, 128).into();
+    let args = args
+        .with_size(window_size)
+        .with_src((128, 128))
+        .with_xy(src_xy);
+    let mut corr = dst.clone();
+    kernels.run_shader("window_corr", &args, 128 * 128, Some(&src), &mut corr)?;
+    let (i, v) = corr
+        .iter()
+        .enumerate()
+        .fold((0, 0.0_f32), |b, (i, v)| if *v > b.1 { (i, *v) } else { b });
+    let full = (i % 128, i / 128);
+    assert_eq!(full, (41, 33));
+
+    let src_pyr = ImagePyramid::gaussian(&kernels, (128, 128), &src, 4, 1.0)?;
+    let dst_pyr = ImagePyramid::gaussian(&kernels, (128, 128), &dst, 4, 1.0)?;
+    let (x, y, v_ctf) = kernels.window_corr_coarse_to_fine(&src_pyr, &dst_pyr, src_xy, 16, 4)?;
+    assert_eq!((x, y), full);
+    assert!((v_ctf - v).abs() < 1E-4, "{v_ctf} != {v}");
+    Ok(())
+}
@@ -21,6 +21,9 @@ impl ModelLine {
         assert!((p0 - p1).length() > 1E-10);
         Self { pts: [p0, p1] }
     }
+    pub fn pts(&self) -> &[Point3D; 2] {
+        &self.pts
+    }
     pub fn mid_point(&self) -> Point3D {
         (self.pts[0] + self.pts[1]) / 2.0
     }
@@ -1,13 +1,86 @@
 //a Imports
 use std::default::Default;
+use std::sync::{Arc, OnceLock};
 
 use geo_nd::Vector;
 
-use ic_base::{utils, Point3D};
+use ic_base::{utils, Point3D, Result};
 use ic_camera::CameraProjection;
+use ic_kernel::{Accelerator, Kernels, KernelsConfig};
+use ic_threads::ThreadPool;
 
 use crate::{ModelLine, ModelLineSubtended, PointMapping};
 
+//a Constants
+//ci MAX_ERR2
+/// Points whose error is at least this are never chosen as an
+/// approximate location
+const MAX_ERR2: f64 = 1E8;
+
+//ci BATCH_SIZE
+/// The number of points evaluated by the kernels in one go by
+/// [ModelLineSet::total_err2_batch]
+const BATCH_SIZE: usize = 4096;
+
+//a Support functions
+//fi default_thread_pool
+/// The thread pool used for location searches if a [ModelLineSet] is
+/// not given one; it is created when first required, with a thread
+/// per core
+fn default_thread_pool() -> Arc<ThreadPool> {
+    static POOL: OnceLock<Arc<ThreadPool>> = OnceLock::new();
+    POOL.get_or_init(|| {
+        let n = std::thread::available_parallelism().map_or(1, |n| n.get());
+        Arc::new(ThreadPool::new(n))
+    })
+    .clone()
+}
+
+//fi min_err2_of
+/// Find the point with the smallest error (below [MAX_ERR2]); if more
+/// than one has the smallest error then the first is returned
+fn min_err2_of<I: Iterator<Item = (Point3D, f64)>>(pts: I) -> Option<(Point3D, f64)> {
+    let mut best: Option<(Point3D, f64)> = None;
+    for (p, err2) in pts {
+        if err2 < best.map_or(MAX_ERR2, |(_, e)| e) {
+            best = Some((p, err2));
+        }
+    }
+    best
+}
+
+//fi kernel_err2
+/// Evaluate the errors of a batch of points with kernels, given lines
+/// from [ModelLineSet::kernel_lines] with the same origin
+fn kernel_err2(
+    kernels: &Kernels,
+    lines: &[f32],
+    origin: &Point3D,
+    pts: &[Point3D],
+) -> Result<Vec<f64>> {
+    let mut data = Vec::with_capacity(pts.len() * 4);
+    for p in pts {
+        let p = *p - *origin;
+        data.extend([p[0] as f32, p[1] as f32, p[2] as f32, 0.0]);
+    }
+    kernels.subtended_err2(lines, &mut data)?;
+    Ok(data.chunks_exact(4).map(|d| d[3] as f64).collect())
+}
+
+//fi err2_excluding
+/// The sum of the squared errors of the lines other than `index`
+fn err2_excluding(lines: &[ModelLineSubtended], p: &Point3D, index: usize) -> f64 {
+    let mut err2 = 0.0;
+    for (i, l) in lines.iter().enumerate() {
+        if i == index {
+            continue;
+        }
+        let err = l.error_in_p_angle(p);
+        err2 += err * err;
+    }
+    err2
+}
+
 //a ModelLineSet
 #[derive(Debug)]
 pub struct ModelLineSet<C>
@@ -22,6 +95,13 @@ where
 
     /// The set of lines and the angle subtended by each
     lines: Vec<ModelLineSubtended>,
+
+    /// The thread pool used to search for locations; if None then a
+    /// pool shared by all sets is used
+    pool: Option<Arc<ThreadPool>>,
+
+    /// The kernels used to evaluate the errors of batches of points
+    kernels: Arc<Kernels>,
 }
 
 //ip ModelLineSet
@@ -30,12 +110,37 @@ where
     C: CameraProjection + Sized,
 {
     //cp new
-    pub fn new(camera: C) -> Self {
-        Self {
+    /// Create a new set, which uses the CPU to evaluate errors
+    pub fn new(camera: C) -> Result<Self> {
+        let config = KernelsConfig::default().with_accelerator(Accelerator::Cpu);
+        let kernels = Kernels::of_config(&config)?;
+        Ok(Self {
             camera,
             model_cog: Point3D::default(),
             lines: vec![],
-        }
+            pool: None,
+            kernels: Arc::new(kernels),
+        })
+    }
+
+    //cp with_thread_pool
+    /// Use a particular thread pool to search for locations
+    pub fn with_thread_pool(mut self, pool: Arc<ThreadPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    //cp with_kernels
+    /// Use particular kernels (such as a GPU accelerator) to evaluate
+    /// the errors of batches of points
+    pub fn with_kernels(mut self, kernels: Arc<Kernels>) -> Self {
+        self.kernels = kernels;
+        self
+    }
+
+    //mi thread_pool
+    fn thread_pool(&self) -> Arc<ThreadPool> {
+        self.pool.clone().unwrap_or_else(default_thread_pool)
     }
 
     //mi derive_model_cog
@@ -90,7 +195,36 @@ where
         n
     }
 
+    //mi kernel_lines
+    /// The lines (other than `exclude`) relative to an origin, as
+    /// required by [Kernels::subtended_err2]
+    fn kernel_lines(&self, origin: &Point3D, exclude: Option<usize>) -> Vec<f32> {
+        let mut lines = Vec::with_capacity(self.lines.len() * 8);
+        for (i, l) in self.lines.iter().enumerate() {
+            if Some(i) == exclude {
+                continue;
+            }
+            let [p0, p1] = l.model_line().pts();
+            let p0 = *p0 - *origin;
+            let p1 = *p1 - *origin;
+            lines.extend(
+                [p0[0], p0[1], p0[2], l.angle(), p1[0], p1[1], p1[2], 0.0].map(|x| x as f32),
+            );
+        }
+        lines
+    }
+
     //mp find_approx_location_using_pt
+    /// Find the point on the surface of the `index`th line (sampled
+    /// with `n_phi` by `n_theta` points) that has the smallest error
+    /// for the other lines, and return it with that error
+    ///
+    /// The rows of the surface are sampled and evaluated with the
+    /// kernels across the thread pool; every point is evaluated
+    /// independently, and the first point with the smallest error is
+    /// chosen, so the result does not depend on the number of threads.
+    /// If the kernels fail (for example, with a GPU error) then the
+    /// CPU is used.
     #[track_caller]
     pub fn find_approx_location_using_pt<F>(
         &self,
@@ -100,36 +234,30 @@ where
         n_theta: usize,
     ) -> (Point3D, f64)
     where
-        F: Fn(&Point3D) -> bool,
+        F: Fn(&Point3D) -> bool + Sync,
     {
         assert!(
             index < self.lines.len(),
             "Expected index to be within the lines array length"
         );
-        let mut pt = Point3D::default();
-        let mut min_err2 = 1E8;
-        for p in self.lines[index].surface(n_phi, n_theta) {
-            if !filter(&p) {
-                continue;
-            }
-            let mut err2 = 0.0;
-            for (i, l) in self.lines.iter().enumerate() {
-                if i == index {
-                    continue;
-                }
-                let err = l.error_in_p_angle(&p);
-                err2 += err * err;
-                if err2 >= min_err2 {
-                    break;
-                }
-            }
-            if err2 >= min_err2 {
-                continue;
-            }
-            min_err2 = err2;
-            pt = p;
+        let origin = self.lines[index].model_line().mid_point();
+        let lines = self.kernel_lines(&origin, Some(index));
+        let kernels = &*self.kernels;
+        let mls = &self.lines;
+        let rows: Vec<usize> = (0..n_phi).collect();
+        let row_minima = self.thread_pool().par_map(&rows, |i_phi| {
+            let pts: Vec<Point3D> = mls[index]
+                .surface_row(n_phi, n_theta, *i_phi)
+                .filter(|p| filter(p))
+                .collect();
+            let errs = kernel_err2(kernels, &lines, &origin, &pts)
+                .unwrap_or_else(|_| pts.iter().map(|p| err2_excluding(mls, p, index)).collect());
+            min_err2_of(pts.into_iter().zip(errs))
+        });
+        match min_err2_of(row_minima.into_iter().flatten()) {
+            Some((pt, _)) => (pt, err2_excluding(mls, &pt, index)),
+            None => (Point3D::default(), MAX_ERR2),
         }
-        (pt, min_err2)
     }
 
     //mp total_err2
@@ -142,6 +270,31 @@ where
         err2
     }
 
+    //mp total_err2_batch
+    /// Evaluate [Self::total_err2] for a batch of points, using the
+    /// kernels across the thread pool
+    ///
+    /// The kernels use f32 coordinates relative to the midpoint of the
+    /// first line, so the results are less precise than those of
+    /// [Self::total_err2]
+    pub fn total_err2_batch(&self, pts: &[Point3D]) -> Result<Vec<f64>> {
+        let origin = self
+            .lines
+            .first()
+            .map_or(Point3D::default(), |l| l.model_line().mid_point());
+        let lines = self.kernel_lines(&origin, None);
+        let batches: Vec<&[Point3D]> = pts.chunks(BATCH_SIZE).collect();
+        let kernels = &*self.kernels;
+        let errs = self
+            .thread_pool()
+            .par_map(&batches, |b| kernel_err2(kernels, &lines, &origin, b));
+        let mut result = Vec::with_capacity(pts.len());
+        for e in errs {
+            result.extend(e?);
+        }
+        Ok(result)
+    }
+
     //mp find_better_min_err_location
     /// fraction should be about 200 max
     pub fn find_better_min_err_location(
@@ -169,31 +322,113 @@ where
         n_theta: usize,
     ) -> (Point3D, f64)
     where
-        F: Fn(&Point3D) -> bool,
+        F: Fn(&Point3D) -> bool + Sync,
+    {
+        self.find_best_min_err_location_with_progress(filter, n_phi, n_theta, &|_| true)
+            .unwrap()
+    }
+
+    //mp find_best_min_err_location_with_progress
+    /// As [Self::find_best_min_err_location], invoking `progress` with
+    /// the fraction of the search completed after each line
+    ///
+    /// If `progress` returns false then the search is abandoned and
+    /// None is returned
+    #[track_caller]
+    pub fn find_best_min_err_location_with_progress<F, P>(
+        &self,
+        filter: &F,
+        n_phi: usize,
+        n_theta: usize,
+        progress: &P,
+    ) -> Option<(Point3D, f64)>
+    where
+        F: Fn(&Point3D) -> bool + Sync,
+        P: Fn(f64) -> bool,
     {
         assert!(
             !self.lines.is_empty(),
             "Cannot find a best_min_err_location with no lines"
         );
+        // The approximate search of each line is the bulk of the work
+        let n = self.num_lines();
+        let fraction_done = |i: usize| 0.9 * (i as f64) / (n as f64);
         let (mut location, mut err) = self.find_approx_location_using_pt(filter, 0, n_phi, n_theta);
-        for i in 1..self.num_lines() {
+        if !progress(fraction_done(1)) {
+            return None;
+        }
+        for i in 1..n {
             let (l, e) = self.find_approx_location_using_pt(filter, i, n_phi, n_theta);
             if e < err {
                 err = e;
                 location = l;
             }
+            if !progress(fraction_done(i + 1)) {
+                return None;
+            }
         }
-        eprintln!("Best location {location} : err {err}");
+        self.refine_min_err_location(location, err, progress)
+    }
 
+    //mp find_best_min_err_location_near_with_progress
+    /// As [Self::find_best_min_err_location_with_progress], but for a
+    /// camera known to be within a distance of a position (such as
+    /// one given by GPS)
+    ///
+    /// The search is limited to points within the distance of the
+    /// position, and the position itself is also refined; the better
+    /// of the two is returned
+    #[track_caller]
+    pub fn find_best_min_err_location_near_with_progress<P>(
+        &self,
+        near: &Point3D,
+        distance: f64,
+        n_phi: usize,
+        n_theta: usize,
+        progress: &P,
+    ) -> Option<(Point3D, f64)>
+    where
+        P: Fn(f64) -> bool,
+    {
+        let filter = |p: &Point3D| (*p - *near).length() <= distance;
+        let found =
+            self.find_best_min_err_location_with_progress(&filter, n_phi, n_theta, &|f| {
+                progress(0.5 * f)
+            })?;
+        let err = self.total_err2(*near);
+        let refined =
+            self.refine_min_err_location(*near, err, &|f: f64| progress(0.5 + 0.5 * f))?;
+        if refined.1 < found.1 {
+            Some(refined)
+        } else {
+            Some(found)
+        }
+    }
+
+    //mi refine_min_err_location
+    /// Refine a location by moving it in decreasing steps to reduce
+    /// its total error, invoking `progress` with the fraction of the
+    /// whole search completed (this being its last 10%)
+    fn refine_min_err_location<P>(
+        &self,
+        mut location: Point3D,
+        mut err: f64,
+        progress: &P,
+    ) -> Option<(Point3D, f64)>
+    where
+        P: Fn(f64) -> bool,
+    {
         for i in 0..10 {
             let fraction = 200.0 * (1.4_f64).powi(i);
             while let Some((l, e)) = self.find_better_min_err_location(location, fraction) {
                 location = l;
                 err = e;
             }
+            if !progress(0.9 + 0.01 * ((i + 1) as f64)) {
+                return None;
+            }
         }
-        eprintln!("Better location {location} : err {err}");
-        (location, err)
+        Some((location, err))
     }
 
     //zz All done
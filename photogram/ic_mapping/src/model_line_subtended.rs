@@ -190,9 +190,16 @@ impl ModelLineSubtended {
         cos_theta.acos() - self.theta
     }
 
-    //mp surface
-    pub fn surface(&self, n_phi: usize, n_theta: usize) -> ModelLineSubtendedSurfaceIter {
-        ModelLineSubtendedSurfaceIter::new(self, n_phi, n_theta)
+    //mp surface_row
+    /// Iterate over the `n_theta` points of the surface for one value
+    /// of phi (the `i_phi`th of `n_phi` around the model line)
+    pub fn surface_row(
+        &self,
+        n_phi: usize,
+        n_theta: usize,
+        i_phi: usize,
+    ) -> ModelLineSubtendedSurfaceIter {
+        ModelLineSubtendedSurfaceIter::new_row(self, n_phi, n_theta, i_phi)
     }
 }
 
@@ -306,6 +313,16 @@ impl ModelLineSubtendedSurfaceIter {
             parametric_point,
         }
     }
+
+    fn new_row(mls: &ModelLineSubtended, n_phi: usize, n_theta: usize, i_phi: usize) -> Self {
+        assert!(i_phi < n_phi, "Row of the surface must be less than n_phi");
+        let mut s = Self::new(mls, n_phi, n_theta);
+        s.i_phi = i_phi;
+        s.n_phi = i_phi + 1;
+        s.parametric_point
+            .derive_from_phi(s.phi_per_i * (i_phi as f64));
+        s
+    }
 }
 
 //ip Iterator for ModelLineSubtendedSurfaceIter
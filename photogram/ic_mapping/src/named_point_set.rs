@@ -3,8 +3,10 @@ use std::collections::HashMap;
 use std::rc::Rc;
 
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use ic_base::{json, Error, Point3D, Result};
+use ic_base::schema::{self, Versioned};
+use ic_base::{Error, Point3D, Result};
 use ic_camera::CameraProjection;
 use ic_image::Color;
 
@@ -53,6 +55,50 @@ impl<'de> Deserialize<'de> for NamedPointSet {
     }
 }
 
+//ip JsonSchema for NamedPointSet
+impl schemars::JsonSchema for NamedPointSet {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "NamedPointSet".into()
+    }
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        <Vec<(String, Color, Option<([f64; 3], f64)>)>>::json_schema(generator)
+    }
+}
+
+//ip Versioned for NamedPointSet
+impl Versioned for NamedPointSet {
+    const SCHEMA_NAME: &'static str = "named_point_set";
+    const SCHEMA_VERSION: usize = 1;
+    const CONTENTS_KEY: Option<&'static str> = Some("named_points");
+
+    //fp upgrade
+    /// Version 0 files may give a model position without its
+    /// uncertainty, as just [x, y, z]; this is given an uncertainty of
+    /// 0
+    fn upgrade(_version: usize, mut contents: Value) -> Result<Value> {
+        let Value::Array(points) = &mut contents else {
+            return Ok(contents);
+        };
+        for pt in points.iter_mut() {
+            let model = match pt {
+                Value::Array(pt) => pt.get_mut(2),
+                Value::Object(pt) => pt.get_mut("model"),
+                _ => None,
+            };
+            let Some(model) = model else {
+                continue;
+            };
+            let is_xyz = model
+                .as_array()
+                .is_some_and(|m| m.len() == 3 && m.iter().all(|c| c.is_number()));
+            if is_xyz {
+                *model = Value::Array(vec![model.take(), 0.0.into()]);
+            }
+        }
+        Ok(contents)
+    }
+}
+
 //ip NamedPointSet
 impl NamedPointSet {
     //fp new
@@ -69,16 +115,12 @@ impl NamedPointSet {
 
     //fp from_json
     pub fn from_json(json: &str) -> Result<Self> {
-        json::from_json("named point set", json)
+        schema::from_json(json)
     }
 
     //mp to_json
     pub fn to_json(&self, pretty: bool) -> Result<String> {
-        if pretty {
-            Ok(serde_json::to_string_pretty(self)?)
-        } else {
-            Ok(serde_json::to_string(self)?)
-        }
+        schema::to_json(self, pretty)
     }
 
     //mp merge
@@ -129,6 +171,17 @@ impl NamedPointSet {
         }
     }
 
+    //mp remove_pt
+    /// Remove a point from the set, returning it if it was present
+    ///
+    /// Any point mappings that refer to the point must be removed
+    /// separately
+    pub fn remove_pt(&mut self, name: &str) -> Option<Rc<NamedPoint>> {
+        let np = self.points.remove(name)?;
+        self.names.retain(|n| n != name);
+        Some(np)
+    }
+
     //fp of_color
     pub fn of_color(&self, color: &Color) -> Vec<Rc<NamedPoint>> {
         self.points
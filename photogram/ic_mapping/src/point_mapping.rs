@@ -227,8 +227,11 @@ impl PointMapping {
     // was get_pm_dxdy
     //
     // used by show_pm_error
+    /// Get the error in the screen position of the mapping given the
+    /// camera, as the mapped screen position minus the projection of
+    /// the model position; None if the named point has no model position
     #[inline]
-    fn get_mapped_dpxy<C: CameraProjection>(&self, camera: &C) -> Option<Point2D> {
+    pub fn get_mapped_dpxy<C: CameraProjection>(&self, camera: &C) -> Option<Point2D> {
         if self.is_unmapped() {
             return None;
         }
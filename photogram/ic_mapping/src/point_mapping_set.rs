@@ -5,7 +5,8 @@ use std::rc::Rc;
 use geo_nd::Vector;
 use serde::{Deserialize, Serialize};
 
-use ic_base::{json, utils, Error, Point2D, Ray, Result};
+use ic_base::schema::{self, Versioned};
+use ic_base::{utils, Error, Point2D, Ray, Result};
 use ic_camera::CameraProjection;
 
 use crate::{ModelLineSet, NamedPoint, NamedPointSet, PointMapping};
@@ -126,6 +127,23 @@ impl<'de> Deserialize<'de> for PointMappingSet {
     }
 }
 
+//ip JsonSchema for PointMappingSet
+impl schemars::JsonSchema for PointMappingSet {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "PointMappingSet".into()
+    }
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        <Vec<(String, [f64; 2], f64)>>::json_schema(generator)
+    }
+}
+
+//ip Versioned for PointMappingSet
+impl Versioned for PointMappingSet {
+    const SCHEMA_NAME: &'static str = "point_mapping_set";
+    const SCHEMA_VERSION: usize = 1;
+    const CONTENTS_KEY: Option<&'static str> = Some("mappings");
+}
+
 //ip PointMappingSet - constructors, add, remove
 impl PointMappingSet {
     //fp new
@@ -157,6 +175,27 @@ impl PointMappingSet {
         }
     }
 
+    //mp insert_mapping
+    /// Insert a mapping at an index (or at the end, if the index is
+    /// beyond it), returning false if the named point is not found
+    pub fn insert_mapping(
+        &mut self,
+        n: usize,
+        nps: &NamedPointSet,
+        name: &str,
+        screen: &Point2D,
+        error: f64,
+    ) -> bool {
+        if let Some(model) = nps.get_pt(name) {
+            let n = n.min(self.mappings.len());
+            self.mappings
+                .insert(n, PointMapping::new_npt(model, screen, error));
+            true
+        } else {
+            false
+        }
+    }
+
     //mp remove_mapping
     pub fn remove_mapping(&mut self, n: usize) -> bool {
         if n < self.mappings.len() {
@@ -223,6 +262,12 @@ impl PointMappingSet {
         s
     }
 
+    //mp mapping_index_of_name
+    /// Find the index of the mapping of the named point with the given name
+    pub fn mapping_index_of_name(&self, name: &str) -> Option<usize> {
+        self.mappings.iter().position(|pm| pm.name() == name)
+    }
+
     //mp get_screen_pts
     pub fn get_screen_pts(&self) -> Vec<Point2D> {
         self.mappings.iter().map(|x| *x.screen()).collect()
@@ -249,7 +294,7 @@ impl PointMappingSet {
 
     //cp from_json
     pub fn from_json(nps: &NamedPointSet, json: &str) -> Result<(Self, String)> {
-        let mut pms: Self = json::from_json("point map set", json)?;
+        let mut pms: Self = schema::from_json(json)?;
         let pms_not_found = pms.rebuild_with_named_point_set(nps);
         if pms_not_found.is_empty() {
             Ok((pms, "".into()))
@@ -269,11 +314,7 @@ impl PointMappingSet {
 
     //mp to_json
     pub fn to_json(&self, pretty: bool) -> Result<String> {
-        if pretty {
-            Ok(serde_json::to_string_pretty(self)?)
-        } else {
-            Ok(serde_json::to_string(self)?)
-        }
+        schema::to_json(self, pretty)
     }
 
     //mp sorted_order
@@ -0,0 +1,76 @@
+//a Imports
+use std::sync::Arc;
+
+use geo_nd::Vector;
+
+use ic_base::Point3D;
+use ic_camera::CameraInstance;
+use ic_mapping::ModelLineSet;
+use ic_threads::ThreadPool;
+
+//a Support
+//fi line_set
+/// Create a set of model lines between pairs of points as viewed from
+/// a camera at `camera`
+fn line_set(camera: Point3D, threads: usize) -> ModelLineSet<CameraInstance> {
+    let pts: [Point3D; 5] = [
+        [0., 0., 0.].into(),
+        [10., 0., 0.].into(),
+        [0., 10., 0.].into(),
+        [10., 10., 2.].into(),
+        [5., 5., 6.].into(),
+    ];
+    let pool = Arc::new(ThreadPool::new(threads));
+    let mut mls = ModelLineSet::new(CameraInstance::default())
+        .unwrap()
+        .with_thread_pool(pool);
+    for (i, p0) in pts.iter().enumerate() {
+        for p1 in &pts[i + 1..] {
+            let d0 = (*p0 - camera).normalize();
+            let d1 = (*p1 - camera).normalize();
+            mls.add_line_of_models(*p0, *p1, d0.dot(&d1).acos());
+        }
+    }
+    mls
+}
+
+//a Tests
+//ft locate
+#[test]
+fn locate() {
+    let camera: Point3D = [3., 4., 20.].into();
+    let mls = line_set(camera, 3);
+    let (location, err) = mls.find_best_min_err_location(&|p| p[2] > 0., 200, 200);
+    assert!(err < 1E-8, "Error {err} should be tiny");
+    assert!(
+        (location - camera).length() < 1E-3,
+        "{location} should be {camera}"
+    );
+
+    let pts = [camera, location, [0., 0., 10.].into()];
+    let batch = mls.total_err2_batch(&pts).unwrap();
+    for (p, e) in pts.iter().zip(batch.iter()) {
+        assert!((mls.total_err2(*p) - e).abs() < 1E-5);
+    }
+}
+
+//ft deterministic
+#[test]
+fn deterministic() {
+    let camera: Point3D = [-6., 12., 15.].into();
+    let results: Vec<_> = [1, 2, 5]
+        .into_iter()
+        .map(|threads| {
+            let mls = line_set(camera, threads);
+            (0..mls.num_lines())
+                .map(|i| mls.find_approx_location_using_pt(&|_| true, i, 50, 40))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    for r in &results[1..] {
+        for ((p0, e0), (p1, e1)) in results[0].iter().zip(r.iter()) {
+            assert_eq!(p0, p1);
+            assert_eq!(e0, e1);
+        }
+    }
+}
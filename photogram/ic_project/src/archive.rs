@@ -0,0 +1,591 @@
+//a Documentation
+//! A single-file archive of a project, for sharing projects
+//!
+//! A project is normally spread over a number of files (the project
+//! JSON, camera database, named point set, per-CIP camera and point
+//! mapping files, and the images) found through a [PathSet]. A
+//! project archive is a tar file containing:
+//!
+//! * 'manifest.json' - the [ArchiveManifest], with the format version,
+//!   the tool that created it, and the SHA-256 hashes of the other
+//!   files and of the images
+//!
+//! * 'project.json' - the complete project (which includes the camera
+//!   database, named point set, and the cameras and point mappings of
+//!   the CIPs), with file names rewritten to be relative
+//!
+//! * 'images/...' - the images of the CIPs, if they are included
+//!
+//! If the images are not included then the CIPs refer to them by
+//! their file names (without directories), and the manifest records
+//! their hashes so that the images found when the archive is used
+//! can be verified
+
+//a Imports
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use ic_base::{json, PathSet, Result};
+
+use crate::Project;
+
+//a Constants
+//ci ARCHIVE_FORMAT
+/// The value of the 'format' of the manifest of a project archive
+const ARCHIVE_FORMAT: &str = "ic_project archive";
+
+//ci ARCHIVE_VERSION
+/// The version of the archive format; archives with a later version
+/// cannot be read
+pub const ARCHIVE_VERSION: usize = 1;
+
+//ci MANIFEST_PATH
+const MANIFEST_PATH: &str = "manifest.json";
+
+//ci PROJECT_PATH
+const PROJECT_PATH: &str = "project.json";
+
+//ci IMAGE_DIR
+const IMAGE_DIR: &str = "images";
+
+//a Support
+//fp sha256_hex
+/// The SHA-256 hash of some data, as a hex string
+pub fn sha256_hex(data: &[u8]) -> String {
+    ring::digest::digest(&ring::digest::SHA256, data)
+        .as_ref()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+//fi base_name
+/// The file name of a path, without its directories
+fn base_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|f| f.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+//fi is_relative
+/// Return true if a path in an archive is relative and stays within the
+/// directory it is relative to
+fn is_relative(path: &str) -> bool {
+    Path::new(path)
+        .components()
+        .all(|c| matches!(c, std::path::Component::Normal(_)))
+}
+
+//a ArchiveOptions
+//tp ArchiveOptions
+/// Options for creating a project archive
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveOptions {
+    tool: String,
+    created: String,
+    include_images: bool,
+}
+
+//ip ArchiveOptions
+impl ArchiveOptions {
+    //cp new
+    /// Create options given the name (and version) of the tool creating
+    /// the archive and the time of creation (as RFC 3339)
+    pub fn new<S: Into<String>>(tool: S, created: S) -> Self {
+        Self {
+            tool: tool.into(),
+            created: created.into(),
+            include_images: false,
+        }
+    }
+
+    //cp with_images
+    /// Set whether to include the images of the CIPs in the archive
+    pub fn with_images(mut self, include_images: bool) -> Self {
+        self.include_images = include_images;
+        self
+    }
+}
+
+//a ArchiveManifest
+//tp ArchiveFile
+/// A file in a project archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveFile {
+    path: String,
+    size: usize,
+    sha256: String,
+}
+
+//ip ArchiveFile
+impl ArchiveFile {
+    //cp of_data
+    fn of_data(path: &str, data: &[u8]) -> Self {
+        Self {
+            path: path.into(),
+            size: data.len(),
+            sha256: sha256_hex(data),
+        }
+    }
+
+    //ap path
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    //ap size
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    //ap sha256
+    pub fn sha256(&self) -> &str {
+        &self.sha256
+    }
+}
+
+//tp ArchiveImage
+/// The image of a CIP of an archived project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveImage {
+    cip: usize,
+    /// The image name of the CIP when the archive was created
+    source: String,
+    /// The image name of the CIP in the archived project
+    name: String,
+    /// Whether the image is in the archive
+    included: bool,
+    /// The hash of the image, if it was found when the archive was
+    /// created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sha256: Option<String>,
+}
+
+//ip ArchiveImage
+impl ArchiveImage {
+    //ap cip
+    pub fn cip(&self) -> usize {
+        self.cip
+    }
+
+    //ap source
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    //ap name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    //ap included
+    pub fn included(&self) -> bool {
+        self.included
+    }
+
+    //ap sha256
+    pub fn sha256(&self) -> Option<&str> {
+        self.sha256.as_deref()
+    }
+}
+
+//tp ArchiveManifest
+/// The manifest of a project archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchiveManifest {
+    format: String,
+    version: usize,
+    /// Name and version of the tool that created the archive
+    tool: String,
+    /// Version of the project library that created the archive
+    library: String,
+    /// Time the archive was created, as RFC 3339
+    created: String,
+    /// Path of the project in the archive
+    project: String,
+    /// All the files in the archive apart from the manifest
+    files: Vec<ArchiveFile>,
+    images: Vec<ArchiveImage>,
+}
+
+//ip ArchiveManifest
+impl ArchiveManifest {
+    //ap version
+    pub fn version(&self) -> usize {
+        self.version
+    }
+
+    //ap tool
+    pub fn tool(&self) -> &str {
+        &self.tool
+    }
+
+    //ap library
+    pub fn library(&self) -> &str {
+        &self.library
+    }
+
+    //ap created
+    pub fn created(&self) -> &str {
+        &self.created
+    }
+
+    //ap project
+    /// The path of the project in the archive
+    pub fn project(&self) -> &str {
+        &self.project
+    }
+
+    //ap files
+    pub fn files(&self) -> &[ArchiveFile] {
+        &self.files
+    }
+
+    //ap images
+    pub fn images(&self) -> &[ArchiveImage] {
+        &self.images
+    }
+}
+
+//a ProjectArchive
+//tp ProjectArchive
+/// A project archive, in memory
+#[derive(Debug)]
+pub struct ProjectArchive {
+    manifest: ArchiveManifest,
+    /// The contents of the files of the archive (other than the
+    /// manifest), by path in the archive
+    contents: HashMap<String, Vec<u8>>,
+}
+
+//ip ProjectArchive
+impl ProjectArchive {
+    //fp is_archive
+    /// Return true if a path is (by its extension) a project archive
+    pub fn is_archive<P: AsRef<Path>>(path: P) -> bool {
+        path.as_ref().extension().is_some_and(|x| x == "tar")
+    }
+
+    //cp of_project
+    /// Create an archive of a project, finding its images (if they are
+    /// to be included, or hashed) through the path set
+    ///
+    /// If images are to be included then every CIP's image must be
+    /// found
+    pub fn of_project(
+        project: &Project,
+        path_set: &PathSet,
+        options: &ArchiveOptions,
+    ) -> Result<Self> {
+        // Make a copy of the project so that its names can be rewritten
+        let mut archived = Project::from_json(&project.to_json(false)?)?;
+
+        let mut contents = HashMap::new();
+        let mut images = vec![];
+        let mut image_of_hash: HashMap<String, String> = HashMap::new();
+        for n in 0..archived.ncips() {
+            let mut cip = archived.cip(n).borrow_mut();
+            let camera_filename = base_name(cip.camera_filename());
+            let pms_filename = base_name(cip.pms_filename());
+            cip.set_camera_filename(camera_filename);
+            cip.set_pms_filename(pms_filename);
+
+            let source = cip.image_name().to_owned();
+            if source.is_empty() {
+                continue;
+            }
+            let opt_data = {
+                if let Some(path) = path_set.find_file(&source) {
+                    let data = std::fs::read(&path).map_err(|e| (path.display(), e))?;
+                    Some(data)
+                } else if options.include_images {
+                    return Err(format!(
+                        "Failed to find image '{source}' of CIP {n} to include in the archive"
+                    )
+                    .into());
+                } else {
+                    None
+                }
+            };
+            let sha256 = opt_data.as_deref().map(sha256_hex);
+            let mut name = base_name(&source);
+            if options.include_images {
+                let data = opt_data.unwrap();
+                let sha256 = sha256.clone().unwrap();
+                if let Some(path) = image_of_hash.get(&sha256) {
+                    name = path.clone();
+                } else {
+                    name = format!("{IMAGE_DIR}/{name}");
+                    if contents.contains_key(&name) {
+                        name = format!("{IMAGE_DIR}/{n}_{}", base_name(&source));
+                    }
+                    image_of_hash.insert(sha256, name.clone());
+                    contents.insert(name.clone(), data);
+                }
+            }
+            cip.set_image(name.clone());
+            cip.set_image_filename(name.clone());
+            images.push(ArchiveImage {
+                cip: n,
+                source,
+                name,
+                included: options.include_images,
+                sha256,
+            });
+        }
+        let cdb_filename = base_name(archived.cdb_filename());
+        let nps_filename = base_name(archived.nps_filename());
+        archived.set_cdb_filename(cdb_filename);
+        archived.set_nps_filename(nps_filename);
+        contents.insert(PROJECT_PATH.into(), archived.to_json(true)?.into_bytes());
+
+        let mut files: Vec<_> = contents
+            .iter()
+            .map(|(path, data)| ArchiveFile::of_data(path, data))
+            .collect();
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        let manifest = ArchiveManifest {
+            format: ARCHIVE_FORMAT.into(),
+            version: ARCHIVE_VERSION,
+            tool: options.tool.clone(),
+            library: format!("ic_project {}", env!("CARGO_PKG_VERSION")),
+            created: options.created.clone(),
+            project: PROJECT_PATH.into(),
+            files,
+            images,
+        };
+        Ok(Self { manifest, contents })
+    }
+
+    //ap manifest
+    pub fn manifest(&self) -> &ArchiveManifest {
+        &self.manifest
+    }
+
+    //mp write
+    /// Write the archive as a tar file
+    pub fn write<W: Write>(&self, writer: W) -> Result<()> {
+        let mut builder = tar::Builder::new(writer);
+        let manifest = serde_json::to_string_pretty(&self.manifest)?;
+        let mut add = |path: &str, data: &[u8]| -> Result<()> {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o644);
+            header.set_mtime(0);
+            header.set_cksum();
+            builder.append_data(&mut header, path, data)?;
+            Ok(())
+        };
+        add(MANIFEST_PATH, manifest.as_bytes())?;
+        for file in &self.manifest.files {
+            add(&file.path, &self.contents[&file.path])?;
+        }
+        builder.into_inner()?.flush()?;
+        Ok(())
+    }
+
+    //cp read
+    /// Read an archive from a tar file, checking that it has a
+    /// manifest of a supported version, and that the files of the
+    /// manifest are present with the correct hashes
+    pub fn read<R: Read>(reader: R) -> Result<Self> {
+        let mut archive = tar::Archive::new(reader);
+        let mut opt_manifest = None;
+        let mut contents = HashMap::new();
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            let path = entry.path()?.to_string_lossy().into_owned();
+            let mut data = vec![];
+            entry.read_to_end(&mut data)?;
+            if path == MANIFEST_PATH {
+                let manifest: ArchiveManifest = json::from_json(
+                    "archive manifest",
+                    std::str::from_utf8(&data).map_err(|e| format!("Bad archive manifest: {e}"))?,
+                )?;
+                opt_manifest = Some(manifest);
+            } else {
+                contents.insert(path, data);
+            }
+        }
+        let Some(manifest) = opt_manifest else {
+            return Err("Archive has no manifest; it is not a project archive".into());
+        };
+        if manifest.format != ARCHIVE_FORMAT {
+            return Err(format!(
+                "Archive format '{}' is not a project archive",
+                manifest.format
+            )
+            .into());
+        }
+        if manifest.version > ARCHIVE_VERSION {
+            return Err(format!(
+                "Archive version {} (created by {}) is newer than the supported version {ARCHIVE_VERSION}",
+                manifest.version, manifest.tool
+            )
+            .into());
+        }
+        for file in &manifest.files {
+            if !is_relative(&file.path) {
+                return Err(format!("Archive file '{}' is not a relative path", file.path).into());
+            }
+            let Some(data) = contents.get(&file.path) else {
+                return Err(format!("Archive is missing file '{}'", file.path).into());
+            };
+            if sha256_hex(data) != file.sha256 {
+                return Err(
+                    format!("Archive file '{}' is corrupt (hash mismatch)", file.path).into(),
+                );
+            }
+        }
+        if !manifest.files.iter().any(|f| f.path == manifest.project) {
+            return Err(format!("Archive is missing project '{}'", manifest.project).into());
+        }
+        for image in &manifest.images {
+            if !is_relative(&image.name) {
+                return Err(
+                    format!("Archive image '{}' is not a relative path", image.name).into(),
+                );
+            }
+            if image.included && !manifest.files.iter().any(|f| f.path == image.name) {
+                return Err(format!("Archive is missing image '{}'", image.name).into());
+            }
+        }
+        Ok(Self { manifest, contents })
+    }
+
+    //cp read_file
+    /// Read an archive from a file
+    pub fn read_file<P: AsRef<Path> + std::fmt::Display>(path: P) -> Result<Self> {
+        let file = std::fs::File::open(&path).map_err(|e| (&path, e))?;
+        Self::read(std::io::BufReader::new(file))
+            .map_err(|e| (e, format!("Failed to read project archive {path}")).into())
+    }
+
+    //mp project_json
+    /// The JSON of the archived project
+    pub fn project_json(&self) -> Result<&str> {
+        std::str::from_utf8(&self.contents[&self.manifest.project])
+            .map_err(|e| format!("Bad project in archive: {e}").into())
+    }
+
+    //mp project
+    /// Get the archived project
+    ///
+    /// The image names of the CIPs are those in the archive, so they
+    /// should be resolved relative to the directory the archive is
+    /// extracted to (or, if the images are not included, found as
+    /// for any other project)
+    pub fn project(&self) -> Result<Project> {
+        Project::from_json(self.project_json()?)
+    }
+
+    //mp extract
+    /// Extract the project and any included images to a directory,
+    /// returning the path of the extracted project
+    ///
+    /// Files that already exist with the same contents are not
+    /// rewritten; symbolic links are never written through
+    pub fn extract<P: AsRef<Path>>(&self, dir: P) -> Result<PathBuf> {
+        let dir = dir.as_ref();
+        for file in &self.manifest.files {
+            let path = dir.join(&file.path);
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| (parent.display(), e))?;
+            }
+            let data = &self.contents[&file.path];
+            if let Ok(metadata) = std::fs::symlink_metadata(&path) {
+                if !metadata.is_file() {
+                    return Err(format!(
+                        "Cannot extract to '{}' as it is not a regular file",
+                        path.display()
+                    )
+                    .into());
+                }
+                if std::fs::read(&path).is_ok_and(|d| d == *data) {
+                    continue;
+                }
+                std::fs::remove_file(&path).map_err(|e| (path.display(), e))?;
+            }
+            // Create the file afresh, so that nothing put in its place
+            // since the check is written through
+            let mut f = std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+                .map_err(|e| (path.display(), e))?;
+            f.write_all(data).map_err(|e| (path.display(), e))?;
+        }
+        Ok(dir.join(&self.manifest.project))
+    }
+
+    //mp verify_images
+    /// Verify the images that are not included in the archive against
+    /// the hashes in the manifest, finding them through the path set;
+    /// returns a list of warnings for those that are not found or do
+    /// not match
+    pub fn verify_images(&self, path_set: &PathSet) -> Vec<String> {
+        let mut warnings = vec![];
+        for image in &self.manifest.images {
+            if image.included {
+                continue;
+            }
+            let Some(path) = path_set.find_file(&image.name) else {
+                warnings.push(format!(
+                    "Image '{}' of CIP {} not found",
+                    image.name, image.cip
+                ));
+                continue;
+            };
+            let Some(sha256) = &image.sha256 else {
+                continue;
+            };
+            if !std::fs::read(&path).is_ok_and(|d| sha256_hex(&d) == *sha256) {
+                warnings.push(format!(
+                    "Image '{}' of CIP {} does not match the image it was archived with",
+                    path.display(),
+                    image.cip
+                ));
+            }
+        }
+        warnings
+    }
+
+    //cp load
+    /// Load a project directly from an archive file
+    ///
+    /// Included images are extracted to a new private directory in
+    /// the system temporary directory, and the CIPs of the project
+    /// refer to them there; the project owns the directory, which is
+    /// removed when the project is dropped
+    pub fn load<P: AsRef<Path> + std::fmt::Display>(path: P) -> Result<Project> {
+        Self::read_file(path)?.load_project()
+    }
+
+    //mp load_project
+    /// Generate the project of the archive, as for [Self::load]
+    pub fn load_project(&self) -> Result<Project> {
+        let mut project = self.project()?;
+        if self.manifest.images.iter().any(|i| i.included) {
+            let dir = tempfile::Builder::new()
+                .prefix("ic_project_archive_")
+                .tempdir()
+                .map_err(|e| format!("Failed to create directory for archive: {e}"))?;
+            self.extract(dir.path())?;
+            for image in &self.manifest.images {
+                if !image.included || image.cip >= project.ncips() {
+                    continue;
+                }
+                let path = dir.path().join(&image.name).display().to_string();
+                let mut cip = project.cip(image.cip).borrow_mut();
+                cip.set_image(path.clone());
+                cip.set_image_filename(path);
+            }
+            project.set_archive_dir(dir);
+        }
+        Ok(project)
+    }
+}
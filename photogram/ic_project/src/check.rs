@@ -0,0 +1,472 @@
+//a Documentation
+//! Validation of a project, reporting problems that loading it does
+//! not
+//!
+//! A [ProjectCheck] is a list of [CheckIssue]s, each an error (the
+//! project is not usable as intended) or a warning (something is
+//! probably wrong, or the project is incomplete). It may be generated
+//! for a loaded [Project] with [Project::check], or for a project
+//! file (which may fail to load) with [ProjectCheck::of_file]
+
+//a Imports
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+use ic_base::PathSet;
+use ic_camera::CameraSensor;
+use ic_image::ImageRgb8;
+
+use crate::{Project, ProjectFileDesc};
+
+//a Constants
+//ci MIN_LOCATE_MAPPINGS
+/// The minimum number of distinct named points with model positions
+/// that must be mapped to locate a camera
+const MIN_LOCATE_MAPPINGS: usize = 3;
+
+//a Severity, CheckKind
+//tp Severity
+/// The severity of a [CheckIssue]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+//ip Display for Severity
+impl std::fmt::Display for Severity {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(fmt, "error"),
+            Self::Warning => write!(fmt, "warning"),
+        }
+    }
+}
+
+//tp CheckKind
+/// The kind of problem a [CheckIssue] reports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckKind {
+    /// A file referred to by the project was not found
+    MissingFile,
+    /// A file could not be read or parsed
+    BadFile,
+    /// A point mapping refers to a name not in the named point set
+    UnknownNamedPoint,
+    /// A named point is mapped more than once in a CIP
+    DuplicateMapping,
+    /// An image is not the size of the sensor of the camera body
+    ImageSize,
+    /// A CIP has too few mappings of named points with model
+    /// positions to locate its camera
+    TooFewMappings,
+    /// A named point is not mapped in any CIP
+    UnobservedNamedPoint,
+    /// A control point of the frame is not a named point with a model
+    /// position
+    UnusableControlPoint,
+    /// The frame has not been fitted to its control points
+    FrameNotFitted,
+}
+
+//a CheckIssue
+//tp CheckIssue
+/// A single problem found with a project
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckIssue {
+    severity: Severity,
+    kind: CheckKind,
+    /// The CIP the issue is with, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cip: Option<usize>,
+    /// The file the issue is with, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    /// The named point the issue is with, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    message: String,
+}
+
+//ip CheckIssue
+impl CheckIssue {
+    //cp new
+    pub fn new<S: Into<String>>(severity: Severity, kind: CheckKind, message: S) -> Self {
+        Self {
+            severity,
+            kind,
+            cip: None,
+            file: None,
+            name: None,
+            message: message.into(),
+        }
+    }
+
+    //cp with_cip
+    pub fn with_cip(mut self, cip: usize) -> Self {
+        self.cip = Some(cip);
+        self
+    }
+
+    //cp with_file
+    pub fn with_file<S: Into<String>>(mut self, file: S) -> Self {
+        self.file = Some(file.into());
+        self
+    }
+
+    //cp with_name
+    pub fn with_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    //ap severity
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    //ap kind
+    pub fn kind(&self) -> CheckKind {
+        self.kind
+    }
+
+    //ap cip
+    pub fn cip(&self) -> Option<usize> {
+        self.cip
+    }
+
+    //ap file
+    pub fn file(&self) -> Option<&str> {
+        self.file.as_deref()
+    }
+
+    //ap name
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    //ap message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+//ip Display for CheckIssue
+impl std::fmt::Display for CheckIssue {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(fmt, "{}: ", self.severity)?;
+        if let Some(cip) = self.cip {
+            write!(fmt, "CIP {cip}: ")?;
+        }
+        write!(fmt, "{}", self.message)
+    }
+}
+
+//a ProjectCheck
+//tp ProjectCheck
+/// The result of checking a project
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct ProjectCheck {
+    errors: usize,
+    warnings: usize,
+    issues: Vec<CheckIssue>,
+}
+
+//ip ProjectCheck
+impl ProjectCheck {
+    //cp of_file
+    /// Check a project file, which may be a project descriptor (naming
+    /// the files that make up the project) or a complete project
+    ///
+    /// Files that are missing or fail to load are reported as errors,
+    /// rather than this failing
+    pub fn of_file(path_set: &PathSet, filename: &str) -> Self {
+        let mut check = Self::default();
+        let (filename, json) = match path_set.read_json_file(filename) {
+            Ok(x) => x,
+            Err(e) => {
+                check.add(
+                    CheckIssue::new(Severity::Error, CheckKind::MissingFile, e.to_string())
+                        .with_file(filename),
+                );
+                return check;
+            }
+        };
+        if let Ok(desc) = ProjectFileDesc::from_json(&json) {
+            return desc.check(path_set);
+        }
+        match Project::from_json(&json) {
+            Ok(project) => project.check(path_set),
+            Err(e) => {
+                check.add(
+                    CheckIssue::new(Severity::Error, CheckKind::BadFile, e.to_string())
+                        .with_file(filename),
+                );
+                check
+            }
+        }
+    }
+
+    //mp add
+    /// Add an issue
+    pub fn add(&mut self, issue: CheckIssue) {
+        match issue.severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+        }
+        self.issues.push(issue);
+    }
+
+    //mp append
+    /// Add all the issues of another check
+    pub fn append(&mut self, other: Self) {
+        for issue in other.issues {
+            self.add(issue);
+        }
+    }
+
+    //ap issues
+    pub fn issues(&self) -> &[CheckIssue] {
+        &self.issues
+    }
+
+    //ap errors
+    /// The number of errors found
+    pub fn errors(&self) -> usize {
+        self.errors
+    }
+
+    //ap warnings
+    /// The number of warnings found
+    pub fn warnings(&self) -> usize {
+        self.warnings
+    }
+
+    //ap is_ok
+    /// Return true if no errors were found
+    pub fn is_ok(&self) -> bool {
+        self.errors == 0
+    }
+}
+
+//ip Display for ProjectCheck
+impl std::fmt::Display for ProjectCheck {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        for issue in &self.issues {
+            writeln!(fmt, "{issue}")?;
+        }
+        write!(fmt, "{} errors, {} warnings", self.errors, self.warnings)
+    }
+}
+
+//a Checks
+//ip ProjectFileDesc - check
+impl ProjectFileDesc {
+    //mp check
+    /// Check that the files of the project descriptor exist, then load
+    /// the project and check it
+    pub fn check(&self, path_set: &PathSet) -> ProjectCheck {
+        let mut check = ProjectCheck::default();
+        let mut check_file = |what: &str, filename: &str, cip: Option<usize>| {
+            if path_set.find_file(filename).is_none() {
+                let mut issue = CheckIssue::new(
+                    Severity::Error,
+                    CheckKind::MissingFile,
+                    format!("Failed to find {what} '{filename}'"),
+                )
+                .with_file(filename);
+                if let Some(cip) = cip {
+                    issue = issue.with_cip(cip);
+                }
+                check.add(issue);
+            }
+        };
+        check_file("camera database", self.cdb(), None);
+        check_file("named point set", self.nps(), None);
+        for (n, cip) in self.cips().iter().enumerate() {
+            check_file("camera", cip.camera_file(), Some(n));
+            check_file("point mapping set", cip.pms_file(), Some(n));
+        }
+        if !self.patches().is_empty() {
+            check_file("patches", self.patches(), None);
+        }
+        for s in self.squares() {
+            check_file("squares", s, None);
+        }
+        if !check.is_ok() {
+            return check;
+        }
+        match self.load_project(path_set) {
+            Ok(project) => check.append(project.check(path_set)),
+            Err(e) => check.add(CheckIssue::new(
+                Severity::Error,
+                CheckKind::BadFile,
+                format!("Failed to load project: {e}"),
+            )),
+        }
+        check
+    }
+}
+
+//ip Project - check
+impl Project {
+    //mp check
+    /// Check the consistency of the project
+    ///
+    /// This reports mappings of unknown (and hence dropped) or
+    /// duplicated named points, missing images and images that do not
+    /// match the size of their camera's sensor, CIPs that have too
+    /// few mappings to be located, named points that are not mapped
+    /// in any CIP, and control points of the frame that cannot be
+    /// used to fit it
+    pub fn check(&self, path_set: &PathSet) -> ProjectCheck {
+        let mut check = ProjectCheck::default();
+        let mut observations: HashMap<String, usize> = HashMap::new();
+        for n in 0..self.ncips() {
+            let cip = self.cip(n).borrow();
+            for name in cip.unknown_names() {
+                check.add(
+                    CheckIssue::new(
+                        Severity::Error,
+                        CheckKind::UnknownNamedPoint,
+                        format!("Mapping of '{name}', which is not in the named point set"),
+                    )
+                    .with_cip(n)
+                    .with_name(name),
+                );
+            }
+
+            let pms = cip.pms_ref();
+            let mut counts: HashMap<&str, usize> = HashMap::new();
+            let mut located: HashSet<&str> = HashSet::new();
+            for pm in pms.mappings() {
+                *counts.entry(pm.name()).or_default() += 1;
+                if pm.is_mapped() {
+                    located.insert(pm.name());
+                }
+            }
+            let mut names: Vec<_> = counts.iter().collect();
+            names.sort();
+            for (name, count) in names {
+                *observations.entry(name.to_string()).or_default() += 1;
+                if *count > 1 {
+                    check.add(
+                        CheckIssue::new(
+                            Severity::Warning,
+                            CheckKind::DuplicateMapping,
+                            format!("Named point '{name}' is mapped {count} times"),
+                        )
+                        .with_cip(n)
+                        .with_name(*name),
+                    );
+                }
+            }
+            let located = located.len();
+            if located < MIN_LOCATE_MAPPINGS {
+                check.add(
+                    CheckIssue::new(
+                        Severity::Warning,
+                        CheckKind::TooFewMappings,
+                        format!(
+                            "Only {located} named points with model positions are mapped; {MIN_LOCATE_MAPPINGS} are required to locate the camera"
+                        ),
+                    )
+                    .with_cip(n),
+                );
+            }
+
+            let image = cip.image_name();
+            if image.is_empty() {
+                continue;
+            }
+            let Some(path) = path_set.find_file(image) else {
+                check.add(
+                    CheckIssue::new(
+                        Severity::Error,
+                        CheckKind::MissingFile,
+                        format!("Failed to find image '{image}'"),
+                    )
+                    .with_cip(n)
+                    .with_file(image),
+                );
+                continue;
+            };
+            match ImageRgb8::read_size(&path) {
+                Ok((width, height)) => {
+                    let camera = cip.camera_ref();
+                    let body = camera.body();
+                    let (px_width, px_height) = (body.px_width(), body.px_height());
+                    if width as f64 != px_width || height as f64 != px_height {
+                        check.add(
+                            CheckIssue::new(
+                                Severity::Error,
+                                CheckKind::ImageSize,
+                                format!(
+                                    "Image '{image}' is {width}x{height}, but camera body '{}' is {px_width}x{px_height}",
+                                    body.name()
+                                ),
+                            )
+                            .with_cip(n)
+                            .with_file(image),
+                        );
+                    }
+                }
+                Err(e) => check.add(
+                    CheckIssue::new(
+                        Severity::Error,
+                        CheckKind::BadFile,
+                        format!("Failed to read image '{image}': {e}"),
+                    )
+                    .with_cip(n)
+                    .with_file(image),
+                ),
+            }
+        }
+
+        let nps = self.nps_ref();
+        let mut unobserved: Vec<_> = nps
+            .iter()
+            .map(|(name, _)| name)
+            .filter(|name| !observations.contains_key(*name))
+            .collect();
+        unobserved.sort();
+        for name in unobserved {
+            check.add(
+                CheckIssue::new(
+                    Severity::Warning,
+                    CheckKind::UnobservedNamedPoint,
+                    format!("Named point '{name}' is not mapped in any CIP"),
+                )
+                .with_name(name),
+            );
+        }
+
+        if let Some(frame) = self.frame() {
+            for cp in frame.control_points() {
+                let name = cp.name();
+                let message = match nps.get_pt(name) {
+                    None => format!("Control point '{name}' is not in the named point set"),
+                    Some(np) if np.opt_model().is_none() => {
+                        format!("Control point '{name}' does not have a model position")
+                    }
+                    _ => continue,
+                };
+                check.add(
+                    CheckIssue::new(Severity::Warning, CheckKind::UnusableControlPoint, message)
+                        .with_name(name),
+                );
+            }
+            if frame.transform().is_none() {
+                check.add(CheckIssue::new(
+                    Severity::Warning,
+                    CheckKind::FrameNotFitted,
+                    "The frame has not been fitted to its control points",
+                ));
+            }
+        }
+        check
+    }
+}
@@ -1,9 +1,11 @@
 //a Imports
 use std::cell::{Ref, RefMut};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use ic_base::{json, PathSet, Result, Rrc};
+use ic_base::schema::{self, Versioned};
+use ic_base::{PathSet, Point3D, Result, Rrc};
 use ic_camera::{CameraInstance, CameraInstanceDesc, CameraProjection};
 use ic_mapping::{ModelLineSet, PointMapping, PointMappingSet};
 
@@ -11,27 +13,44 @@ use crate::Project;
 
 //a Cip
 //tp CipFileDesc
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct CipFileDesc {
     camera_file: String,
     image: String,
     pms_file: String,
 }
 
+//ip Versioned for CipFileDesc
+impl Versioned for CipFileDesc {
+    const SCHEMA_NAME: &'static str = "cip_descriptor";
+    const SCHEMA_VERSION: usize = 1;
+}
+
 //ip CipFileDesc
 impl CipFileDesc {
     //cp from_json
     pub fn from_json(json: &str) -> Result<Self> {
-        json::from_json("cip", json)
+        schema::from_json(json)
     }
 
     //mp to_json
     pub fn to_json(&self, pretty: bool) -> Result<String> {
-        if pretty {
-            Ok(serde_json::to_string_pretty(self)?)
-        } else {
-            Ok(serde_json::to_string(self)?)
-        }
+        schema::to_json(self, pretty)
+    }
+
+    //ap camera_file
+    pub fn camera_file(&self) -> &str {
+        &self.camera_file
+    }
+
+    //ap image
+    pub fn image(&self) -> &str {
+        &self.image
+    }
+
+    //ap pms_file
+    pub fn pms_file(&self) -> &str {
+        &self.pms_file
     }
 
     //mp load_cip
@@ -49,20 +68,20 @@ impl CipFileDesc {
         let (_pms_filename, pms_json) = path_set
             .read_json_file(&self.pms_file)
             .map_err(|e| (e, "point mapping set".to_owned()))?;
-        let (pms, warnings) = PointMappingSet::from_json(&project.nps_ref(), &pms_json)?;
+        let pms = schema::from_json(&pms_json)?;
+        let warnings = cip.set_pms_of_project(project, pms);
         if !warnings.is_empty() {
             eprintln!(
                 "Warning load point mapping set '{}': {warnings}",
                 &self.pms_file
             );
         }
-        cip.pms = Rrc::new(pms);
         Ok(cip)
     }
 }
 
 //tp CipDesc
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, JsonSchema)]
 pub struct CipDesc {
     camera_filename: String,
     pms_filename: String,
@@ -73,6 +92,11 @@ pub struct CipDesc {
 }
 
 //tp Cip
+/// A camera, image and point mapping set
+///
+/// The names of point mappings that could not be found in the
+/// project's named point set when the CIP was loaded are kept (the
+/// mappings themselves are dropped) so that they may be reported
 #[derive(Debug, Default, Serialize)]
 pub struct Cip {
     camera_filename: String,
@@ -81,6 +105,8 @@ pub struct Cip {
     camera: Rrc<CameraInstance>,
     pms: Rrc<PointMappingSet>,
     image: String,
+    #[serde(skip)]
+    unknown_names: Vec<String>,
 }
 
 //ip Cip
@@ -133,34 +159,57 @@ impl Cip {
         pms_json: &str,
     ) -> Result<String> {
         let camera = CameraInstance::from_json(&project.cdb().borrow(), camera_json)?;
-        let (pms, warnings) = PointMappingSet::from_json(&project.nps().borrow(), pms_json)?;
+        let pms = schema::from_json(pms_json)?;
         self.camera = camera.into();
-        self.pms = pms.into();
-        Ok(warnings)
+        Ok(self.set_pms_of_project(project, pms))
     }
 
     //cp from_desc
     pub fn from_desc(project: &Project, cip_desc: CipDesc) -> Result<(Self, String)> {
         let image = cip_desc.image;
         let camera = CameraInstance::from_desc(&project.cdb().borrow(), cip_desc.camera)?.into();
-        let pms: Rrc<PointMappingSet> = cip_desc.pms.into();
-        let _warnings = pms
-            .borrow_mut()
-            .rebuild_with_named_point_set(&project.nps_ref());
         let camera_filename = cip_desc.camera_filename;
         let pms_filename = cip_desc.pms_filename;
         let image_filename = cip_desc.image_filename;
-        Ok((
-            Self {
-                camera_filename,
-                pms_filename,
-                image_filename,
-                camera,
-                pms,
-                image,
-            },
-            "".into(),
-        ))
+        let mut cip = Self {
+            camera_filename,
+            pms_filename,
+            image_filename,
+            camera,
+            image,
+            ..Default::default()
+        };
+        let warnings = cip.set_pms_of_project(project, cip_desc.pms);
+        Ok((cip, warnings))
+    }
+
+    //mi set_pms_of_project
+    /// Set the point mapping set, resolving its named points in the
+    /// project's named point set; mappings of names that are not found
+    /// are dropped, and a warning listing them is returned
+    fn set_pms_of_project(&mut self, project: &Project, mut pms: PointMappingSet) -> String {
+        let not_found = pms.rebuild_with_named_point_set(&project.nps_ref());
+        self.pms = pms.into();
+        self.unknown_names = not_found.iter().map(|pm| pm.name().to_owned()).collect();
+        if self.unknown_names.is_empty() {
+            return "".into();
+        }
+        let names: Vec<_> = self
+            .unknown_names
+            .iter()
+            .map(|n| format!("'{n}'"))
+            .collect();
+        format!(
+            "Failed to find points {} to map in named point set",
+            names.join(", ")
+        )
+    }
+
+    //ap unknown_names
+    /// The names of point mappings that were dropped when the CIP was
+    /// loaded, as they are not in the project's named point set
+    pub fn unknown_names(&self) -> &[String] {
+        &self.unknown_names
     }
 
     //ap camera
@@ -207,7 +256,25 @@ impl Cip {
     where
         F: Fn(usize, &PointMapping) -> bool,
     {
-        let mut mls = ModelLineSet::new(self.camera().borrow().clone());
+        self.locate_with_progress(filter, max_pairs, &|_| true)
+    }
+
+    //mp model_lines
+    /// Get the model lines for locating the camera, from pairs of the
+    /// point mappings that pass the filter; at least two are required
+    ///
+    /// The model lines are independent of the CIP, so the (lengthy)
+    /// search for the camera location may be performed with them
+    /// without access to the project (see [Self::find_location])
+    pub fn model_lines<F>(
+        &self,
+        filter: F,
+        max_pairs: usize,
+    ) -> Result<ModelLineSet<CameraInstance>>
+    where
+        F: Fn(usize, &PointMapping) -> bool,
+    {
+        let mut mls = ModelLineSet::new(self.camera().borrow().clone())?;
         self.pms
             .borrow()
             .add_good_model_lines(&mut mls, filter, max_pairs);
@@ -219,10 +286,68 @@ impl Cip {
             )
             .into());
         }
+        Ok(mls)
+    }
 
+    //fp find_location
+    /// Find the camera location (and its error) from model lines,
+    /// invoking `progress` with the fraction of the search completed;
+    /// if it returns false then the search is abandoned and an error
+    /// is returned
+    pub fn find_location<P>(
+        mls: &ModelLineSet<CameraInstance>,
+        progress: &P,
+    ) -> Result<(Point3D, f64)>
+    where
+        P: Fn(f64) -> bool,
+    {
+        mls.find_best_min_err_location_with_progress(&|_| true, 1000, 1000, progress)
+            .ok_or_else(|| "Camera location search cancelled".to_string().into())
+    }
+
+    //mp locate_with_progress
+    /// Locate the camera, invoking `progress` with the fraction of the
+    /// search completed; if it returns false then the search is
+    /// abandoned, the camera is not moved, and an error is returned
+    pub fn locate_with_progress<F, P>(
+        &self,
+        filter: F,
+        max_pairs: usize,
+        progress: &P,
+    ) -> Result<f64>
+    where
+        F: Fn(usize, &PointMapping) -> bool,
+        P: Fn(f64) -> bool,
+    {
+        let mls = self.model_lines(filter, max_pairs)?;
         eprintln!("Using {} model lines", mls.num_lines());
+        let (location, err) = Self::find_location(&mls, progress)?;
+        self.camera_mut().set_position(&location);
+        Ok(err)
+    }
 
-        let (location, err) = mls.find_best_min_err_location(&|_| true, 1000, 1000);
+    //mp locate_near_with_progress
+    /// As [Self::locate_with_progress], for a camera known to be
+    /// within a distance (in model units) of a position, such as one
+    /// given by GPS
+    pub fn locate_near_with_progress<F, P>(
+        &self,
+        filter: F,
+        max_pairs: usize,
+        near: &Point3D,
+        distance: f64,
+        progress: &P,
+    ) -> Result<f64>
+    where
+        F: Fn(usize, &PointMapping) -> bool,
+        P: Fn(f64) -> bool,
+    {
+        let mls = self.model_lines(filter, max_pairs)?;
+        let Some((location, err)) =
+            mls.find_best_min_err_location_near_with_progress(near, distance, 1000, 1000, progress)
+        else {
+            return Err("Camera location search cancelled".to_string().into());
+        };
         self.camera_mut().set_position(&location);
         Ok(err)
     }
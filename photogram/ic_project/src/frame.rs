@@ -0,0 +1,315 @@
+//a Documentation
+//! Coordinate reference frames for georeferencing projects
+//!
+//! The model coordinates of a project (those of its named points and
+//! cameras) are in an arbitrary frame, nominally in mm. A
+//! [ProjectFrame] ties these to real coordinates: it has a kind of
+//! frame (local east-north-up about an origin, WGS84 earth-centred
+//! earth-fixed, or UTM), and a set of control points, which are named
+//! points whose geodetic coordinates (latitude, longitude and height)
+//! are known, for example from a survey.
+//!
+//! A similarity transform (scale, rotation and translation) from
+//! model coordinates to the Cartesian coordinates of the frame (in
+//! metres) is fitted to the control points that have model
+//! positions; at least three, not in a line, are required. With this
+//! any model position may be converted to frame or geodetic
+//! coordinates, and a geodetic position (such as the GPS position of
+//! a camera) may be converted to model coordinates.
+//!
+//! UTM coordinates are not quite Cartesian (the grid scale varies
+//! slightly across a zone), but over the extent of a typical survey
+//! the difference is negligible.
+
+//a Imports
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use ic_base::{Error, Geodetic, Point3D, Result, Similarity, UtmZone};
+use ic_mapping::NamedPointSet;
+
+//a FrameKind
+//tp FrameKind
+/// The kind of a project frame, giving its Cartesian coordinates in
+/// metres
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FrameKind {
+    /// Local east, north and up about an origin
+    Enu { origin: Geodetic },
+    /// WGS84 earth-centred earth-fixed
+    Ecef,
+    /// UTM easting, northing and ellipsoidal height in a zone
+    Utm { zone: UtmZone },
+}
+
+//ip Display for FrameKind
+impl std::fmt::Display for FrameKind {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        match self {
+            Self::Enu { origin } => write!(fmt, "ENU about {origin}"),
+            Self::Ecef => write!(fmt, "ECEF"),
+            Self::Utm { zone } => write!(fmt, "UTM zone {zone}"),
+        }
+    }
+}
+
+//ip FrameKind
+impl FrameKind {
+    //cp of_str
+    /// Parse a frame kind given as 'enu:LAT,LON[,ALT]', 'ecef' or
+    /// 'utm:ZONE' (such as 'utm:31N')
+    pub fn of_str(s: &str) -> Result<Self> {
+        let (kind, arg) = s.split_once(':').unwrap_or((s, ""));
+        match (kind, arg) {
+            ("enu", arg) => Ok(Self::Enu {
+                origin: Geodetic::of_str(arg)?,
+            }),
+            ("ecef", "") => Ok(Self::Ecef),
+            ("utm", arg) => Ok(Self::Utm {
+                zone: UtmZone::of_str(arg)?,
+            }),
+            _ => Err(format!(
+                "Expected a frame of 'enu:LAT,LON[,ALT]', 'ecef' or 'utm:ZONE', got '{s}'"
+            )
+            .into()),
+        }
+    }
+
+    //mp of_geodetic
+    /// Convert a geodetic position to coordinates in the frame
+    pub fn of_geodetic(&self, geodetic: &Geodetic) -> Point3D {
+        match self {
+            Self::Enu { origin } => geodetic.to_enu(origin),
+            Self::Ecef => geodetic.to_ecef(),
+            Self::Utm { zone } => zone.to_utm(geodetic),
+        }
+    }
+
+    //mp to_geodetic
+    /// Convert coordinates in the frame to a geodetic position
+    pub fn to_geodetic(&self, p: &Point3D) -> Geodetic {
+        match self {
+            Self::Enu { origin } => Geodetic::of_enu(origin, p),
+            Self::Ecef => Geodetic::of_ecef(p),
+            Self::Utm { zone } => zone.to_geodetic(p),
+        }
+    }
+}
+
+//a ControlPoint
+//tp ControlPoint
+/// A named point whose geodetic position is known
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ControlPoint {
+    name: String,
+    geodetic: Geodetic,
+}
+
+//ip ControlPoint
+impl ControlPoint {
+    //ap name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    //ap geodetic
+    pub fn geodetic(&self) -> &Geodetic {
+        &self.geodetic
+    }
+}
+
+//a ControlResidual
+//tp ControlResidual
+/// The residual of a control point after fitting a frame: the
+/// distance in metres between its geodetic position and its
+/// transformed model position
+#[derive(Debug, Clone, Serialize)]
+pub struct ControlResidual {
+    name: String,
+    residual: f64,
+}
+
+//ip ControlResidual
+impl ControlResidual {
+    //ap name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    //ap residual
+    pub fn residual(&self) -> f64 {
+        self.residual
+    }
+}
+
+//a ProjectFrame
+//tp ProjectFrame
+/// A coordinate reference frame for a project, with its control
+/// points and the transform from model coordinates to the frame
+/// fitted to them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct ProjectFrame {
+    #[serde(flatten)]
+    kind: FrameKind,
+    #[serde(default)]
+    control_points: Vec<ControlPoint>,
+    /// The transform from model coordinates to frame coordinates, if
+    /// it has been fitted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    transform: Option<Similarity>,
+}
+
+//ip Display for ProjectFrame
+impl std::fmt::Display for ProjectFrame {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::result::Result<(), std::fmt::Error> {
+        writeln!(fmt, "Frame: {}", self.kind)?;
+        for cp in &self.control_points {
+            writeln!(fmt, "  control point {} @ {}", cp.name, cp.geodetic)?;
+        }
+        match &self.transform {
+            Some(transform) => write!(fmt, "Transform: {transform}"),
+            None => write!(fmt, "Transform: not fitted"),
+        }
+    }
+}
+
+//ip ProjectFrame
+impl ProjectFrame {
+    //cp new
+    pub fn new(kind: FrameKind) -> Self {
+        Self {
+            kind,
+            control_points: vec![],
+            transform: None,
+        }
+    }
+
+    //ap kind
+    pub fn kind(&self) -> &FrameKind {
+        &self.kind
+    }
+
+    //mp set_kind
+    /// Change the kind of the frame, keeping the control points; the
+    /// transform must be fitted again
+    pub fn set_kind(&mut self, kind: FrameKind) {
+        self.kind = kind;
+        self.transform = None;
+    }
+
+    //ap control_points
+    pub fn control_points(&self) -> &[ControlPoint] {
+        &self.control_points
+    }
+
+    //ap transform
+    pub fn transform(&self) -> Option<&Similarity> {
+        self.transform.as_ref()
+    }
+
+    //mp set_control_point
+    /// Set the geodetic position of a control point, replacing any
+    /// previous position of the named point; the transform must be
+    /// fitted again
+    pub fn set_control_point<S: Into<String>>(&mut self, name: S, geodetic: Geodetic) {
+        let name = name.into();
+        self.transform = None;
+        if let Some(cp) = self.control_points.iter_mut().find(|cp| cp.name == name) {
+            cp.geodetic = geodetic;
+        } else {
+            self.control_points.push(ControlPoint { name, geodetic });
+        }
+    }
+
+    //mp remove_control_point
+    /// Remove a control point, returning true if it was present; the
+    /// transform must be fitted again
+    pub fn remove_control_point(&mut self, name: &str) -> bool {
+        let n = self.control_points.len();
+        self.control_points.retain(|cp| cp.name != name);
+        if self.control_points.len() == n {
+            return false;
+        }
+        self.transform = None;
+        true
+    }
+
+    //mp fit
+    /// Fit the transform from model coordinates to frame coordinates
+    /// to the control points whose named points have model positions,
+    /// returning the residual of each of those control points
+    pub fn fit(&mut self, nps: &NamedPointSet) -> Result<Vec<ControlResidual>> {
+        let mut names = vec![];
+        let mut pairs = vec![];
+        for cp in &self.control_points {
+            let Some(np) = nps.get_pt(&cp.name) else {
+                continue;
+            };
+            let Some((model, _)) = np.opt_model() else {
+                continue;
+            };
+            names.push(cp.name.clone());
+            pairs.push((model, self.kind.of_geodetic(&cp.geodetic)));
+        }
+        if pairs.len() < 3 {
+            return Err(format!(
+                "Fitting the frame requires at least three control points with model positions, but there are {}",
+                pairs.len()
+            )
+            .into());
+        }
+        let Some(transform) = Similarity::fit(&pairs) else {
+            return Err(format!(
+                "Fitting the frame requires control points with model positions that are not in a line, but all {} are",
+                pairs.len()
+            )
+            .into());
+        };
+        let residuals = transform
+            .residuals(&pairs)
+            .into_iter()
+            .zip(names)
+            .map(|(residual, name)| ControlResidual { name, residual })
+            .collect();
+        self.transform = Some(transform);
+        Ok(residuals)
+    }
+
+    //mi fitted
+    fn fitted(&self) -> Result<&Similarity> {
+        self.transform
+            .as_ref()
+            .ok_or_else(|| Error::from("The frame has not been fitted to its control points"))
+    }
+
+    //mp model_to_frame
+    /// Convert model coordinates to frame coordinates
+    pub fn model_to_frame(&self, model: &Point3D) -> Result<Point3D> {
+        Ok(self.fitted()?.apply(model))
+    }
+
+    //mp frame_to_model
+    /// Convert frame coordinates to model coordinates
+    pub fn frame_to_model(&self, p: &Point3D) -> Result<Point3D> {
+        Ok(self.fitted()?.apply_inverse(p))
+    }
+
+    //mp model_to_geodetic
+    /// Convert model coordinates to a geodetic position
+    pub fn model_to_geodetic(&self, model: &Point3D) -> Result<Geodetic> {
+        Ok(self.kind.to_geodetic(&self.model_to_frame(model)?))
+    }
+
+    //mp geodetic_to_model
+    /// Convert a geodetic position to model coordinates
+    pub fn geodetic_to_model(&self, geodetic: &Geodetic) -> Result<Point3D> {
+        self.frame_to_model(&self.kind.of_geodetic(geodetic))
+    }
+
+    //mp metres_to_model
+    /// Convert a distance in metres to model units
+    pub fn metres_to_model(&self, metres: f64) -> Result<f64> {
+        Ok(metres / self.fitted()?.scale())
+    }
+}
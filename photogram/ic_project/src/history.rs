@@ -0,0 +1,581 @@
+//a Documentation
+//! The undo/redo history of edits to a project
+//!
+//! Edits to a project (adding, moving and removing named points,
+//! adding, moving and removing point mappings, and changing the pose
+//! or lens of the camera of a CIP) are recorded as [ProjectEdit]s,
+//! each of which holds enough of the state before the edit that it
+//! can be inverted
+//!
+//! The history is kept with the project (but not serialized with
+//! it); it may be stored in a file next to the project file, named
+//! by [ProjectHistory::path_of_project]
+
+//a Imports
+use std::path::{Path, PathBuf};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use ic_base::schema::{self, Versioned};
+use ic_base::{Point2D, Point3D, Quat, Result};
+use ic_camera::{CameraInstance, CameraLens, CameraProjection};
+use ic_image::Color;
+use ic_mapping::{NamedPointSet, PointMappingSet};
+
+use crate::Project;
+
+//a Constants
+//ci MAX_HISTORY
+/// The maximum number of edits kept for undo; older edits are
+/// discarded
+const MAX_HISTORY: usize = 1000;
+
+//a Pose
+//tp Pose
+/// The position and orientation of a camera
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Pose {
+    #[schemars(with = "[f64; 3]")]
+    position: Point3D,
+    #[schemars(with = "[f64; 4]")]
+    orientation: Quat,
+}
+
+//ip Pose
+impl Pose {
+    //cp new
+    pub fn new(position: Point3D, orientation: Quat) -> Self {
+        Self {
+            position,
+            orientation,
+        }
+    }
+
+    //cp of_camera
+    /// The pose of a camera
+    pub fn of_camera(camera: &CameraInstance) -> Self {
+        Self::new(camera.position(), camera.orientation())
+    }
+
+    //ap position
+    pub fn position(&self) -> &Point3D {
+        &self.position
+    }
+
+    //ap orientation
+    pub fn orientation(&self) -> &Quat {
+        &self.orientation
+    }
+
+    //mp apply_to
+    /// Set the pose of a camera
+    pub fn apply_to(&self, camera: &mut CameraInstance) {
+        camera.set_position(&self.position);
+        camera.set_orientation(&self.orientation);
+    }
+}
+
+//a CipMapping
+//tp CipMapping
+/// A point mapping of a CIP, as recorded in the history
+///
+/// The index of a mapping that is removed is recorded, so that an undo
+/// restores it in the same place
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct CipMapping {
+    cip: usize,
+    #[schemars(with = "[f64; 2]")]
+    screen: Point2D,
+    error: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    index: Option<usize>,
+}
+
+//ip CipMapping
+impl CipMapping {
+    //cp new
+    pub fn new(cip: usize, screen: Point2D, error: f64) -> Self {
+        Self {
+            cip,
+            screen,
+            error,
+            index: None,
+        }
+    }
+
+    //cp with_index
+    /// Set the index of the mapping in the point mapping set of the CIP
+    pub fn with_index(mut self, index: usize) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    //mi add_to
+    /// Add the mapping to a point mapping set, at its index if it has
+    /// one, returning false if the named point is not found
+    fn add_to(&self, pms: &mut PointMappingSet, nps: &NamedPointSet, name: &str) -> bool {
+        match self.index {
+            Some(n) => pms.insert_mapping(n, nps, name, &self.screen, self.error),
+            None => pms.add_mapping(nps, name, &self.screen, self.error),
+        }
+    }
+}
+
+//a ProjectEdit
+//tp ProjectEdit
+/// A reversible edit of a project
+///
+/// Removing a named point removes its mappings in every CIP, so these
+/// are recorded with it to be restored by an undo
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "edit", rename_all = "snake_case")]
+pub enum ProjectEdit {
+    /// Add a named point, and mappings of it
+    AddNamedPoint {
+        name: String,
+        color: Color,
+        #[schemars(with = "Option<([f64; 3], f64)>")]
+        model: Option<(Point3D, f64)>,
+        mappings: Vec<CipMapping>,
+    },
+    /// Remove a named point and its mappings
+    RemoveNamedPoint {
+        name: String,
+        color: Color,
+        #[schemars(with = "Option<([f64; 3], f64)>")]
+        model: Option<(Point3D, f64)>,
+        mappings: Vec<CipMapping>,
+    },
+    /// Move (or unmap) the model position of a named point
+    MoveNamedPoint {
+        name: String,
+        #[schemars(with = "Option<([f64; 3], f64)>")]
+        from: Option<(Point3D, f64)>,
+        #[schemars(with = "Option<([f64; 3], f64)>")]
+        to: Option<(Point3D, f64)>,
+    },
+    /// Add a mapping of a named point to a CIP
+    AddMapping { name: String, mapping: CipMapping },
+    /// Remove a mapping of a named point from a CIP
+    RemoveMapping { name: String, mapping: CipMapping },
+    /// Move the mapping of a named point in a CIP
+    MoveMapping {
+        name: String,
+        from: CipMapping,
+        to: CipMapping,
+    },
+    /// Change the pose of the camera of a CIP
+    SetPose { cip: usize, from: Pose, to: Pose },
+    /// Change the lens of the camera of a CIP
+    SetLens {
+        cip: usize,
+        from: Box<CameraLens>,
+        to: Box<CameraLens>,
+    },
+}
+
+//ip ProjectEdit
+impl ProjectEdit {
+    //cp add_named_point
+    /// An edit adding a named point
+    pub fn add_named_point<S: Into<String>>(
+        name: S,
+        color: Color,
+        model: Option<(Point3D, f64)>,
+    ) -> Self {
+        Self::AddNamedPoint {
+            name: name.into(),
+            color,
+            model,
+            mappings: vec![],
+        }
+    }
+
+    //cp remove_named_point
+    /// An edit removing a named point (and its mappings) from the
+    /// project
+    pub fn remove_named_point(project: &Project, name: &str) -> Result<Self> {
+        let np = project.nps_ref().get_pt_err(name)?;
+        let mut mappings = vec![];
+        for cip in 0..project.ncips() {
+            let cip_r = project.cip(cip).borrow();
+            for (n, pm) in cip_r.pms_ref().mappings().iter().enumerate() {
+                if pm.name() == name {
+                    mappings.push(CipMapping::new(cip, *pm.screen(), pm.error()).with_index(n));
+                }
+            }
+        }
+        Ok(Self::RemoveNamedPoint {
+            name: name.into(),
+            color: *np.color(),
+            model: np.opt_model(),
+            mappings,
+        })
+    }
+
+    //cp move_named_point
+    /// An edit moving the model position of a named point; None
+    /// unmaps it
+    pub fn move_named_point(
+        project: &Project,
+        name: &str,
+        model: Option<(Point3D, f64)>,
+    ) -> Result<Self> {
+        let np = project.nps_ref().get_pt_err(name)?;
+        Ok(Self::MoveNamedPoint {
+            name: name.into(),
+            from: np.opt_model(),
+            to: model,
+        })
+    }
+
+    //cp add_mapping
+    /// An edit adding a mapping of a named point to a CIP
+    pub fn add_mapping<S: Into<String>>(name: S, cip: usize, screen: Point2D, error: f64) -> Self {
+        Self::AddMapping {
+            name: name.into(),
+            mapping: CipMapping::new(cip, screen, error),
+        }
+    }
+
+    //cp remove_mapping
+    /// An edit removing the mapping of a named point from a CIP
+    pub fn remove_mapping(project: &Project, cip: usize, name: &str) -> Result<Self> {
+        let mapping = Self::find_mapping(project, cip, name)?;
+        Ok(Self::RemoveMapping {
+            name: name.into(),
+            mapping,
+        })
+    }
+
+    //cp move_mapping
+    /// An edit moving the mapping of a named point in a CIP
+    pub fn move_mapping(
+        project: &Project,
+        cip: usize,
+        name: &str,
+        screen: Point2D,
+        error: f64,
+    ) -> Result<Self> {
+        let from = Self::find_mapping(project, cip, name)?;
+        Ok(Self::MoveMapping {
+            name: name.into(),
+            from,
+            to: CipMapping::new(cip, screen, error),
+        })
+    }
+
+    //cp set_pose
+    /// An edit setting the pose of the camera of a CIP
+    pub fn set_pose(project: &Project, cip: usize, pose: Pose) -> Result<Self> {
+        check_cip(project, cip)?;
+        let from = Pose::of_camera(&project.cip(cip).borrow().camera_ref());
+        Ok(Self::SetPose {
+            cip,
+            from,
+            to: pose,
+        })
+    }
+
+    //cp set_lens
+    /// An edit setting the lens of the camera of a CIP
+    pub fn set_lens(project: &Project, cip: usize, lens: CameraLens) -> Result<Self> {
+        check_cip(project, cip)?;
+        let from = project.cip(cip).borrow().camera_ref().lens().clone();
+        Ok(Self::SetLens {
+            cip,
+            from: Box::new(from),
+            to: Box::new(lens),
+        })
+    }
+
+    //fi find_mapping
+    fn find_mapping(project: &Project, cip: usize, name: &str) -> Result<CipMapping> {
+        check_cip(project, cip)?;
+        let cip_r = project.cip(cip).borrow();
+        let pms = cip_r.pms_ref();
+        let Some(n) = pms.mapping_index_of_name(name) else {
+            return Err(format!("Named point {name} is not mapped in CIP {cip}").into());
+        };
+        let pm = &pms.mappings()[n];
+        Ok(CipMapping::new(cip, *pm.screen(), pm.error()).with_index(n))
+    }
+
+    //mp inverse
+    /// The edit that undoes this edit
+    pub fn inverse(&self) -> Self {
+        match self.clone() {
+            Self::AddNamedPoint {
+                name,
+                color,
+                model,
+                mappings,
+            } => Self::RemoveNamedPoint {
+                name,
+                color,
+                model,
+                mappings,
+            },
+            Self::RemoveNamedPoint {
+                name,
+                color,
+                model,
+                mappings,
+            } => Self::AddNamedPoint {
+                name,
+                color,
+                model,
+                mappings,
+            },
+            Self::MoveNamedPoint { name, from, to } => Self::MoveNamedPoint {
+                name,
+                from: to,
+                to: from,
+            },
+            Self::AddMapping { name, mapping } => Self::RemoveMapping { name, mapping },
+            Self::RemoveMapping { name, mapping } => Self::AddMapping { name, mapping },
+            Self::MoveMapping { name, from, to } => Self::MoveMapping {
+                name,
+                from: to,
+                to: from,
+            },
+            Self::SetPose { cip, from, to } => Self::SetPose {
+                cip,
+                from: to,
+                to: from,
+            },
+            Self::SetLens { cip, from, to } => Self::SetLens {
+                cip,
+                from: to,
+                to: from,
+            },
+        }
+    }
+
+    //mp apply
+    /// Apply the edit to a project
+    ///
+    /// This fails (leaving the project unchanged) if the project is
+    /// not in a state the edit can be applied to, such as adding a
+    /// named point that already exists
+    pub fn apply(&self, project: &Project) -> Result<()> {
+        match self {
+            Self::AddNamedPoint {
+                name,
+                color,
+                model,
+                mappings,
+            } => {
+                if project.nps_ref().get_pt(name).is_some() {
+                    return Err(format!("Named point {name} already exists").into());
+                }
+                for m in mappings {
+                    check_cip(project, m.cip)?;
+                }
+                project.nps_mut().add_pt(
+                    name,
+                    *color,
+                    model.map(|m| m.0),
+                    model.map_or(0.0, |m| m.1),
+                );
+                let nps = project.nps_ref();
+                for m in mappings {
+                    let cip_r = project.cip(m.cip).borrow();
+                    m.add_to(&mut cip_r.pms_mut(), &nps, name);
+                }
+            }
+            Self::RemoveNamedPoint { name, .. } => {
+                if project.nps_mut().remove_pt(name).is_none() {
+                    return Err(format!("Named point {name} not found").into());
+                }
+                for cip in 0..project.ncips() {
+                    let cip_r = project.cip(cip).borrow();
+                    let mut pms = cip_r.pms_mut();
+                    while let Some(n) = pms.mapping_index_of_name(name) {
+                        pms.remove_mapping(n);
+                    }
+                }
+            }
+            Self::MoveNamedPoint { name, to, .. } => {
+                project.nps_ref().get_pt_err(name)?.set_model(*to);
+            }
+            Self::AddMapping { name, mapping } => {
+                check_cip(project, mapping.cip)?;
+                let nps = project.nps_ref();
+                let cip_r = project.cip(mapping.cip).borrow();
+                let mut pms = cip_r.pms_mut();
+                if pms.mapping_index_of_name(name).is_some() {
+                    return Err(format!(
+                        "Named point {name} is already mapped in CIP {}",
+                        mapping.cip
+                    )
+                    .into());
+                }
+                if !mapping.add_to(&mut pms, &nps, name) {
+                    return Err(format!("Named point {name} not found").into());
+                }
+            }
+            Self::RemoveMapping { name, mapping } => {
+                Self::find_mapping(project, mapping.cip, name)?;
+                let cip_r = project.cip(mapping.cip).borrow();
+                let mut pms = cip_r.pms_mut();
+                let n = pms.mapping_index_of_name(name).unwrap();
+                pms.remove_mapping(n);
+            }
+            Self::MoveMapping { name, to, .. } => {
+                Self::find_mapping(project, to.cip, name)?;
+                let nps = project.nps_ref();
+                let cip_r = project.cip(to.cip).borrow();
+                let mut pms = cip_r.pms_mut();
+                // The mapping is replaced in place, so that undoing the
+                // move leaves the mappings in the same order
+                let n = pms.mapping_index_of_name(name).unwrap();
+                pms.remove_mapping(n);
+                pms.insert_mapping(n, &nps, name, &to.screen, to.error);
+            }
+            Self::SetPose { cip, to, .. } => {
+                check_cip(project, *cip)?;
+                to.apply_to(&mut project.cip(*cip).borrow().camera_mut());
+            }
+            Self::SetLens { cip, to, .. } => {
+                check_cip(project, *cip)?;
+                let to = (**to).clone();
+                project.cip(*cip).borrow().camera_mut().set_lens(to);
+            }
+        }
+        Ok(())
+    }
+}
+
+//ip Display for ProjectEdit
+impl std::fmt::Display for ProjectEdit {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::AddNamedPoint { name, .. } => write!(fmt, "add named point {name}"),
+            Self::RemoveNamedPoint { name, .. } => write!(fmt, "remove named point {name}"),
+            Self::MoveNamedPoint { name, .. } => write!(fmt, "move named point {name}"),
+            Self::AddMapping { name, mapping } => {
+                write!(fmt, "add mapping of {name} to CIP {}", mapping.cip)
+            }
+            Self::RemoveMapping { name, mapping } => {
+                write!(fmt, "remove mapping of {name} from CIP {}", mapping.cip)
+            }
+            Self::MoveMapping { name, to, .. } => {
+                write!(fmt, "move mapping of {name} in CIP {}", to.cip)
+            }
+            Self::SetPose { cip, .. } => write!(fmt, "set pose of camera of CIP {cip}"),
+            Self::SetLens { cip, .. } => write!(fmt, "set lens of camera of CIP {cip}"),
+        }
+    }
+}
+
+//fi check_cip
+fn check_cip(project: &Project, cip: usize) -> Result<()> {
+    if cip >= project.ncips() {
+        Err(format!(
+            "CIP {cip} is too large for the project (it has {} cips)",
+            project.ncips()
+        )
+        .into())
+    } else {
+        Ok(())
+    }
+}
+
+//a ProjectHistory
+//tp ProjectHistory
+/// The undo and redo stacks of edits of a project, most recent last
+#[derive(Debug, Default, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ProjectHistory {
+    #[serde(default)]
+    undo: Vec<ProjectEdit>,
+    #[serde(default)]
+    redo: Vec<ProjectEdit>,
+}
+
+//ip Versioned for ProjectHistory
+impl Versioned for ProjectHistory {
+    const SCHEMA_NAME: &'static str = "project_history";
+    const SCHEMA_VERSION: usize = 1;
+}
+
+//ip ProjectHistory
+impl ProjectHistory {
+    //cp from_json
+    pub fn from_json(json: &str) -> Result<Self> {
+        schema::from_json(json)
+    }
+
+    //mp to_json
+    pub fn to_json(&self, pretty: bool) -> Result<String> {
+        schema::to_json(self, pretty)
+    }
+
+    //fp path_of_project
+    /// The path of the history file for a project file; for
+    /// 'dir/proj.json' this is 'dir/proj.history.json'
+    pub fn path_of_project<P: AsRef<Path>>(project_path: P) -> PathBuf {
+        let project_path = project_path.as_ref();
+        let stem = project_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        project_path.with_file_name(format!("{stem}.history.json"))
+    }
+
+    //ap undo_edits
+    /// The edits that may be undone, oldest first
+    pub fn undo_edits(&self) -> &[ProjectEdit] {
+        &self.undo
+    }
+
+    //ap redo_edits
+    /// The edits that may be redone, the next to be redone last
+    pub fn redo_edits(&self) -> &[ProjectEdit] {
+        &self.redo
+    }
+
+    //ap is_empty
+    /// Return true if there are no edits to undo or redo
+    pub fn is_empty(&self) -> bool {
+        self.undo.is_empty() && self.redo.is_empty()
+    }
+
+    //mp push
+    /// Record an edit that has been applied; this clears the redo
+    /// stack
+    pub(crate) fn push(&mut self, edit: ProjectEdit) {
+        self.redo.clear();
+        self.undo.push(edit);
+        if self.undo.len() > MAX_HISTORY {
+            self.undo.remove(0);
+        }
+    }
+
+    //mp pop_undo
+    pub(crate) fn pop_undo(&mut self) -> Option<ProjectEdit> {
+        self.undo.pop()
+    }
+
+    //mp pop_redo
+    pub(crate) fn pop_redo(&mut self) -> Option<ProjectEdit> {
+        self.redo.pop()
+    }
+
+    //mp push_undo
+    pub(crate) fn push_undo(&mut self, edit: ProjectEdit) {
+        self.undo.push(edit);
+    }
+
+    //mp push_redo
+    pub(crate) fn push_redo(&mut self, edit: ProjectEdit) {
+        self.redo.push(edit);
+    }
+
+    //mp clear
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
@@ -1,5 +1,18 @@
+mod archive;
+mod check;
 mod cip;
+mod frame;
+mod history;
 mod project;
+mod provenance;
 
+pub use archive::{
+    sha256_hex, ArchiveFile, ArchiveImage, ArchiveManifest, ArchiveOptions, ProjectArchive,
+    ARCHIVE_VERSION,
+};
+pub use check::{CheckIssue, CheckKind, ProjectCheck, Severity};
 pub use cip::{Cip, CipDesc, CipFileDesc};
+pub use frame::{ControlPoint, ControlResidual, FrameKind, ProjectFrame};
+pub use history::{CipMapping, Pose, ProjectEdit, ProjectHistory};
 pub use project::{Project, ProjectFileDesc};
+pub use provenance::{InputFile, Provenance, ProvenanceLog};
@@ -1,23 +1,30 @@
 //a Imports
 use std::cell::{Ref, RefMut};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use ic_base::{json, PathSet, Point3D, Ray, Result, Rrc};
-use ic_camera::CameraDatabase;
-use ic_mapping::{NamedPointSet, PointMapping};
+use ic_base::schema::{self, Versioned};
+use ic_base::{PathSet, Point3D, Ray, Result, Rrc};
+use ic_camera::{CameraDatabase, CameraInstanceDesc};
+use ic_mapping::{NamedPointSet, PointMapping, PointMappingSet};
 
-use crate::{Cip, CipDesc, CipFileDesc};
+use crate::{
+    Cip, CipDesc, CipFileDesc, ControlResidual, ProjectEdit, ProjectFrame, ProjectHistory,
+    Provenance,
+};
 
 //a ProjectFileDesc
 //tp ProjectFileDesc
 /// A project description is a deserializable that can be stored in a
 /// JSON file
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
 pub struct ProjectFileDesc {
     cdb: String,
     nps: String,
-    /// A list of (camera filename, image filename, point mapping set filename)
+    /// The camera filename, image filename and point mapping set
+    /// filename of each CIP
     cips: Vec<CipFileDesc>,
     /// File containing the patch sets
     #[serde(default)]
@@ -25,22 +32,79 @@ pub struct ProjectFileDesc {
     /// Files containing image squares
     #[serde(default)]
     squares: Vec<String>,
+    /// Coordinate reference frame, if the project is georeferenced
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    frame: Option<ProjectFrame>,
+}
+
+//ip Versioned for ProjectFileDesc
+impl Versioned for ProjectFileDesc {
+    const SCHEMA_NAME: &'static str = "project_descriptor";
+    const SCHEMA_VERSION: usize = 1;
+
+    //fp upgrade
+    /// Version 0 files may give each CIP as a list of (camera
+    /// filename, image filename, point mapping set filename); these
+    /// become objects
+    fn upgrade(_version: usize, mut contents: Value) -> Result<Value> {
+        let Some(Value::Array(cips)) = contents.get_mut("cips") else {
+            return Ok(contents);
+        };
+        for cip in cips.iter_mut() {
+            let Value::Array(files) = cip else {
+                continue;
+            };
+            let mut files = std::mem::take(files).into_iter();
+            let mut cip_desc = serde_json::Map::new();
+            for key in ["camera_file", "image", "pms_file"] {
+                cip_desc.insert(key.into(), files.next().unwrap_or_default());
+            }
+            *cip = Value::Object(cip_desc);
+        }
+        Ok(contents)
+    }
 }
 
 //ip ProjectFileDesc
 impl ProjectFileDesc {
     //cp from_json
     pub fn from_json(json: &str) -> Result<Self> {
-        json::from_json("project", json)
+        schema::from_json(json)
     }
 
     //mp to_json
     pub fn to_json(&self, pretty: bool) -> Result<String> {
-        if pretty {
-            Ok(serde_json::to_string_pretty(self)?)
-        } else {
-            Ok(serde_json::to_string(self)?)
-        }
+        schema::to_json(self, pretty)
+    }
+
+    //ap cdb
+    pub fn cdb(&self) -> &str {
+        &self.cdb
+    }
+
+    //ap nps
+    pub fn nps(&self) -> &str {
+        &self.nps
+    }
+
+    //ap cips
+    pub fn cips(&self) -> &[CipFileDesc] {
+        &self.cips
+    }
+
+    //ap patches
+    pub fn patches(&self) -> &str {
+        &self.patches
+    }
+
+    //ap squares
+    pub fn squares(&self) -> &[String] {
+        &self.squares
+    }
+
+    //ap frame
+    pub fn frame(&self) -> Option<&ProjectFrame> {
+        self.frame.as_ref()
     }
 
     //mp load_project
@@ -61,6 +125,7 @@ impl ProjectFileDesc {
         // project.set_patches(Rrc::new(path_set.load_from_json_file("patches", &self.patches)?,
         //));
         for s in &self.squares {}
+        project.set_frame(self.frame.clone());
         Ok(project)
     }
 }
@@ -69,7 +134,7 @@ impl ProjectFileDesc {
 //tp ProjectDesc
 /// A project description is a deserializable that can be stored in a
 /// JSON file
-#[derive(Debug, Default, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize, JsonSchema)]
 struct ProjectDesc {
     cdb: CameraDatabase,
     nps: Rrc<NamedPointSet>,
@@ -79,6 +144,10 @@ struct ProjectDesc {
     cdb_filename: String,
     #[serde(default)]
     nps_filename: String,
+    #[serde(default)]
+    provenance: Vec<Provenance>,
+    #[serde(default)]
+    frame: Option<ProjectFrame>,
 }
 
 //a Project
@@ -96,6 +165,16 @@ struct ProjectDesc {
 /// The nps is in an Rrc to enable the Wasm (for example) to 'borrow'
 /// it to add points, move points, etc without having to have such
 /// methods on the project itself.
+///
+/// The provenance is a log of the commands that have written files
+/// derived from the project, oldest first
+///
+/// The history is the undo/redo log of edits made to the project
+/// using [Project::apply_edit]; it is not serialized with the
+/// project, but may be stored next to the project file
+///
+/// The frame, if any, georeferences the model coordinates of the
+/// project
 #[derive(Debug, Default, Serialize)]
 pub struct Project {
     cdb: Rrc<CameraDatabase>,
@@ -105,6 +184,17 @@ pub struct Project {
     cdb_filename: String,
     #[serde(default)]
     nps_filename: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    provenance: Vec<Provenance>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frame: Option<ProjectFrame>,
+    #[serde(skip)]
+    history: Rrc<ProjectHistory>,
+    /// The directory that the images of a project loaded from an
+    /// archive are extracted to; it is removed when the project is
+    /// dropped
+    #[serde(skip)]
+    archive_dir: Option<tempfile::TempDir>,
 }
 
 //ip Deserialize for Project
@@ -120,12 +210,18 @@ impl<'de> Deserialize<'de> for Project {
         let cips = project_desc.cips;
         let cdb_filename = project_desc.cdb_filename;
         let nps_filename = project_desc.nps_filename;
+        let provenance = project_desc.provenance;
+        let frame = project_desc.frame;
         let mut project = Self {
             cdb,
             nps,
             cips: vec![],
             cdb_filename,
             nps_filename,
+            provenance,
+            frame,
+            history: Rrc::default(),
+            archive_dir: None,
         };
         for cip_desc in cips {
             use serde::de::Error;
@@ -140,6 +236,47 @@ impl<'de> Deserialize<'de> for Project {
     }
 }
 
+//ip JsonSchema for Project
+impl JsonSchema for Project {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Project".into()
+    }
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        ProjectDesc::json_schema(generator)
+    }
+}
+
+//ip Versioned for Project
+impl Versioned for Project {
+    const SCHEMA_NAME: &'static str = "project";
+    const SCHEMA_VERSION: usize = 1;
+
+    //fp upgrade
+    /// Version 0 projects hold the version 0 forms of the named point
+    /// set, and of the camera and point mapping set of each CIP; these
+    /// are upgraded as for their own files
+    fn upgrade(version: usize, mut contents: Value) -> Result<Value> {
+        if version != 0 {
+            return Ok(contents);
+        }
+        if let Some(nps) = contents.get_mut("nps") {
+            *nps = schema::upgrade::<NamedPointSet>(0, nps.take())?;
+        }
+        let Some(Value::Array(cips)) = contents.get_mut("cips") else {
+            return Ok(contents);
+        };
+        for cip in cips.iter_mut() {
+            if let Some(camera) = cip.get_mut("camera") {
+                *camera = schema::upgrade::<CameraInstanceDesc>(0, camera.take())?;
+            }
+            if let Some(pms) = cip.get_mut("pms") {
+                *pms = schema::upgrade::<PointMappingSet>(0, pms.take())?;
+            }
+        }
+        Ok(contents)
+    }
+}
+
 //ip Project
 impl Project {
     //ap cdb
@@ -162,6 +299,137 @@ impl Project {
         &self.nps_filename
     }
 
+    //ap provenance
+    /// The log of commands that have written files derived from the
+    /// project
+    pub fn provenance(&self) -> &[Provenance] {
+        &self.provenance
+    }
+
+    //mp add_provenance
+    /// Append an entry to the provenance log
+    pub fn add_provenance(&mut self, provenance: Provenance) {
+        self.provenance.push(provenance);
+    }
+
+    //ap frame
+    /// The coordinate reference frame of the project, if it is
+    /// georeferenced
+    pub fn frame(&self) -> Option<&ProjectFrame> {
+        self.frame.as_ref()
+    }
+
+    //ap frame_mut
+    pub fn frame_mut(&mut self) -> Option<&mut ProjectFrame> {
+        self.frame.as_mut()
+    }
+
+    //mp set_frame
+    pub fn set_frame(&mut self, frame: Option<ProjectFrame>) {
+        self.frame = frame;
+    }
+
+    //mp set_archive_dir
+    /// Give the project the directory its archived images are
+    /// extracted to, so that it is removed with the project
+    pub(crate) fn set_archive_dir(&mut self, dir: tempfile::TempDir) {
+        self.archive_dir = Some(dir);
+    }
+
+    //mp fit_frame
+    /// Fit the frame of the project to its control points, using the
+    /// current model positions of the named points, returning the
+    /// residual of each control point used
+    pub fn fit_frame(&mut self) -> Result<Vec<ControlResidual>> {
+        let Some(frame) = &mut self.frame else {
+            return Err("The project does not have a frame".into());
+        };
+        frame.fit(&self.nps.borrow())
+    }
+
+    //ap history
+    /// The undo/redo history of edits of the project
+    pub fn history(&self) -> Ref<'_, ProjectHistory> {
+        self.history.borrow()
+    }
+
+    //mp apply_edit
+    /// Apply an edit to the project, recording it in the history so
+    /// that it can be undone
+    pub fn apply_edit(&self, edit: ProjectEdit) -> Result<()> {
+        edit.apply(self)?;
+        self.history.borrow_mut().push(edit);
+        Ok(())
+    }
+
+    //mp record_edit
+    /// Record an edit that has already been made to the project (such
+    /// as the pose change from locating a camera) so that it can be
+    /// undone
+    pub fn record_edit(&self, edit: ProjectEdit) {
+        self.history.borrow_mut().push(edit);
+    }
+
+    //mp undo
+    /// Undo the most recent edit, returning it; None is returned if
+    /// there is nothing to undo
+    pub fn undo(&self) -> Result<Option<ProjectEdit>> {
+        let Some(edit) = self.history.borrow_mut().pop_undo() else {
+            return Ok(None);
+        };
+        if let Err(e) = edit.inverse().apply(self) {
+            self.history.borrow_mut().push_undo(edit);
+            return Err(e);
+        }
+        self.history.borrow_mut().push_redo(edit.clone());
+        Ok(Some(edit))
+    }
+
+    //mp redo
+    /// Redo the most recently undone edit, returning it; None is
+    /// returned if there is nothing to redo
+    pub fn redo(&self) -> Result<Option<ProjectEdit>> {
+        let Some(edit) = self.history.borrow_mut().pop_redo() else {
+            return Ok(None);
+        };
+        if let Err(e) = edit.apply(self) {
+            self.history.borrow_mut().push_redo(edit);
+            return Err(e);
+        }
+        self.history.borrow_mut().push_undo(edit.clone());
+        Ok(Some(edit))
+    }
+
+    //mp clear_history
+    pub fn clear_history(&self) {
+        self.history.borrow_mut().clear();
+    }
+
+    //mp load_history
+    /// Load the history for the project from the file next to the
+    /// project file; if there is no such file the history is cleared
+    pub fn load_history<P: AsRef<std::path::Path>>(&self, project_path: P) -> Result<()> {
+        let path = ProjectHistory::path_of_project(project_path);
+        let history = if path.is_file() {
+            let json = std::fs::read_to_string(&path).map_err(|e| (path.display(), e))?;
+            ProjectHistory::from_json(&json)?
+        } else {
+            ProjectHistory::default()
+        };
+        *self.history.borrow_mut() = history;
+        Ok(())
+    }
+
+    //mp save_history
+    /// Save the history for the project to the file next to the
+    /// project file
+    pub fn save_history<P: AsRef<std::path::Path>>(&self, project_path: P) -> Result<()> {
+        let path = ProjectHistory::path_of_project(project_path);
+        let json = self.history.borrow().to_json(true)?;
+        std::fs::write(&path, json).map_err(|e| (path.display(), e))?;
+        Ok(())
+    }
+
     //mp set_cdb_filename
     pub fn set_cdb_filename<S: Into<String>>(&mut self, cdb_filename: S) {
         self.cdb_filename = cdb_filename.into();
@@ -201,7 +469,7 @@ impl Project {
 
     //cp from_json
     pub fn from_json(json: &str) -> Result<Self> {
-        json::from_json("project", json)
+        schema::from_json(json)
     }
 
     //mp set_cdb
@@ -236,11 +504,7 @@ impl Project {
 
     //mp to_json
     pub fn to_json(&self, pretty: bool) -> Result<String> {
-        if pretty {
-            Ok(serde_json::to_string_pretty(self)?)
-        } else {
-            Ok(serde_json::to_string(self)?)
-        }
+        schema::to_json(self, pretty)
     }
 
     //mp locate_all
@@ -0,0 +1,170 @@
+//a Imports
+use std::path::{Path, PathBuf};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use ic_base::schema::{self, Versioned};
+use ic_base::Result;
+
+//a InputFile
+//tp InputFile
+/// A file that was read by a command, with the SHA-256 hash of its
+/// contents when it was read
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct InputFile {
+    filename: String,
+    sha256: String,
+}
+
+//ip InputFile
+impl InputFile {
+    //cp new
+    pub fn new<S: Into<String>>(filename: S, sha256: S) -> Self {
+        Self {
+            filename: filename.into(),
+            sha256: sha256.into(),
+        }
+    }
+
+    //ap filename
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    //ap sha256
+    pub fn sha256(&self) -> &str {
+        &self.sha256
+    }
+}
+
+//a Provenance
+//tp Provenance
+/// A record of a command that wrote data derived from a project, so
+/// that results (such as calibrations) can be traced back to how they
+/// were produced
+///
+/// The project holds a log of these, to which an entry is appended
+/// whenever a command writes files; other files written have their own
+/// [ProvenanceLog]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Provenance {
+    /// Time the files were written, as RFC 3339
+    timestamp: String,
+    /// Name and version of the tool
+    tool: String,
+    /// The command line, starting with where it was executed from
+    /// (the program, or a batch file and line)
+    command: Vec<String>,
+    /// The files read to produce the data
+    #[serde(default)]
+    inputs: Vec<InputFile>,
+    /// The files written
+    #[serde(default)]
+    outputs: Vec<String>,
+}
+
+//ip Provenance
+impl Provenance {
+    //cp new
+    pub fn new<S: Into<String>>(timestamp: S, tool: S, command: &[String]) -> Self {
+        Self {
+            timestamp: timestamp.into(),
+            tool: tool.into(),
+            command: command.to_vec(),
+            inputs: vec![],
+            outputs: vec![],
+        }
+    }
+
+    //cp with_inputs
+    pub fn with_inputs(mut self, inputs: &[InputFile]) -> Self {
+        self.inputs = inputs.to_vec();
+        self
+    }
+
+    //cp with_outputs
+    pub fn with_outputs(mut self, outputs: &[String]) -> Self {
+        self.outputs = outputs.to_vec();
+        self
+    }
+
+    //ap timestamp
+    pub fn timestamp(&self) -> &str {
+        &self.timestamp
+    }
+
+    //ap tool
+    pub fn tool(&self) -> &str {
+        &self.tool
+    }
+
+    //ap command
+    pub fn command(&self) -> &[String] {
+        &self.command
+    }
+
+    //ap inputs
+    pub fn inputs(&self) -> &[InputFile] {
+        &self.inputs
+    }
+
+    //ap outputs
+    pub fn outputs(&self) -> &[String] {
+        &self.outputs
+    }
+}
+
+//a ProvenanceLog
+//tp ProvenanceLog
+/// The provenance log of a file other than a project, kept in a file
+/// next to it named by [ProvenanceLog::path_of_output]
+///
+/// An entry is appended whenever a command writes the file
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ProvenanceLog {
+    provenance: Vec<Provenance>,
+}
+
+//ip Versioned for ProvenanceLog
+impl Versioned for ProvenanceLog {
+    const SCHEMA_NAME: &'static str = "provenance_log";
+    const SCHEMA_VERSION: usize = 1;
+}
+
+//ip ProvenanceLog
+impl ProvenanceLog {
+    //cp from_json
+    pub fn from_json(json: &str) -> Result<Self> {
+        schema::from_json(json)
+    }
+
+    //mp to_json
+    pub fn to_json(&self, pretty: bool) -> Result<String> {
+        schema::to_json(self, pretty)
+    }
+
+    //fp path_of_output
+    /// The path of the provenance log for an output file; for
+    /// 'dir/camera.json' this is 'dir/camera.provenance.json'
+    pub fn path_of_output<P: AsRef<Path>>(output_path: P) -> PathBuf {
+        let output_path = output_path.as_ref();
+        let stem = output_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        output_path.with_file_name(format!("{stem}.provenance.json"))
+    }
+
+    //mp push
+    /// Append an entry to the log
+    pub fn push(&mut self, provenance: Provenance) {
+        self.provenance.push(provenance);
+    }
+
+    //ap provenance
+    /// The entries of the log, oldest first
+    pub fn provenance(&self) -> &[Provenance] {
+        &self.provenance
+    }
+}
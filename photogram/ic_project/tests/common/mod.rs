@@ -0,0 +1,31 @@
+//a Imports
+use ic_base::{Point3D, Rrc};
+use ic_image::Color;
+use ic_project::{Cip, Project};
+
+//a Support
+//tp Mappings
+/// The names of named points and the screen positions they are mapped
+/// to in a CIP
+pub type Mappings<'a> = &'a [(&'a str, [f64; 2])];
+
+//fp project
+/// Create a project with named points (given by name, optional model
+/// position and model error) and CIPs, each with its mappings
+pub fn project(points: &[(&str, Option<Point3D>, f64)], cips: Vec<(Cip, Mappings)>) -> Project {
+    let mut project = Project::default();
+    for (name, model, error) in points {
+        project
+            .nps_mut()
+            .add_pt(*name, Color::black(), *model, *error);
+    }
+    for (cip, mappings) in cips {
+        let n = project.add_cip(Rrc::new(cip));
+        let nps = project.nps_ref();
+        let cip = project.cip(n).borrow();
+        for (name, screen) in mappings {
+            cip.pms_mut().add_mapping(&nps, name, &(*screen).into(), 1.);
+        }
+    }
+    project
+}
@@ -0,0 +1,154 @@
+//a Imports
+use ic_base::PathSet;
+use ic_project::{sha256_hex, ArchiveOptions, Project, ProjectArchive};
+
+//a Support
+//fi image_dir
+/// Create a new temporary directory containing 'photos/img.jpg'
+/// (whose contents do not matter to the archive) and return it with a
+/// path set for the directory and its 'photos' subdirectory
+fn image_dir() -> (tempfile::TempDir, PathSet) {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let dir = temp_dir.path();
+    let photos = dir.join("photos");
+    std::fs::create_dir_all(&photos).unwrap();
+    std::fs::write(photos.join("img.jpg"), b"not really a jpeg").unwrap();
+    let mut path_set = PathSet::default();
+    path_set.add_path(dir.display().to_string()).unwrap();
+    path_set.add_path(photos.display().to_string()).unwrap();
+    (temp_dir, path_set)
+}
+
+//fi project
+/// Create a project with a named point, and two CIPs with the same
+/// image (given with a directory that must be dropped)
+fn project() -> Project {
+    let cip = r#"{"camera_filename": "/some/where/camera.json", "pms_filename": "pms.json", "image_filename": "",
+        "camera": {"body": "body", "lens": "lens", "mm_focus_distance": 400.0,
+                   "position": [0.0, 0.0, 0.0], "orientation": [0.0, 0.0, 0.0, 1.0]},
+        "pms": [], "image": "photos/img.jpg"}"#;
+    let json = format!(
+        r##"{{"cdb": {{"bodies": [{{"name": "body", "aliases": [], "flip_y": false, "px_centre": [200.0, 150.0],
+                                    "px_width": 400.0, "px_height": 300.0,
+                                    "mm_sensor_width": 36.0, "mm_sensor_height": 24.0}}],
+                     "lenses": [{{"name": "lens", "aliases": [], "mm_focal_length": 50.0,
+                                   "stw_poly": [0.0], "wts_poly": [0.0]}}]}},
+            "nps": [["alpha", "#000000", [[1.0, 2.0, 3.0], 0.5]]],
+            "cips": [{cip}, {cip}],
+            "nps_filename": "/some/where/nps.json"}}"##
+    );
+    Project::from_json(&json).unwrap()
+}
+
+//fi archive_bytes
+fn archive_bytes(project: &Project, path_set: &PathSet, include_images: bool) -> Vec<u8> {
+    let options = ArchiveOptions::new("test", "2025-01-01T00:00:00Z").with_images(include_images);
+    let archive = ProjectArchive::of_project(project, path_set, &options).unwrap();
+    let mut data = vec![];
+    archive.write(&mut data).unwrap();
+    data
+}
+
+//fi replace
+/// Replace some bytes of an archive with others of the same length
+fn replace(data: &mut [u8], from: &str, to: &str) {
+    assert_eq!(from.len(), to.len());
+    let n = data
+        .windows(from.len())
+        .position(|w| w == from.as_bytes())
+        .unwrap();
+    data[n..n + to.len()].copy_from_slice(to.as_bytes());
+}
+
+//a Tests
+//ft round_trip
+#[test]
+fn round_trip() {
+    let (_dir, path_set) = image_dir();
+    let data = archive_bytes(&project(), &path_set, true);
+    let archive = ProjectArchive::read(data.as_slice()).unwrap();
+
+    let manifest = archive.manifest();
+    assert_eq!(manifest.tool(), "test");
+    let paths: Vec<_> = manifest.files().iter().map(|f| f.path()).collect();
+    assert_eq!(paths, vec!["images/img.jpg", "project.json"]);
+    assert_eq!(manifest.images().len(), 2);
+    assert_eq!(
+        manifest.images()[1].sha256(),
+        Some(sha256_hex(b"not really a jpeg").as_str())
+    );
+
+    let project = archive.project().unwrap();
+    assert_eq!(project.nps_filename(), "nps.json");
+    assert!(project.nps_ref().get_pt("alpha").is_some());
+    let cip = project.cip(1).borrow();
+    assert_eq!(cip.image_name(), "images/img.jpg");
+    assert_eq!(cip.camera_filename(), "camera.json");
+}
+
+//ft images_not_included
+#[test]
+fn images_not_included() {
+    let (_dir, path_set) = image_dir();
+    let data = archive_bytes(&project(), &path_set, false);
+    let archive = ProjectArchive::read(data.as_slice()).unwrap();
+    assert_eq!(archive.manifest().files().len(), 1);
+    assert_eq!(
+        archive.project().unwrap().cip(0).borrow().image_name(),
+        "img.jpg"
+    );
+    assert!(archive.verify_images(&path_set).is_empty());
+    assert_eq!(archive.verify_images(&PathSet::default()).len(), 2);
+}
+
+//ft missing_image
+#[test]
+fn missing_image() {
+    let options = ArchiveOptions::new("test", "").with_images(true);
+    assert!(ProjectArchive::of_project(&project(), &PathSet::default(), &options).is_err());
+}
+
+//ft corrupt
+#[test]
+fn corrupt() {
+    let (_dir, path_set) = image_dir();
+    let mut data = archive_bytes(&project(), &path_set, false);
+    replace(&mut data, "alpha", "omega");
+    let e = ProjectArchive::read(data.as_slice()).unwrap_err();
+    assert!(e.to_string().contains("corrupt"), "{e}");
+}
+
+//ft newer_version
+#[test]
+fn newer_version() {
+    let (_dir, path_set) = image_dir();
+    let mut data = archive_bytes(&project(), &path_set, false);
+    replace(&mut data, "\"version\": 1", "\"version\": 9");
+    let e = ProjectArchive::read(data.as_slice()).unwrap_err();
+    assert!(e.to_string().contains("newer"), "{e}");
+}
+
+//ft load_project
+#[test]
+fn load_project() {
+    let (_dir, path_set) = image_dir();
+    let data = archive_bytes(&project(), &path_set, true);
+    let archive = ProjectArchive::read(data.as_slice()).unwrap();
+    let project = archive.load_project().unwrap();
+    let image = std::path::PathBuf::from(project.cip(0).borrow().image_name());
+    assert_eq!(std::fs::read(&image).unwrap(), b"not really a jpeg");
+
+    // The extracted images are removed with the project
+    drop(project);
+    assert!(!image.exists());
+}
+
+//ft bad_image_name
+#[test]
+fn bad_image_name() {
+    let (_dir, path_set) = image_dir();
+    let mut data = archive_bytes(&project(), &path_set, false);
+    replace(&mut data, "\"name\": \"img.jpg\"", "\"name\": \"/etc/xx\"");
+    let e = ProjectArchive::read(data.as_slice()).unwrap_err();
+    assert!(e.to_string().contains("not a relative path"), "{e}");
+}
@@ -0,0 +1,75 @@
+//a Imports
+use ic_base::PathSet;
+use ic_project::{CheckKind, Cip, Project, ProjectCheck, Severity};
+
+mod common;
+
+//a Support
+//fi project
+/// Create a project with three named points with model positions and
+/// one without, and a CIP that maps two of them (one twice)
+fn project() -> Project {
+    let model = Some([1., 2., 3.].into());
+    let mut cip = Cip::default();
+    cip.set_image("no_such_image.jpg");
+    common::project(
+        &[
+            ("a", model, 0.5),
+            ("b", model, 0.5),
+            ("c", model, 0.5),
+            ("d", None, 0.5),
+        ],
+        vec![(cip, &[("a", [10., 0.]), ("b", [20., 0.]), ("b", [30., 0.])])],
+    )
+}
+
+//fi kinds
+fn kinds(check: &ProjectCheck) -> Vec<(CheckKind, Option<&str>)> {
+    check
+        .issues()
+        .iter()
+        .map(|i| (i.kind(), i.name()))
+        .collect()
+}
+
+//a Tests
+//ft check
+#[test]
+fn check() {
+    let project = project();
+    let check = project.check(&PathSet::default());
+    assert_eq!(
+        kinds(&check),
+        vec![
+            (CheckKind::DuplicateMapping, Some("b")),
+            // A duplicated mapping does not count towards locating
+            (CheckKind::TooFewMappings, None),
+            (CheckKind::MissingFile, None),
+            (CheckKind::UnobservedNamedPoint, Some("c")),
+            (CheckKind::UnobservedNamedPoint, Some("d")),
+        ]
+    );
+    assert_eq!(check.errors(), 1);
+    assert_eq!(check.warnings(), 4);
+    assert_eq!(check.issues()[2].severity(), Severity::Error);
+    assert_eq!(check.issues()[2].cip(), Some(0));
+    assert!(!check.is_ok());
+}
+
+//ft too_few
+#[test]
+fn too_few() {
+    let project = project();
+    project.cip(0).borrow().pms_mut().remove_mapping(2);
+    project.cip(0).borrow().pms_mut().remove_mapping(1);
+    let check = project.check(&PathSet::default());
+    assert_eq!(check.issues()[0].kind(), CheckKind::TooFewMappings);
+}
+
+//ft missing_file
+#[test]
+fn missing_file() {
+    let check = ProjectCheck::of_file(&PathSet::default(), "no_such_project.json");
+    assert_eq!(kinds(&check), vec![(CheckKind::MissingFile, None)]);
+    assert_eq!(check.issues()[0].file(), Some("no_such_project.json"));
+}
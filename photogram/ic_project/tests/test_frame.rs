@@ -0,0 +1,164 @@
+//a Imports
+use geo_nd::Vector;
+use ic_base::{Geodetic, PathSet, Point3D};
+use ic_image::Color;
+use ic_project::{CheckKind, FrameKind, Project, ProjectFrame};
+
+mod common;
+
+//a Support
+//fi project
+/// Create a project with four named points with model positions (in
+/// mm, with x east, y north and z up) and one without, and an ENU frame
+/// whose control points are the geodetic positions of four of them
+fn project() -> Project {
+    let origin = Geodetic::new(51.5, -0.1, 45.);
+    let points: Vec<(&str, Option<Point3D>, f64)> = vec![
+        ("a", Some([0., 0., 0.].into()), 0.),
+        ("b", Some([10_000., 0., 0.].into()), 0.),
+        ("c", Some([0., 20_000., 0.].into()), 0.),
+        ("d", Some([3_000., 4_000., 5_000.].into()), 0.),
+        ("e", None, 0.),
+    ];
+    let mut project = common::project(&points, vec![]);
+    let mut frame = ProjectFrame::new(FrameKind::Enu { origin });
+    for (name, model, _) in &points {
+        // The model origin is 100m east of the frame origin
+        let geodetic = match model {
+            Some(model) => {
+                Geodetic::of_enu(&origin, &(*model / 1000. + Point3D::from([100., 0., 0.])))
+            }
+            None => origin,
+        };
+        frame.set_control_point(*name, geodetic);
+    }
+    project.set_frame(Some(frame));
+    project
+}
+
+//a Tests
+//ft frame_kind
+#[test]
+fn frame_kind() {
+    assert_eq!(FrameKind::of_str("ecef").unwrap(), FrameKind::Ecef);
+    assert!(matches!(
+        FrameKind::of_str("utm:31N").unwrap(),
+        FrameKind::Utm { .. }
+    ));
+    let FrameKind::Enu { origin } = FrameKind::of_str("enu:51.5,-0.1,45").unwrap() else {
+        panic!("Expected an ENU frame");
+    };
+    assert_eq!(
+        (origin.lat(), origin.lon(), origin.alt()),
+        (51.5, -0.1, 45.)
+    );
+    assert!(FrameKind::of_str("ecef:1").is_err());
+    assert!(FrameKind::of_str("utm").is_err());
+    assert!(FrameKind::of_str("wgs84").is_err());
+}
+
+//ft fit
+#[test]
+fn fit() {
+    let mut project = project();
+    assert!(project.frame().unwrap().transform().is_none());
+    assert!(project
+        .frame()
+        .unwrap()
+        .model_to_frame(&Point3D::default())
+        .is_err());
+
+    // Control point 'e' has no model position, so is not used
+    let residuals = project.fit_frame().unwrap();
+    assert_eq!(residuals.len(), 4);
+    assert!(residuals.iter().all(|r| r.residual() < 1.0E-6));
+
+    let frame = project.frame().unwrap();
+    assert!((frame.metres_to_model(1.).unwrap() - 1000.).abs() < 1.0E-6);
+    let p = frame.model_to_frame(&[1000., 0., 0.].into()).unwrap();
+    assert!((p - Point3D::from([101., 0., 0.])).length() < 1.0E-6, "{p}");
+
+    let model: Point3D = [-5_000., 7_000., 300.].into();
+    let geodetic = frame.model_to_geodetic(&model).unwrap();
+    let p = frame.geodetic_to_model(&geodetic).unwrap();
+    assert!((p - model).length() < 1.0E-3, "{p}");
+
+    // Changing the control points requires fitting again
+    project.frame_mut().unwrap().remove_control_point("d");
+    assert!(project.frame().unwrap().transform().is_none());
+    assert_eq!(project.fit_frame().unwrap().len(), 3);
+    assert!(project.frame_mut().unwrap().remove_control_point("c"));
+    assert!(!project.frame_mut().unwrap().remove_control_point("c"));
+    let err = project.fit_frame().unwrap_err().to_string();
+    assert!(err.contains("but there are 2"), "{err}");
+
+    // Three control points in a line cannot be fitted either
+    project
+        .nps_mut()
+        .add_pt("f", Color::black(), Some([20_000., 0., 0.].into()), 0.);
+    let origin = Geodetic::new(51.5, -0.1, 45.);
+    project
+        .frame_mut()
+        .unwrap()
+        .set_control_point("f", Geodetic::of_enu(&origin, &[120., 0., 0.].into()));
+    let err = project.fit_frame().unwrap_err().to_string();
+    assert!(err.contains("not in a line"), "{err}");
+}
+
+//ft serialize
+#[test]
+fn serialize() {
+    let mut project = project();
+    project.fit_frame().unwrap();
+    let json = project.to_json(false).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["frame"]["kind"], "enu");
+    assert_eq!(
+        value["frame"]["control_points"].as_array().unwrap().len(),
+        5
+    );
+
+    // The JSON round trip of floats need not be exact
+    let project2 = Project::from_json(&json).unwrap();
+    let (frame, frame2) = (project.frame().unwrap(), project2.frame().unwrap());
+    assert_eq!(frame2.kind(), frame.kind());
+    assert_eq!(frame2.control_points().len(), frame.control_points().len());
+    for (cp, cp2) in frame.control_points().iter().zip(frame2.control_points()) {
+        assert_eq!(cp2.name(), cp.name());
+        assert!((cp2.geodetic().lat() - cp.geodetic().lat()).abs() < 1.0E-12);
+        assert!((cp2.geodetic().lon() - cp.geodetic().lon()).abs() < 1.0E-12);
+        assert!((cp2.geodetic().alt() - cp.geodetic().alt()).abs() < 1.0E-9);
+    }
+    let model: Point3D = [-5_000., 7_000., 300.].into();
+    let p = frame.model_to_frame(&model).unwrap();
+    let p2 = frame2.model_to_frame(&model).unwrap();
+    assert!((p2 - p).length() < 1.0E-9, "{p2} != {p}");
+
+    // A project without a frame does not write one
+    project.set_frame(None);
+    let json = project.to_json(false).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert!(value.get("frame").is_none());
+    assert!(Project::from_json(&json).unwrap().frame().is_none());
+}
+
+//ft check
+#[test]
+fn check() {
+    let mut project = project();
+    let check = project.check(&PathSet::default());
+    let kinds: Vec<_> = check
+        .issues()
+        .iter()
+        .map(|i| (i.kind(), i.name()))
+        .collect();
+    assert!(kinds.contains(&(CheckKind::UnusableControlPoint, Some("e"))));
+    assert!(kinds.contains(&(CheckKind::FrameNotFitted, None)));
+
+    project.fit_frame().unwrap();
+    let check = project.check(&PathSet::default());
+    assert!(!check
+        .issues()
+        .iter()
+        .any(|i| i.kind() == CheckKind::FrameNotFitted));
+}
@@ -0,0 +1,131 @@
+//a Imports
+use ic_base::Point3D;
+use ic_camera::CameraProjection;
+use ic_image::Color;
+use ic_project::{Cip, Pose, Project, ProjectEdit, ProjectHistory};
+
+mod common;
+
+//a Support
+//fi project
+/// Create a project with two named points, both mapped in each of two
+/// CIPs
+fn project() -> Project {
+    let mappings = [("a", [10., 20.]), ("b", [30., 40.])];
+    common::project(
+        &[("a", Some([1., 2., 3.].into()), 0.5), ("b", None, 0.)],
+        vec![(Cip::default(), &mappings), (Cip::default(), &mappings)],
+    )
+}
+
+//fi state
+/// A summary of the state of the project, including the order of the
+/// mappings of each CIP
+fn state(project: &Project) -> String {
+    let mut names: Vec<_> = project
+        .nps_ref()
+        .iter()
+        .map(|(n, np)| format!("{n}:{:?}", np.opt_model()))
+        .collect();
+    names.sort();
+    let mut s = names.join(",");
+    for n in 0..project.ncips() {
+        let cip = project.cip(n).borrow();
+        s += &format!(" {:?}", cip.camera_ref().position());
+        let mappings: Vec<_> = cip
+            .pms_ref()
+            .mappings()
+            .iter()
+            .map(|pm| format!("{}@{}", pm.name(), pm.screen()))
+            .collect();
+        s += &format!(" {}", mappings.join(" "));
+    }
+    s
+}
+
+//a Tests
+//ft undo_redo
+#[test]
+fn undo_redo() {
+    let project = project();
+    let initial = state(&project);
+
+    let edit = ProjectEdit::remove_named_point(&project, "a").unwrap();
+    project.apply_edit(edit).unwrap();
+    assert!(project.nps_ref().get_pt("a").is_none());
+    assert_eq!(project.cip(1).borrow().pms_ref().mappings().len(), 1);
+    let removed = state(&project);
+
+    let edit = ProjectEdit::move_mapping(&project, 0, "b", [5., 6.].into(), 2.).unwrap();
+    project.apply_edit(edit).unwrap();
+    let pose = Pose::new([0., 0., 10.].into(), Default::default());
+    let edit = ProjectEdit::set_pose(&project, 1, pose).unwrap();
+    project.apply_edit(edit).unwrap();
+    let edited = state(&project);
+    assert_eq!(project.history().undo_edits().len(), 3);
+
+    project.undo().unwrap().unwrap();
+    project.undo().unwrap().unwrap();
+    assert_eq!(state(&project), removed);
+    project.undo().unwrap().unwrap();
+    assert_eq!(state(&project), initial);
+    assert!(project.undo().unwrap().is_none());
+
+    for _ in 0..3 {
+        project.redo().unwrap().unwrap();
+    }
+    assert_eq!(state(&project), edited);
+    let position: Point3D = [0., 0., 10.].into();
+    assert_eq!(project.cip(1).borrow().camera_ref().position(), position);
+    assert!(project.redo().unwrap().is_none());
+
+    // A new edit discards the edits that could be redone
+    project.undo().unwrap().unwrap();
+    let edit = ProjectEdit::move_named_point(&project, "b", Some(([0., 1., 0.].into(), 0.)));
+    project.apply_edit(edit.unwrap()).unwrap();
+    assert!(project.history().redo_edits().is_empty());
+}
+
+//ft mapping_order
+#[test]
+fn mapping_order() {
+    // Undoing the removal or move of a mapping restores it in place
+    let project = project();
+    let initial = state(&project);
+    let edit = ProjectEdit::remove_mapping(&project, 1, "a").unwrap();
+    project.apply_edit(edit).unwrap();
+    let edit = ProjectEdit::move_mapping(&project, 0, "a", [5., 6.].into(), 2.).unwrap();
+    project.apply_edit(edit).unwrap();
+    assert_eq!(project.cip(0).borrow().pms_ref().mappings()[0].name(), "a");
+    project.undo().unwrap().unwrap();
+    project.undo().unwrap().unwrap();
+    assert_eq!(state(&project), initial);
+    assert_eq!(project.cip(1).borrow().pms_ref().mappings()[0].name(), "a");
+}
+
+//ft invalid
+#[test]
+fn invalid() {
+    let project = project();
+    let edit = ProjectEdit::add_named_point("a", Color::black(), None);
+    assert!(project.apply_edit(edit).is_err());
+    let edit = ProjectEdit::add_mapping("b", 0, [0., 0.].into(), 1.);
+    assert!(project.apply_edit(edit).is_err());
+    assert!(ProjectEdit::remove_mapping(&project, 2, "a").is_err());
+    assert!(project.history().undo_edits().is_empty());
+}
+
+//ft json
+#[test]
+fn json() {
+    let project = project();
+    let edit = ProjectEdit::remove_named_point(&project, "b").unwrap();
+    project.apply_edit(edit).unwrap();
+    let json = project.history().to_json(false).unwrap();
+    let history = ProjectHistory::from_json(&json).unwrap();
+    assert_eq!(history.undo_edits().len(), 1);
+    assert_eq!(
+        ProjectHistory::path_of_project("dir/proj.json"),
+        std::path::PathBuf::from("dir/proj.history.json")
+    );
+}
@@ -0,0 +1,149 @@
+//a Imports
+use ic_base::schema::{self, Versioned};
+use ic_image::Color;
+use ic_mapping::NamedPointSet;
+use ic_project::{Project, ProjectFileDesc, Provenance, ProvenanceLog};
+
+//a Tests
+//ft named_point_set_version_0
+#[test]
+fn named_point_set_version_0() {
+    // Version 0 files are the bare list of points, and may give a
+    // model position without its uncertainty
+    let json = r##"[
+        ["a", "#ff0000", [1.0, 2.0, 3.0]],
+        ["b", "#00ff00", [[4.0, 5.0, 6.0], 0.5]],
+        ["c", "#0000ff", null]
+    ]"##;
+    assert_eq!(schema::version_of_json::<NamedPointSet>(json).unwrap(), 0);
+    let nps = NamedPointSet::from_json(json).unwrap();
+    let a = nps.get_pt("a").unwrap().opt_model().unwrap();
+    assert_eq!(a.0, [1., 2., 3.].into());
+    assert_eq!(a.1, 0.);
+    let b = nps.get_pt("b").unwrap().opt_model().unwrap();
+    assert_eq!(b.0, [4., 5., 6.].into());
+    assert_eq!(b.1, 0.5);
+    assert!(nps.get_pt("c").unwrap().opt_model().is_none());
+
+    // It is written back as the current version
+    let json = nps.to_json(false).unwrap();
+    assert_eq!(
+        schema::version_of_json::<NamedPointSet>(&json).unwrap(),
+        NamedPointSet::SCHEMA_VERSION
+    );
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["named_points"].as_array().unwrap().len(), 3);
+    let nps2 = NamedPointSet::from_json(&json).unwrap();
+    assert_eq!(nps2.to_json(false).unwrap(), json);
+}
+
+//ft project_descriptor_version_0
+#[test]
+fn project_descriptor_version_0() {
+    // Version 0 descriptors may give each CIP as a list of filenames
+    let json = r#"{
+        "cdb": "cdb.json",
+        "nps": "nps.json",
+        "cips": [
+            ["camera.json", "image.jpg", "pms.json"],
+            {"camera_file": "camera2.json", "image": "image2.jpg", "pms_file": "pms2.json"}
+        ]
+    }"#;
+    let desc = ProjectFileDesc::from_json(json).unwrap();
+    assert_eq!(desc.cips().len(), 2);
+    assert_eq!(desc.cips()[0].camera_file(), "camera.json");
+    assert_eq!(desc.cips()[0].image(), "image.jpg");
+    assert_eq!(desc.cips()[0].pms_file(), "pms.json");
+    assert_eq!(desc.cips()[1].camera_file(), "camera2.json");
+
+    let json = desc.to_json(false).unwrap();
+    assert!(json.starts_with(r#"{"version":1,"#));
+    let desc = ProjectFileDesc::from_json(&json).unwrap();
+    assert_eq!(desc.cips()[0].pms_file(), "pms.json");
+}
+
+//ft project_version_0
+#[test]
+fn project_version_0() {
+    let json = r##"{
+        "cdb": {"bodies": [], "lenses": []},
+        "nps": [["a", "#ff0000", [1.0, 2.0, 3.0]]],
+        "cips": []
+    }"##;
+    let project = Project::from_json(json).unwrap();
+    let a = project.nps_ref().get_pt("a").unwrap().opt_model().unwrap();
+    assert_eq!(a.1, 0.);
+
+    let json = project.to_json(false).unwrap();
+    assert_eq!(
+        schema::version_of_json::<Project>(&json).unwrap(),
+        Project::SCHEMA_VERSION
+    );
+    let project = Project::from_json(&json).unwrap();
+    assert_eq!(
+        project.nps_ref().get_pt("a").unwrap().color(),
+        &Color::try_from("#ff0000").unwrap()
+    );
+}
+
+//ft newer_version
+#[test]
+fn newer_version() {
+    let json = r#"{"version": 1000, "named_points": []}"#;
+    let e = NamedPointSet::from_json(json).unwrap_err().to_string();
+    assert!(e.contains("newer"), "{e}");
+
+    let json = r#"{"version": 1000, "cdb": "", "nps": "", "cips": []}"#;
+    assert!(ProjectFileDesc::from_json(json).is_err());
+
+    let json = r#"{"version": "one", "cdb": "", "nps": "", "cips": []}"#;
+    assert!(ProjectFileDesc::from_json(json).is_err());
+}
+
+//ft provenance_log
+#[test]
+fn provenance_log() {
+    assert_eq!(
+        ProvenanceLog::path_of_output("dir/camera.json"),
+        std::path::PathBuf::from("dir/camera.provenance.json")
+    );
+
+    let mut log = ProvenanceLog::default();
+    let command = vec!["photogram".to_string(), "locate".to_string()];
+    log.push(Provenance::new(
+        "2025-01-01T00:00:00Z",
+        "photogram",
+        &command,
+    ));
+    log.push(Provenance::new(
+        "2025-01-02T00:00:00Z",
+        "photogram",
+        &command,
+    ));
+    let json = log.to_json(false).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["version"], ProvenanceLog::SCHEMA_VERSION);
+
+    let log = ProvenanceLog::from_json(&json).unwrap();
+    assert_eq!(log.provenance().len(), 2);
+    assert_eq!(log.provenance()[1].timestamp(), "2025-01-02T00:00:00Z");
+    assert_eq!(log.provenance()[1].command(), &command[..]);
+}
+
+//ft json_schema
+#[test]
+fn json_schema() {
+    let schema = schema::json_schema::<ProjectFileDesc>();
+    assert_eq!(schema["title"], "project_descriptor");
+    assert_eq!(schema["properties"]["version"]["const"], 1);
+    assert!(schema["properties"]["cips"].is_object());
+    assert_eq!(schema["required"][0], "version");
+
+    let schema = schema::json_schema::<NamedPointSet>();
+    assert_eq!(schema["title"], "named_point_set");
+    assert_eq!(schema["properties"]["version"]["const"], 1);
+    assert_eq!(schema["properties"]["named_points"]["type"], "array");
+
+    let schema = schema::json_schema::<Project>();
+    assert!(schema["properties"]["nps"].is_object());
+}
@@ -3,7 +3,7 @@ use geo_nd::{quat, Quaternion, Vector};
 use serde::{Deserialize, Serialize};
 use star_catalog::{Catalog, CatalogIndex, Subcube};
 
-use ic_base::json;
+use ic_base::schema::{self, Versioned};
 use ic_base::{Point2D, Point3D, Quat, Result, RollYaw, TanXTanY};
 use ic_camera::CameraProjection;
 use ic_camera::{CalibrationMapping, CameraInstance};
@@ -117,20 +117,33 @@ impl<'de> Deserialize<'de> for StarMapping {
     }
 }
 
+//ip JsonSchema for StarMapping
+impl schemars::JsonSchema for StarMapping {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "StarMapping".into()
+    }
+    fn json_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        <Vec<(isize, isize, usize, usize)>>::json_schema(generator)
+    }
+}
+
+//ip Versioned for StarMapping
+impl Versioned for StarMapping {
+    const SCHEMA_NAME: &'static str = "star_mapping";
+    const SCHEMA_VERSION: usize = 1;
+    const CONTENTS_KEY: Option<&'static str> = Some("mappings");
+}
+
 //ip StarMapping - Constructors and Destructors
 impl StarMapping {
     //cp from_json
     pub fn from_json(json: &str) -> Result<Self> {
-        json::from_json("star mapping", json)
+        schema::from_json(json)
     }
 
     //mp to_json
     pub fn to_json(&self, pretty: bool) -> Result<String> {
-        if pretty {
-            Ok(serde_json::to_string_pretty(self)?)
-        } else {
-            Ok(serde_json::to_string(self)?)
-        }
+        schema::to_json(self, pretty)
     }
 }
 
@@ -0,0 +1,179 @@
+//a Imports
+use std::any::Any;
+use std::future::Future;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+
+//a JobError
+//tp JobError
+/// The reason a job did not produce a result
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobError {
+    /// The job panicked; the panic message is given if it was a string
+    Panicked(String),
+    /// The job was dropped without being run
+    Cancelled,
+}
+
+//ip std::fmt::Display for JobError
+impl std::fmt::Display for JobError {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::result::Result<(), std::fmt::Error> {
+        match self {
+            Self::Panicked(message) => write!(fmt, "job panicked: {message}"),
+            Self::Cancelled => write!(fmt, "job was cancelled"),
+        }
+    }
+}
+
+//ip std::error::Error for JobError
+impl std::error::Error for JobError {}
+
+//fi panic_message
+fn panic_message(panic: Box<dyn Any + Send>) -> String {
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "<unknown>".into()
+    }
+}
+
+//a Slot
+//ti SlotState
+struct SlotState<T> {
+    result: Option<Result<T, JobError>>,
+    waker: Option<Waker>,
+}
+
+//ti Slot
+/// The place a job's result is delivered to its [JobHandle]
+struct Slot<T> {
+    state: Mutex<SlotState<T>>,
+    done: Condvar,
+}
+
+//ii Slot
+impl<T> Slot<T> {
+    //mi set
+    fn set(&self, result: Result<T, JobError>) {
+        let waker = {
+            let mut state = self.state.lock().unwrap();
+            state.result = Some(result);
+            state.waker.take()
+        };
+        self.done.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+//a Completer
+//ti Completer
+/// The producing end of a job's result; if it is dropped without
+/// completing then the job is reported as cancelled
+pub(crate) struct Completer<T> {
+    slot: Option<Arc<Slot<T>>>,
+}
+
+//ii Completer
+impl<T> Completer<T> {
+    //mi complete
+    /// Run the job and deliver its result, returning false if it
+    /// panicked
+    pub(crate) fn complete<F: FnOnce() -> T>(mut self, f: F) -> bool {
+        let result =
+            catch_unwind(AssertUnwindSafe(f)).map_err(|p| JobError::Panicked(panic_message(p)));
+        let ok = result.is_ok();
+        self.slot.take().unwrap().set(result);
+        ok
+    }
+}
+
+//ii Drop for Completer
+impl<T> Drop for Completer<T> {
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot.take() {
+            slot.set(Err(JobError::Cancelled));
+        }
+    }
+}
+
+//fi job_pair
+/// Create the producing and consuming ends for the result of a job
+pub(crate) fn job_pair<T>() -> (Completer<T>, JobHandle<T>) {
+    let slot = Arc::new(Slot {
+        state: Mutex::new(SlotState {
+            result: None,
+            waker: None,
+        }),
+        done: Condvar::new(),
+    });
+    (
+        Completer {
+            slot: Some(slot.clone()),
+        },
+        JobHandle { slot },
+    )
+}
+
+//a JobHandle
+//tp JobHandle
+/// A handle on the result of a job submitted to a [crate::ThreadPool]
+///
+/// The result may be waited for with [JobHandle::join]; or the handle
+/// may be used as a [Future], for example with
+/// `ic_base::utils::rtc::run_to_completion`.
+///
+/// Waiting for a job from within a job on the same pool blocks that
+/// worker thread; use [crate::ThreadPool::scope] for jobs that wait
+/// for other jobs.
+pub struct JobHandle<T> {
+    slot: Arc<Slot<T>>,
+}
+
+//ip std::fmt::Debug for JobHandle
+impl<T> std::fmt::Debug for JobHandle<T> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::result::Result<(), std::fmt::Error> {
+        write!(fmt, "JobHandle[finished:{}]", self.is_finished())
+    }
+}
+
+//ip JobHandle
+impl<T> JobHandle<T> {
+    //ap is_finished
+    /// Return true if the job has completed (or has been dropped), so
+    /// that [JobHandle::join] will not block
+    pub fn is_finished(&self) -> bool {
+        self.slot.state.lock().unwrap().result.is_some()
+    }
+
+    //mp join
+    /// Wait for the job to complete, and return its result
+    pub fn join(self) -> Result<T, JobError> {
+        let mut state = self.slot.state.lock().unwrap();
+        loop {
+            if let Some(result) = state.result.take() {
+                return result;
+            }
+            state = self.slot.done.wait(state).unwrap();
+        }
+    }
+}
+
+//ip Future for JobHandle
+impl<T> Future for JobHandle<T> {
+    type Output = Result<T, JobError>;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.slot.state.lock().unwrap();
+        if let Some(result) = state.result.take() {
+            Poll::Ready(result)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
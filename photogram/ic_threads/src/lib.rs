@@ -1,11 +1,154 @@
 //a Imports
-use std::sync::{mpsc, Arc, Mutex};
+use std::cell::Cell;
+use std::collections::VecDeque;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, RwLock};
 use std::thread;
 
+mod handle;
+mod scope;
+pub use handle::{JobError, JobHandle};
+pub use scope::Scope;
+
 //a WorkItem
 //tp WorkItem
 type WorkItem = Box<dyn FnOnce() + Send + 'static>;
 
+//a Worker identification
+thread_local! {
+    /// The pool (as the address of its [Shared] state) and index of
+    /// the worker, if the current thread is a worker of a pool
+    static CURRENT_WORKER: Cell<Option<(usize, usize)>> = const { Cell::new(None) };
+}
+
+//a Shared
+//ti PoolState
+#[derive(Debug, Default)]
+struct PoolState {
+    /// Number of work items in the queues
+    queued: usize,
+    /// Number of work items being run
+    running: usize,
+    /// Set when the pool is shutting down; work is then only accepted
+    /// from the workers of the pool, which exit once the queues are
+    /// empty
+    stopping: bool,
+}
+
+//ti Shared
+/// The state shared by a [ThreadPool] and its worker threads
+///
+/// Work issued by a worker thread is pushed on to that worker's own
+/// queue, and other work on to the injector queue. A worker takes the
+/// most recent work from its own queue, else the oldest from the
+/// injector, else it steals the oldest work from another worker.
+struct Shared {
+    injector: Mutex<VecDeque<WorkItem>>,
+    locals: RwLock<Vec<Mutex<VecDeque<WorkItem>>>>,
+    state: Mutex<PoolState>,
+    work_available: Condvar,
+    idle: Condvar,
+}
+
+//ii Shared
+impl Shared {
+    //ci new
+    fn new() -> Self {
+        Self {
+            injector: Mutex::new(VecDeque::new()),
+            locals: RwLock::new(vec![]),
+            state: Mutex::new(PoolState::default()),
+            work_available: Condvar::new(),
+            idle: Condvar::new(),
+        }
+    }
+
+    //mi lock_state
+    fn lock_state(&self) -> MutexGuard<'_, PoolState> {
+        self.state.lock().unwrap()
+    }
+
+    //mi current_worker
+    /// The index of the current thread if it is a worker of this pool
+    fn current_worker(&self) -> Option<usize> {
+        let id = self as *const Self as usize;
+        CURRENT_WORKER
+            .get()
+            .filter(|(pool, _)| *pool == id)
+            .map(|(_, index)| index)
+    }
+
+    //mi push
+    /// Queue a work item, handing it back if the pool is shutting down
+    fn push(&self, item: WorkItem) -> Result<(), WorkItem> {
+        let worker = self.current_worker();
+        {
+            let mut state = self.lock_state();
+            if state.stopping && worker.is_none() {
+                return Err(item);
+            }
+            state.queued += 1;
+        }
+        match worker {
+            Some(index) => self.locals.read().unwrap()[index]
+                .lock()
+                .unwrap()
+                .push_back(item),
+            None => self.injector.lock().unwrap().push_back(item),
+        }
+        self.work_available.notify_one();
+        Ok(())
+    }
+
+    //mi pop
+    /// Take a work item to run on the current thread
+    fn pop(&self) -> Option<WorkItem> {
+        let worker = self.current_worker();
+        let item = {
+            let locals = self.locals.read().unwrap();
+            let n = locals.len();
+            let start = worker.map_or(0, |index| index + 1);
+            worker
+                .and_then(|index| locals[index].lock().unwrap().pop_back())
+                .or_else(|| self.injector.lock().unwrap().pop_front())
+                .or_else(|| {
+                    (0..n)
+                        .map(|i| (start + i) % n)
+                        .filter(|i| Some(*i) != worker)
+                        .find_map(|i| locals[i].lock().unwrap().pop_front())
+                })
+        }?;
+        let mut state = self.lock_state();
+        state.queued -= 1;
+        state.running += 1;
+        Some(item)
+    }
+
+    //mi run
+    /// Run a work item taken with [Shared::pop]
+    fn run(&self, item: WorkItem) {
+        // A work item that panics must not take down the thread
+        let _ = catch_unwind(AssertUnwindSafe(item));
+        let mut state = self.lock_state();
+        state.running -= 1;
+        if state.queued == 0 && state.running == 0 {
+            self.idle.notify_all();
+        }
+    }
+
+    //mi run_one
+    /// Run one queued work item on the current thread, returning false
+    /// if there was none
+    fn run_one(&self) -> bool {
+        if let Some(item) = self.pop() {
+            self.run(item);
+            true
+        } else {
+            false
+        }
+    }
+}
+
 //a ThreadPool
 //tp ThreadStats
 #[derive(Debug, Default)]
@@ -44,17 +187,26 @@ impl std::fmt::Display for ThreadStats {
 }
 
 //tp ThreadPool
+/// A pool of worker threads that run work items
+///
+/// Work is issued with [ThreadPool::issue_work], or with
+/// [ThreadPool::submit] to get a [JobHandle] for the result. Jobs that
+/// borrow data from the caller can be run within
+/// [ThreadPool::scope], which is used by the parallel helpers
+/// [ThreadPool::par_map] and [ThreadPool::par_for_each_mut].
+///
+/// The pool is shut down by [ThreadPool::shutdown] or by dropping it;
+/// work already issued is completed before the threads exit.
 pub struct ThreadPool {
-    workers: Vec<WorkThread>,
-    tx: Option<mpsc::Sender<WorkItem>>,
-    rx: Option<Arc<Mutex<mpsc::Receiver<WorkItem>>>>,
+    shared: Arc<Shared>,
+    workers: Mutex<Vec<WorkThread>>,
     stats: Vec<Arc<Mutex<ThreadStats>>>,
 }
 
 //ip std::fmt::Debug for ThreadPool
 impl std::fmt::Debug for ThreadPool {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::result::Result<(), std::fmt::Error> {
-        write!(fmt, "ThreadPool[{}]", self.workers.len())?;
+        write!(fmt, "ThreadPool[{}]", self.stats.len())?;
         for (i, s) in self.stats.iter().enumerate() {
             if let Ok(m) = s.try_lock() {
                 write!(fmt, "  {i}: {m}")?;
@@ -69,16 +221,10 @@ impl std::fmt::Debug for ThreadPool {
 //ip std::default::Default for ThreadPool
 impl std::default::Default for ThreadPool {
     fn default() -> ThreadPool {
-        let (tx, rx) = mpsc::channel();
-        let rx = Arc::new(Mutex::new(rx));
-        let workers = vec![];
-        let stats = vec![];
-
         ThreadPool {
-            workers,
-            tx: Some(tx),
-            rx: Some(rx),
-            stats,
+            shared: Arc::new(Shared::new()),
+            workers: Mutex::new(vec![]),
+            stats: vec![],
         }
     }
 }
@@ -100,23 +246,162 @@ impl ThreadPool {
     /// Add a new thread to the [ThreadPool]
     pub fn add_thread(&mut self) {
         assert!(
-            self.rx.is_some(),
-            "Must have set up the MPSC channel and not torn it down"
+            !self.shared.lock_state().stopping,
+            "Must not add threads to a pool that has been shut down"
         );
         let thread_id = self.stats.len();
         let stats = Arc::new(Mutex::new(ThreadStats::default()));
         self.stats.push(stats.clone());
-        let rx = self.rx.as_ref().unwrap().clone();
-        self.workers.push(WorkThread::new(thread_id, rx, stats));
+        self.shared
+            .locals
+            .write()
+            .unwrap()
+            .push(Mutex::new(VecDeque::new()));
+        let shared = self.shared.clone();
+        self.workers
+            .get_mut()
+            .unwrap()
+            .push(WorkThread::new(thread_id, shared, stats));
+    }
+
+    //ap num_threads
+    pub fn num_threads(&self) -> usize {
+        self.stats.len()
     }
 
     //mp issue_work
     /// Issue a work item (a callback) to a thread in the [ThreadPool]
+    ///
+    /// If the pool has been shut down then the work is dropped
     pub fn issue_work<F>(&self, f: F)
     where
         F: FnOnce() + Send + 'static,
     {
-        self.tx.as_ref().unwrap().send(Box::new(f)).unwrap();
+        let _ = self.shared.push(Box::new(f));
+    }
+
+    //mp submit
+    /// Issue a job to a thread in the [ThreadPool], returning a handle
+    /// on its result
+    ///
+    /// If the pool has been shut down then the job is dropped, and the
+    /// handle returns [JobError::Cancelled]
+    pub fn submit<F, T>(&self, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let (completer, handle) = handle::job_pair();
+        self.issue_work(move || {
+            completer.complete(f);
+        });
+        handle
+    }
+
+    //mp scope
+    /// Run a function that may spawn jobs on the pool that borrow data
+    /// from outside the scope, waiting until all the jobs have
+    /// completed
+    ///
+    /// While waiting the current thread runs queued work of the pool,
+    /// so a scope may be used from within a job on the same pool. If
+    /// the function or any of the jobs panics then the panic is
+    /// propagated once all the jobs have completed.
+    pub fn scope<'env, F, R>(&self, f: F) -> R
+    where
+        F: for<'scope> FnOnce(&'scope Scope<'scope, 'env>) -> R,
+    {
+        let scope = Scope::new(self.shared.clone());
+        let result = catch_unwind(AssertUnwindSafe(|| f(&scope)));
+        let job_panicked = scope.wait();
+        match result {
+            Err(e) => resume_unwind(e),
+            Ok(_) if job_panicked => panic!("a job in a thread pool scope panicked"),
+            Ok(result) => result,
+        }
+    }
+
+    //mi chunk_size
+    /// The size of chunks to split `n` items into to spread them
+    /// across the threads of the pool
+    fn chunk_size(&self, n: usize) -> usize {
+        n.div_ceil(self.num_threads().max(1) * 4).max(1)
+    }
+
+    //mp par_map
+    /// Apply a function to every element of a slice, using the threads
+    /// of the pool, returning the results in order
+    pub fn par_map<T, U, F>(&self, items: &[T], f: F) -> Vec<U>
+    where
+        T: Sync,
+        U: Send,
+        F: Fn(&T) -> U + Sync,
+    {
+        let chunk_size = self.chunk_size(items.len());
+        let f = &f;
+        let handles: Vec<_> = self.scope(|s| {
+            items
+                .chunks(chunk_size)
+                .map(|chunk| s.spawn(move || chunk.iter().map(f).collect::<Vec<U>>()))
+                .collect()
+        });
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("Jobs of a scope have completed"))
+            .collect()
+    }
+
+    //mp par_for_each_mut
+    /// Apply a function to every element of a mutable slice, using the
+    /// threads of the pool
+    pub fn par_for_each_mut<T, F>(&self, items: &mut [T], f: F)
+    where
+        T: Send,
+        F: Fn(&mut T) + Sync,
+    {
+        let chunk_size = self.chunk_size(items.len());
+        let f = &f;
+        self.scope(|s| {
+            for chunk in items.chunks_mut(chunk_size) {
+                s.spawn(move || chunk.iter_mut().for_each(f));
+            }
+        });
+    }
+
+    //mp wait_idle
+    /// Wait until all the work issued to the pool has completed
+    ///
+    /// This must not be invoked from a job on the pool
+    pub fn wait_idle(&self) {
+        let mut state = self.shared.lock_state();
+        while state.queued > 0 || state.running > 0 {
+            state = self.shared.idle.wait(state).unwrap();
+        }
+    }
+
+    //mp shutdown
+    /// Shut down the pool, waiting for all the work issued to it to
+    /// complete
+    ///
+    /// Work issued after this (except by the jobs being completed) is
+    /// dropped. If there are no threads then the queued work is run on
+    /// the current thread.
+    pub fn shutdown(&self) {
+        self.shared.lock_state().stopping = true;
+        self.shared.work_available.notify_all();
+        let workers = std::mem::take(&mut *self.workers.lock().unwrap());
+        let current_worker = self.shared.current_worker();
+        for mut wt in workers {
+            if current_worker == Some(wt.thread_id()) {
+                // A worker cannot wait for itself; it exits when its
+                // current job completes
+                continue;
+            }
+            if let Some(thread) = wt.take_thread() {
+                thread.join().unwrap();
+            }
+        }
+        while self.shared.run_one() {}
     }
 
     //zz All done
@@ -125,29 +410,9 @@ impl ThreadPool {
 //ip Drop for ThreadPool
 impl Drop for ThreadPool {
     fn drop(&mut self) {
-        // drop the MPSC transmitter
-        //
-        // This causes any thread that does a *later* 'recv' on the
-        // channel to get an error, which causes the thread to break
-        // out of its work loop and terminate; hence the threads will
-        // eventually all be ready to join
-        drop(self.tx.take());
-
-        // Join the work threads one at a time
-        for wt in &mut self.workers {
-            if let Some(thread) = wt.take_thread() {
-                eprintln!(
-                    "Waiting for work thread {} to have completed",
-                    wt.thread_id()
-                );
-                thread.join().unwrap();
-            } else {
-                eprintln!(
-                    "Bug - work thread {} has already lost its JoinHandle - double drop of thread?",
-                    wt.thread_id()
-                );
-            }
-        }
+        // Workers exit once the queues are empty after the pool is
+        // marked as stopping; each is joined
+        self.shutdown();
     }
 }
 
@@ -176,24 +441,28 @@ impl WorkThread {
     }
 
     //cp new
-    /// Create a new thread watching an MPSC rx channel for work
-    fn new(
-        thread_id: usize,
-        rx: Arc<Mutex<mpsc::Receiver<WorkItem>>>,
-        stats: Arc<Mutex<ThreadStats>>,
-    ) -> WorkThread {
-        let thread = thread::spawn(move || loop {
-            // Get the next WorkItem - or Err(_) if the pool has killed the MPSC transmitter
-            let work_item_or_err = rx.lock().unwrap().recv();
-
-            // Handle the work and loop;
-            let Ok(work_item) = work_item_or_err else {
-                eprintln!("WorkThread {thread_id} disconnected; shutting down.");
-                break;
-            };
-            let _ = stats.lock().map(|mut s| s.inc_delivered());
-            work_item();
-            let _ = stats.lock().map(|mut s| s.inc_completed());
+    /// Create a new thread running work from the pool
+    fn new(thread_id: usize, shared: Arc<Shared>, stats: Arc<Mutex<ThreadStats>>) -> WorkThread {
+        let thread = thread::spawn(move || {
+            let pool_id = Arc::as_ptr(&shared) as usize;
+            CURRENT_WORKER.set(Some((pool_id, thread_id)));
+            loop {
+                if let Some(work_item) = shared.pop() {
+                    let _ = stats.lock().map(|mut s| s.inc_delivered());
+                    shared.run(work_item);
+                    let _ = stats.lock().map(|mut s| s.inc_completed());
+                    continue;
+                }
+                let state = shared.lock_state();
+                if state.queued > 0 {
+                    // Work is being queued; retry
+                    continue;
+                }
+                if state.stopping {
+                    break;
+                }
+                drop(shared.work_available.wait(state).unwrap());
+            }
         });
 
         WorkThread {
@@ -0,0 +1,119 @@
+//a Imports
+use std::marker::PhantomData;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use crate::handle::job_pair;
+use crate::{JobHandle, Shared, WorkItem};
+
+//a ScopeData
+//ti ScopeData
+/// The count of the jobs of a [Scope] that have not completed, and
+/// whether any of them panicked
+#[derive(Debug, Default)]
+pub(crate) struct ScopeData {
+    state: Mutex<(usize, bool)>,
+    done: Condvar,
+}
+
+//ti ScopeJobGuard
+/// Marks a job of a scope as complete when dropped
+struct ScopeJobGuard {
+    data: Arc<ScopeData>,
+    panicked: bool,
+}
+
+//ii Drop for ScopeJobGuard
+impl Drop for ScopeJobGuard {
+    fn drop(&mut self) {
+        let mut state = self.data.state.lock().unwrap();
+        state.0 -= 1;
+        state.1 |= self.panicked;
+        if state.0 == 0 {
+            self.data.done.notify_all();
+        }
+    }
+}
+
+//a Scope
+//tp Scope
+/// A scope in which jobs that borrow data from outside the scope may
+/// be run on a [crate::ThreadPool]; see [crate::ThreadPool::scope]
+pub struct Scope<'scope, 'env: 'scope> {
+    shared: Arc<Shared>,
+    data: Arc<ScopeData>,
+    scope: PhantomData<&'scope mut &'scope ()>,
+    env: PhantomData<&'env mut &'env ()>,
+}
+
+//ip Scope
+impl<'scope, 'env> Scope<'scope, 'env> {
+    //cp new
+    pub(crate) fn new(shared: Arc<Shared>) -> Self {
+        Self {
+            shared,
+            data: Arc::new(ScopeData::default()),
+            scope: PhantomData,
+            env: PhantomData,
+        }
+    }
+
+    //mp spawn
+    /// Run a job on the thread pool, returning a handle on its result
+    ///
+    /// The job may borrow anything that outlives the scope; the scope
+    /// does not end until the job has completed
+    pub fn spawn<F, T>(&'scope self, f: F) -> JobHandle<T>
+    where
+        F: FnOnce() -> T + Send + 'scope,
+        T: Send + 'scope,
+    {
+        let (completer, handle) = job_pair();
+        self.data.state.lock().unwrap().0 += 1;
+        let guard = ScopeJobGuard {
+            data: self.data.clone(),
+            panicked: false,
+        };
+        let work: Box<dyn FnOnce() + Send + 'scope> = Box::new(move || {
+            let mut guard = guard;
+            guard.panicked = !completer.complete(f);
+        });
+        // SAFETY: the work only borrows data that outlives 'scope, and
+        // [crate::ThreadPool::scope] does not return until every job
+        // spawned in the scope has run (the guard being dropped at the
+        // end of the job)
+        let work: WorkItem =
+            unsafe { std::mem::transmute::<Box<dyn FnOnce() + Send + 'scope>, WorkItem>(work) };
+        // If the pool is shutting down the job is run immediately
+        if let Err(work) = self.shared.push(work) {
+            work();
+        }
+        handle
+    }
+
+    //mi wait
+    /// Wait for all the jobs of the scope to complete, running queued
+    /// work of the pool while waiting; return true if any panicked
+    pub(crate) fn wait(&self) -> bool {
+        loop {
+            {
+                let state = self.data.state.lock().unwrap();
+                if state.0 == 0 {
+                    return state.1;
+                }
+            }
+            if !self.shared.run_one() {
+                let state = self.data.state.lock().unwrap();
+                if state.0 != 0 {
+                    // The jobs are running elsewhere; wake up
+                    // occasionally in case more work can be helped with
+                    let _ = self
+                        .data
+                        .done
+                        .wait_timeout(state, Duration::from_millis(1))
+                        .unwrap();
+                }
+            }
+        }
+    }
+}
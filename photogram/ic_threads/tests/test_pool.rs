@@ -0,0 +1,79 @@
+//a Imports
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use ic_base::utils::rtc::run_to_completion;
+use ic_threads::{JobError, ThreadPool};
+
+//a Tests
+//ft submit
+#[test]
+fn submit() {
+    let pool = ThreadPool::new(3);
+    let handles: Vec<_> = (0..20_usize).map(|i| pool.submit(move || i * i)).collect();
+    let results: Vec<usize> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    assert_eq!(results, (0..20).map(|i| i * i).collect::<Vec<_>>());
+
+    let h = pool.submit(|| -> usize { panic!("deliberate") });
+    assert_eq!(h.join(), Err(JobError::Panicked("deliberate".into())));
+
+    // The pool survives a panic
+    assert_eq!(pool.submit(|| 3).join(), Ok(3));
+}
+
+//ft future
+#[test]
+fn future() {
+    let pool = ThreadPool::new(2);
+    let h = pool.submit(|| "done".to_string());
+    let result = run_to_completion(h);
+    assert_eq!(result.unwrap(), "done");
+}
+
+//ft scope
+#[test]
+fn scope() {
+    let pool = ThreadPool::new(4);
+    let data: Vec<usize> = (0..1000).collect();
+    let mut sums = [0_usize; 4];
+    pool.scope(|s| {
+        for (i, sum) in sums.iter_mut().enumerate() {
+            let data = &data;
+            s.spawn(move || *sum = data.iter().skip(i).step_by(4).sum());
+        }
+    });
+    assert_eq!(sums.iter().sum::<usize>(), data.iter().sum::<usize>());
+
+    let squares = pool.par_map(&data, |x| x * x);
+    assert_eq!(squares.len(), 1000);
+    assert!(squares.iter().enumerate().all(|(i, s)| *s == i * i));
+
+    let mut values = data.clone();
+    pool.par_for_each_mut(&mut values, |x| *x += 1);
+    assert!(values.iter().enumerate().all(|(i, v)| *v == i + 1));
+
+    // Nested scopes from within jobs, and a pool with no threads
+    let nested: Vec<usize> = pool.par_map(&[10_usize, 20, 30], |n| {
+        pool.par_map(&(0..*n).collect::<Vec<_>>(), |x| *x).iter().sum()
+    });
+    assert_eq!(nested, vec![45, 190, 435]);
+    let empty_pool = ThreadPool::default();
+    assert_eq!(empty_pool.par_map(&[1, 2, 3], |x| x * 2), vec![2, 4, 6]);
+}
+
+//ft shutdown
+#[test]
+fn shutdown() {
+    let pool = ThreadPool::new(2);
+    let count = Arc::new(AtomicUsize::new(0));
+    for _ in 0..50 {
+        let count = count.clone();
+        pool.issue_work(move || {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+            count.fetch_add(1, Ordering::SeqCst);
+        });
+    }
+    pool.shutdown();
+    assert_eq!(count.load(Ordering::SeqCst), 50);
+    assert_eq!(pool.submit(|| 1).join(), Err(JobError::Cancelled));
+}
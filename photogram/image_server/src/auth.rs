@@ -0,0 +1,150 @@
+//a Imports
+use std::collections::{HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+use ic_base::Result;
+use ic_http::{HttpRequest, HttpResponse};
+
+//a Permission
+//tp Permission
+/// The access a user has to a project
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Permission {
+    /// The project is not visible to the user
+    #[default]
+    None,
+    /// The project may be read (GET and HEAD requests)
+    Read,
+    /// The project may be read and modified (all requests)
+    Write,
+}
+
+//a User
+//tp User
+/// A user of the server, who may authenticate with HTTP basic
+/// authentication (using the name and password) or with any of their
+/// bearer tokens
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    pub name: String,
+    #[serde(default)]
+    pub password: Option<String>,
+    #[serde(default)]
+    pub tokens: Vec<String>,
+    /// The permission for each project by name; the entry for "*"
+    /// applies to projects that are not listed
+    #[serde(default)]
+    pub projects: HashMap<String, Permission>,
+}
+
+//ip User
+impl User {
+    //ap permission
+    pub fn permission(&self, project: &str) -> Permission {
+        self.projects
+            .get(project)
+            .or_else(|| self.projects.get("*"))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    //ap permits
+    pub fn permits(&self, project: &str, permission: Permission) -> bool {
+        self.permission(project) >= permission
+    }
+}
+
+//a AuthConfig
+//fi default_realm
+fn default_realm() -> String {
+    "image_server".into()
+}
+
+//fi same_secret
+/// Compare a secret with a candidate in a time that does not depend
+/// on where they differ
+fn same_secret(secret: &str, candidate: &str) -> bool {
+    let (a, b) = (secret.as_bytes(), candidate.as_bytes());
+    let diff = a
+        .iter()
+        .zip(b.iter())
+        .fold(0_u8, |acc, (x, y)| acc | (x ^ y));
+    a.len() == b.len() && diff == 0
+}
+
+//tp AuthConfig
+/// The users permitted to access the server
+///
+/// If the server has an [AuthConfig] then every request (including
+/// those for static files) must be authenticated; requests for a
+/// project also require the user to have permission for the project,
+/// and the project list contains only the projects they may read.
+///
+/// Passwords and tokens are held in the configuration file in plain
+/// text, so it must be readable only by the server; basic
+/// authentication should only be used over TLS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthConfig {
+    #[serde(default = "default_realm")]
+    pub realm: String,
+    pub users: Vec<User>,
+}
+
+//ip AuthConfig
+impl AuthConfig {
+    //mp validate
+    /// Check that user names and tokens are unique and that every user
+    /// has some means of authenticating
+    pub fn validate(&self) -> Result<()> {
+        let mut names = HashSet::new();
+        let mut tokens = HashSet::new();
+        for user in &self.users {
+            if !names.insert(user.name.as_str()) {
+                return Err(format!("User {} is configured more than once", user.name).into());
+            }
+            if user.password.is_none() && user.tokens.is_empty() {
+                return Err(
+                    format!("User {} has neither a password nor a token", user.name).into(),
+                );
+            }
+            for token in &user.tokens {
+                if token.is_empty() || !tokens.insert(token.as_str()) {
+                    return Err(
+                        format!("User {} has an empty or duplicated token", user.name).into(),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    //mp authenticate
+    /// Find the user that a request is authenticated as, if any
+    pub fn authenticate(&self, request: &HttpRequest) -> Option<&User> {
+        if let Some(token) = request.bearer_token() {
+            return self
+                .users
+                .iter()
+                .find(|u| u.tokens.iter().any(|t| same_secret(t, token)));
+        }
+        let (name, password) = request.basic_auth()?;
+        self.users.iter().find(|u| {
+            u.name == name
+                && u.password
+                    .as_ref()
+                    .is_some_and(|p| same_secret(p, &password))
+        })
+    }
+
+    //mp set_unauthorized
+    /// Set a response to request authentication
+    pub fn set_unauthorized(&self, response: &mut HttpResponse) {
+        response.set_error(401, "Authentication required");
+        response.set_header(
+            "WWW-Authenticate",
+            format!("Basic realm=\"{}\", charset=\"UTF-8\"", self.realm),
+        );
+    }
+}
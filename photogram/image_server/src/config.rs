@@ -0,0 +1,238 @@
+//a Imports
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
+
+use clap::{ArgMatches, Command};
+use serde::{Deserialize, Serialize};
+
+use ic_base::Result;
+use ic_cmdline as cmdline_args;
+use ic_kernel::{Accelerator, KernelsConfig};
+
+use crate::auth::AuthConfig;
+
+//a TlsConfig
+//tp TlsConfig
+/// The PEM files of the certificate chain and private key for TLS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsConfig {
+    pub cert: String,
+    pub key: String,
+}
+
+//a ServerConfig
+//tp ServerConfig
+/// The configuration of the image server
+///
+/// This is read from a JSON file (if one is given with '--config'),
+/// and then any options given on the command line override it. Paths
+/// in the file are relative to the directory of the file. An example
+/// is:
+///
+/// ```json
+/// {
+///   "bind": "0.0.0.0", "port": 8443, "threads": 8,
+///   "file_root": "http", "image_root": "images", "project_root": "projects",
+///   "accelerator": "cpu", "shader_paths": ["shaders"],
+///   "tls": { "cert": "cert.pem", "key": "key.pem" },
+///   "auth": { "users": [
+///     { "name": "alice", "password": "secret", "projects": { "*": "write" } },
+///     { "name": "bob", "tokens": ["abc123"], "projects": { "nikon": "read" } }
+///   ] }
+/// }
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind: IpAddr,
+    pub port: u16,
+    pub threads: usize,
+    pub file_root: Option<String>,
+    pub image_root: Option<String>,
+    pub project_root: Option<String>,
+    pub cache_dir: Option<String>,
+    /// Maximum size of the persistent cache in MB
+    pub cache_size: u64,
+    /// Accelerator for the kernels; if not given then one is chosen
+    /// automatically
+    pub accelerator: Option<String>,
+    /// Directories searched for kernel shaders before the embedded
+    /// shaders
+    pub shader_paths: Vec<String>,
+    pub tls: Option<TlsConfig>,
+    pub auth: Option<AuthConfig>,
+}
+
+//ip Default for ServerConfig
+impl std::default::Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 8020,
+            threads: 4,
+            file_root: None,
+            image_root: None,
+            project_root: None,
+            cache_dir: None,
+            cache_size: 1024,
+            accelerator: None,
+            shader_paths: vec![],
+            tls: None,
+            auth: None,
+        }
+    }
+}
+
+//ip ServerConfig
+impl ServerConfig {
+    //fp add_args
+    /// Add the command line options for the configuration
+    pub fn add_args(cmd: Command) -> Command {
+        let cmd = cmdline_args::file_system::add_config_arg(cmd);
+        let cmd = cmdline_args::threads::add_bind_arg(cmd);
+        let cmd = cmdline_args::threads::add_threads_arg(cmd);
+        let cmd = cmdline_args::threads::add_port_arg(cmd);
+        let cmd = cmdline_args::file_system::add_file_root_arg(cmd, false);
+        let cmd = cmdline_args::file_system::add_image_root_arg(cmd, false);
+        let cmd = cmdline_args::file_system::add_project_root_arg(cmd, false);
+        let cmd = cmdline_args::file_system::add_cache_dir_arg(cmd, false);
+        let cmd = cmdline_args::file_system::add_cache_size_arg(cmd);
+        let cmd = cmdline_args::kernels::add_accelerator_args(cmd);
+        cmdline_args::file_system::add_tls_args(cmd)
+    }
+
+    //cp read_file
+    /// Read a configuration from a JSON file, making its paths relative
+    /// to the directory of the file
+    pub fn read_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read config file {}: {e}", path.display()))?;
+        let mut config: Self = serde_json::from_str(&json)
+            .map_err(|e| format!("Failed to parse config file {}: {e}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let relative = |p: &mut String| {
+            *p = dir.join(&*p).to_string_lossy().into_owned();
+        };
+        for p in [
+            &mut config.file_root,
+            &mut config.image_root,
+            &mut config.project_root,
+            &mut config.cache_dir,
+        ]
+        .into_iter()
+        .flatten()
+        .chain(config.shader_paths.iter_mut())
+        {
+            relative(p);
+        }
+        if let Some(tls) = &mut config.tls {
+            relative(&mut tls.cert);
+            relative(&mut tls.key);
+        }
+        Ok(config)
+    }
+
+    //cp of_matches
+    /// Create the configuration from the command line, reading the
+    /// config file if one was given
+    pub fn of_matches(matches: &ArgMatches) -> Result<Self> {
+        let mut config = {
+            match cmdline_args::file_system::get_config(matches)? {
+                Some(path) => Self::read_file(path)?,
+                None => Self::default(),
+            }
+        };
+        if cmdline_args::is_given(matches, "bind") {
+            config.bind = cmdline_args::threads::get_bind(matches)?;
+        }
+        if cmdline_args::is_given(matches, "threads") {
+            config.threads = cmdline_args::threads::get_threads(matches)?;
+        }
+        if cmdline_args::is_given(matches, "port") {
+            let port = cmdline_args::threads::get_port(matches)?;
+            config.port = port
+                .try_into()
+                .map_err(|_| format!("Port {port} must be no more than 65535"))?;
+        }
+        if cmdline_args::is_given(matches, "cache_size") {
+            config.cache_size = cmdline_args::file_system::get_cache_size(matches)? >> 20;
+        }
+        if let Ok(file_root) = cmdline_args::file_system::get_file_root(matches) {
+            config.file_root = Some(file_root);
+        }
+        if let Ok(image_root) = cmdline_args::file_system::get_image_root(matches) {
+            config.image_root = Some(image_root);
+        }
+        if let Ok(project_root) = cmdline_args::file_system::get_project_root(matches) {
+            config.project_root = Some(project_root);
+        }
+        if let Some(cache_dir) = cmdline_args::file_system::get_cache_dir(matches)? {
+            config.cache_dir = Some(cache_dir);
+        }
+        if let Some(accelerator) = cmdline_args::kernels::get_accelerator(matches)? {
+            config.accelerator = Some(accelerator);
+        }
+        config
+            .shader_paths
+            .extend(cmdline_args::kernels::get_shader_paths(matches)?);
+        if let Some((cert, key)) = cmdline_args::file_system::get_tls_files(matches)? {
+            config.tls = Some(TlsConfig { cert, key });
+        }
+        config.validate()?;
+        Ok(config)
+    }
+
+    //mp validate
+    pub fn validate(&self) -> Result<()> {
+        if self.threads == 0 || self.threads > 20 {
+            return Err(format!(
+                "Number of threads {} must be non-zero and no more than 20",
+                self.threads
+            )
+            .into());
+        }
+        if self.port == 0 {
+            return Err("Port must be non-zero".into());
+        }
+        if self.file_root.is_none() {
+            return Err("A file root must be given".into());
+        }
+        if self.image_root.is_none() {
+            return Err("An image root must be given".into());
+        }
+        if self.project_root.is_none() {
+            return Err("A project root must be given".into());
+        }
+        if let Some(auth) = &self.auth {
+            auth.validate()?;
+        }
+        self.kernels_config()?;
+        Ok(())
+    }
+
+    //ap kernels_config
+    /// The configuration for creating the kernels, with the shader
+    /// paths searched before the default one
+    pub fn kernels_config(&self) -> Result<KernelsConfig> {
+        let mut config = KernelsConfig::default();
+        if let Some(accelerator) = &self.accelerator {
+            let accelerator: Accelerator = accelerator.parse()?;
+            config.set_accelerator(accelerator);
+        }
+        for path in &self.shader_paths {
+            config.add_shader_path(path);
+        }
+        Ok(config)
+    }
+
+    //ap socket_addr
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind, self.port)
+    }
+
+    //ap cache_size_bytes
+    pub fn cache_size_bytes(&self) -> u64 {
+        self.cache_size << 20
+    }
+}
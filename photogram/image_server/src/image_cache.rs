@@ -1,20 +1,21 @@
 //a Imports
-use std::path::{Path, PathBuf};
-use std::sync::Mutex;
+use std::path::Path;
 
 use ic_base::Result;
-use ic_cache::{Cache, CacheRef, Cacheable};
+use ic_cache::{
+    CacheRef, CacheStats, Cacheable, ContentHash, DiskCache, EvictionPolicy, FileHashes,
+    SharedCache,
+};
 use ic_image::{Image, ImageGray16, ImageRgb8};
 
+/// Images are keyed by the content hash of their source image, so
+/// that if a source image changes then neither it nor anything derived
+/// from it is taken from the cache
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
 enum KeyType {
-    ImagePath {
-        path: PathBuf,
-    },
-    #[allow(dead_code)]
-    Derived {
-        name: String,
-    },
+    Source { hash: ContentHash },
+    Derived { source: ContentHash, name: String },
+    Content { hash: ContentHash },
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
@@ -22,12 +23,21 @@ struct ImageCacheKey {
     key_type: KeyType,
 }
 impl ImageCacheKey {
-    pub fn of_image_path<P: AsRef<Path>>(path: &P) -> Self {
-        let key_type = KeyType::ImagePath {
-            path: path.as_ref().to_owned(),
+    pub fn of_source(hash: ContentHash) -> Self {
+        let key_type = KeyType::Source { hash };
+        Self { key_type }
+    }
+    pub fn of_derived(source: ContentHash, name: &str) -> Self {
+        let key_type = KeyType::Derived {
+            source,
+            name: name.into(),
         };
         Self { key_type }
     }
+    pub fn of_content(hash: ContentHash) -> Self {
+        let key_type = KeyType::Content { hash };
+        Self { key_type }
+    }
 }
 #[derive(Debug)]
 pub enum ImageCacheEntry {
@@ -36,6 +46,8 @@ pub enum ImageCacheEntry {
     Gray(ImageGray16),
     #[allow(dead_code)]
     F32(usize, usize, Vec<f32>),
+    /// An encoded image (such as a JPEG or PNG file)
+    Encoded(Vec<u8>),
 }
 impl Cacheable for ImageCacheEntry {
     fn as_any(&self) -> &dyn std::any::Any {
@@ -52,6 +64,7 @@ impl Cacheable for ImageCacheEntry {
                 w as usize * h as usize * 2
             }
             ImageCacheEntry::F32(w, h, _) => w * h * 4,
+            ImageCacheEntry::Encoded(v) => v.len(),
         }
     }
 }
@@ -76,6 +89,12 @@ impl ImageCacheEntry {
             _ => panic!("Cannot unmap as Float32 array"),
         }
     }
+    fn as_encoded(&self) -> &[u8] {
+        match &self {
+            Self::Encoded(v) => v,
+            _ => panic!("Cannot unmap as encoded image"),
+        }
+    }
     pub fn cr_as_rgb8(cr: &CacheRef) -> &ImageRgb8 {
         cr.downcast::<Self>().unwrap().as_rgb8()
     }
@@ -87,34 +106,143 @@ impl ImageCacheEntry {
     pub fn cr_as_f32(cr: &CacheRef) -> (usize, usize, &[f32]) {
         cr.downcast::<Self>().unwrap().as_f32()
     }
+    pub fn cr_as_encoded(cr: &CacheRef) -> &[u8] {
+        cr.downcast::<Self>().unwrap().as_encoded()
+    }
 }
 
+//ci IMAGE_CACHE_BUDGET
+/// Default maximum total size of the images in the cache
+const IMAGE_CACHE_BUDGET: usize = 2 << 30;
+
+//ci IMAGE_CACHE_SHARDS
+/// Number of shards of the image cache; images are large, so few
+/// shards are used so that each has a reasonable share of the budget
+const IMAGE_CACHE_SHARDS: usize = 4;
+
+/// The cache of source and derived images, shared by all the threads
+/// of the server
+///
+/// Images are evicted when the cache exceeds its budget, except those
+/// currently in use by a request
+///
+/// Encoded images derived from source images (such as thumbnails and
+/// tiles) may also be kept in a [DiskCache], so that they survive a
+/// restart of the server. These are keyed by a [ContentHash] of the
+/// source image contents and the derivation parameters, so if a
+/// source image changes its derived images are created again.
 #[derive(Debug)]
 pub struct ImageCache {
-    m_cache: Mutex<Cache<ImageCacheKey>>,
+    cache: SharedCache<ImageCacheKey>,
+    disk_cache: Option<DiskCache>,
+    file_hashes: FileHashes,
 }
 impl ImageCache {
     pub fn new() -> Self {
-        let m_cache = Mutex::new(Cache::default());
-        Self { m_cache }
+        Self::default().with_budget(IMAGE_CACHE_BUDGET, EvictionPolicy::Lru)
     }
 
+    /// Set the budget and eviction policy of the cache, emptying it
+    pub fn with_budget(mut self, budget: usize, policy: EvictionPolicy) -> Self {
+        self.cache = SharedCache::new(IMAGE_CACHE_SHARDS, Some(budget), policy);
+        self
+    }
+
+    /// Use a disk cache as a second tier for encoded derived images
+    pub fn set_disk_cache(&mut self, disk_cache: DiskCache) {
+        self.disk_cache = Some(disk_cache);
+    }
+
+    /// Get the hash of the contents of a source image, to form the key
+    /// for images derived from it
+    pub fn source_hash<P: AsRef<Path>>(&self, path: P) -> Result<ContentHash> {
+        self.file_hashes
+            .hash_of(path.as_ref())
+            .map_err(|e| format!("Failed to read {}: {e}", path.as_ref().display()).into())
+    }
+
+    /// Get the size of a source image, without decoding it if it is
+    /// not in the cache
+    pub fn src_size<P: AsRef<Path>>(&self, path: P) -> Result<(u32, u32)> {
+        let key = ImageCacheKey::of_source(self.source_hash(&path)?);
+        if let Some(cr) = self.cache.get(&key) {
+            return Ok(ImageCacheEntry::cr_as_rgb8(&cr).size());
+        }
+        ImageRgb8::read_size(path)
+    }
+
+    /// Get a source image, reading it if it is not in the cache (or
+    /// if the file has changed since it was read)
     pub fn src_image<P: AsRef<Path>>(&self, path: P) -> Result<CacheRef> {
-        let mut cache = self.m_cache.lock().map_err(|e| format!("{e:?}"))?;
-        let key = ImageCacheKey::of_image_path(&path);
-        if !cache.contains(&key) {
+        let key = ImageCacheKey::of_source(self.source_hash(&path)?);
+        self.cache.get_or_insert_with(&key, || {
             eprintln!("Cache miss for {:?}", path.as_ref());
-            let src_img = ImageRgb8::read_image(path)?;
-            let src_img = ImageCacheEntry::Rgb(src_img);
-            cache.insert(key.clone(), src_img);
-        }
-        Ok(cache.get(&key).unwrap())
+            let src_img = ImageRgb8::read_image(path.as_ref())?;
+            Ok(ImageCacheEntry::Rgb(src_img))
+        })
     }
 
-    #[allow(dead_code)]
-    pub fn shrink_cache(&mut self, to_size: usize) -> Result<usize> {
-        let mut cache = self.m_cache.lock().map_err(|e| format!("{e:?}"))?;
-        cache.shrink_to(to_size);
-        Ok(cache.total_size())
+    /// Get an image derived from a source image (such as a level of
+    /// an image pyramid), creating it if it is not in the cache
+    ///
+    /// The image is keyed by the content hash of the source image (see
+    /// [Self::source_hash]) and its name
+    ///
+    /// The cache is not locked while the image is created, so `create`
+    /// may itself use the cache
+    pub fn derived<F>(&self, source: ContentHash, name: &str, create: F) -> Result<CacheRef>
+    where
+        F: FnOnce() -> Result<ImageCacheEntry>,
+    {
+        let key = ImageCacheKey::of_derived(source, name);
+        self.cache.get_or_insert_with(&key, create)
+    }
+
+    /// Get an encoded derived image, from the cache or the disk cache,
+    /// creating it if it is in neither
+    ///
+    /// A failure to write to the disk cache is not an error, as the
+    /// image can be created again
+    pub fn encoded<F>(&self, hash: ContentHash, create: F) -> Result<CacheRef>
+    where
+        F: FnOnce() -> Result<Vec<u8>>,
+    {
+        let key = ImageCacheKey::of_content(hash);
+        self.cache.get_or_insert_with(&key, || {
+            if let Some(data) = self.disk_cache.as_ref().and_then(|d| d.get(&hash)) {
+                return Ok(ImageCacheEntry::Encoded(data));
+            }
+            let data = create()?;
+            let written = self.disk_cache.as_ref().map(|d| d.insert(&hash, &data));
+            if let Some(Err(e)) = written {
+                eprintln!("Failed to write to disk cache: {e}");
+            }
+            Ok(ImageCacheEntry::Encoded(data))
+        })
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.cache.stats()
+    }
+
+    pub fn disk_stats(&self) -> Option<CacheStats> {
+        self.disk_cache.as_ref().map(|d| d.stats())
+    }
+
+    pub fn shrink_cache(&self, to_size: usize) -> usize {
+        self.cache.shrink_to(to_size);
+        self.cache.total_size()
+    }
+}
+
+//ip Default for ImageCache
+impl std::default::Default for ImageCache {
+    fn default() -> Self {
+        let cache = SharedCache::default();
+        Self {
+            cache,
+            disk_cache: None,
+            file_hashes: FileHashes::default(),
+        }
     }
 }
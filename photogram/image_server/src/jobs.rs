@@ -0,0 +1,543 @@
+//a Imports
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::Duration;
+
+use serde::Serialize;
+
+use ic_base::Result;
+use ic_http::ServerSentEvent;
+use ic_threads::ThreadPool;
+
+//a Constants
+//ci MAX_FINISHED_JOBS
+/// Number of finished jobs whose status and results are kept
+const MAX_FINISHED_JOBS: usize = 32;
+
+//ci KEEP_ALIVE_INTERVAL
+/// Interval between keep-alive comments on an event stream for a job
+/// that is making no progress
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+//a JobState, JobStatus, JobResult
+//tp JobState
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    #[default]
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+//ip JobState
+impl JobState {
+    //ap is_finished
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+
+    //ap as_str
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Completed => "completed",
+            Self::Failed => "failed",
+            Self::Cancelled => "cancelled",
+        }
+    }
+}
+
+//tp JobStatus
+/// The status of a job, as returned to clients
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct JobStatus {
+    id: usize,
+    name: String,
+    state: JobState,
+    /// Fraction of the job completed, from 0 to 1
+    progress: f64,
+    /// Progress message, or the error if the job failed
+    message: String,
+    /// Incremented on every change, to detect updates
+    #[serde(skip)]
+    version: usize,
+}
+
+//ip JobStatus
+impl JobStatus {
+    //ap id
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    //ap state
+    pub fn state(&self) -> JobState {
+        self.state
+    }
+
+    //ap message
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+//tp JobResult
+/// The result of a completed job; this is returned as the content of
+/// an HTTP response
+#[derive(Debug, Default, Clone)]
+pub struct JobResult {
+    pub content: Vec<u8>,
+    pub mime_type: Option<String>,
+}
+
+//a Support
+//fi panic_message
+/// The message for a job whose work panicked
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        format!("Job panicked: {s}")
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        format!("Job panicked: {s}")
+    } else {
+        "Job panicked".into()
+    }
+}
+
+//a Job
+//tp Job
+/// A job submitted to a [JobSet]
+///
+/// The work of the job may report progress, and should check
+/// [Job::is_cancelled] regularly (or use [Job::check_current] between
+/// its stages)
+#[derive(Debug)]
+pub struct Job {
+    /// The project the job is for; access to the job requires access
+    /// to the project
+    project: String,
+    status: Mutex<JobStatus>,
+    changed: Condvar,
+    cancelled: AtomicBool,
+    result: Mutex<Option<JobResult>>,
+}
+
+thread_local! {
+    /// The job being run by the current thread, if any
+    static CURRENT_JOB: RefCell<Option<Arc<Job>>> = const { RefCell::new(None) };
+}
+
+//ip Job
+impl Job {
+    //cp new
+    fn new(id: usize, name: &str, project: &str) -> Self {
+        let status = JobStatus {
+            id,
+            name: name.into(),
+            ..Default::default()
+        };
+        Self {
+            project: project.into(),
+            status: status.into(),
+            changed: Condvar::new(),
+            cancelled: AtomicBool::new(false),
+            result: None.into(),
+        }
+    }
+
+    //fp current
+    /// The job being run by this thread, if any
+    ///
+    /// This allows code that is not aware of jobs to report progress
+    /// and to check for cancellation when it is run as a job
+    pub fn current() -> Option<Arc<Job>> {
+        CURRENT_JOB.with(|j| j.borrow().clone())
+    }
+
+    //fp progress_of_current
+    /// Report the progress of the current job (if any), returning
+    /// false if it has been cancelled
+    pub fn progress_of_current(fraction: f64) -> bool {
+        match Self::current() {
+            Some(job) => {
+                job.update(|s| s.progress = fraction.clamp(0.0, 1.0));
+                !job.is_cancelled()
+            }
+            None => true,
+        }
+    }
+
+    //fp check_current
+    /// Report the progress of the current job (if any) with a message,
+    /// returning an error if it has been cancelled
+    ///
+    /// Work that may be run as a job calls this between its stages, so
+    /// that a cancelled job stops at the next one
+    pub fn check_current(fraction: f64, message: &str) -> Result<()> {
+        if let Some(job) = Self::current() {
+            job.set_progress(fraction, message);
+            if job.is_cancelled() {
+                return Err(format!("Job cancelled while {message}").into());
+            }
+        }
+        Ok(())
+    }
+
+    //mi update
+    fn update<F: FnOnce(&mut JobStatus)>(&self, f: F) {
+        let mut status = self.status.lock().unwrap();
+        f(&mut status);
+        status.version += 1;
+        self.changed.notify_all();
+    }
+
+    //ap project
+    pub fn project(&self) -> &str {
+        &self.project
+    }
+
+    //ap status
+    pub fn status(&self) -> JobStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    //ap is_cancelled
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    //ap result
+    pub fn result(&self) -> Option<JobResult> {
+        self.result.lock().unwrap().clone()
+    }
+
+    //mp set_progress
+    /// Report the progress of the job with a message
+    pub fn set_progress(&self, fraction: f64, message: &str) {
+        self.update(|s| {
+            s.progress = fraction.clamp(0.0, 1.0);
+            s.message = message.into();
+        });
+    }
+
+    //mp cancel
+    /// Request that the job be cancelled; returns false if it has
+    /// already finished
+    ///
+    /// A queued job is cancelled immediately; a running job finishes
+    /// when its work next checks for cancellation
+    pub fn cancel(&self) -> bool {
+        let mut status = self.status.lock().unwrap();
+        if status.state.is_finished() {
+            return false;
+        }
+        self.cancelled.store(true, Ordering::Relaxed);
+        if status.state == JobState::Queued {
+            status.state = JobState::Cancelled;
+        }
+        status.version += 1;
+        self.changed.notify_all();
+        true
+    }
+
+    //mp events
+    /// Get an iterator of the server-sent events for the job
+    ///
+    /// An event is produced for the current status, then for each
+    /// change of status; the last is a 'completed', 'failed' or
+    /// 'cancelled' event. If there are no changes for a while then a
+    /// keep-alive comment is produced.
+    pub fn events(self: &Arc<Self>) -> JobEvents {
+        JobEvents {
+            job: self.clone(),
+            version: None,
+            finished: false,
+        }
+    }
+}
+
+//a JobEvents
+//tp JobEvents
+pub struct JobEvents {
+    job: Arc<Job>,
+    version: Option<usize>,
+    finished: bool,
+}
+
+//ip Iterator for JobEvents
+impl Iterator for JobEvents {
+    type Item = ServerSentEvent;
+    fn next(&mut self) -> Option<ServerSentEvent> {
+        if self.finished {
+            return None;
+        }
+        let mut status = self.job.status.lock().unwrap();
+        if self.version == Some(status.version) {
+            let (s, timeout) = self
+                .job
+                .changed
+                .wait_timeout_while(status, KEEP_ALIVE_INTERVAL, |s| {
+                    Some(s.version) == self.version
+                })
+                .unwrap();
+            if timeout.timed_out() {
+                return Some(ServerSentEvent::keep_alive());
+            }
+            status = s;
+        }
+        self.version = Some(status.version);
+        let event = {
+            if status.state.is_finished() {
+                self.finished = true;
+                status.state.as_str()
+            } else {
+                "progress"
+            }
+        };
+        let data = serde_json::to_string(&*status).unwrap();
+        Some(ServerSentEvent::new(event, data))
+    }
+}
+
+//a JobSet
+//tp JobSet
+/// A set of jobs that are run on a thread pool
+#[derive(Debug)]
+pub struct JobSet {
+    pool: ThreadPool,
+    next_id: AtomicUsize,
+    jobs: Mutex<HashMap<usize, Arc<Job>>>,
+}
+
+//ip JobSet
+impl JobSet {
+    //cp new
+    pub fn new(num_threads: usize) -> Self {
+        Self {
+            pool: ThreadPool::new(num_threads),
+            next_id: AtomicUsize::new(1),
+            jobs: HashMap::new().into(),
+        }
+    }
+
+    //mp submit
+    /// Submit some work for a project as a job, returning the job
+    ///
+    /// The work is run on the thread pool; while it runs,
+    /// [Job::current] returns the job. If the work panics then the job
+    /// fails, with the panic message as its message
+    pub fn submit<F>(&self, name: &str, project: &str, work: F) -> Arc<Job>
+    where
+        F: FnOnce(&Job) -> Result<JobResult> + Send + 'static,
+    {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let job = Arc::new(Job::new(id, name, project));
+        {
+            let mut jobs = self.jobs.lock().unwrap();
+            Self::prune(&mut jobs);
+            jobs.insert(id, job.clone());
+        }
+        let work_job = job.clone();
+        self.pool.issue_work(move || {
+            let job = work_job;
+            // The job may have been cancelled while it was queued
+            let mut started = false;
+            job.update(|s| {
+                if s.state == JobState::Queued {
+                    s.state = JobState::Running;
+                    started = true;
+                }
+            });
+            if !started {
+                return;
+            }
+            CURRENT_JOB.with(|j| *j.borrow_mut() = Some(job.clone()));
+            let result = catch_unwind(AssertUnwindSafe(|| work(&job)));
+            CURRENT_JOB.with(|j| *j.borrow_mut() = None);
+            let (result, panicked) = match result {
+                Ok(result) => (result, false),
+                Err(panic) => (Err(panic_message(panic.as_ref()).into()), true),
+            };
+            match result {
+                Ok(result) => {
+                    *job.result.lock().unwrap() = Some(result);
+                    job.update(|s| {
+                        s.state = JobState::Completed;
+                        s.progress = 1.0;
+                    });
+                }
+                Err(e) => {
+                    let state = if job.is_cancelled() && !panicked {
+                        JobState::Cancelled
+                    } else {
+                        JobState::Failed
+                    };
+                    job.update(|s| {
+                        s.state = state;
+                        s.message = e.to_string();
+                    });
+                }
+            }
+        });
+        job
+    }
+
+    //fi prune
+    /// Remove the oldest finished jobs so that at most
+    /// MAX_FINISHED_JOBS remain
+    fn prune(jobs: &mut HashMap<usize, Arc<Job>>) {
+        let mut finished: Vec<usize> = jobs
+            .iter()
+            .filter(|(_, j)| j.status().state.is_finished())
+            .map(|(id, _)| *id)
+            .collect();
+        if finished.len() < MAX_FINISHED_JOBS {
+            return;
+        }
+        finished.sort();
+        for id in &finished[0..=finished.len() - MAX_FINISHED_JOBS] {
+            jobs.remove(id);
+        }
+    }
+
+    //ap get
+    pub fn get(&self, id: usize) -> Option<Arc<Job>> {
+        self.jobs.lock().unwrap().get(&id).cloned()
+    }
+
+    //ap list
+    /// The status of the jobs that pass a filter (such as being for
+    /// projects that a user may read), in order of submission
+    pub fn list<F: Fn(&Job) -> bool>(&self, filter: F) -> Vec<JobStatus> {
+        let mut list: Vec<JobStatus> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|j| filter(j))
+            .map(|j| j.status())
+            .collect();
+        list.sort_by_key(|s| s.id);
+        list
+    }
+
+    //mp shutdown
+    /// Wait for the jobs that have been submitted to complete, and stop
+    /// the threads that run them
+    pub fn shutdown(&self) {
+        self.pool.shutdown();
+    }
+}
+
+//a Tests
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+
+    use super::*;
+
+    //fi wait
+    /// Wait for a job to finish, returning its final status
+    fn wait(job: &Arc<Job>) -> JobStatus {
+        job.events().for_each(drop);
+        job.status()
+    }
+
+    #[test]
+    fn test_submit() {
+        let jobs = JobSet::new(2);
+        let job = jobs.submit("ok", "p", |job| {
+            assert_eq!(Job::current().unwrap().status().id(), job.status().id());
+            Job::check_current(0.5, "working")?;
+            Ok(JobResult {
+                content: b"done".to_vec(),
+                mime_type: None,
+            })
+        });
+        let status = wait(&job);
+        assert_eq!(status.state(), JobState::Completed);
+        assert_eq!(job.result().unwrap().content, b"done");
+        assert_eq!(job.project(), "p");
+        assert!(!job.cancel());
+
+        let job = jobs.submit("error", "p", |_| Err("bad input".into()));
+        let status = wait(&job);
+        assert_eq!(status.state(), JobState::Failed);
+        assert_eq!(status.message(), "bad input");
+        assert!(job.result().is_none());
+
+        let job = jobs.submit("panic", "p", |_| panic!("out of range"));
+        let status = wait(&job);
+        assert_eq!(status.state(), JobState::Failed);
+        assert_eq!(status.message(), "Job panicked: out of range");
+
+        // The thread that ran the panicking job can still run jobs
+        assert!(Job::current().is_none());
+        let job = jobs.submit("ok", "p", |_| Ok(JobResult::default()));
+        assert_eq!(wait(&job).state(), JobState::Completed);
+
+        let ids: Vec<usize> = jobs.list(|_| true).iter().map(|s| s.id()).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+        assert!(jobs.list(|j| j.project() == "q").is_empty());
+        jobs.shutdown();
+    }
+
+    #[test]
+    fn test_cancel() {
+        let jobs = JobSet::new(1);
+        let (started_tx, started_rx) = mpsc::channel();
+        let running = jobs.submit("running", "p", move |_| {
+            started_tx.send(()).unwrap();
+            loop {
+                Job::check_current(0.5, "waiting")?;
+                std::thread::sleep(Duration::from_millis(1));
+            }
+        });
+        let ran = Arc::new(AtomicBool::new(false));
+        let queued_ran = ran.clone();
+        let queued = jobs.submit("queued", "p", move |_| {
+            queued_ran.store(true, Ordering::Relaxed);
+            Ok(JobResult::default())
+        });
+        started_rx.recv().unwrap();
+
+        // A queued job is cancelled at once, and its work is never run
+        assert!(queued.cancel());
+        assert_eq!(queued.status().state(), JobState::Cancelled);
+
+        // A running job is cancelled when its work next checks
+        assert!(running.cancel());
+        let status = wait(&running);
+        assert_eq!(status.state(), JobState::Cancelled);
+        assert_eq!(status.message(), "Job cancelled while waiting");
+        assert!(!running.cancel());
+
+        jobs.shutdown();
+        assert!(!ran.load(Ordering::Relaxed));
+        assert_eq!(queued.status().state(), JobState::Cancelled);
+    }
+
+    #[test]
+    fn test_prune() {
+        let jobs = JobSet::new(2);
+        let n = MAX_FINISHED_JOBS + 4;
+        for _ in 0..n {
+            let job = jobs.submit("ok", "p", |_| Ok(JobResult::default()));
+            wait(&job);
+        }
+        // Finished jobs are pruned when a job is submitted, keeping
+        // room for it
+        let ids: Vec<usize> = jobs.list(|_| true).iter().map(|s| s.id()).collect();
+        assert_eq!(ids.len(), MAX_FINISHED_JOBS);
+        assert_eq!(ids[0], n - MAX_FINISHED_JOBS + 1);
+        assert!(jobs.get(n - MAX_FINISHED_JOBS).is_none());
+        assert!(jobs.get(n).is_some());
+        jobs.shutdown();
+    }
+}
@@ -1,29 +1,56 @@
 //a Imports
 use std::collections::HashMap;
-use std::net::TcpListener;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, TcpStream};
 use std::path::{Path, PathBuf};
-use std::sync::OnceLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
 use clap::Command;
+use signal_hook::consts::{SIGINT, SIGTERM};
+use signal_hook::iterator::Signals;
 
 use ic_base::{Mesh, Result};
-// use ic_cache::{Cache, CacheEntry, Cacheable};
+use ic_cache::{CacheStats, DiskCache};
 use ic_camera::CameraProjection;
 use ic_cmdline as cmdline_args;
 use ic_http::{
-    HttpRequest, HttpRequestType, HttpResponse, HttpResponseType, HttpServer, HttpServerExt,
+    load_tls_config, HttpRequest, HttpRequestType, HttpResponse, HttpResponseType, HttpServer,
+    HttpServerExt,
 };
 use ic_image::{Image, ImageGray16, ImageRgb8, Patch};
-use ic_kernel::{KernelArgs, Kernels};
+use ic_kernel::{KernelArgs, KernelPipeline, Kernels};
+use ic_project::ProjectArchive;
 use ic_threads::ThreadPool;
 
+mod auth;
+mod config;
+mod jobs;
 mod project_decode;
+mod project_edit;
 mod project_entry;
+mod tiles;
 
+use auth::{AuthConfig, Permission, User};
+use config::ServerConfig;
 use project_decode::ProjectDecode;
 use project_entry::NamedProject;
 mod image_cache;
 use image_cache::{ImageCache, ImageCacheEntry};
+use jobs::{Job, JobResult, JobSet, JobState};
+use project_edit::set_json_response;
+
+//a Types
+//tp HttpHandler
+/// A method of [ProjectSet] that handles a request for a project
+type HttpHandler = fn(
+    &ProjectSet,
+    &HttpServer<ProjectSet>,
+    &HttpRequest,
+    &[u8],
+    &mut HttpResponse,
+    &ProjectDecode,
+) -> Result<()>;
 
 //a ProjectSet
 //ti ProjectSet
@@ -34,31 +61,55 @@ struct ProjectSet {
     index_by_name: HashMap<String, usize>,
     kernels: Kernels,
     image_cache: ImageCache,
+    jobs: JobSet,
+    auth: Option<AuthConfig>,
 }
 
 //ip ProjectSet
 impl ProjectSet {
-    fn new() -> Self {
-        let kernels = Kernels::new();
+    fn new(kernels: Kernels) -> Self {
         let image_root = "".into();
         let projects = vec![];
         let index_by_name = HashMap::new();
         let image_cache = ImageCache::new();
+        let jobs = JobSet::new(2);
         Self {
             image_root,
             projects,
             index_by_name,
             kernels,
             image_cache,
+            jobs,
+            auth: None,
         }
     }
 
+    //mp set_auth
+    /// Require requests to be authenticated as one of the users of an
+    /// [AuthConfig]
+    pub fn set_auth(&mut self, auth: Option<AuthConfig>) {
+        self.auth = auth;
+    }
+
+    //mp shutdown
+    /// Wait for the background jobs to complete
+    pub fn shutdown(&self) {
+        self.jobs.shutdown();
+    }
+
+    //mp set_disk_cache
+    pub fn set_disk_cache(&mut self, disk_cache: DiskCache) {
+        self.image_cache.set_disk_cache(disk_cache);
+    }
+
     //mp set_image_root
     pub fn set_image_root<I: Into<PathBuf>>(&mut self, image_root: I) {
         self.image_root = image_root.into();
     }
 
     //mp fill_from_project_dir
+    /// Add the projects in a directory - files named '*_proj.json', and
+    /// (read-only) project archives named '*_proj.tar'
     pub fn fill_from_project_dir<P: AsRef<Path> + std::fmt::Display>(
         &mut self,
         path: P,
@@ -79,7 +130,7 @@ impl ProjectSet {
                 continue;
             }
             let pb = d.path();
-            if pb.extension().is_some_and(|x| x == "json")
+            if (pb.extension().is_some_and(|x| x == "json") || ProjectArchive::is_archive(&pb))
                 && pb
                     .file_stem()
                     .and_then(|x| x.to_str())
@@ -117,14 +168,21 @@ impl ProjectSet {
     }
 
     //mi http_list_projects
+    /// List the projects, restricted to those the user (if any) may read
     fn http_list_projects(
         &self,
         server: &HttpServer<Self>,
         _request: &HttpRequest,
         _content: &[u8],
         response: &mut HttpResponse,
+        user: Option<&User>,
     ) -> Result<()> {
-        let names: Vec<String> = self.index_by_name.keys().cloned().collect();
+        let names: Vec<String> = self
+            .index_by_name
+            .keys()
+            .filter(|name| user.is_none_or(|u| u.permits(name, Permission::Read)))
+            .cloned()
+            .collect();
         let json = serde_json::to_string(&names).unwrap();
         response.content = json.into_bytes();
         response.mime_type = server.mime_type("json");
@@ -153,6 +211,9 @@ impl ProjectSet {
     }
 
     //mi http_save_project
+    /// Replace the project with the JSON content and save it; the edit
+    /// history is cleared, as its edits may not apply to the new
+    /// project; project archives are read-only, and cannot be saved
     fn http_save_project(
         &self,
         _server: &HttpServer<Self>,
@@ -161,6 +222,9 @@ impl ProjectSet {
         response: &mut HttpResponse,
         idx: usize,
     ) -> Result<()> {
+        if self.projects[idx].is_read_only() {
+            return Err(format!("Project {idx} is a project archive, which is read-only").into());
+        }
         let mut str_content = "";
         let mut e = match std::str::from_utf8(content) {
             Ok(c) => {
@@ -201,6 +265,7 @@ impl ProjectSet {
         let cip = p.cip(cip).clone();
         let cip_r = cip.borrow();
         let pms = cip_r.pms();
+        Job::check_current(0.0, "building the mesh")?;
         let mesh = Mesh::optimized(pms.borrow().mappings().iter().map(|p| *p.screen()));
         let triangles: Vec<_> = mesh.triangles().collect();
         eprintln!("Triangles of mesh {triangles:?}");
@@ -232,28 +297,37 @@ impl ProjectSet {
         let path = self.image_root.as_path().join(cip_r.image_filename());
         server.verbose().then(|| eprintln!("Open image {path:?}"));
 
-        let src_img_ref = self.image_cache.src_image(&path)?;
-        let src_img = ImageCacheEntry::cr_as_rgb8(&src_img_ref);
-
-        let src_size = src_img.size();
-        let src_size = (src_size.0 as f64, src_size.1 as f64);
-        let x_scale = pd.width.map(|w| src_size.0 / w).unwrap_or(1.0);
-        let y_scale = pd.height.map(|h| src_size.1 / h).unwrap_or(1.0);
-        let scale = x_scale.max(y_scale);
-        let width = (src_size.0 / scale) as usize;
-        let height = (src_size.1 / scale) as usize;
-        let no_file: Option<String> = None;
-        let mut scaled_img = ImageRgb8::read_or_create_image(width, height, no_file).unwrap();
-        for y in 0..height {
-            let sy = (y as f64 + 0.5) * scale;
-            for x in 0..width {
-                let sx = (x as f64 + 0.5) * scale;
-                let c = src_img.get(sx as u32, sy as u32);
-                scaled_img.put(x as u32, y as u32, &c);
+        let hash = self
+            .image_cache
+            .source_hash(&path)?
+            .with_str("thumbnail")
+            .with_str(&format!("{:?}x{:?}", pd.width, pd.height));
+        let thumbnail_ref = self.image_cache.encoded(hash, || {
+            Job::check_current(0.0, "reading the image")?;
+            let src_img_ref = self.image_cache.src_image(&path)?;
+            let src_img = ImageCacheEntry::cr_as_rgb8(&src_img_ref);
+
+            let src_size = src_img.size();
+            let src_size = (src_size.0 as f64, src_size.1 as f64);
+            let x_scale = pd.width.map(|w| src_size.0 / w).unwrap_or(1.0);
+            let y_scale = pd.height.map(|h| src_size.1 / h).unwrap_or(1.0);
+            let scale = x_scale.max(y_scale);
+            let width = (src_size.0 / scale) as usize;
+            let height = (src_size.1 / scale) as usize;
+            let no_file: Option<String> = None;
+            let mut scaled_img = ImageRgb8::read_or_create_image(width, height, no_file).unwrap();
+            for y in 0..height {
+                Job::check_current(0.5 + 0.5 * (y as f64) / (height as f64), "scaling")?;
+                let sy = (y as f64 + 0.5) * scale;
+                for x in 0..width {
+                    let sx = (x as f64 + 0.5) * scale;
+                    let c = src_img.get(sx as u32, sy as u32);
+                    scaled_img.put(x as u32, y as u32, &c);
+                }
             }
-        }
-        let img_bytes = scaled_img.encode("jpeg")?;
-        response.content = img_bytes;
+            scaled_img.encode("jpeg")
+        })?;
+        response.content = ImageCacheEntry::cr_as_encoded(&thumbnail_ref).to_vec();
         response.mime_type = server.mime_type("jpeg");
         response.resp_type = HttpResponseType::FileRead;
         Ok(())
@@ -278,10 +352,6 @@ impl ProjectSet {
         let cip_r = cip.borrow();
         let path = self.image_root.as_path().join(cip_r.image_filename());
 
-        let src_img_ref = self.image_cache.src_image(&path)?;
-        let src_img = ImageCacheEntry::cr_as_rgb8(&src_img_ref);
-        // let src_img = ImageRgb8::read_image(path)?;
-
         let nps = p.nps_ref();
         let camera = cip_r.camera_ref();
 
@@ -308,83 +378,228 @@ impl ProjectSet {
         let model_pts: Vec<_> = model_pts.into_iter().map(|(_, m, _)| m).collect();
 
         let px_per_model = pd.px_per_model.unwrap_or(10.0);
-        let Some(patch) = Patch::create(src_img, px_per_model, model_pts.iter(), &|m| {
-            camera.world_xyz_to_px_abs_xy(&m)
-        })?
-        else {
-            return Err("Failled to create patch".into());
-        };
-
         let to_width = pd.width.map(|x| x as usize).unwrap_or(200);
         let ws = pd.window.unwrap_or(4) as u32;
-        let img = patch.img();
-        let (w, h, mut img_data) = img.as_vec_gray_f32(Some(to_width));
-        let mut img_data_sq = img_data.clone();
-        let args: KernelArgs = (w, h).into();
-
-        // sum(x)^2 - sum(x^2)
-
-        let args = args.with_size(ws as usize);
-        let ws_f = ws as f32;
-        let args_mean = args.with_scale(1.0 / ws_f);
-        self.kernels
-            .run_shader("square", &args, w * h, None, img_data_sq.as_mut_slice())?;
-        self.kernels.run_shader(
-            "window_sum_x",
-            &args_mean,
-            w * h,
-            None,
-            img_data_sq.as_mut_slice(),
-        )?;
-        self.kernels.run_shader(
-            "window_sum_y",
-            &args_mean,
-            w * h,
-            None,
-            img_data_sq.as_mut_slice(),
-        )?;
-        self.kernels.run_shader(
-            "window_sum_x",
-            &args_mean,
-            w * h,
-            None,
-            img_data.as_mut_slice(),
-        )?;
-        self.kernels.run_shader(
-            "window_sum_y",
-            &args_mean,
-            w * h,
-            None,
-            img_data.as_mut_slice(),
-        )?;
-        self.kernels
-            .run_shader("square", &args, w * h, None, img_data.as_mut_slice())?;
-
-        self.kernels.run_shader(
-            "sub_scaled",
-            &args,
-            w * h,
-            Some(img_data.as_slice()),
-            img_data_sq.as_mut_slice(),
-        )?;
-        self.kernels.run_shader(
-            "sqrt",
-            &args.with_scale(2.0),
-            w * h,
-            None,
-            img_data_sq.as_mut_slice(),
-        )?;
-
-        // minus
-        // square sum sum
-        let img = ImageGray16::of_vec_f32(w, h, img_data_sq, 1.0);
-        let img_bytes = img.encode("png")?;
-        response.content = img_bytes;
+
+        // The patch depends on the camera and the model positions of
+        // the named points, so a change to either changes the key
+        let mut hash = self
+            .image_cache
+            .source_hash(&path)?
+            .with_str("patch")
+            .with_str(&serde_json::to_string(&*camera)?)
+            .with_str(&format!("{px_per_model} {to_width} {ws}"));
+        for m in &model_pts {
+            hash = hash.with_str(&format!("{m}"));
+        }
+        let patch_ref = self.image_cache.encoded(hash, || {
+            Job::check_current(0.0, "reading the image")?;
+            let src_img_ref = self.image_cache.src_image(&path)?;
+            let src_img = ImageCacheEntry::cr_as_rgb8(&src_img_ref);
+            Job::check_current(0.5, "creating the patch")?;
+            let Some(patch) = Patch::create(src_img, px_per_model, model_pts.iter(), &|m| {
+                camera.world_xyz_to_px_abs_xy(&m)
+            })?
+            else {
+                return Err("Failled to create patch".into());
+            };
+
+            let img = patch.img();
+            let (w, h, img_data) = img.as_vec_gray_f32(Some(to_width));
+            let args: KernelArgs = (w, h).into();
+            let args = args.with_size(ws as usize);
+
+            // sqrt(mean(x^2) - mean(x)^2) over the window
+            Job::check_current(0.75, "filtering the patch")?;
+            let pipeline = KernelPipeline::builtin("window_std_dev")?;
+            let img_data_sq = self
+                .kernels
+                .run_pipeline(&pipeline, &args, &[("image", img_data.as_slice())])?
+                .take_output();
+
+            let img = ImageGray16::of_vec_f32(w, h, img_data_sq, 1.0);
+            img.encode("png")
+        })?;
+        response.content = ImageCacheEntry::cr_as_encoded(&patch_ref).to_vec();
         response.mime_type = server.mime_type("png");
         response.resp_type = HttpResponseType::FileRead;
         Ok(())
     }
 
+    //mi run_or_submit
+    /// Handle a request for a project, or if it has a 'job' argument,
+    /// submit it as a job and respond with 202 and the job status
+    fn run_or_submit(
+        &self,
+        server: &HttpServer<Self>,
+        request: &HttpRequest,
+        content: &[u8],
+        response: &mut HttpResponse,
+        pd: &ProjectDecode,
+        handler: HttpHandler,
+    ) -> Result<()> {
+        if !request.has_arg("job") {
+            return handler(self, server, request, content, response, pd);
+        }
+        let name = format!("{} {}", pd.project, request.uri.action().unwrap_or(""));
+        let request = request.clone();
+        let content = content.to_vec();
+        let project = pd.project.clone();
+        let pd = pd.clone();
+        let job = self.jobs.submit(&name, &project, move |_job| {
+            let server = HTTP_SRV.get().unwrap();
+            let mut response = HttpResponse::default();
+            handler(
+                server.data(),
+                server,
+                &request,
+                &content,
+                &mut response,
+                &pd,
+            )?;
+            if response.status_code() >= 400 {
+                return Err(String::from_utf8_lossy(&response.content)
+                    .to_string()
+                    .into());
+            }
+            Ok(JobResult {
+                content: response.content,
+                mime_type: response.mime_type,
+            })
+        });
+        let status = job.status();
+        set_json_response(server, response, &status)?;
+        response.set_status(202);
+        response.set_header("Location", format!("/jobs?status&id={}", status.id()));
+        Ok(())
+    }
+
+    //mi http_jobs
+    /// Handle a request for 'jobs'
+    ///
+    /// 'list' returns the status of all jobs; 'status', 'events' (a
+    /// stream of server-sent events), 'result' and 'cancel' (POST or
+    /// DELETE) operate on the job given by 'id'
+    ///
+    /// With authentication, only the jobs for projects that the user
+    /// may read are listed or may be queried, and cancelling a job
+    /// requires write permission for its project
+    fn http_jobs(
+        &self,
+        server: &HttpServer<Self>,
+        request: &HttpRequest,
+        response: &mut HttpResponse,
+        user: Option<&User>,
+    ) -> Result<()> {
+        let permits =
+            |job: &Job, permission| user.is_none_or(|u| u.permits(job.project(), permission));
+        let is_get = request.req_type == HttpRequestType::Get;
+        if request.action_is("list") && is_get {
+            let list = self.jobs.list(|job| permits(job, Permission::Read));
+            return set_json_response(server, response, &list);
+        }
+        let Some(Ok(id)) = request.get_one::<usize>("id") else {
+            response.set_error(400, "Job id required");
+            return Ok(());
+        };
+        let Some(job) = self.jobs.get(id) else {
+            response.set_error(404, &format!("Job {id} not found"));
+            return Ok(());
+        };
+        let needed = if is_get {
+            Permission::Read
+        } else {
+            Permission::Write
+        };
+        if !permits(&job, needed) {
+            response.set_error(403, &format!("Not permitted to access job {id}"));
+            return Ok(());
+        }
+        if request.action_is("status") && is_get {
+            set_json_response(server, response, &job.status())
+        } else if request.action_is("events") && is_get {
+            response.set_event_stream(job.events());
+            Ok(())
+        } else if request.action_is("result") && is_get {
+            let status = job.status();
+            match (status.state(), job.result()) {
+                (JobState::Completed, Some(result)) => {
+                    response.content = result.content;
+                    response.mime_type = result.mime_type;
+                    response.resp_type = HttpResponseType::FileRead;
+                }
+                (JobState::Failed, _) | (JobState::Cancelled, _) => {
+                    response.set_error(
+                        409,
+                        &format!("Job {id} {}: {}", status.state().as_str(), status.message()),
+                    );
+                }
+                _ => {
+                    set_json_response(server, response, &status)?;
+                    response.set_status(202);
+                }
+            }
+            Ok(())
+        } else if request.action_is("cancel")
+            && matches!(
+                request.req_type,
+                HttpRequestType::Post | HttpRequestType::Delete
+            )
+        {
+            if !job.cancel() {
+                response.set_error(409, &format!("Job {id} has already finished"));
+                return Ok(());
+            }
+            set_json_response(server, response, &job.status())
+        } else {
+            response.set_error(400, "Unknown job action");
+            Ok(())
+        }
+    }
+
+    //mi http_cache
+    /// Handle a request for 'cache'
+    ///
+    /// 'stats' returns the statistics of the image cache and of the disk
+    /// cache (if any); 'shrink' (POST) evicts images until the
+    /// in-memory cache is below 'size' bytes (or as far as possible)
+    fn http_cache(
+        &self,
+        server: &HttpServer<Self>,
+        request: &HttpRequest,
+        response: &mut HttpResponse,
+    ) -> Result<()> {
+        if request.action_is("shrink") && request.req_type == HttpRequestType::Post {
+            let size = match request.get_one::<usize>("size") {
+                Some(Ok(size)) => size,
+                None => 0,
+                Some(Err(_)) => {
+                    response.set_error(400, "Bad cache size");
+                    return Ok(());
+                }
+            };
+            self.image_cache.shrink_cache(size);
+        } else if !(request.action_is("stats") && request.req_type == HttpRequestType::Get) {
+            response.set_error(400, "Unknown cache action");
+            return Ok(());
+        }
+        let stats_json = |stats: CacheStats| {
+            serde_json::json!({
+                "hits": stats.hits,
+                "misses": stats.misses,
+                "insertions": stats.insertions,
+                "evictions": stats.evictions,
+                "total_size": stats.total_size,
+                "num_entries": stats.num_entries,
+            })
+        };
+        let json = serde_json::json!({
+            "memory": stats_json(self.image_cache.stats()),
+            "disk": self.image_cache.disk_stats().map(stats_json),
+        });
+        set_json_response(server, response, &json)
+    }
+
     //zz All done
 }
 
@@ -398,6 +613,37 @@ impl HttpServerExt for ProjectSet {
         content: &[u8],
         response: &mut HttpResponse,
     ) -> bool {
+        // With authentication every request, including those for
+        // files, must come from a known user
+        let user = match &self.auth {
+            None => None,
+            Some(auth) => match auth.authenticate(request) {
+                Some(user) => Some(user),
+                None => {
+                    auth.set_unauthorized(response);
+                    return true;
+                }
+            },
+        };
+        if request.uri.path() == Some(Path::new("jobs")) {
+            if let Err(e) = self.http_jobs(server, request, response, user) {
+                response.set_error(500, &e.to_string());
+            }
+            return true;
+        }
+        if request.uri.path() == Some(Path::new("cache")) {
+            // Changing the cache affects all projects
+            if request.req_type != HttpRequestType::Get
+                && user.is_some_and(|u| !u.permits("*", Permission::Write))
+            {
+                response.set_error(403, "Not permitted to modify the cache");
+                return true;
+            }
+            if let Err(e) = self.http_cache(server, request, response) {
+                response.set_error(500, &e.to_string());
+            }
+            return true;
+        }
         let Some(pd) = self.decode_project(request) else {
             return false;
         };
@@ -405,11 +651,29 @@ impl HttpServerExt for ProjectSet {
             eprintln!("ImageServer: {request:?}");
             eprintln!("    Decoded: {pd:?}");
         });
+        // Reading a project requires read permission; anything else
+        // requires write permission
+        if let (Some(user), Some(project)) = (user, pd.project()) {
+            let needed = {
+                if request.req_type == HttpRequestType::Get {
+                    Permission::Read
+                } else {
+                    Permission::Write
+                }
+            };
+            if !user.permits(project, needed) {
+                response.set_error(403, &format!("Not permitted to access project {project}"));
+                return true;
+            }
+        }
+        // Status code for the response if the request fails
+        let mut status = 500;
         let result = {
             if pd.is_root() {
                 if request.action_is("list") && request.req_type == HttpRequestType::Get {
-                    self.http_list_projects(server, request, content, response)
+                    self.http_list_projects(server, request, content, response, user)
                 } else {
+                    status = 400;
                     Err("Unknown project action".into())
                 }
             } else if let Some(idx) = pd.project_idx() {
@@ -418,26 +682,81 @@ impl HttpServerExt for ProjectSet {
                 } else if request.action_is("save") && request.req_type == HttpRequestType::Put {
                     self.http_save_project(server, request, content, response, idx)
                 } else if request.action_is("mesh") && request.req_type == HttpRequestType::Get {
-                    self.http_cip_pms_mesh(server, request, content, response, &pd)
+                    self.run_or_submit(
+                        server,
+                        request,
+                        content,
+                        response,
+                        &pd,
+                        Self::http_cip_pms_mesh,
+                    )
                 } else if request.action_is("thumbnail") && request.req_type == HttpRequestType::Get
                 {
-                    self.http_cip_thumbnail(server, request, content, response, &pd)
+                    self.run_or_submit(
+                        server,
+                        request,
+                        content,
+                        response,
+                        &pd,
+                        Self::http_cip_thumbnail,
+                    )
+                } else if request.action_is("tile") && request.req_type == HttpRequestType::Get {
+                    self.http_cip_tile(server, request, content, response, &pd)
+                } else if request.action_is("dzi") && request.req_type == HttpRequestType::Get {
+                    self.http_cip_dzi(server, request, content, response, &pd)
                 } else if request.action_is("patch") && request.req_type == HttpRequestType::Get {
-                    self.http_cip_patch(server, request, content, response, &pd)
+                    self.run_or_submit(
+                        server,
+                        request,
+                        content,
+                        response,
+                        &pd,
+                        Self::http_cip_patch,
+                    )
+                } else if request.action_is("nps") {
+                    self.http_nps(server, request, content, response, &pd)
+                } else if request.action_is("pms") {
+                    self.http_pms(server, request, content, response, &pd)
+                } else if request.action_is("locate") && request.req_type == HttpRequestType::Post {
+                    self.run_or_submit(
+                        server,
+                        request,
+                        content,
+                        response,
+                        &pd,
+                        Self::http_cip_locate,
+                    )
+                } else if request.action_is("orient") && request.req_type == HttpRequestType::Post {
+                    self.run_or_submit(
+                        server,
+                        request,
+                        content,
+                        response,
+                        &pd,
+                        Self::http_cip_orient,
+                    )
+                } else if (request.action_is("history") && request.req_type == HttpRequestType::Get)
+                    || ((request.action_is("undo") || request.action_is("redo"))
+                        && request.req_type == HttpRequestType::Post)
+                {
+                    self.http_history(server, request, content, response, idx)
+                } else if request.action_is("persist") && request.req_type == HttpRequestType::Post
+                {
+                    self.http_persist_project(server, request, content, response, idx)
                 } else {
+                    status = 405;
                     Err("Bad request type".into())
                 }
             } else {
+                status = 404;
                 Err(format!("Failed to find project {}", pd.project().unwrap()).into())
             }
         };
-        match result {
-            Err(e) => {
-                eprintln!("Failed to handle request: {e}\n  {pd:?}");
-                false
-            }
-            _ => true,
+        if let Err(e) = result {
+            eprintln!("Failed to handle request: {e}\n  {pd:?}");
+            response.set_error(status, &e.to_string());
         }
+        true
     }
 }
 
@@ -448,52 +767,104 @@ impl HttpServerExt for ProjectSet {
 /// One instance of this is created with a [OnceLock]
 static HTTP_SRV: OnceLock<HttpServer<ProjectSet>> = OnceLock::new();
 
+//fi handle_shutdown_signals
+/// Start a thread that handles SIGINT and SIGTERM by setting
+/// `shutdown` and waking the server's accept loop (by connecting to
+/// it), so that the server stops once outstanding requests are
+/// complete; a second signal exits immediately
+fn handle_shutdown_signals(addr: SocketAddr, shutdown: Arc<AtomicBool>) -> Result<()> {
+    let mut signals = Signals::new([SIGINT, SIGTERM])
+        .map_err(|e| format!("Failed to install signal handlers: {e}"))?;
+    let wake_addr = {
+        match addr.ip() {
+            IpAddr::V4(ip) if ip.is_unspecified() => (Ipv4Addr::LOCALHOST, addr.port()).into(),
+            IpAddr::V6(ip) if ip.is_unspecified() => (Ipv6Addr::LOCALHOST, addr.port()).into(),
+            _ => addr,
+        }
+    };
+    std::thread::spawn(move || {
+        for _ in signals.forever() {
+            if shutdown.swap(true, Ordering::SeqCst) {
+                eprintln!("Exiting without waiting for outstanding requests");
+                std::process::exit(1);
+            }
+            eprintln!("Shutting down once outstanding requests are complete");
+            let _ = TcpStream::connect_timeout(&wake_addr, Duration::from_secs(1));
+        }
+    });
+    Ok(())
+}
+
 //fp main
 fn main() -> Result<()> {
     let cmd = Command::new("image_server")
         .about("Image calibration/correlation server")
         .version("0.1.0");
     let cmd = cmdline_args::add_verbose_arg(cmd);
-    let cmd = cmdline_args::threads::add_threads_arg(cmd);
-    let cmd = cmdline_args::threads::add_port_arg(cmd);
-    let cmd = cmdline_args::file_system::add_file_root_arg(cmd, true);
-    let cmd = cmdline_args::file_system::add_image_root_arg(cmd, true);
-    let cmd = cmdline_args::file_system::add_project_root_arg(cmd, true);
+    let cmd = ServerConfig::add_args(cmd);
 
     let matches = cmd.get_matches();
     let verbose = cmdline_args::get_verbose(&matches);
-    let num_threads = cmdline_args::threads::get_threads(&matches)?;
-    let port = cmdline_args::threads::get_port(&matches)?;
-    let file_root = cmdline_args::file_system::get_file_root(&matches)?;
-    let image_root = cmdline_args::file_system::get_image_root(&matches)?;
-    let project_root = cmdline_args::file_system::get_project_root(&matches)?;
-    if num_threads == 0 || num_threads > 20 {
-        return Err(format!(
-            "Number of threads {num_threads} must be non-zero and no more than 20"
-        )
-        .into());
-    }
-    if !(1024..=60000).contains(&port) {
-        return Err(format!("Port {port} must be in the range 1024..60000").into());
+    let config = ServerConfig::of_matches(&matches)?;
+
+    let tls_config = {
+        match &config.tls {
+            Some(tls) => Some(load_tls_config(&tls.cert, &tls.key)?),
+            None => None,
+        }
+    };
+    if config.auth.is_some() && tls_config.is_none() && !config.bind.is_loopback() {
+        eprintln!(
+            "Warning: credentials will be sent without TLS to {}",
+            config.bind
+        );
     }
 
-    let mut project_set = ProjectSet::new();
-    project_set.set_image_root(image_root);
-    project_set.fill_from_project_dir(project_root)?;
+    let kernels = Kernels::of_config(&config.kernels_config()?)?;
+    let mut project_set = ProjectSet::new(kernels);
+    project_set.set_image_root(config.image_root.clone().unwrap());
+    if let Some(cache_dir) = &config.cache_dir {
+        let disk_cache = DiskCache::open(cache_dir, config.cache_size_bytes())
+            .map_err(|e| format!("Failed to open cache directory {cache_dir}: {e}"))?;
+        project_set.set_disk_cache(disk_cache);
+    }
+    project_set.fill_from_project_dir(config.project_root.clone().unwrap())?;
+    project_set.set_auth(config.auth.clone());
+    // Each connection held open between requests, or streaming job
+    // events, occupies a thread of the pool, so at most half of them
+    // may be held
+    let http_srv = HttpServer::new(verbose, config.file_root.clone().unwrap(), project_set)
+        .with_max_held_connections(config.threads / 2);
     HTTP_SRV
-        .set(HttpServer::new(verbose, file_root, project_set))
+        .set(http_srv)
         .map_err(|_| "Bug - faiiled to config server".to_string())?;
 
-    let pool = ThreadPool::new(4);
-    let listener = TcpListener::bind(format!("127.0.0.1:{port}"))
-        .map_err(|_a| (format!("Failed to bind to port {port}")))?;
+    let pool = ThreadPool::new(config.threads);
+    let addr = config.socket_addr();
+    let listener = TcpListener::bind(addr).map_err(|e| format!("Failed to bind to {addr}: {e}"))?;
+    let shutdown = Arc::new(AtomicBool::new(false));
+    handle_shutdown_signals(addr, shutdown.clone())?;
     for stream in listener.incoming() {
-        let stream = stream.unwrap();
-
-        pool.issue_work(|| {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let tls_config = tls_config.clone();
+        pool.issue_work(move || {
             let http_srv = HTTP_SRV.get().unwrap();
-            http_srv.handle_connection(stream);
+            match &tls_config {
+                Some(tls_config) => http_srv.handle_tls_connection(stream, tls_config),
+                None => http_srv.handle_connection(stream),
+            }
         });
     }
+
+    // Drain the connections in progress, then the background jobs
+    drop(listener);
+    pool.shutdown();
+    HTTP_SRV.get().unwrap().data().shutdown();
+    eprintln!("Server stopped");
     Ok(())
 }
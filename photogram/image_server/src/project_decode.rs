@@ -3,7 +3,7 @@ use ic_http::HttpRequest;
 
 //a ProjectDecode
 //tp ProjectDecodeType
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub enum ProjectDecodeType {
     #[default]
     Root,
@@ -12,7 +12,7 @@ pub enum ProjectDecodeType {
 }
 
 //tp ProjectDecode
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct ProjectDecode {
     pub dec_type: ProjectDecodeType,
     pub project: String,
@@ -23,6 +23,13 @@ pub struct ProjectDecode {
     pub px_per_model: Option<f64>,
     pub window: Option<usize>,
     pub radius: Option<usize>,
+    pub max_pairs: Option<usize>,
+    pub max_error: Option<f64>,
+    pub level: Option<u32>,
+    pub col: Option<u32>,
+    pub row: Option<u32>,
+    pub format: Option<String>,
+    pub overlay: bool,
     pub nps: Vec<String>,
 }
 
@@ -62,6 +69,35 @@ impl ProjectDecode {
         if let Some(Ok(radius)) = request.get_one::<usize>("window") {
             pd.radius = Some(radius);
         }
+        if let Some(Ok(max_pairs)) = request.get_one::<usize>("max_pairs") {
+            pd.max_pairs = Some(max_pairs);
+        }
+        if let Some(Ok(max_error)) = request.get_one::<f64>("max_error") {
+            pd.max_error = Some(max_error);
+        }
+        // Tiles may be given as level/col/row (Deep Zoom) or z/x/y (XYZ)
+        if let Some(Ok(level)) = request
+            .get_one::<u32>("level")
+            .or_else(|| request.get_one::<u32>("z"))
+        {
+            pd.level = Some(level);
+        }
+        if let Some(Ok(col)) = request
+            .get_one::<u32>("col")
+            .or_else(|| request.get_one::<u32>("x"))
+        {
+            pd.col = Some(col);
+        }
+        if let Some(Ok(row)) = request
+            .get_one::<u32>("row")
+            .or_else(|| request.get_one::<u32>("y"))
+        {
+            pd.row = Some(row);
+        }
+        if let Some(Ok(format)) = request.get_one::<String>("format") {
+            pd.format = Some(format);
+        }
+        pd.overlay = request.has_arg("overlay");
         for np in request.get_many::<String>("np").flatten() {
             pd.nps.push(np);
         }
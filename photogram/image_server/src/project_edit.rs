@@ -0,0 +1,601 @@
+//a Imports
+use geo_nd::Vector;
+use serde::{Deserialize, Serialize};
+
+use ic_base::Result;
+use ic_camera::{CameraInstance, CameraProjection};
+use ic_http::{HttpRequest, HttpRequestType, HttpResponse, HttpResponseType, HttpServer};
+use ic_image::Color;
+use ic_mapping::PointMapping;
+use ic_project::{Cip, Pose, Project, ProjectEdit};
+
+use crate::jobs::Job;
+use crate::project_decode::ProjectDecode;
+use crate::ProjectSet;
+
+//a Request and response types
+//tp NpDesc
+/// A named point as returned by the server, and as sent to add or
+/// move one
+///
+/// When moving a point only the model position and error are used; a
+/// point without a model position is unmapped
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct NpDesc {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color: Option<Color>,
+    #[serde(default)]
+    model: Option<[f64; 3]>,
+    #[serde(default)]
+    error: f64,
+}
+
+//tp PmDesc
+/// A point mapping as sent to add or move one
+#[derive(Debug, Serialize, Deserialize)]
+struct PmDesc {
+    name: String,
+    screen: [f64; 2],
+    /// Error in pixels of the screen position
+    #[serde(default = "PmDesc::default_error")]
+    error: f64,
+}
+
+//ip PmDesc
+impl PmDesc {
+    //fi default_error
+    fn default_error() -> f64 {
+        5.0
+    }
+}
+
+//tp PmReport
+/// A point mapping with its reprojection error for the CIP's camera
+#[derive(Debug, Serialize)]
+struct PmReport {
+    name: String,
+    screen: [f64; 2],
+    error: f64,
+    model_error: f64,
+    /// Mapped screen position minus the projected model position, if
+    /// the named point has a model position
+    reproj: Option<[f64; 2]>,
+    reproj_error: Option<f64>,
+}
+
+//tp CipReport
+/// The camera and point mappings of a CIP, with their reprojection
+/// errors; returned by all of the point mapping operations
+#[derive(Debug, Serialize)]
+struct CipReport {
+    cip: usize,
+    camera: CameraInstance,
+    /// Total of the (weighted) squared reprojection errors
+    total_error: f64,
+    /// Error returned by a locate or orient operation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fit_error: Option<f64>,
+    mappings: Vec<PmReport>,
+}
+
+//ip CipReport
+impl CipReport {
+    //cp of_cip
+    fn of_cip(project: &Project, cip: usize, fit_error: Option<f64>) -> Self {
+        let cip_r = project.cip(cip).borrow();
+        let camera = cip_r.camera_ref();
+        let pms = cip_r.pms_ref();
+        let mappings = pms
+            .mappings()
+            .iter()
+            .map(|pm| {
+                let reproj = pm.get_mapped_dpxy(&*camera);
+                PmReport {
+                    name: pm.name().into(),
+                    screen: [pm.screen()[0], pm.screen()[1]],
+                    error: pm.error(),
+                    model_error: pm.model_error(),
+                    reproj: reproj.map(|d| [d[0], d[1]]),
+                    reproj_error: reproj.map(|d| d.length()),
+                }
+            })
+            .collect();
+        Self {
+            cip,
+            camera: camera.clone(),
+            total_error: pms.total_error(&*camera),
+            fit_error,
+            mappings,
+        }
+    }
+}
+
+//a Helpers
+//fp set_json_response
+pub(crate) fn set_json_response<T: Serialize>(
+    server: &HttpServer<ProjectSet>,
+    response: &mut HttpResponse,
+    value: &T,
+) -> Result<()> {
+    response.content = serde_json::to_string(value)?.into_bytes();
+    response.mime_type = server.mime_type("json");
+    response.resp_type = HttpResponseType::FileRead;
+    response.set_cache_control("no-cache");
+    Ok(())
+}
+
+//fi reply_error
+/// Set an error response for a request that was understood but could
+/// not be carried out
+fn reply_error(response: &mut HttpResponse, code: u16, message: String) -> Result<()> {
+    response.set_error(code, &message);
+    Ok(())
+}
+
+//fi nps_list
+fn nps_list(project: &Project) -> Vec<NpDesc> {
+    let nps = project.nps_ref();
+    let mut list: Vec<NpDesc> = nps
+        .iter()
+        .map(|(name, np)| {
+            let model = np.opt_model();
+            NpDesc {
+                name: name.clone(),
+                color: Some(*np.color()),
+                model: model.map(|(m, _)| [m[0], m[1], m[2]]),
+                error: model.map_or(0.0, |(_, e)| e),
+            }
+        })
+        .collect();
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    list
+}
+
+//a ProjectSet edit methods
+//ip ProjectSet - editing
+impl ProjectSet {
+    //mi http_nps
+    /// List (GET), add (POST), move (PUT) or delete (DELETE, with
+    /// 'np=<name>') named points
+    ///
+    /// Deleting a named point also deletes its mappings in every CIP;
+    /// the response is the updated list of named points
+    pub(crate) fn http_nps(
+        &self,
+        server: &HttpServer<Self>,
+        request: &HttpRequest,
+        content: &[u8],
+        response: &mut HttpResponse,
+        pd: &ProjectDecode,
+    ) -> Result<()> {
+        let up = self.projects[pd.idx].ensure_loaded()?;
+        let p = up.as_ref();
+        match request.req_type {
+            HttpRequestType::Get => {}
+            HttpRequestType::Post => {
+                let np_desc: NpDesc = match serde_json::from_slice(content) {
+                    Ok(np_desc) => np_desc,
+                    Err(e) => return reply_error(response, 400, format!("Bad named point: {e}")),
+                };
+                if p.nps_ref().get_pt(&np_desc.name).is_some() {
+                    return reply_error(
+                        response,
+                        409,
+                        format!("Named point {} already exists", np_desc.name),
+                    );
+                }
+                let model = np_desc.model.map(|m| (m.into(), np_desc.error));
+                let edit = ProjectEdit::add_named_point(
+                    np_desc.name,
+                    np_desc.color.unwrap_or_default(),
+                    model,
+                );
+                p.apply_edit(edit)?;
+            }
+            HttpRequestType::Put => {
+                let np_desc: NpDesc = match serde_json::from_slice(content) {
+                    Ok(np_desc) => np_desc,
+                    Err(e) => return reply_error(response, 400, format!("Bad named point: {e}")),
+                };
+                let model = np_desc.model.map(|m| (m.into(), np_desc.error));
+                let edit = match ProjectEdit::move_named_point(p, &np_desc.name, model) {
+                    Ok(edit) => edit,
+                    Err(e) => return reply_error(response, 404, e.to_string()),
+                };
+                p.apply_edit(edit)?;
+            }
+            HttpRequestType::Delete => {
+                if pd.nps.is_empty() {
+                    return reply_error(response, 400, "No named point to delete".into());
+                }
+                for name in &pd.nps {
+                    let edit = match ProjectEdit::remove_named_point(p, name) {
+                        Ok(edit) => edit,
+                        Err(e) => return reply_error(response, 404, e.to_string()),
+                    };
+                    p.apply_edit(edit)?;
+                }
+            }
+            _ => {
+                return reply_error(response, 405, "Bad request type for named points".into());
+            }
+        }
+        set_json_response(server, response, &nps_list(p))
+    }
+
+    //mi http_pms
+    /// List (GET), add (POST), move (PUT) or delete (DELETE, with
+    /// 'np=<name>') the point mappings of a CIP
+    ///
+    /// The response is the [CipReport] for the CIP
+    pub(crate) fn http_pms(
+        &self,
+        server: &HttpServer<Self>,
+        request: &HttpRequest,
+        content: &[u8],
+        response: &mut HttpResponse,
+        pd: &ProjectDecode,
+    ) -> Result<()> {
+        let cip = pd.cip().unwrap_or_default();
+        let up = self.projects[pd.idx].ensure_loaded()?;
+        let p = up.as_ref();
+        if cip >= p.ncips() {
+            return reply_error(response, 404, format!("Cip {cip} out of range"));
+        }
+        match request.req_type {
+            HttpRequestType::Get => {}
+            HttpRequestType::Post | HttpRequestType::Put => {
+                let pm_desc: PmDesc = match serde_json::from_slice(content) {
+                    Ok(pm_desc) => pm_desc,
+                    Err(e) => return reply_error(response, 400, format!("Bad point mapping: {e}")),
+                };
+                if p.nps_ref().get_pt(&pm_desc.name).is_none() {
+                    return reply_error(
+                        response,
+                        404,
+                        format!("Named point {} not found", pm_desc.name),
+                    );
+                }
+                let existing = p
+                    .cip(cip)
+                    .borrow()
+                    .pms_ref()
+                    .mapping_index_of_name(&pm_desc.name);
+                let screen = pm_desc.screen.into();
+                let edit = match (request.req_type, existing) {
+                    (HttpRequestType::Post, Some(_)) => {
+                        return reply_error(
+                            response,
+                            409,
+                            format!("Named point {} is already mapped", pm_desc.name),
+                        );
+                    }
+                    (HttpRequestType::Put, None) => {
+                        return reply_error(
+                            response,
+                            404,
+                            format!("Named point {} is not mapped", pm_desc.name),
+                        );
+                    }
+                    (_, Some(_)) => {
+                        ProjectEdit::move_mapping(p, cip, &pm_desc.name, screen, pm_desc.error)?
+                    }
+                    _ => ProjectEdit::add_mapping(pm_desc.name, cip, screen, pm_desc.error),
+                };
+                p.apply_edit(edit)?;
+            }
+            HttpRequestType::Delete => {
+                if pd.nps.is_empty() {
+                    return reply_error(response, 400, "No point mapping to delete".into());
+                }
+                for name in &pd.nps {
+                    let edit = match ProjectEdit::remove_mapping(p, cip, name) {
+                        Ok(edit) => edit,
+                        Err(e) => return reply_error(response, 404, e.to_string()),
+                    };
+                    p.apply_edit(edit)?;
+                }
+            }
+            _ => {
+                return reply_error(response, 405, "Bad request type for point mappings".into());
+            }
+        }
+        set_json_response(server, response, &CipReport::of_cip(p, cip, None))
+    }
+
+    //mi http_cip_locate
+    pub(crate) fn http_cip_locate(
+        &self,
+        server: &HttpServer<Self>,
+        request: &HttpRequest,
+        content: &[u8],
+        response: &mut HttpResponse,
+        pd: &ProjectDecode,
+    ) -> Result<()> {
+        self.http_cip_locate_orient(server, request, content, response, pd, false)
+    }
+
+    //mi http_cip_orient
+    pub(crate) fn http_cip_orient(
+        &self,
+        server: &HttpServer<Self>,
+        request: &HttpRequest,
+        content: &[u8],
+        response: &mut HttpResponse,
+        pd: &ProjectDecode,
+    ) -> Result<()> {
+        self.http_cip_locate_orient(server, request, content, response, pd, true)
+    }
+
+    //mi http_cip_locate_orient
+    /// Locate (or, if `orient` is true, orient) the camera of a CIP
+    /// using its point mappings
+    ///
+    /// When run as a job, locating reports its progress and may be
+    /// cancelled; the project is not locked while the camera location
+    /// is searched for, so that other requests on it may proceed
+    ///
+    /// The mappings used are those of the 'np' arguments (all if none
+    /// are given) whose named point model error is less than
+    /// 'max_error' (default 10.0); locate uses at most 'max_pairs'
+    /// (default 100) pairs of mappings
+    fn http_cip_locate_orient(
+        &self,
+        server: &HttpServer<Self>,
+        _request: &HttpRequest,
+        _content: &[u8],
+        response: &mut HttpResponse,
+        pd: &ProjectDecode,
+        orient: bool,
+    ) -> Result<()> {
+        let cip = pd.cip().unwrap_or_default();
+        let max_pairs = pd.max_pairs.unwrap_or(100);
+        let max_np_error = pd.max_error.unwrap_or(10.0);
+        let mls = {
+            let up = self.projects[pd.idx].ensure_loaded()?;
+            let p = up.as_ref();
+            if cip >= p.ncips() {
+                return reply_error(response, 404, format!("Cip {cip} out of range"));
+            }
+            let pms_n: Vec<usize> = p
+                .cip(cip)
+                .borrow()
+                .pms_ref()
+                .mappings()
+                .iter()
+                .enumerate()
+                .filter(|(_, pm)| pm.is_mapped())
+                .filter(|(_, pm)| pd.nps.is_empty() || pd.nps.iter().any(|n| n == pm.name()))
+                .map(|(n, _)| n)
+                .collect();
+            let min_mappings = if orient { 2 } else { 3 };
+            if pms_n.len() < min_mappings {
+                return reply_error(
+                    response,
+                    422,
+                    format!(
+                        "Required at least {min_mappings} mapped points, but found {}",
+                        pms_n.len()
+                    ),
+                );
+            }
+
+            let filter =
+                |n, pm: &PointMapping| pms_n.contains(&n) && pm.model_error() < max_np_error;
+            if orient {
+                Job::check_current(0.0, "orienting the camera")?;
+                let from = Pose::of_camera(&p.cip(cip).borrow().camera_ref());
+                let fit_error = p
+                    .cip(cip)
+                    .borrow_mut()
+                    .orient_camera_using_model_directions(filter);
+                let fit_error = match fit_error {
+                    Ok(e) => e,
+                    Err(e) => return reply_error(response, 422, e.to_string()),
+                };
+                return Self::apply_pose(server, response, p, cip, from, fit_error);
+            }
+            match p.cip(cip).borrow().model_lines(filter, max_pairs) {
+                Ok(mls) => mls,
+                Err(e) => return reply_error(response, 422, e.to_string()),
+            }
+        };
+
+        // The search uses only the model lines, so the project is not
+        // locked while it runs
+        if let Some(job) = Job::current() {
+            job.set_progress(0.0, &format!("Using {} model lines", mls.num_lines()));
+        }
+        let (location, fit_error) = match Cip::find_location(&mls, &Job::progress_of_current) {
+            Ok(l) => l,
+            Err(e) => return reply_error(response, 422, e.to_string()),
+        };
+        Job::check_current(1.0, "locating the camera")?;
+
+        let up = self.projects[pd.idx].ensure_loaded()?;
+        let p = up.as_ref();
+        if cip >= p.ncips() {
+            return reply_error(response, 404, format!("Cip {cip} out of range"));
+        }
+        let from = Pose::of_camera(&p.cip(cip).borrow().camera_ref());
+        p.cip(cip).borrow().camera_mut().set_position(&location);
+        Self::apply_pose(server, response, p, cip, from, fit_error)
+    }
+
+    //fi apply_pose
+    /// Record the change of pose of the camera of a CIP (if it has
+    /// changed) as an edit, and respond with the report for the CIP
+    fn apply_pose(
+        server: &HttpServer<Self>,
+        response: &mut HttpResponse,
+        p: &Project,
+        cip: usize,
+        from: Pose,
+        fit_error: f64,
+    ) -> Result<()> {
+        let to = Pose::of_camera(&p.cip(cip).borrow().camera_ref());
+        if to != from {
+            p.record_edit(ProjectEdit::SetPose { cip, from, to });
+        }
+        set_json_response(
+            server,
+            response,
+            &CipReport::of_cip(p, cip, Some(fit_error)),
+        )
+    }
+
+    //mi http_history
+    /// Get the edit history (GET 'history'), or undo or redo the most
+    /// recent edit (POST 'undo' or 'redo')
+    ///
+    /// The response is the list of edits that may be undone and
+    /// redone, with descriptions
+    pub(crate) fn http_history(
+        &self,
+        server: &HttpServer<Self>,
+        request: &HttpRequest,
+        _content: &[u8],
+        response: &mut HttpResponse,
+        idx: usize,
+    ) -> Result<()> {
+        let up = self.projects[idx].ensure_loaded()?;
+        let p = up.as_ref();
+        let result = {
+            if request.action_is("undo") {
+                p.undo()
+            } else if request.action_is("redo") {
+                p.redo()
+            } else {
+                Ok(None)
+            }
+        };
+        if let Err(e) = result {
+            return reply_error(response, 409, e.to_string());
+        }
+        let history = p.history();
+        let describe = |edits: &[ProjectEdit]| -> Vec<String> {
+            edits.iter().map(|e| e.to_string()).collect()
+        };
+        let report = serde_json::json!({
+            "undo": describe(history.undo_edits()),
+            "redo": describe(history.redo_edits()),
+        });
+        set_json_response(server, response, &report)
+    }
+
+    //mi http_persist_project
+    /// Save the in-memory project (and its edit history) to its file
+    pub(crate) fn http_persist_project(
+        &self,
+        _server: &HttpServer<Self>,
+        _request: &HttpRequest,
+        _content: &[u8],
+        response: &mut HttpResponse,
+        idx: usize,
+    ) -> Result<()> {
+        if let Some(result) = self.projects[idx].save() {
+            result?;
+        }
+        response.set_status(204);
+        Ok(())
+    }
+}
+
+//a Tests
+#[cfg(test)]
+mod test {
+    use ic_http::HttpServerExt;
+    use ic_kernel::{Accelerator, Kernels, KernelsConfig};
+
+    use super::*;
+
+    //fi server
+    /// Create a server with a single empty project, named 'my proj'
+    fn server() -> (tempfile::TempDir, HttpServer<ProjectSet>) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("my proj.json");
+        std::fs::write(&path, Project::default().to_json(true).unwrap()).unwrap();
+        let config = KernelsConfig::default().with_accelerator(Accelerator::Cpu);
+        let mut project_set = ProjectSet::new(Kernels::of_config(&config).unwrap());
+        project_set.add_project(path.into_boxed_path()).unwrap();
+        let server = HttpServer::new(false, dir.path(), project_set);
+        (dir, server)
+    }
+
+    //fi request
+    /// Make a request of the server, returning the status code and the
+    /// content of the response
+    fn request(server: &HttpServer<ProjectSet>, req: &str, content: &str) -> (u16, String) {
+        let req = format!(
+            "{req} HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            content.len()
+        );
+        let (request, _) = HttpRequest::parse_request(req.as_bytes()).unwrap();
+        let mut response = HttpResponse::default();
+        assert!(server.data().set_http_response(
+            server,
+            &request,
+            content.as_bytes(),
+            &mut response
+        ));
+        let content = String::from_utf8(response.content.clone()).unwrap();
+        (response.status_code(), content)
+    }
+
+    //fi json
+    fn json(content: &str) -> serde_json::Value {
+        serde_json::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn test_nps() {
+        let (_dir, server) = server();
+        let (status, content) = request(&server, "GET /project/my%20proj?nps", "");
+        assert_eq!((status, content.as_str()), (200, "[]"));
+
+        let np = r#"{"name": "big ben", "model": [1.0, 2.0, 3.0], "error": 0.5}"#;
+        let (status, content) = request(&server, "POST /project/my%20proj?nps", np);
+        assert_eq!(status, 200);
+        assert_eq!(json(&content)[0]["name"], "big ben");
+        assert_eq!(json(&content)[0]["model"][2], 3.0);
+        assert_eq!(request(&server, "POST /project/my%20proj?nps", np).0, 409);
+        assert_eq!(request(&server, "POST /project/my%20proj?nps", "{").0, 400);
+
+        let np = r#"{"name": "big ben", "model": [4.0, 5.0, 6.0]}"#;
+        let (status, content) = request(&server, "PUT /project/my%20proj?nps", np);
+        assert_eq!(status, 200);
+        assert_eq!(json(&content)[0]["model"][0], 4.0);
+        let np = r#"{"name": "big", "model": [4.0, 5.0, 6.0]}"#;
+        assert_eq!(request(&server, "PUT /project/my%20proj?nps", np).0, 404);
+
+        // Names in arguments are percent-decoded
+        let delete = "DELETE /project/my%20proj?nps&np=big%20ben";
+        let (status, content) = request(&server, delete, "");
+        assert_eq!((status, content.as_str()), (200, "[]"));
+        assert_eq!(request(&server, delete, "").0, 404);
+        assert_eq!(request(&server, "DELETE /project/my%20proj?nps", "").0, 400);
+
+        let (status, content) = request(&server, "GET /project/my%20proj?history", "");
+        assert_eq!(status, 200);
+        assert_eq!(json(&content)["undo"].as_array().unwrap().len(), 3);
+        let (status, content) = request(&server, "POST /project/my%20proj?undo", "");
+        assert_eq!(status, 200);
+        assert_eq!(json(&content)["redo"].as_array().unwrap().len(), 1);
+        let (_, content) = request(&server, "GET /project/my%20proj?nps", "");
+        assert_eq!(json(&content)[0]["name"], "big ben");
+    }
+
+    #[test]
+    fn test_not_found() {
+        let (_dir, server) = server();
+        assert_eq!(request(&server, "GET /project/my%20pro?nps", "").0, 404);
+        assert_eq!(
+            request(&server, "GET /project/my%20proj?pms&cip=0", "").0,
+            404
+        );
+        assert_eq!(request(&server, "PATCH /project/my%20proj?nps", "").0, 405);
+        let (status, content) = request(&server, "POST /project/my%20proj?locate&cip=0", "");
+        assert_eq!(status, 404, "{content}");
+    }
+}
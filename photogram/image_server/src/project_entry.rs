@@ -5,7 +5,7 @@ use std::path::Path;
 use std::sync::{Mutex, MutexGuard};
 
 use ic_base::{json, Result};
-use ic_project::Project;
+use ic_project::{Project, ProjectArchive};
 
 //a ProjectPath
 //tp ProjectPath
@@ -45,25 +45,39 @@ impl ProjectWrap {
     //mp of_json
     /// Set to be a project from some Json
     fn of_json(&mut self, project_json: &str) -> Result<()> {
-        self.0 = json::from_json("project", project_json)?;
+        self.0 = Project::from_json(project_json)?;
         Ok(())
     }
 
     //mp load
-    /// Load the project from a path - it drops the old project
+    /// Load the project, and its edit history, from a path - it drops
+    /// the old project
+    ///
+    /// The path may be of a project archive, in which case any images
+    /// included in it are extracted to a temporary directory
     fn load<P: AsRef<Path> + std::fmt::Display>(&mut self, path: P) -> Result<()> {
-        let project_json = json::read_file(path)?;
-        self.of_json(&project_json)
+        if ProjectArchive::is_archive(&path) {
+            self.0 = ProjectArchive::load(&path)?;
+        } else {
+            let project_json = json::read_file(&path)?;
+            self.of_json(&project_json)?;
+        }
+        self.0.load_history(path)
     }
 
     //mp save
-    /// Save the project to a path
+    /// Save the project, and its edit history, to a path
+    ///
+    /// Project archives are read-only, and cannot be saved
     fn save<P: AsRef<Path> + std::fmt::Display>(&self, path: P) -> Result<()> {
+        if ProjectArchive::is_archive(&path) {
+            return Err(format!("Project archive {path} is read-only, and cannot be saved").into());
+        }
         let mut f = File::create(&path).map_err(|e| format!("Failed to open file {path}: {e}"))?;
         let json = self.0.to_json(true)?;
         f.write(json.as_bytes())
             .map_err(|e| format!("Failed to write Json to {path}: {e}"))?;
-        Ok(())
+        self.0.save_history(path)
     }
 
     //zz All done
@@ -124,6 +138,13 @@ impl NamedProject {
         })
     }
 
+    //ap is_read_only
+    /// Return true if the project cannot be saved, as it is a project
+    /// archive
+    pub fn is_read_only(&self) -> bool {
+        ProjectArchive::is_archive(&self.path)
+    }
+
     //ap is_mapped
     #[allow(dead_code)]
     pub fn is_mapped(&self) -> bool {
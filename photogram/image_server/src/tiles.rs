@@ -0,0 +1,333 @@
+//a Imports
+use std::path::Path;
+
+use ic_base::{Point2D, Result};
+use ic_cache::CacheRef;
+use ic_camera::CameraProjection;
+use ic_http::{HttpRequest, HttpResponse, HttpResponseType, HttpServer};
+use ic_image::{Color, Image, ImageRgb8};
+
+use crate::image_cache::ImageCacheEntry;
+use crate::project_decode::ProjectDecode;
+use crate::ProjectSet;
+
+//a Constants
+//ci TILE_SIZE
+/// Width and height of the tiles of an image pyramid
+pub const TILE_SIZE: u32 = 256;
+
+//ci TILE_MAX_AGE
+/// Time in seconds for which clients may cache a tile without an
+/// overlay; the images of a project are not expected to change
+const TILE_MAX_AGE: usize = 3600;
+
+//ci OVERLAY_MARGIN
+/// Margin around a tile within which overlay markers are drawn
+const OVERLAY_MARGIN: u32 = 16;
+
+//a TilePyramid
+//tp TilePyramid
+/// The levels of a Deep Zoom image pyramid for an image
+///
+/// The top level has the full image size; each level below it is half
+/// the size (rounded up) of the level above, down to level 0 which is
+/// a single pixel. Each level is split into tiles of [TILE_SIZE]
+/// pixels square, the last row and column of which may be smaller.
+///
+/// Tiles are also addressed as XYZ tiles, with the level as 'z' and
+/// the column and row as 'x' and 'y'
+#[derive(Debug, Clone, Copy)]
+pub struct TilePyramid {
+    width: u32,
+    height: u32,
+    max_level: u32,
+}
+
+//ip TilePyramid
+impl TilePyramid {
+    //cp of_size
+    pub fn of_size((width, height): (u32, u32)) -> Self {
+        let max_dim = width.max(height).max(1);
+        let max_level = u32::BITS - (max_dim - 1).leading_zeros();
+        Self {
+            width,
+            height,
+            max_level,
+        }
+    }
+
+    //ap max_level
+    pub fn max_level(&self) -> u32 {
+        self.max_level
+    }
+
+    //ap scale
+    /// The number of full-size image pixels per pixel at a level
+    pub fn scale(&self, level: u32) -> f64 {
+        (1_u64 << (self.max_level - level)) as f64
+    }
+
+    //ap level_size
+    pub fn level_size(&self, level: u32) -> (u32, u32) {
+        let shift = self.max_level - level;
+        let round = (1_u64 << shift) - 1;
+        let w = ((self.width as u64 + round) >> shift).max(1);
+        let h = ((self.height as u64 + round) >> shift).max(1);
+        (w as u32, h as u32)
+    }
+
+    //ap num_tiles
+    /// The number of columns and rows of tiles at a level
+    pub fn num_tiles(&self, level: u32) -> (u32, u32) {
+        let (w, h) = self.level_size(level);
+        (w.div_ceil(TILE_SIZE), h.div_ceil(TILE_SIZE))
+    }
+
+    //ap tile_origin
+    /// The pixel position within its level of the top left of a tile,
+    /// if the tile exists
+    pub fn tile_origin(&self, level: u32, col: u32, row: u32) -> Option<(u32, u32)> {
+        if level > self.max_level {
+            return None;
+        }
+        let (cols, rows) = self.num_tiles(level);
+        (col < cols && row < rows).then_some((col * TILE_SIZE, row * TILE_SIZE))
+    }
+
+    //mp dzi
+    /// The Deep Zoom descriptor of the pyramid for tiles of a format
+    pub fn dzi(&self, format: &str) -> String {
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<Image xmlns="http://schemas.microsoft.com/deepzoom/2008" Format="{format}" Overlap="0" TileSize="{TILE_SIZE}">
+  <Size Width="{}" Height="{}"/>
+</Image>
+"#,
+            self.width, self.height
+        )
+    }
+}
+
+//a ProjectSet tile methods
+//ip ProjectSet
+impl ProjectSet {
+    //mi level_image
+    /// Get a level of the image pyramid of an image
+    ///
+    /// The top level is the source image; the levels below it are
+    /// derived from the level above, and cached, so that each is
+    /// created at most once
+    fn level_image(&self, path: &Path, pyramid: &TilePyramid, level: u32) -> Result<CacheRef> {
+        if level == pyramid.max_level() {
+            return self.image_cache.src_image(path);
+        }
+        let source = self.image_cache.source_hash(path)?;
+        let name = format!("level{level}");
+        self.image_cache.derived(source, &name, || {
+            let above_ref = self.level_image(path, pyramid, level + 1)?;
+            let above = ImageCacheEntry::cr_as_rgb8(&above_ref);
+            Ok(ImageCacheEntry::Rgb(above.half_size()))
+        })
+    }
+
+    //mi region_image
+    /// Get a region of a level of the image pyramid of an image,
+    /// clipped to the level
+    fn region_image(
+        &self,
+        path: &Path,
+        pyramid: &TilePyramid,
+        level: u32,
+        (x, y): (u32, u32),
+        size: u32,
+    ) -> Result<ImageRgb8> {
+        let img_ref = self.level_image(path, pyramid, level)?;
+        let img = ImageCacheEntry::cr_as_rgb8(&img_ref);
+        Ok(img.sub_image(x, y, size, size))
+    }
+
+    //mi draw_tile_overlay
+    /// Draw the named points of the project reprojected by the camera
+    /// of the CIP onto a tile, and the point mappings of the CIP
+    ///
+    /// Each named point is drawn as a cross in its color at its
+    /// reprojected position; each point mapping is drawn as a white X
+    /// at its screen position with a line to the reprojected position
+    /// of its named point. If 'np' arguments are given then only
+    /// those named points are drawn.
+    fn draw_tile_overlay(
+        &self,
+        tile: &mut ImageRgb8,
+        pd: &ProjectDecode,
+        scale: f64,
+        (x, y): (u32, u32),
+    ) -> Result<()> {
+        let cip = pd.cip().unwrap_or_default();
+        let up = self.projects[pd.idx].ensure_loaded()?;
+        let p = up.as_ref();
+        let cip_r = p.cip(cip).borrow();
+        let camera = cip_r.camera_ref();
+        let to_tile = |px: &Point2D| -> Point2D {
+            [px[0] / scale - x as f64, px[1] / scale - y as f64].into()
+        };
+        let included = |name: &str| pd.nps.is_empty() || pd.nps.iter().any(|n| n == name);
+
+        let white: Color = [255, 255, 255, 255].into();
+        for pm in cip_r.pms_ref().mappings() {
+            if !included(pm.name()) {
+                continue;
+            }
+            let screen = to_tile(pm.screen());
+            tile.draw_x(&screen, 4.0, &white);
+            if pm.is_mapped() {
+                let reprojected = to_tile(&camera.world_xyz_to_px_abs_xy(&pm.model()));
+                tile.draw_line(&screen, &reprojected, &white);
+            }
+        }
+        for (name, np) in p.nps_ref().iter() {
+            if np.is_unmapped() || !included(name) {
+                continue;
+            }
+            let reprojected = to_tile(&camera.world_xyz_to_px_abs_xy(&np.model().0));
+            tile.draw_cross(&reprojected, 6.0, np.color());
+        }
+        Ok(())
+    }
+
+    //mp http_cip_tile
+    /// Get a tile of the image pyramid of the image of a CIP
+    ///
+    /// The tile is given by 'level', 'col' and 'row' (or 'z', 'x' and
+    /// 'y'); it is a JPEG unless 'format=png' is given. If 'overlay'
+    /// is given then the named points and point mappings are drawn on
+    /// the tile.
+    ///
+    /// Tiles without an overlay are cached once encoded, including in
+    /// the disk cache if there is one.
+    pub(crate) fn http_cip_tile(
+        &self,
+        server: &HttpServer<Self>,
+        _request: &HttpRequest,
+        _content: &[u8],
+        response: &mut HttpResponse,
+        pd: &ProjectDecode,
+    ) -> Result<()> {
+        let format = pd.format.as_deref().unwrap_or("jpeg");
+        let format = match format {
+            "jpg" | "jpeg" => "jpeg",
+            "png" => "png",
+            _ => {
+                response.set_error(400, &format!("Unsupported tile format {format}"));
+                return Ok(());
+            }
+        };
+        let (Some(level), Some(col), Some(row)) = (pd.level, pd.col, pd.row) else {
+            response.set_error(400, "Tile requires a level, col and row");
+            return Ok(());
+        };
+
+        let path = {
+            let cip = pd.cip().unwrap_or_default();
+            let up = self.projects[pd.idx].ensure_loaded()?;
+            let p = up.as_ref();
+            if cip >= p.ncips() {
+                response.set_error(404, "Cip out of range");
+                return Ok(());
+            }
+            let cip_r = p.cip(cip).borrow();
+            self.image_root.as_path().join(cip_r.image_filename())
+        };
+        let pyramid = TilePyramid::of_size(self.image_cache.src_size(&path)?);
+        let Some(origin) = pyramid.tile_origin(level, col, row) else {
+            response.set_error(404, &format!("No tile {level}/{col}/{row}"));
+            return Ok(());
+        };
+
+        if pd.overlay {
+            // Draw on a larger region so that markers near the edge of
+            // the tile are not lost
+            let margin = (OVERLAY_MARGIN.min(origin.0), OVERLAY_MARGIN.min(origin.1));
+            let region_origin = (origin.0 - margin.0, origin.1 - margin.1);
+            let size = TILE_SIZE + 2 * OVERLAY_MARGIN;
+            let mut region = self.region_image(&path, &pyramid, level, region_origin, size)?;
+            self.draw_tile_overlay(&mut region, pd, pyramid.scale(level), region_origin)?;
+            let tile = region.sub_image(margin.0, margin.1, TILE_SIZE, TILE_SIZE);
+            response.content = tile.encode(format)?;
+            response.set_cache_control("no-cache");
+        } else {
+            let hash = self
+                .image_cache
+                .source_hash(&path)?
+                .with_str("tile")
+                .with_str(&format!("{level}/{col}/{row}.{format}"));
+            let tile_ref = self.image_cache.encoded(hash, || {
+                let tile = self.region_image(&path, &pyramid, level, origin, TILE_SIZE)?;
+                tile.encode(format)
+            })?;
+            response.content = ImageCacheEntry::cr_as_encoded(&tile_ref).to_vec();
+            response.set_cache_control(&format!("max-age={TILE_MAX_AGE}"));
+        }
+        response.mime_type = server.mime_type(format);
+        response.resp_type = HttpResponseType::FileRead;
+        Ok(())
+    }
+
+    //mp http_cip_dzi
+    /// Get the Deep Zoom descriptor for the image of a CIP
+    pub(crate) fn http_cip_dzi(
+        &self,
+        server: &HttpServer<Self>,
+        _request: &HttpRequest,
+        _content: &[u8],
+        response: &mut HttpResponse,
+        pd: &ProjectDecode,
+    ) -> Result<()> {
+        let cip = pd.cip().unwrap_or_default();
+        let up = self.projects[pd.idx].ensure_loaded()?;
+        let p = up.as_ref();
+        if cip >= p.ncips() {
+            response.set_error(404, "Cip out of range");
+            return Ok(());
+        }
+        let path = self
+            .image_root
+            .as_path()
+            .join(p.cip(cip).borrow().image_filename());
+        let pyramid = TilePyramid::of_size(self.image_cache.src_size(&path)?);
+        let format = match pd.format.as_deref() {
+            Some("png") => "png",
+            _ => "jpeg",
+        };
+        response.content = pyramid.dzi(format).into_bytes();
+        response.mime_type = server.mime_type("xml");
+        response.resp_type = HttpResponseType::FileRead;
+        Ok(())
+    }
+}
+
+//a Tests
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pyramid() {
+        let pyramid = TilePyramid::of_size((6000, 4000));
+        assert_eq!(pyramid.max_level(), 13);
+        assert_eq!(pyramid.level_size(13), (6000, 4000));
+        assert_eq!(pyramid.level_size(12), (3000, 2000));
+        assert_eq!(pyramid.level_size(8), (188, 125));
+        assert_eq!(pyramid.level_size(0), (1, 1));
+        assert_eq!(pyramid.num_tiles(13), (24, 16));
+        assert_eq!(pyramid.num_tiles(8), (1, 1));
+        assert_eq!(pyramid.tile_origin(13, 23, 15), Some((5888, 3840)));
+        assert_eq!(pyramid.tile_origin(13, 24, 0), None);
+        assert_eq!(pyramid.tile_origin(14, 0, 0), None);
+
+        let pyramid = TilePyramid::of_size((256, 1));
+        assert_eq!(pyramid.max_level(), 8);
+        assert_eq!(pyramid.num_tiles(8), (1, 1));
+        let pyramid = TilePyramid::of_size((1, 1));
+        assert_eq!(pyramid.max_level(), 0);
+    }
+}
@@ -187,7 +187,7 @@ fn locate_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     }
 
     //cb Create ModelLineSet
-    let mut mls = ModelLineSet::new(cmd_args.camera().clone());
+    let mut mls = ModelLineSet::new(cmd_args.camera().clone())?;
 
     for n0 in &closest_n {
         let pm0 = &pms.mappings()[*n0];
@@ -411,7 +411,7 @@ fn lens_calibrate_fn(cmd_args: &mut CmdArgs) -> CmdResult {
 
     let mut camera_lens = cmd_args.camera().lens().clone();
     camera_lens.set_polys(lens_poly);
-    cmd_args.camera_mut().set_lens(camera_lens);
+    cmd_args.set_lens(camera_lens)?;
 
     cmd_args.write_outputs()?;
 
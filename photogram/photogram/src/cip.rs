@@ -7,9 +7,9 @@ use ic_base::{Ray, Rrc};
 use ic_camera::CameraProjection;
 use ic_image::Image;
 use ic_mapping::PointMapping;
-use ic_project::Cip;
+use ic_project::{Cip, Pose};
 
-use crate::cmd::{CmdArgs, CmdResult};
+use crate::cmd::{cmd_ok, CmdArgs, CmdResult, CmdValue};
 
 //a Help
 //hi IMAGE_LONG_HELP
@@ -47,6 +47,12 @@ minimized. This provides the initial best location for the camera.
 This position is then adjusted by small amounts, to reduce the total
 error seen by *all* of the surfaces.
 
+If the GPS position of the camera is given (with --gps) then the
+project must have a fitted frame; the search is limited to positions
+within --gps_radius metres of the GPS position, and the GPS position
+itself is also adjusted to reduce the error, the better of the two
+being used.
+
 ";
 
 //hi ORIENT_LONG_HELP
@@ -107,6 +113,7 @@ fn locate_cmd() -> CommandBuilder<CmdArgs> {
     CmdArgs::add_arg_named_point(&mut build, (None, true));
     CmdArgs::add_arg_max_pairs(&mut build, Some("100"));
     CmdArgs::add_arg_max_error(&mut build, Some("10.0"));
+    CmdArgs::add_arg_gps(&mut build);
 
     build
 }
@@ -123,7 +130,24 @@ fn locate_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     let max_np_error = cmd_args.max_error();
 
     let filter = |n, pm: &PointMapping| (pms_n.contains(&n) && pm.model_error() < max_np_error);
-    cmd_args.cip().borrow_mut().locate(filter, max_pairs)?;
+    let from = Pose::of_camera(&cmd_args.cip().borrow().camera_ref());
+    if let Some((gps, radius)) = cmd_args.gps() {
+        let Some(frame) = cmd_args.project().frame() else {
+            return Err("Locating a camera near a GPS position requires a project frame".into());
+        };
+        let near = frame.geodetic_to_model(gps)?;
+        let distance = frame.metres_to_model(radius)?;
+        cmd_args.cip().borrow_mut().locate_near_with_progress(
+            filter,
+            max_pairs,
+            &near,
+            distance,
+            &|_| true,
+        )?;
+    } else {
+        cmd_args.cip().borrow_mut().locate(filter, max_pairs)?;
+    }
+    cmd_args.record_pose_change(from);
 
     let camera = cmd_args.cip().borrow().camera().borrow().clone();
     *cmd_args.camera_mut() = camera;
@@ -131,7 +155,7 @@ fn locate_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         eprintln!("{}", cmd_args.camera());
     });
     cmd_args.write_outputs()?;
-    cmd_args.output_camera()
+    cmd_args.output_located_camera(&pms_n)
 }
 
 //a orient
@@ -156,10 +180,12 @@ fn orient_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     let max_np_error = cmd_args.max_error();
 
     let filter = |n, pm: &PointMapping| (pms_n.contains(&n) && pm.model_error() < max_np_error);
+    let from = Pose::of_camera(&cmd_args.cip().borrow().camera_ref());
     let _total_error = cmd_args
         .cip()
         .borrow_mut()
         .orient_camera_using_model_directions(|n, _pm| pms_n.contains(&n))?;
+    cmd_args.record_pose_change(from);
 
     let camera = cmd_args.cip().borrow().camera().borrow().clone();
     *cmd_args.camera_mut() = camera;
@@ -167,7 +193,7 @@ fn orient_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         eprintln!("{}", cmd_args.camera());
     });
     cmd_args.write_outputs()?;
-    cmd_args.output_camera()
+    cmd_args.output_located_camera(&pms_n)
 }
 
 //a Image and image_patch commands
@@ -304,7 +330,7 @@ fn image_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         }
     }
     img.write(write_filename)?;
-    Ok("".into())
+    cmd_ok()
 }
 
 //fi image_patch_cmd
@@ -353,7 +379,7 @@ fn image_patch_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         }
     }
 
-    Ok("".into())
+    cmd_ok()
 }
 
 //a Create/show Rays
@@ -388,7 +414,7 @@ fn show_rays_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         let end = ray.start() + ray.direction() * camera.focus_distance();
         eprintln!("{} {end}", pm.name());
     }
-    Ok("".into())
+    cmd_ok()
 }
 
 //fi create_rays_cmd
@@ -420,7 +446,7 @@ fn create_rays_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         .filter(|(n, _pm_ray)| pms_n.contains(n))
         .map(|(_, (pm, ray))| (pm.name().to_owned(), ray))
         .collect();
-    Ok(serde_json::to_string_pretty(&named_rays)?)
+    CmdValue::new(&named_rays)
 }
 
 //a Interrogate (show_mappings etc)
@@ -444,13 +470,30 @@ fn show_mappings_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     for pm in pms.mappings() {
         pm.show_mapped_error(camera);
     }
-    nps.borrow().show_mappings(camera);
 
-    let te = pms.total_error(camera);
-    let we = pms.find_worst_error(camera).1;
-    println!("WE {we:.2} TE {te:.2}");
+    // The text is only printed without '--json', so that the JSON
+    // output can be parsed
+    let mut text = String::new();
+    for (name, np) in nps.borrow().iter() {
+        if np.is_unmapped() {
+            continue;
+        }
+        let (model, error) = np.model();
+        let camera_pxy = camera.world_xyz_to_px_abs_xy(&model);
+        text += &format!("{name} : {model}+-{error} maps to {camera_pxy}\n");
+    }
 
-    Ok("".into())
+    let te = pms.total_error(camera);
+    let (wn, we) = pms.find_worst_error(camera);
+    text += &format!("WE {we:.2} TE {te:.2}");
+
+    let worst = pms.mappings().get(wn).map(|pm| pm.name());
+    let value = serde_json::json!({
+        "total_error": te,
+        "worst_error": we,
+        "worst": worst,
+    });
+    Ok(CmdValue::new(&value)?.with_text(text))
 }
 
 //fi list_cmd
@@ -471,18 +514,21 @@ fn list_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     let pms = cmd_args.pms().borrow();
     let mappings = pms.mappings();
 
+    let mut listed = vec![];
+    let mut text = vec![];
     for i in pms_n {
         let m = &mappings[i];
-        println!(
+        listed.push(m);
+        text.push(format!(
             "{} : {} -> [{:.1}, {:.1}] @ {:.1}",
             m.name(),
             m.model(),
             m.screen()[0],
             m.screen()[1],
             m.error()
-        );
+        ));
     }
-    Ok("".into())
+    Ok(CmdValue::new(&listed)?.with_text(text.join("\n")))
 }
 
 //fi add_cmd
@@ -531,7 +577,7 @@ fn add_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     let n = cmd_args.project().ncips();
     cmd_args.project_mut().add_cip(cip.clone());
     let _ = cmd_args.set_cip(n);
-    Ok("".into())
+    Ok(CmdValue::new(&n)?.with_text(""))
 }
 
 //a CIP command
@@ -2,10 +2,11 @@
 
 use star_catalog::Catalog;
 
-use ic_base::{Point2D, Point3D, Ray, Result, Rrc};
+use ic_base::{Geodetic, PathSet, Point2D, Point3D, Ray, Result, Rrc};
 use ic_camera::CameraInstance;
 use ic_camera::{CalibrationMapping, CameraDatabase};
 use ic_image::Color;
+use ic_kernel::{Kernels, KernelsConfig};
 use ic_mapping::{NamedPointSet, PointMappingSet};
 use ic_project::{Cip, Project};
 use ic_stars::StarMapping;
@@ -15,6 +16,17 @@ use super::CmdArgs;
 //a CmdArgs accessors
 //ip CmdArgs accessors
 impl CmdArgs {
+    //mi kernels_config
+    pub fn kernels_config(&self) -> &KernelsConfig {
+        &self.kernels_config
+    }
+
+    //mi create_kernels
+    /// Create the kernels using the selected accelerator and shaders
+    pub fn create_kernels(&self) -> Result<Kernels> {
+        Ok(Kernels::of_config(&self.kernels_config)?)
+    }
+
     //mi project
     pub fn project(&self) -> &Project {
         &self.project
@@ -25,6 +37,11 @@ impl CmdArgs {
         self.pretty_json
     }
 
+    //mi path_set
+    pub fn path_set(&self) -> &PathSet {
+        &self.path_set
+    }
+
     //mi project_mut
     pub fn project_mut(&mut self) -> &mut Project {
         &mut self.project
@@ -50,6 +67,20 @@ impl CmdArgs {
         &self.cip
     }
 
+    //mi project_cip
+    /// The number of the CIP of the project that is the current CIP,
+    /// if it is one; edits of it may be recorded in the project
+    /// history
+    pub fn project_cip(&self) -> Option<usize> {
+        if self.cip_number < self.project.ncips()
+            && self.project.cip(self.cip_number).ptr_eq(&self.cip)
+        {
+            Some(self.cip_number)
+        } else {
+            None
+        }
+    }
+
     //mi np_names
     pub fn np_names(&self) -> &[String] {
         &self.np
@@ -158,11 +189,28 @@ impl CmdArgs {
         &self.kernels
     }
 
+    //mi pipeline
+    pub fn pipeline(&self) -> Option<&str> {
+        self.pipeline.as_deref()
+    }
+
     //mi kernel_size
+    /// The kernel size, which is 8 unless given explicitly
     pub fn kernel_size(&self) -> usize {
+        self.kernel_size.unwrap_or(8)
+    }
+
+    //mi explicit_kernel_size
+    /// The kernel size, if it was given explicitly
+    pub fn explicit_kernel_size(&self) -> Option<usize> {
         self.kernel_size
     }
 
+    //mi sigmas
+    pub fn sigmas(&self) -> (f64, f64) {
+        (self.sigma, self.sigma_b)
+    }
+
     //mi flags
     pub fn flags(&self) -> usize {
         self.flags
@@ -238,6 +286,22 @@ impl CmdArgs {
         self.use_deltas
     }
 
+    //mi dry_run
+    pub fn dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    //mi include_images
+    pub fn include_images(&self) -> bool {
+        self.include_images
+    }
+
+    //mi gps
+    /// The GPS position of the camera, and its accuracy in metres
+    pub fn gps(&self) -> Option<(&Geodetic, f64)> {
+        self.gps.as_ref().map(|gps| (gps, self.gps_radius))
+    }
+
     //mi poly_degree
     pub fn poly_degree(&self) -> usize {
         self.poly_degree
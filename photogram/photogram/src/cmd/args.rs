@@ -28,16 +28,60 @@ impl CmdArgs {
         );
     }
 
+    //mp add_arg_accelerator
+    pub fn add_arg_accelerator(build: &mut CommandBuilder<Self>) {
+        build.add_arg_string(
+            "accelerator",
+            None,
+            "Accelerator for kernels (auto, cpu, wgpu, vulkan, metal, dx12, gl or software)",
+            false.into(),
+            None,
+            CmdArgs::set_accelerator,
+        );
+    }
+
+    //mp add_arg_shader_path
+    pub fn add_arg_shader_path(build: &mut CommandBuilder<Self>) {
+        build.add_arg_string(
+            "shader_path",
+            None,
+            "Add a directory to search for kernel shaders (before the embedded shaders)",
+            (0,).into(),
+            None,
+            CmdArgs::add_shader_path,
+        );
+    }
+
     //mp add_arg_pretty_json
     pub fn add_arg_pretty_json(build: &mut CommandBuilder<Self>) {
         build.add_flag(
             "pretty_json",
-            Some('v'),
+            None,
             "Enable pretty_json output",
             CmdArgs::set_pretty_json,
         );
     }
 
+    //mp add_arg_dry_run
+    pub fn add_arg_dry_run(build: &mut CommandBuilder<Self>) {
+        build.add_flag(
+            "dry_run",
+            None,
+            "Show the differences that writing files would make, without writing them",
+            CmdArgs::set_dry_run,
+        );
+    }
+
+    //mp add_arg_no_backup
+    pub fn add_arg_no_backup(build: &mut CommandBuilder<Self>) {
+        build.add_flag(
+            "no_backup",
+            None,
+            "Do not keep a '.bak' copy of files that are overwritten",
+            CmdArgs::set_no_backup,
+        );
+    }
+
     //fp add_arg_kernel
     pub fn add_arg_kernel<I: Into<ArgCount>>(build: &mut CommandBuilder<Self>, arg_count: I) {
         build.add_arg_string(
@@ -50,6 +94,18 @@ impl CmdArgs {
         );
     }
 
+    //fp add_arg_pipeline
+    pub fn add_arg_pipeline(build: &mut CommandBuilder<Self>) {
+        build.add_arg_string(
+            "pipeline",
+            None,
+            "Kernel pipeline JSON file to run",
+            false.into(),
+            None,
+            CmdArgs::set_pipeline,
+        );
+    }
+
     //fp add_arg_nps
     pub fn add_arg_nps(build: &mut CommandBuilder<Self>) {
         build.add_arg_string(
@@ -224,13 +280,33 @@ impl CmdArgs {
         build.add_arg_usize(
             "kernel_size",
             None,
-            "Size parameter for a kernel",
+            "Size parameter for a kernel (default 8)",
             required.into(),
-            Some("8"),
+            None,
             CmdArgs::set_kernel_size,
         );
     }
 
+    //fp add_arg_sigma
+    pub fn add_arg_sigma(build: &mut CommandBuilder<Self>) {
+        build.add_arg_f64(
+            "sigma",
+            None,
+            "Standard deviation for Gaussian-derived kernels",
+            false.into(),
+            Some("1"),
+            CmdArgs::set_sigma,
+        );
+        build.add_arg_f64(
+            "sigma_b",
+            None,
+            "Second standard deviation for difference-of-Gaussians (0 for 1.6*sigma)",
+            false.into(),
+            Some("0"),
+            CmdArgs::set_sigma_b,
+        );
+    }
+
     //fp add_arg_flags
     pub fn add_arg_flags(build: &mut CommandBuilder<Self>) {
         build.add_arg_usize(
@@ -337,6 +413,36 @@ impl CmdArgs {
         );
     }
 
+    //fp add_arg_include_images
+    pub fn add_arg_include_images(build: &mut CommandBuilder<Self>) {
+        build.add_flag(
+            "include_images",
+            None,
+            "Include the images of the project in the archive, rather than just their hashes",
+            CmdArgs::set_include_images,
+        );
+    }
+
+    //fp add_arg_gps
+    pub fn add_arg_gps(build: &mut CommandBuilder<Self>) {
+        build.add_arg_string(
+            "gps",
+            None,
+            "GPS position of the camera as 'lat,lon[,alt]' (requires a fitted project frame)",
+            ArgCount::Optional,
+            None,
+            CmdArgs::set_gps,
+        );
+        build.add_arg_f64(
+            "gps_radius",
+            None,
+            "Accuracy of the GPS position in metres; the camera is located within this of it",
+            ArgCount::Optional,
+            Some("25.0"),
+            CmdArgs::set_gps_radius,
+        );
+    }
+
     //fp add_arg_num_pts
     pub fn add_arg_num_pts(build: &mut CommandBuilder<Self>) {
         build.add_arg_usize(
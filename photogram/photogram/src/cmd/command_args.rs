@@ -4,6 +4,7 @@ use thunderclap::CommandArgs;
 
 use ic_base::Error;
 
+use crate::cmd::CmdValue;
 use crate::{CmdArgs, CmdResult};
 
 //ip CommandArgs for CmdArgs
@@ -134,10 +135,10 @@ const KEY_FNS: &[KeyFn] = &[
 
 impl CommandArgs for CmdArgs {
     type Error = Error;
-    type Value = String;
+    type Value = CmdValue;
 
     fn cmd_ok() -> CmdResult {
-        Ok("".into())
+        crate::cmd_ok()
     }
 
     fn reset_args(&mut self) {
@@ -147,6 +148,7 @@ impl CommandArgs for CmdArgs {
         self.read_img = vec![];
         self.np = vec![];
         self.kernels = vec![];
+        self.pipeline = None;
         self.arg_strings = vec![];
         self.arg_f64s = vec![];
         self.arg_usizes = vec![];
@@ -166,10 +168,15 @@ impl CommandArgs for CmdArgs {
         self.max_error = 0.0;
         self.use_pts = 0;
         self.use_deltas = false;
+        self.include_images = false;
+        self.gps = None;
+        self.gps_radius = 0.0;
         self.flags = 0;
         self.scale = 1.0;
         self.angle = 0.0;
-        self.kernel_size = 8;
+        self.kernel_size = None;
+        self.sigma = 1.0;
+        self.sigma_b = 0.0;
         if let Some(catalog) = &mut self.star_catalog {
             catalog.clear_filter();
         }
@@ -178,6 +185,11 @@ impl CommandArgs for CmdArgs {
         self.pms_color = None;
     }
 
+    /// Record the command line, for the provenance of files written
+    fn set_command_line(&mut self, command_line: &[String]) {
+        self.command_line = command_line.to_vec();
+    }
+
     /// Get the keys (elements) of the arguments - used in batch and interactive only
     fn keys(&self) -> Box<dyn Iterator<Item = &str>> {
         Box::new(KEY_FNS.iter().map(|k| k.0))
@@ -193,6 +205,18 @@ impl CommandArgs for CmdArgs {
         None
     }
 
+    /// Convert the value of a command to JSON, with the files written
+    /// by the command
+    fn value_json(&self, value: &CmdValue) -> Option<serde_json::Value> {
+        let mut json = serde_json::Map::new();
+        json.insert("value".into(), value.value().clone());
+        let files_written = self.files_written();
+        if !files_written.is_empty() {
+            json.insert("files_written".into(), files_written.into());
+        }
+        Some(json.into())
+    }
+
     /// Set the value
     fn value_set(&mut self, key: &str, value: &str) -> Result<bool, Error> {
         for k in KEY_FNS.iter() {
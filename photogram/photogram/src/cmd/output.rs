@@ -1,74 +1,240 @@
 //a Imports
 use std::io::Write;
 
-use ic_base::Result;
+use geo_nd::Vector;
 
-use super::{CmdArgs, CmdResult};
+use ic_base::{diff, Error, Result};
+use ic_project::{ProjectHistory, Provenance, ProvenanceLog};
+
+use super::{CmdArgs, CmdResult, CmdValue};
 
 //a CmdArgs output methods
 //ip CmdArgs output methods
 impl CmdArgs {
     //mp write_outputs
-    pub fn write_outputs(&self) -> Result<()> {
-        if let Some(filename) = &self.write_project {
-            let s = self.project.to_json(true)?;
-            let mut f = std::fs::File::create(filename)?;
-            f.write_all(s.as_bytes())?;
-        }
+    /// Write the data files that the command has been asked to write
+    ///
+    /// Unless this is a dry run, an entry is first added to the
+    /// provenance log of the project, so that a project file written
+    /// includes it, and to the provenance log file of every other file
+    /// written (see [ProvenanceLog::path_of_output])
+    pub fn write_outputs(&mut self) -> Result<()> {
+        let mut outputs = vec![];
         if let Some(filename) = &self.write_named_points {
-            let s = self.nps().borrow().to_json(true)?;
-            let mut f = std::fs::File::create(filename)?;
-            f.write_all(s.as_bytes())?;
+            outputs.push((filename.clone(), self.nps().borrow().to_json(true)?));
         }
         if let Some(filename) = &self.write_point_mapping {
-            let s = self.pms().borrow().to_json(true)?;
-            let mut f = std::fs::File::create(filename)?;
-            f.write_all(s.as_bytes())?;
+            outputs.push((filename.clone(), self.pms().borrow().to_json(true)?));
         }
         if let Some(filename) = &self.write_camera {
-            let s = self.camera.to_json(true)?;
-            let mut f = std::fs::File::create(filename)?;
-            f.write_all(s.as_bytes())?;
+            outputs.push((filename.clone(), self.camera.to_json(true)?));
         }
         if let Some(filename) = &self.write_polys {
-            let s = self.camera.lens().polys().to_json(true)?;
-            let mut f = std::fs::File::create(filename)?;
-            f.write_all(s.as_bytes())?;
+            outputs.push((filename.clone(), self.camera.lens().polys().to_json(true)?));
         }
         if let Some(filename) = &self.write_calibration_mapping {
-            let s = self.calibration_mapping.to_json(true)?;
-            let mut f = std::fs::File::create(filename)?;
-            f.write_all(s.as_bytes())?;
+            outputs.push((filename.clone(), self.calibration_mapping.to_json(true)?));
         }
         if let Some(filename) = &self.write_star_mapping {
-            let s = self.star_mapping.to_json(true)?;
-            let mut f = std::fs::File::create(filename)?;
-            f.write_all(s.as_bytes())?;
+            outputs.push((filename.clone(), self.star_mapping.to_json(true)?));
+        }
+        let mut written: Vec<String> = outputs.iter().map(|(f, _)| f.clone()).collect();
+        written.extend(self.write_project.iter().cloned());
+        if written.is_empty() {
+            return Ok(());
+        }
+        if !self.dry_run {
+            let provenance = self.provenance(&written);
+            let mut logs = vec![];
+            for (filename, _) in &outputs {
+                let log_path = ProvenanceLog::path_of_output(filename);
+                let mut log = match std::fs::read_to_string(&log_path) {
+                    Ok(json) => ProvenanceLog::from_json(&json)?,
+                    Err(_) => ProvenanceLog::default(),
+                };
+                log.push(provenance.clone());
+                logs.push((log_path.display().to_string(), log.to_json(true)?));
+            }
+            outputs.extend(logs);
+            self.project.add_provenance(provenance);
+        }
+        if let Some(filename) = &self.write_project {
+            outputs.push((filename.clone(), self.project.to_json(true)?));
+            let history_path = ProjectHistory::path_of_project(filename);
+            if !self.project.history().is_empty() || history_path.is_file() {
+                let history_filename = history_path.display().to_string();
+                outputs.push((history_filename, self.project.history().to_json(true)?));
+            }
+        }
+        for (filename, contents) in &outputs {
+            self.write_output(filename, contents)?;
+        }
+        Ok(())
+    }
+
+    //mi provenance
+    /// Create a provenance entry for the current command writing the
+    /// given files
+    fn provenance(&self, outputs: &[String]) -> Provenance {
+        let timestamp = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+        let tool = format!("photogram {}", env!("CARGO_PKG_VERSION"));
+        Provenance::new(timestamp, tool, &self.command_line)
+            .with_inputs(&self.inputs)
+            .with_outputs(outputs)
+    }
+
+    //mi write_output
+    /// Write a file, keeping a '.bak' copy of it if it exists and is
+    /// to change (unless --no_backup)
+    ///
+    /// With --dry_run the changes to the file are shown as a unified
+    /// diff on stderr, and nothing is written
+    fn write_output(&self, filename: &str, contents: &str) -> Result<()> {
+        let existing = std::fs::read_to_string(filename).ok();
+        let unchanged = existing.as_deref() == Some(contents);
+        if self.dry_run {
+            if unchanged {
+                eprintln!("Would leave '{filename}' unchanged");
+            } else {
+                let old = existing.as_deref().unwrap_or("");
+                let old_name = if existing.is_some() {
+                    filename
+                } else {
+                    "/dev/null"
+                };
+                eprintln!("--- {old_name}\n+++ {filename}");
+                eprint!("{}", diff::unified(old, contents, 3));
+            }
+            return Ok(());
+        }
+        if existing.is_some() && !unchanged {
+            self.backup_output(filename)?;
         }
+        let mut f = std::fs::File::create(filename)
+            .map_err(|e| Error::File(format!("Error writing file {filename}"), e))?;
+        f.write_all(contents.as_bytes())?;
         Ok(())
     }
 
+    //mp write_binary_output
+    /// Write a binary file, keeping a '.bak' copy of it if it exists
+    /// and is to change (unless --no_backup)
+    ///
+    /// With --dry_run the file is not written, and just its size is
+    /// reported on stderr
+    pub fn write_binary_output(&self, filename: &str, contents: &[u8]) -> Result<()> {
+        let existing = std::fs::read(filename).ok();
+        let unchanged = existing.as_deref() == Some(contents);
+        if self.dry_run {
+            if unchanged {
+                eprintln!("Would leave '{filename}' unchanged");
+            } else {
+                eprintln!("Would write {} bytes to '{filename}'", contents.len());
+            }
+            return Ok(());
+        }
+        if existing.is_some() && !unchanged {
+            self.backup_output(filename)?;
+        }
+        std::fs::write(filename, contents)
+            .map_err(|e| Error::File(format!("Error writing file {filename}"), e))?;
+        Ok(())
+    }
+
+    //mi backup_output
+    /// Copy a file that is to be overwritten to a '.bak' file (unless
+    /// --no_backup)
+    fn backup_output(&self, filename: &str) -> Result<()> {
+        if self.no_backup {
+            return Ok(());
+        }
+        let backup = format!("{filename}.bak");
+        std::fs::copy(filename, &backup)
+            .map_err(|e| Error::File(format!("Error backing up file {filename}"), e))?;
+        self.if_verbose(|| eprintln!("Backed up '{filename}' to '{backup}'"));
+        Ok(())
+    }
+
+    //ap files_written
+    /// The files that the command has been asked to write; as the
+    /// command succeeded, these have been written (unless this is a
+    /// dry run)
+    pub fn files_written(&self) -> Vec<String> {
+        if self.dry_run {
+            return vec![];
+        }
+        [
+            &self.write_project,
+            &self.write_named_points,
+            &self.write_point_mapping,
+            &self.write_camera,
+            &self.write_polys,
+            &self.write_calibration_mapping,
+            &self.write_star_mapping,
+            &self.write_img,
+            &self.write_svg,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect()
+    }
+
     //mp output_camera
+    /// Output the camera as it would be written by '--write_camera'
     pub fn output_camera(&self) -> CmdResult {
-        let s = self.camera.to_json(true)?;
-        Ok(s.to_string())
+        CmdValue::of_json(self.camera.to_json(true)?)
+    }
+
+    //mp output_located_camera
+    /// Output the camera, with the residual errors of the given point
+    /// mappings in its structured form; the text form is just the
+    /// camera
+    pub fn output_located_camera(&self, pms_n: &[usize]) -> CmdResult {
+        let pms = self.pms().borrow();
+        let camera = self.camera();
+        let mut residuals = vec![];
+        for (n, pm) in pms.mappings().iter().enumerate() {
+            if !pms_n.contains(&n) {
+                continue;
+            }
+            let Some(dpxy) = pm.get_mapped_dpxy(camera) else {
+                continue;
+            };
+            residuals.push(serde_json::json!({
+                "name": pm.name(),
+                "dx": dpxy[0],
+                "dy": dpxy[1],
+                "error": dpxy.length(),
+            }));
+        }
+        let camera_json = camera.to_json(true)?;
+        let value = serde_json::json!({
+            "camera": serde_json::from_str::<serde_json::Value>(&camera_json)?,
+            "total_error": pms.total_error(camera),
+            "residuals": residuals,
+        });
+        Ok(CmdValue::new(&value)?.with_text(camera_json))
     }
 
     //mp output_calibration_mapping
+    /// Output the calibration mapping as it would be written by
+    /// '--write_calibration_mapping'
     pub fn output_calibration_mapping(&self) -> CmdResult {
-        let s = self.calibration_mapping.clone().to_json(true)?;
-        Ok(s.to_string())
+        CmdValue::of_json(self.calibration_mapping.to_json(true)?)
     }
 
     //mp output_star_mapping
+    /// Output the star mapping as it would be written by
+    /// '--write_star_mapping'
     pub fn output_star_mapping(&self) -> CmdResult {
-        let s = self.star_mapping.to_json(true)?;
-        Ok(s.to_string())
+        CmdValue::of_json(self.star_mapping.to_json(true)?)
     }
 
     //mp output_polynomials
+    /// Output the lens polynomials as they would be written by
+    /// '--write_polys'
     pub fn output_polynomials(&self) -> CmdResult {
-        let s = self.camera.lens().polys().to_json(true)?;
-        Ok(s.to_string())
+        CmdValue::of_json(self.camera.lens().polys().to_json(true)?)
     }
 }
@@ -1,13 +1,14 @@
 //a Imports
 use star_catalog::Catalog;
 
-use ic_base::Result;
-use ic_base::{json, Ray, Rrc};
-use ic_camera::CameraInstance;
+use ic_base::{json, Geodetic, Ray, Rrc};
+use ic_base::{Error, Result};
+use ic_camera::{CameraInstance, CameraLens};
 use ic_camera::{CalibrationMapping, CameraDatabase, LensPolys};
 use ic_image::Color;
+use ic_kernel::Accelerator;
 use ic_mapping::{NamedPointSet, PointMappingSet};
-use ic_project::{Cip, ProjectFileDesc};
+use ic_project::{sha256_hex, Cip, InputFile, Pose, ProjectArchive, ProjectEdit, ProjectFileDesc};
 use ic_stars::StarMapping;
 
 use super::CmdArgs;
@@ -21,18 +22,55 @@ impl CmdArgs {
         Ok(())
     }
 
+    //mi set_accelerator
+    pub(crate) fn set_accelerator(&mut self, s: &str) -> Result<()> {
+        let accelerator: Accelerator = s.parse()?;
+        self.kernels_config.set_accelerator(accelerator);
+        Ok(())
+    }
+
+    //mi add_shader_path
+    pub(crate) fn add_shader_path(&mut self, s: &str) -> Result<()> {
+        self.kernels_config.add_shader_path(s);
+        Ok(())
+    }
+
     //mi set_pretty_json
     pub(crate) fn set_pretty_json(&mut self, pretty_json: bool) -> Result<()> {
         self.pretty_json = pretty_json;
         Ok(())
     }
 
+    //mi set_dry_run
+    pub(crate) fn set_dry_run(&mut self, dry_run: bool) -> Result<()> {
+        self.dry_run = dry_run;
+        Ok(())
+    }
+
+    //mi set_no_backup
+    pub(crate) fn set_no_backup(&mut self, no_backup: bool) -> Result<()> {
+        self.no_backup = no_backup;
+        Ok(())
+    }
+
+    //mi add_input
+    /// Record a file that has been read, with the hash of its
+    /// contents, for the provenance of the files that are written
+    pub(crate) fn add_input(&mut self, filename: &str) -> Result<()> {
+        let data = std::fs::read(filename).map_err(|e| Error::from((filename, e)))?;
+        self.inputs.retain(|i| i.filename() != filename);
+        self.inputs
+            .push(InputFile::new(filename.to_string(), sha256_hex(&data)));
+        Ok(())
+    }
+
     //mi set_camera_db
     pub(crate) fn set_camera_db(&mut self, filename: &str) -> Result<()> {
         let (cdb_filename, mut camera_db): (String, CameraDatabase) = self
             .path_set
             .load_from_json_file("camera database", filename)?;
         self.if_verbose(|| eprintln!("Loaded camera database from '{cdb_filename}'"));
+        self.add_input(&cdb_filename)?;
         camera_db.derive();
         self.project.set_cdb(camera_db);
         self.cdb = self.project.cdb().clone();
@@ -40,11 +78,27 @@ impl CmdArgs {
     }
 
     //mi set_project_file
+    /// Load a project from a JSON file, or from a project archive
+    /// (a '.tar' file)
     pub(crate) fn set_project_file(&mut self, filename: &str) -> Result<()> {
         let project_filename;
-        (project_filename, self.project) =
-            self.path_set.load_from_json_file("project", filename)?;
+        if ProjectArchive::is_archive(filename) {
+            let Some(path) = self.path_set.find_file(filename) else {
+                return Err(format!("Failed to find project archive '{filename}'").into());
+            };
+            project_filename = path.display().to_string();
+            let archive = ProjectArchive::read_file(&project_filename)?;
+            self.project = archive.load_project()?;
+            for warning in archive.verify_images(&self.path_set) {
+                eprintln!("Warning loading project archive '{project_filename}': {warning}");
+            }
+        } else {
+            (project_filename, self.project) =
+                self.path_set.load_from_json_file("project", filename)?;
+        }
         self.if_verbose(|| eprintln!("Loaded project from '{project_filename}'"));
+        self.add_input(&project_filename)?;
+        self.project.load_history(&project_filename)?;
         self.nps = self.project.nps().clone();
         self.cdb = self.project.cdb().clone();
         self.cip_number = 0;
@@ -58,6 +112,7 @@ impl CmdArgs {
             self.path_set
                 .load_from_json_file("project descriptor", filename)?;
         self.if_verbose(|| eprintln!("Loaded project desc from '{project_desc_filename}'"));
+        self.add_input(&project_desc_filename)?;
         self.project = project_file_desc.load_project(&self.path_set)?;
         self.nps = self.project.nps().clone();
         self.cdb = self.project.cdb().clone();
@@ -68,6 +123,7 @@ impl CmdArgs {
     pub fn set_calibration_mapping_file(&mut self, filename: &str) -> Result<()> {
         let json = json::read_file(filename)?;
         self.calibration_mapping = CalibrationMapping::from_json(&json)?;
+        self.add_input(filename)?;
         Ok(())
     }
 
@@ -87,6 +143,7 @@ impl CmdArgs {
     pub(crate) fn set_camera_file(&mut self, camera_filename: &str) -> Result<()> {
         let camera_json = json::read_file(camera_filename)?;
         let camera = CameraInstance::from_json(&self.cdb.borrow(), &camera_json)?;
+        self.add_input(camera_filename)?;
         self.set_camera(camera);
         Ok(())
     }
@@ -114,7 +171,8 @@ impl CmdArgs {
     //mi set_camera_polys
     pub(crate) fn set_camera_polys(&mut self, polys: &str) -> Result<()> {
         let json = json::read_file(polys)?;
-        let lens_polys: LensPolys = json::from_json("lens polynomials", &json)?;
+        let lens_polys = LensPolys::from_json(&json)?;
+        self.add_input(polys)?;
         let mut lens = self.camera.lens().clone();
         lens.set_polys(lens_polys);
         self.camera.set_lens(lens);
@@ -136,6 +194,7 @@ impl CmdArgs {
     /// Could perhaps do with a way to reset the nps for batch mode
     pub(crate) fn add_nps(&mut self, nps_filename: &str) -> Result<()> {
         let nps_json = json::read_file(nps_filename)?;
+        self.add_input(nps_filename)?;
         self.project
             .nps_mut()
             .merge(&NamedPointSet::from_json(&nps_json)?);
@@ -147,6 +206,7 @@ impl CmdArgs {
     pub(crate) fn add_pms(&mut self, pms_filename: &str) -> Result<()> {
         let mut pms = PointMappingSet::new();
         let pms_json = json::read_file(pms_filename)?;
+        self.add_input(pms_filename)?;
         let nf = pms.read_json(&self.project.nps_ref(), &pms_json, true)?;
         if !nf.is_empty() {
             eprintln!("Warning: {nf}");
@@ -175,6 +235,7 @@ impl CmdArgs {
     pub(crate) fn set_star_mapping_file(&mut self, filename: &str) -> Result<()> {
         let json = json::read_file(filename)?;
         self.star_mapping = StarMapping::from_json(&json)?;
+        self.add_input(filename)?;
         Ok(())
     }
 
@@ -183,11 +244,17 @@ impl CmdArgs {
         let mut catalog = Catalog::load_catalog(filename, 99.)?;
         catalog.derive_data();
         self.star_catalog = Some(Box::new(catalog));
+        if std::path::Path::new(filename).is_file() {
+            self.add_input(filename)?;
+        }
         Ok(())
     }
 
     //mi add_read_img
     pub(crate) fn add_read_img(&mut self, s: &str) -> Result<()> {
+        if let Some(filename) = self.path_set.find_file(s) {
+            self.add_input(&filename.to_string_lossy())?;
+        }
         self.read_img.push(s.into());
         Ok(())
     }
@@ -215,6 +282,34 @@ impl CmdArgs {
         Ok(())
     }
 
+    //mi record_pose_change
+    /// Record in the project history a change of the pose of the
+    /// camera of the current CIP from 'from', if it is a CIP of the
+    /// project and the pose has changed
+    pub(crate) fn record_pose_change(&self, from: Pose) {
+        let Some(cip) = self.project_cip() else {
+            return;
+        };
+        let to = Pose::of_camera(&self.cip.borrow().camera_ref());
+        if to != from {
+            self.project
+                .record_edit(ProjectEdit::SetPose { cip, from, to });
+        }
+    }
+
+    //mi set_lens
+    /// Set the lens of the camera; if the camera is that of a CIP of
+    /// the project then the lens of the CIP's camera is set too, as an
+    /// edit recorded in the project history
+    pub(crate) fn set_lens(&mut self, lens: CameraLens) -> Result<()> {
+        if let Some(cip) = self.project_cip() {
+            let edit = ProjectEdit::set_lens(&self.project, cip, lens.clone())?;
+            self.project.apply_edit(edit)?;
+        }
+        self.camera.set_lens(lens);
+        Ok(())
+    }
+
     //mi add_np
     pub(crate) fn add_np(&mut self, s: &str) -> Result<()> {
         self.np.push(s.into());
@@ -224,6 +319,7 @@ impl CmdArgs {
     //mi set_ray_file
     pub(crate) fn set_ray_file(&mut self, ray_filename: &str) -> Result<()> {
         let r_json = json::read_file(ray_filename)?;
+        self.add_input(ray_filename)?;
         let mut named_rays: Vec<(String, Ray)> = json::from_json("ray list", &r_json)?;
         self.named_rays.append(&mut named_rays);
         Ok(())
@@ -236,6 +332,13 @@ impl CmdArgs {
         Ok(())
     }
 
+    //mi set_pipeline
+    /// Set the kernel pipeline JSON file to run
+    pub(crate) fn set_pipeline(&mut self, pipeline: &str) -> Result<()> {
+        self.pipeline = Some(pipeline.to_owned());
+        Ok(())
+    }
+
     //mi set_bg_color
     pub(crate) fn set_bg_color(&mut self, s: &str) -> Result<()> {
         let c: Color = s.try_into()?;
@@ -307,7 +410,22 @@ impl CmdArgs {
 
     //mi set_kernel_size
     pub(crate) fn set_kernel_size(&mut self, v: usize) -> Result<()> {
-        self.kernel_size = v;
+        self.kernel_size = Some(v);
+        Ok(())
+    }
+
+    //mi set_sigma
+    pub(crate) fn set_sigma(&mut self, v: f64) -> Result<()> {
+        if v <= 0.0 {
+            return Err(format!("Sigma {v} must be positive").into());
+        }
+        self.sigma = v;
+        Ok(())
+    }
+
+    //mi set_sigma_b
+    pub(crate) fn set_sigma_b(&mut self, v: f64) -> Result<()> {
+        self.sigma_b = v;
         Ok(())
     }
 
@@ -370,6 +488,28 @@ impl CmdArgs {
         Ok(())
     }
 
+    //mi set_include_images
+    pub(crate) fn set_include_images(&mut self, include_images: bool) -> Result<()> {
+        self.include_images = include_images;
+        Ok(())
+    }
+
+    //mi set_gps
+    /// Set the GPS position of the camera, as 'lat,lon' or
+    /// 'lat,lon,alt'
+    pub(crate) fn set_gps(&mut self, gps: &str) -> Result<()> {
+        self.gps = Some(Geodetic::of_str(gps)?);
+        Ok(())
+    }
+
+    //mi set_gps_radius
+    pub(crate) fn set_gps_radius(&mut self, v: f64) -> Result<()> {
+        self.gps_radius = thunderclap::bound(v, Some(0.), None, |v, _| {
+            format!("GPS radius ({v}) must not be negative")
+        })?;
+        Ok(())
+    }
+
     //mi set_use_pts
     pub(crate) fn set_use_pts(&mut self, v: usize) -> Result<()> {
         self.use_pts = thunderclap::bound(v, Some(6), None, |v, _| {
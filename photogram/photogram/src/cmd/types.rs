@@ -1,19 +1,75 @@
 //a Imports
-
+use serde::Serialize;
 use star_catalog::Catalog;
 
-use ic_base::{PathSet, Ray, Rrc};
+use ic_base::{Geodetic, PathSet, Ray, Rrc};
 use ic_camera::CameraInstance;
 use ic_camera::{CalibrationMapping, CameraDatabase};
 use ic_image::Color;
+use ic_kernel::KernelsConfig;
 use ic_mapping::{NamedPointSet, PointMappingSet};
-use ic_project::{Cip, Project};
+use ic_project::{Cip, InputFile, Project};
 use ic_stars::StarMapping;
 
+//a CmdValue
+//tp CmdValue
+/// The value returned by a command
+///
+/// This has a text form, which is printed when the command completes
+/// (and is `${0}` in batch mode), and a structured form that is
+/// printed with '--json' (whose fields are `${0.value.<field>}` in
+/// batch mode)
+#[derive(Debug, Default, Clone)]
+pub struct CmdValue {
+    text: String,
+    value: serde_json::Value,
+}
+
+//ip CmdValue
+impl CmdValue {
+    //cp new
+    /// Create a value from anything serializable, whose text form is
+    /// its pretty-printed JSON
+    pub fn new<T: Serialize + ?Sized>(value: &T) -> ic_base::Result<Self> {
+        let text = serde_json::to_string_pretty(value)?;
+        let value = serde_json::to_value(value)?;
+        Ok(Self { text, value })
+    }
+
+    //cp of_json
+    /// Create a value from the JSON of a file (such as one written by
+    /// the type's 'to_json', which includes its version), whose text
+    /// form is that JSON
+    pub fn of_json(text: String) -> ic_base::Result<Self> {
+        let value = serde_json::from_str(&text)?;
+        Ok(Self { text, value })
+    }
+
+    //cp with_text
+    /// Replace the text form of the value
+    pub fn with_text<S: Into<String>>(mut self, text: S) -> Self {
+        self.text = text.into();
+        self
+    }
+
+    //ap value
+    /// The structured form of the value
+    pub fn value(&self) -> &serde_json::Value {
+        &self.value
+    }
+}
+
+//ip Display for CmdValue
+impl std::fmt::Display for CmdValue {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.write_str(&self.text)
+    }
+}
+
 //a CmdResult
-pub type CmdResult = std::result::Result<String, ic_base::Error>;
+pub type CmdResult = std::result::Result<CmdValue, ic_base::Error>;
 pub fn cmd_ok() -> CmdResult {
-    Ok("".into())
+    Ok(CmdValue::default())
 }
 
 //a CmdArgs
@@ -24,6 +80,9 @@ pub struct CmdArgs {
 
     pub(crate) path_set: PathSet,
 
+    // Accelerator and shader selection for kernels
+    pub(crate) kernels_config: KernelsConfig,
+
     pub(crate) project: Project,
 
     // Camera database that is part of the project
@@ -60,10 +119,20 @@ pub struct CmdArgs {
 
     pub(crate) pretty_json: bool,
 
+    // Show the changes that writing files would make, rather than
+    // writing them; and whether to not back up overwritten files
+    pub(crate) dry_run: bool,
+    pub(crate) no_backup: bool,
+
+    // Command line being executed and the files read, for provenance
+    pub(crate) command_line: Vec<String>,
+    pub(crate) inputs: Vec<InputFile>,
+
     // Items clear during reset
     pub(crate) read_img: Vec<String>,
     pub(crate) np: Vec<String>, // could be name, 3D, pixel XY (from camera mapping of 3D); might need at least 3
     pub(crate) kernels: Vec<String>,
+    pub(crate) pipeline: Option<String>,
     pub(crate) write_project: Option<String>,
     pub(crate) write_named_points: Option<String>,
     pub(crate) write_point_mapping: Option<String>,
@@ -85,13 +154,21 @@ pub struct CmdArgs {
 
     pub(crate) named_rays: Vec<(String, Ray)>,
 
-    pub(crate) kernel_size: usize,
+    /// Kernel size, if given explicitly
+    pub(crate) kernel_size: Option<usize>,
+    pub(crate) sigma: f64,
+    pub(crate) sigma_b: f64,
     pub(crate) scale: f64,
     pub(crate) angle: f64,
     pub(crate) flags: usize,
     pub(crate) use_deltas: bool,
+    pub(crate) include_images: bool,
     pub(crate) use_pts: usize,
     pub(crate) max_error: f64,
     pub(crate) max_points: usize,
     pub(crate) max_pairs: usize,
+
+    // GPS position of the camera, and its accuracy in metres
+    pub(crate) gps: Option<Geodetic>,
+    pub(crate) gps_radius: f64,
 }
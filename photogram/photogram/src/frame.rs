@@ -0,0 +1,330 @@
+//a Imports
+
+use clap::Command;
+use serde::Serialize;
+
+use thunderclap::CommandBuilder;
+
+use ic_base::{Geodetic, Point3D};
+use ic_camera::CameraProjection;
+use ic_project::{FrameKind, ProjectFrame};
+
+use crate::cmd::{CmdArgs, CmdResult, CmdValue};
+
+//a Help
+//hi FRAME_LONG_HELP
+const FRAME_LONG_HELP: &str = "\
+Georeference the project, by giving it a coordinate reference frame
+tied to the model coordinates through control points.
+
+The frame is one of: local east-north-up coordinates about an origin
+('enu:LAT,LON[,ALT]'); WGS84 earth-centred earth-fixed coordinates
+('ecef'); or UTM coordinates in a zone ('utm:31N'). All are in metres,
+and positions are given as latitude and longitude in degrees and
+height above the WGS84 ellipsoid in metres.
+
+Control points are named points whose positions are known (from a
+survey, for example). A similarity transform (scale, rotation and
+translation) from model coordinates to the frame is fitted to the
+control points that have model positions; at least three, not in a
+line, are required.
+
+Once fitted the named points and cameras may be exported in geographic
+coordinates, and GPS positions of cameras may be used to seed 'cip
+locate'. The frame is stored in the project, and written with it by
+'--write_project'.";
+
+//hi SET_LONG_HELP
+const SET_LONG_HELP: &str = "\
+Set the frame of the project, as 'enu:LAT,LON[,ALT]', 'ecef' or
+'utm:ZONE' (such as 'utm:31N' or 'utm:56S'); or remove it with 'none'.
+
+Any control points of an existing frame are kept, but the frame must
+be fitted again.";
+
+//hi EXPORT_LONG_HELP
+const EXPORT_LONG_HELP: &str = "\
+Export the positions of the named points with model positions, and of
+the cameras of the CIPs, in the frame and as latitude, longitude and
+height.
+
+The frame must have been fitted.";
+
+//a Types
+//tp GeoPosition
+/// A named point or camera position in model, frame and geodetic
+/// coordinates
+#[derive(Debug, Serialize)]
+struct GeoPosition {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cip: Option<usize>,
+    model: Point3D,
+    frame: Point3D,
+    geodetic: Geodetic,
+}
+
+//ip GeoPosition
+impl GeoPosition {
+    //cp of_model
+    fn of_model(
+        frame: &ProjectFrame,
+        name: String,
+        cip: Option<usize>,
+        model: Point3D,
+    ) -> ic_base::Result<Self> {
+        let frame_pt = frame.model_to_frame(&model)?;
+        Ok(Self {
+            name,
+            cip,
+            model,
+            frame: frame_pt,
+            geodetic: frame.kind().to_geodetic(&frame_pt),
+        })
+    }
+}
+
+//a Support
+//fi project_frame
+/// The frame of the project, which must exist
+fn project_frame(cmd_args: &CmdArgs) -> ic_base::Result<&ProjectFrame> {
+    cmd_args
+        .project()
+        .frame()
+        .ok_or_else(|| "The project does not have a frame; use 'frame set'".into())
+}
+
+//fi project_frame_mut
+/// The frame of the project, which must exist, for modification
+fn project_frame_mut(cmd_args: &mut CmdArgs) -> ic_base::Result<&mut ProjectFrame> {
+    cmd_args
+        .project_mut()
+        .frame_mut()
+        .ok_or_else(|| "The project does not have a frame; use 'frame set'".into())
+}
+
+//fi frame_result
+/// Write any outputs, and return the frame of the project
+fn frame_result(cmd_args: &mut CmdArgs) -> CmdResult {
+    cmd_args.write_outputs()?;
+    match cmd_args.project().frame() {
+        Some(frame) => Ok(CmdValue::new(frame)?.with_text(frame.to_string())),
+        None => Ok(CmdValue::new(&None::<ProjectFrame>)?.with_text("No frame")),
+    }
+}
+
+//a Show
+//fp show_cmd
+fn show_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("show")
+        .about("Show the frame of the project, its control points and transform")
+        .long_about(FRAME_LONG_HELP);
+
+    CommandBuilder::new(command, Some(Box::new(show_fn)))
+}
+
+//fi show_fn
+fn show_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    frame_result(cmd_args)
+}
+
+//a Set
+//fp set_cmd
+fn set_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("set")
+        .about("Set the kind of frame of the project")
+        .long_about(SET_LONG_HELP);
+
+    let mut build = CommandBuilder::new(command, Some(Box::new(set_fn)));
+
+    CmdArgs::add_arg_positional_string(
+        &mut build,
+        "frame",
+        "Frame: 'enu:LAT,LON[,ALT]', 'ecef', 'utm:ZONE' or 'none'",
+        Some(1),
+        None,
+    );
+
+    build
+}
+
+//fi set_fn
+fn set_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let frame = cmd_args.get_string_arg(0).unwrap();
+    if frame == "none" {
+        cmd_args.project_mut().set_frame(None);
+        return frame_result(cmd_args);
+    }
+    let kind = FrameKind::of_str(frame)?;
+    if let Some(frame) = cmd_args.project_mut().frame_mut() {
+        frame.set_kind(kind);
+    } else {
+        cmd_args
+            .project_mut()
+            .set_frame(Some(ProjectFrame::new(kind)));
+    }
+    frame_result(cmd_args)
+}
+
+//a Control points
+//fp control_cmd
+fn control_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("control")
+        .about("Set the position of a control point of the frame")
+        .long_about(FRAME_LONG_HELP);
+
+    let mut build = CommandBuilder::new(command, Some(Box::new(control_fn)));
+
+    CmdArgs::add_arg_positional_string(
+        &mut build,
+        "name",
+        "Named point that is the control point",
+        Some(1),
+        None,
+    );
+    CmdArgs::add_arg_positional_string(
+        &mut build,
+        "position",
+        "Position of the control point as 'lat,lon[,alt]'",
+        Some(1),
+        None,
+    );
+
+    build
+}
+
+//fi control_fn
+fn control_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let name = cmd_args.get_string_arg(0).unwrap().to_owned();
+    let geodetic = Geodetic::of_str(cmd_args.get_string_arg(1).unwrap())?;
+    if cmd_args.nps().borrow().get_pt(&name).is_none() {
+        eprintln!("Warning: control point '{name}' is not in the named point set");
+    }
+    project_frame_mut(cmd_args)?.set_control_point(name, geodetic);
+    frame_result(cmd_args)
+}
+
+//fp remove_control_cmd
+fn remove_control_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("remove_control")
+        .about("Remove a control point of the frame")
+        .long_about(FRAME_LONG_HELP);
+
+    let mut build = CommandBuilder::new(command, Some(Box::new(remove_control_fn)));
+
+    CmdArgs::add_arg_positional_string(
+        &mut build,
+        "name",
+        "Control point to remove",
+        Some(1),
+        None,
+    );
+
+    build
+}
+
+//fi remove_control_fn
+fn remove_control_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let name = cmd_args.get_string_arg(0).unwrap().to_owned();
+    if !project_frame_mut(cmd_args)?.remove_control_point(&name) {
+        return Err(format!("Frame does not have a control point '{name}'").into());
+    }
+    frame_result(cmd_args)
+}
+
+//a Fit
+//fp fit_cmd
+fn fit_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("fit")
+        .about("Fit the frame to the model positions of its control points")
+        .long_about(FRAME_LONG_HELP);
+
+    CommandBuilder::new(command, Some(Box::new(fit_fn)))
+}
+
+//fi fit_fn
+fn fit_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let residuals = cmd_args.project_mut().fit_frame()?;
+    cmd_args.write_outputs()?;
+    let rms = (residuals.iter().map(|r| r.residual().powi(2)).sum::<f64>()
+        / residuals.len() as f64)
+        .sqrt();
+    let mut text = vec![project_frame(cmd_args)?.to_string()];
+    for r in &residuals {
+        text.push(format!("  {} residual {:.3}m", r.name(), r.residual()));
+    }
+    text.push(format!("RMS residual {rms:.3}m"));
+    let value = serde_json::json!({
+        "frame": project_frame(cmd_args)?,
+        "residuals": residuals,
+        "rms": rms,
+    });
+    Ok(CmdValue::new(&value)?.with_text(text.join("\n")))
+}
+
+//a Export
+//fp export_cmd
+fn export_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("export")
+        .about("Export named points and cameras in geographic coordinates")
+        .long_about(EXPORT_LONG_HELP);
+
+    CommandBuilder::new(command, Some(Box::new(export_fn)))
+}
+
+//fi export_fn
+fn export_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let frame = project_frame(cmd_args)?;
+    let project = cmd_args.project();
+
+    let nps = project.nps_ref();
+    let mut named_points = vec![];
+    for (name, np) in nps.iter() {
+        if let Some((model, _)) = np.opt_model() {
+            named_points.push(GeoPosition::of_model(frame, name.clone(), None, model)?);
+        }
+    }
+    named_points.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut cameras = vec![];
+    for n in 0..project.ncips() {
+        let cip = project.cip(n).borrow();
+        let position = cip.camera_ref().position();
+        let name = cip.image_name().to_owned();
+        cameras.push(GeoPosition::of_model(frame, name, Some(n), position)?);
+    }
+
+    let mut text = vec![];
+    for p in &named_points {
+        text.push(format!("{} {}", p.name, p.geodetic));
+    }
+    for (n, p) in cameras.iter().enumerate() {
+        text.push(format!("camera {n} '{}' {}", p.name, p.geodetic));
+    }
+    let value = serde_json::json!({
+        "frame": frame.kind(),
+        "named_points": named_points,
+        "cameras": cameras,
+    });
+    Ok(CmdValue::new(&value)?.with_text(text.join("\n")))
+}
+
+//a frame command
+//fp frame_cmd
+pub fn frame_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("frame")
+        .about("Georeference the project with a coordinate reference frame")
+        .long_about(FRAME_LONG_HELP)
+        .version("0.1.0");
+
+    let mut build = CommandBuilder::new(command, None);
+
+    build.add_subcommand(show_cmd());
+    build.add_subcommand(set_cmd());
+    build.add_subcommand(control_cmd());
+    build.add_subcommand(remove_control_cmd());
+    build.add_subcommand(fit_cmd());
+    build.add_subcommand(export_cmd());
+
+    build
+}
@@ -10,7 +10,7 @@ use ic_camera::polynomial::CalcPoly;
 use ic_image::{Color, Image, Region};
 use ic_mapping::PointMappingSet;
 
-use crate::cmd::{CmdArgs, CmdResult};
+use crate::cmd::{cmd_ok, CmdArgs, CmdResult};
 
 //a Help
 //hi FIND_REGIONS_LONG_HELP
@@ -345,7 +345,7 @@ fn find_regions_fn(cmd_args: &mut CmdArgs) -> CmdResult {
 
     println!("{}", serde_json::to_string_pretty(&cogs).unwrap());
 
-    Ok("".into())
+    cmd_ok()
 }
 
 //fi find_grid_points_cmd
@@ -403,7 +403,7 @@ fn find_grid_points_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     }
 
     println!("{}", serde_json::to_string_pretty(&mappings).unwrap());
-    Ok("".into())
+    cmd_ok()
 }
 
 //a Get point mappings
@@ -452,7 +452,7 @@ fn get_point_mappings_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     cmd_args.if_verbose(|| {
         eprintln!("Exported {} mappings", pms.mappings().len());
     });
-    Ok("".into())
+    cmd_ok()
 }
 
 //a Image_analyze command
@@ -411,7 +411,7 @@ fn locate_cmd() -> CommandBuilder<CmdArgs> {
 
 //fi locate_fn
 fn locate_fn(cmd_args: &mut CmdArgs) -> CmdResult {
-    let mut mls = ModelLineSet::new(cmd_args.camera().clone());
+    let mut mls = ModelLineSet::new(cmd_args.camera().clone())?;
     cmd_args.pms_map(|pms| {
         let mappings = pms.mappings();
         let n = pms.mappings().len();
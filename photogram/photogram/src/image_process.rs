@@ -4,9 +4,9 @@ use clap::Command;
 use thunderclap::CommandBuilder;
 
 use ic_image::{Image, ImageGray16};
-use ic_kernel::{KernelArgs, Kernels};
+use ic_kernel::{ImagePyramid, KernelArgs, KernelPipeline};
 
-use crate::cmd::{CmdArgs, CmdResult};
+use crate::cmd::{cmd_ok, CmdArgs, CmdResult};
 
 //a Help
 //hi AS_LUMA_LONG_HELP
@@ -25,9 +25,28 @@ Analyze kernels to an image in luma space
 
 Convert the image to a 16-bit luma
 
-Apply a number of kernels (with a single set of size, scale etc arguments)
+Apply a number of kernels (with a single set of size, scale etc arguments),
+or a kernel pipeline described in a JSON file; the pipeline's input
+buffer must be called 'image'
 
 Output the image as a 16-bit luma image (so the kernel output should be in the range 0.0 to 1.0)
+
+Kernels include window_sum_x, window_sum_y, window_mean, window_var,
+square and sqrt; and for feature detection:
+
+  gaussian_x, gaussian_y: separable Gaussian blur of standard deviation
+  'sigma'; the kernel radius is 'kernel_size' if that is given (and not
+  0), otherwise 3*sigma; this also applies to log and dog
+
+  sobel_mag, sobel_angle, scharr_mag, scharr_angle: gradient magnitude
+  or angle (in radians, times 'scale')
+
+  log: scale-normalized negated Laplacian of Gaussian (bright blobs of
+  radius about sigma are positive)
+
+  dog: difference of Gaussians of 'sigma' and 'sigma_b'
+
+  nms: non-maximum suppression within a radius of 'kernel_size'
 ";
 
 //hi LUMA_KERNEL_PAIR_LONG_HELP
@@ -39,6 +58,11 @@ Convert the images to 16-bit luma
 Apply a number of kernels (with a single set of size, scale etc arguments)
 
 Output the image as a 16-bit luma image (so the kernel output should be in the range 0.0 to 1.0)
+
+Flags: 1 applies window_var_scaled to both images first; 2 finds the
+best points in the result; 4 searches for the match of (px, py) from the
+first image in the second using Gaussian pyramids (of 'sigma') and
+window_corr coarse-to-fine
 ";
 
 //a Luma
@@ -68,7 +92,7 @@ fn as_luma_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     } else {
         eprintln!("Image not written as no output image provided");
     }
-    Ok("".into())
+    cmd_ok()
 }
 
 //fi luma_window_cmd
@@ -84,22 +108,14 @@ fn luma_window_cmd() -> CommandBuilder<CmdArgs> {
 fn luma_window_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     let img = cmd_args.get_image_read_or_create()?;
 
-    eprintln!(
-        "Read initial image, size is {:?} (max pixels in kernel is 4M)",
-        img.size()
-    );
-    let (w, h) = img.size();
-    let npix = w as usize * h as usize;
-    let max = 4 * 1024 * 1024;
-    let scale =
-        (npix > max).then_some(((max as f32 / npix as f32).sqrt() * w as f32).floor() as usize);
-    let (w, h, mut img_data) = img.as_vec_gray_f32(scale);
+    eprintln!("Read initial image, size is {:?}", img.size());
+    let (w, h, mut img_data) = img.as_vec_gray_f32(None);
     eprintln!(
         "Using size {w}, {h} ({:.2} Mpx)",
         (w * h) as f32 / 1024.0 / 1024.0
     );
 
-    let kernels = Kernels::new();
+    let kernels = cmd_args.create_kernels()?;
     let ws = 8;
     let args: KernelArgs = (w, h).into();
     let args = args.with_size(ws as usize);
@@ -124,7 +140,7 @@ fn luma_window_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     } else {
         eprintln!("Image not written as no output image provided");
     }
-    Ok("".into())
+    cmd_ok()
 }
 
 //fi luma_kernel_cmd
@@ -134,9 +150,11 @@ fn luma_kernel_cmd() -> CommandBuilder<CmdArgs> {
         .long_about(LUMA_KERNEL_LONG_HELP);
 
     let mut build = CommandBuilder::new(command, Some(Box::new(luma_kernel_fn)));
-    CmdArgs::add_arg_kernel(&mut build, (1,));
+    CmdArgs::add_arg_kernel(&mut build, (0,));
+    CmdArgs::add_arg_pipeline(&mut build);
     CmdArgs::add_arg_scale(&mut build);
     CmdArgs::add_arg_kernel_size(&mut build, false);
+    CmdArgs::add_arg_sigma(&mut build);
     CmdArgs::add_arg_px(&mut build, true);
     CmdArgs::add_arg_py(&mut build, true);
 
@@ -150,32 +168,43 @@ fn luma_kernel_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     let ws = cmd_args.kernel_size();
     let scale = cmd_args.scale();
     let xy = cmd_args.pxy();
-    let kernels_to_apply = cmd_args.kernels();
+    let (sigma, sigma_b) = cmd_args.sigmas();
+    let pipeline = {
+        if let Some(pipeline) = cmd_args.pipeline() {
+            KernelPipeline::read_file(pipeline)?
+        } else if !cmd_args.kernels().is_empty() {
+            KernelPipeline::of_kernels(cmd_args.kernels())
+        } else {
+            return Err("One of a kernel pipeline or kernels must be provided".into());
+        }
+    };
+    // Without an explicit kernel size the radius of Gaussian-derived
+    // kernels comes from sigma, rather than the default size
+    let pipeline = {
+        if cmd_args.explicit_kernel_size().is_none() {
+            pipeline.with_sigma_radius()
+        } else {
+            pipeline
+        }
+    };
 
-    eprintln!(
-        "Read initial image, size is {:?} (max pixels in kernel is 4M)",
-        img.size()
-    );
-    let (w, h) = img.size();
-    let npix = w as usize * h as usize;
-    let max = 4 * 1024 * 1024;
-    let img_scale =
-        (npix > max).then_some(((max as f32 / npix as f32).sqrt() * w as f32).floor() as usize);
-    let (w, h, mut img_data) = img.as_vec_gray_f32(img_scale);
+    eprintln!("Read initial image, size is {:?}", img.size());
+    let (w, h, img_data) = img.as_vec_gray_f32(None);
     eprintln!(
         "Using size {w}, {h} ({:.2} Mpx)",
         (w * h) as f32 / 1024.0 / 1024.0
     );
 
-    let kernels = Kernels::new();
+    let kernels = cmd_args.create_kernels()?;
     let args: KernelArgs = (w, h).into();
     let args = args.with_size(ws);
     let args = args.with_scale(scale as f32);
     let args = args.with_xy(xy);
+    let args = args.with_sigmas(sigma as f32, sigma_b as f32);
 
-    for k in kernels_to_apply {
-        kernels.run_shader(k, &args, w * h, None, img_data.as_mut_slice())?;
-    }
+    let img_data = kernels
+        .run_pipeline(&pipeline, &args, &[("image", img_data.as_slice())])?
+        .take_output();
 
     eprintln!("Completed kernel");
     let img = ImageGray16::of_vec_f32(w, h, img_data, 1.0);
@@ -187,7 +216,7 @@ fn luma_kernel_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     } else {
         eprintln!("Image not written as no output image provided");
     }
-    Ok("".into())
+    cmd_ok()
 }
 
 //fi luma_kernel_pair_cmd
@@ -203,6 +232,7 @@ fn luma_kernel_pair_cmd() -> CommandBuilder<CmdArgs> {
     CmdArgs::add_arg_px(&mut build, true);
     CmdArgs::add_arg_py(&mut build, true);
     CmdArgs::add_arg_angle(&mut build);
+    CmdArgs::add_arg_sigma(&mut build);
     CmdArgs::add_arg_flags(&mut build);
 
     build
@@ -220,17 +250,9 @@ fn luma_kernel_pair_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     let flags = cmd_args.flags();
     let kernels_to_apply = cmd_args.kernels();
 
-    eprintln!(
-        "Read initial image, size is {:?} (max pixels in kernel is 4M) : xy {xy:?}",
-        img1.size()
-    );
+    eprintln!("Read initial image, size is {:?} : xy {xy:?}", img1.size());
 
-    let (src_w, src_h) = img1.size();
-    let src_npix = src_w as usize * src_h as usize;
-    let src_max = (4 * 1024 * 1024).min(src_npix);
-    let src_img_scale =
-        Some(((src_max as f32 / src_npix as f32).sqrt() * src_w as f32).floor() as usize);
-    let (src_w, src_h, mut src_img) = img1.as_vec_gray_f32(src_img_scale);
+    let (src_w, src_h, mut src_img) = img1.as_vec_gray_f32(None);
     eprintln!(
         "Using size {src_w}, {src_h} ({:.2} Mpx)",
         (src_w * src_h) as f32 / 1024.0 / 1024.0
@@ -240,12 +262,7 @@ fn luma_kernel_pair_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         img.write("src_kernel.png")?;
     }
 
-    let (dst_w, dst_h) = img1.size();
-    let dst_npix = dst_w as usize * dst_h as usize;
-    let dst_max = (4 * 1024 * 1024).min(dst_npix);
-    let dst_img_scale =
-        Some(((dst_max as f32 / dst_npix as f32).sqrt() * dst_w as f32).floor() as usize);
-    let (dst_w, dst_h, mut img_data) = img2.as_vec_gray_f32(dst_img_scale);
+    let (dst_w, dst_h, mut img_data) = img2.as_vec_gray_f32(None);
     eprintln!(
         "Other size {dst_w}, {dst_h} ({:.2} Mpx)",
         (dst_w * dst_h) as f32 / 1024.0 / 1024.0
@@ -255,7 +272,7 @@ fn luma_kernel_pair_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         img.write("dst_kernel.png")?;
     }
 
-    let kernels = Kernels::new();
+    let kernels = cmd_args.create_kernels()?;
 
     if flags & 1 != 0 {
         eprintln!("Applying window_var_scaled to first");
@@ -280,6 +297,15 @@ fn luma_kernel_pair_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         )?;
     }
 
+    if flags & 4 != 0 {
+        eprintln!("Searching coarse-to-fine for the match of {xy:?}");
+        let sigma = cmd_args.sigmas().0 as f32;
+        let src_pyr = ImagePyramid::gaussian(&kernels, (src_w, src_h), &src_img, 6, sigma)?;
+        let dst_pyr = ImagePyramid::gaussian(&kernels, (dst_w, dst_h), &img_data, 6, sigma)?;
+        let (x, y, v) = kernels.window_corr_coarse_to_fine(&src_pyr, &dst_pyr, xy, ws, 4)?;
+        eprintln!("Best match at ({x}, {y}) with value {v}");
+    }
+
     {
         let img = ImageGray16::of_vec_f32(dst_w, dst_h, img_data.clone(), 1.0);
         img.write("dst2_kernel.png")?;
@@ -330,7 +356,7 @@ fn luma_kernel_pair_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     } else {
         eprintln!("Image not written as no output image provided");
     }
-    Ok("".into())
+    cmd_ok()
 }
 
 //a Image_process command
@@ -0,0 +1,182 @@
+//a Imports
+use std::time::Instant;
+
+use clap::Command;
+use serde::Serialize;
+use thunderclap::CommandBuilder;
+
+use ic_kernel::{Accelerator, KernelArgs, Kernels};
+
+use crate::cmd::{CmdArgs, CmdResult, CmdValue};
+
+//a Help
+//hi BENCH_LONG_HELP
+const BENCH_LONG_HELP: &str = "\
+Time kernels on the CPU and on the selected accelerator
+
+Each kernel is run on square images of each of the given widths
+(512, 1024 and 2048 if none are given), with the kernel size and
+sigma arguments; the time per run and the throughput in megapixels
+per second are reported for the CPU and the accelerator.
+
+If no kernels are given then a standard set is timed. Images larger
+than the accelerator buffer are processed in strips, so large sizes
+also measure the cost of tiling.
+";
+
+//ci BENCH_KERNELS
+/// Kernels timed if none are specified
+const BENCH_KERNELS: &[&str] = &[
+    "square",
+    "sqrt",
+    "window_sum_x",
+    "window_sum_y",
+    "gaussian_x",
+    "gaussian_y",
+    "sobel_mag",
+    "scharr_mag",
+    "log",
+    "dog",
+    "nms",
+];
+
+//a Bench
+//fi time_kernel
+/// Time a kernel on some data, returning the mean seconds per run
+/// (after one untimed run), or None if the kernel fails
+fn time_kernel(
+    kernels: &Kernels,
+    kernel: &str,
+    args: &KernelArgs,
+    data: &[f32],
+    iterations: usize,
+) -> Option<f64> {
+    let mut out = data.to_vec();
+    let work_items = out.len();
+    if let Err(e) = kernels.run_shader(kernel, args, work_items, None, &mut out) {
+        eprintln!("Kernel {kernel} failed: {e}");
+        return None;
+    }
+    let start = Instant::now();
+    for _ in 0..iterations {
+        out.copy_from_slice(data);
+        kernels
+            .run_shader(kernel, args, work_items, None, &mut out)
+            .ok()?;
+    }
+    Some(start.elapsed().as_secs_f64() / (iterations as f64))
+}
+
+//tp KernelTiming
+/// The timing of a kernel for one image width, in seconds per run
+#[derive(Debug, Serialize)]
+struct KernelTiming {
+    kernel: String,
+    width: usize,
+    cpu: Option<f64>,
+    accel: Option<f64>,
+}
+
+//fi bench_cmd
+fn bench_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("bench")
+        .about("Time kernels on the CPU and the accelerator")
+        .long_about(BENCH_LONG_HELP);
+
+    let mut build = CommandBuilder::new(command, Some(Box::new(bench_fn)));
+    CmdArgs::add_arg_kernel(&mut build, (0,));
+    CmdArgs::add_arg_kernel_size(&mut build, false);
+    CmdArgs::add_arg_sigma(&mut build);
+    CmdArgs::add_arg_positional_usize(&mut build, "widths", "Image widths to time", None, None);
+
+    build
+}
+
+//fi bench_fn
+fn bench_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let kernel_names: Vec<String> = {
+        if cmd_args.kernels().is_empty() {
+            BENCH_KERNELS.iter().map(|s| s.to_string()).collect()
+        } else {
+            cmd_args.kernels().to_vec()
+        }
+    };
+    let widths: Vec<usize> = {
+        if cmd_args.arg_usizes().is_empty() {
+            vec![512, 1024, 2048]
+        } else {
+            cmd_args.arg_usizes().to_vec()
+        }
+    };
+    let ws = cmd_args.kernel_size();
+    let (sigma, sigma_b) = cmd_args.sigmas();
+
+    let cpu = Kernels::of_config(
+        &cmd_args
+            .kernels_config()
+            .clone()
+            .with_accelerator(Accelerator::Cpu),
+    )?;
+    let accel = cmd_args.create_kernels()?;
+    eprintln!("Accelerator: {}", accel.description());
+
+    let mut timings = vec![];
+    let mut result = String::new();
+    result += &format!(
+        "{:16} {:>6} {:>10} {:>10} {:>10} {:>10} {:>8}\n",
+        "kernel", "width", "cpu ms", "cpu Mpx/s", "accel ms", "accel Mpx/s", "speedup"
+    );
+    for width in widths {
+        let npix = width * width;
+        let iterations = (4 * 1024 * 1024 / npix).clamp(1, 16);
+        let data: Vec<f32> = (0..npix)
+            .map(|i| ((i * 7919) % 251) as f32 / 251.0)
+            .collect();
+        let args: KernelArgs = (width, width).into();
+        let args = args.with_size(ws);
+        let args = args.with_sigmas(sigma as f32, sigma_b as f32);
+        for k in &kernel_names {
+            let cpu_t = time_kernel(&cpu, k, &args, &data, iterations);
+            let accel_t = time_kernel(&accel, k, &args, &data, iterations);
+            let ms = |t: Option<f64>| t.map_or("-".to_string(), |t| format!("{:.3}", t * 1000.0));
+            let mpx = |t: Option<f64>| {
+                t.map_or("-".to_string(), |t| {
+                    format!("{:.1}", npix as f64 / t / 1.0E6)
+                })
+            };
+            let speedup = match (cpu_t, accel_t) {
+                (Some(c), Some(a)) if a > 0.0 => format!("{:.2}", c / a),
+                _ => "-".to_string(),
+            };
+            result += &format!(
+                "{:16} {:>6} {:>10} {:>10} {:>10} {:>10} {:>8}\n",
+                k,
+                width,
+                ms(cpu_t),
+                mpx(cpu_t),
+                ms(accel_t),
+                mpx(accel_t),
+                speedup
+            );
+            timings.push(KernelTiming {
+                kernel: k.clone(),
+                width,
+                cpu: cpu_t,
+                accel: accel_t,
+            });
+        }
+    }
+    Ok(CmdValue::new(&timings)?.with_text(result))
+}
+
+//a Kernels command
+//fp kernels_cmd
+pub fn kernels_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("kernels").about("Information about and timing of kernels");
+
+    let mut build = CommandBuilder::new(command, None);
+
+    build.add_subcommand(bench_cmd());
+
+    build
+}
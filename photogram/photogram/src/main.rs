@@ -275,8 +275,10 @@ mod cmd;
 pub use cmd::{cmd_ok, CmdArgs, CmdResult};
 mod calibration;
 mod cip;
+mod frame;
 mod image_analyze;
 mod image_process;
+mod kernels;
 mod named_points;
 mod point_mappings;
 mod project;
@@ -293,7 +295,11 @@ fn main() -> Result<()> {
 
     CmdArgs::add_arg_verbose(&mut build);
     CmdArgs::add_arg_pretty_json(&mut build);
+    CmdArgs::add_arg_dry_run(&mut build);
+    CmdArgs::add_arg_no_backup(&mut build);
     CmdArgs::add_arg_path(&mut build);
+    CmdArgs::add_arg_accelerator(&mut build);
+    CmdArgs::add_arg_shader_path(&mut build);
 
     // Project comes first - if you want to change the camera database
     // for a project, then set that too
@@ -314,8 +320,10 @@ fn main() -> Result<()> {
     CmdArgs::add_arg_write_point_mapping(&mut build);
 
     build.add_subcommand(project::project_cmd());
+    build.add_subcommand(frame::frame_cmd());
     build.add_subcommand(image_process::image_process_cmd());
     build.add_subcommand(image_analyze::image_analyze_cmd());
+    build.add_subcommand(kernels::kernels_cmd());
     build.add_subcommand(star::star_cmd());
     build.add_subcommand(calibration::calibration_cmd());
     build.add_subcommand(cip::cip_cmd());
@@ -8,9 +8,10 @@ use thunderclap::CommandBuilder;
 use ic_base::Ray;
 use ic_camera::CameraProjection;
 use ic_image::Color;
-use ic_mapping::{NamedPoint, NamedPointSet};
+use ic_mapping::NamedPointSet;
+use ic_project::ProjectEdit;
 
-use crate::cmd::{CmdArgs, CmdResult};
+use crate::cmd::{cmd_ok, CmdArgs, CmdResult, CmdValue};
 
 //a Help
 //hi COMBINE_RAYS_FROM_MODEL_LONG_HELP
@@ -110,7 +111,7 @@ fn combine_rays_from_model_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     cmd_args.camera_mut().set_position(&position);
     let camera = cmd_args.camera();
     println!("{}", serde_json::to_string_pretty(&camera).unwrap());
-    Ok("".into())
+    cmd_ok()
 }
 
 //fi combine_rays_from_camera_cmd
@@ -153,7 +154,7 @@ fn combine_rays_from_camera_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         }
     }
 
-    Ok("".into())
+    cmd_ok()
 }
 
 //a Get model points
@@ -215,7 +216,7 @@ fn get_model_points_fn(cmd_args: &mut CmdArgs) -> CmdResult {
             }
         }
     }
-    result_nps.to_json(false)
+    Ok(CmdValue::new(&result_nps)?.with_text(result_nps.to_json(false)?))
 }
 
 //a List, add command
@@ -238,7 +239,7 @@ fn list_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     for np in nps {
         println!("{np}");
     }
-    Ok("".into())
+    cmd_ok()
 }
 
 //fi add_cmd
@@ -278,9 +279,9 @@ fn add_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         return Err(format!("Named point {name} already exists in the set").into());
     }
 
-    let np = NamedPoint::new(name, color, model);
-    cmd_args.project().nps_mut().add_np(&np);
-    Ok("".into())
+    let edit = ProjectEdit::add_named_point(name, color, model);
+    cmd_args.project().apply_edit(edit)?;
+    cmd_ok()
 }
 
 //fi update_model_cmd
@@ -306,22 +307,25 @@ fn update_model_cmd() -> CommandBuilder<CmdArgs> {
 fn update_model_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     let json = cmd_args.get_string_arg(0).unwrap();
     let new_nps = NamedPointSet::from_json(json)?;
+    let project = cmd_args.project();
     for (new_name, new_np) in new_nps.iter() {
-        if let Some(np) = cmd_args.nps().borrow().get_pt(new_name) {
-            if let Some(new_np_model) = new_np.opt_model() {
-                if let Some((_np_model, np_err)) = np.opt_model() {
-                    if np_err != 0.0 {
-                        np.set_model(Some(new_np_model));
-                    }
-                } else {
-                    np.set_model(Some(new_np_model));
+        let opt_np = project.nps_ref().get_pt(new_name);
+        let edit = {
+            if let Some(np) = opt_np {
+                let Some(new_np_model) = new_np.opt_model() else {
+                    continue;
+                };
+                if np.opt_model().is_some_and(|(_, np_err)| np_err == 0.0) {
+                    continue;
                 }
+                ProjectEdit::move_named_point(project, new_name, Some(new_np_model))?
+            } else {
+                ProjectEdit::add_named_point(new_name, *new_np.color(), new_np.opt_model())
             }
-        } else {
-            cmd_args.nps().borrow_mut().add_np(new_np);
-        }
+        };
+        project.apply_edit(edit)?;
     }
-    Ok("".into())
+    cmd_ok()
 }
 
 //a Named points command
@@ -3,7 +3,9 @@
 use clap::Command;
 use thunderclap::CommandBuilder;
 
-use crate::cmd::{CmdArgs, CmdResult};
+use ic_project::ProjectEdit;
+
+use crate::cmd::{cmd_ok, CmdArgs, CmdResult};
 
 //a Help
 //a Interrogate (show_mappings etc)
@@ -35,7 +37,7 @@ fn list_fn(cmd_args: &mut CmdArgs) -> CmdResult {
             m.error()
         );
     }
-    Ok("".into())
+    cmd_ok()
 }
 
 //fi add_cmd
@@ -67,6 +69,14 @@ fn add_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     let name = cmd_args.get_string_arg(0).unwrap();
     let pxy = cmd_args.arg_as_point2d(1)?;
     let error = cmd_args.get_f64_arg(0).unwrap_or(0.0);
+    let project_cip = cmd_args
+        .project_cip()
+        .filter(|_| cmd_args.cip().borrow().pms().ptr_eq(cmd_args.pms()));
+    if let Some(cip) = project_cip {
+        let edit = ProjectEdit::add_mapping(name, cip, pxy, error);
+        cmd_args.project().apply_edit(edit)?;
+        return cmd_ok();
+    }
     if !cmd_args
         .pms()
         .borrow_mut()
@@ -74,7 +84,7 @@ fn add_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     {
         Err(format!("Failed to add mapping for '{name}' to the point mapping set; it is probably not in the named point set").into())
     } else {
-        Ok("".into())
+        cmd_ok()
     }
 }
 
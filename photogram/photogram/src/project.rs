@@ -4,10 +4,19 @@ use clap::Command;
 
 use thunderclap::CommandBuilder;
 
+use ic_base::schema::{self, Versioned};
 use ic_base::Point3D;
-use ic_camera::CameraProjection;
+use ic_camera::{
+    CalibrationMapping, CameraDatabase, CameraInstanceDesc, CameraProjection, LensPolys,
+};
+use ic_mapping::{NamedPointSet, PointMappingSet};
+use ic_project::{
+    ArchiveOptions, CipFileDesc, Project, ProjectArchive, ProjectCheck, ProjectEdit,
+    ProjectFileDesc, ProjectHistory, ProvenanceLog,
+};
+use ic_stars::StarMapping;
 
-use crate::cmd::{CmdArgs, CmdResult};
+use crate::cmd::{cmd_ok, CmdArgs, CmdResult, CmdValue};
 
 //a Help
 //hi LIST_LONG_HELP
@@ -21,14 +30,13 @@ pub fn as_json_cmd() -> CommandBuilder<CmdArgs> {
         .about("As_Json the project as a *single* JSON file")
         .long_about(LIST_LONG_HELP);
 
-    
-
     CommandBuilder::new(command, Some(Box::new(as_json_fn)))
 }
 
 //fi as_json_fn
 fn as_json_fn(cmd_args: &mut CmdArgs) -> CmdResult {
-    cmd_args.project().to_json(cmd_args.pretty_json())
+    let project = cmd_args.project();
+    Ok(CmdValue::new(project)?.with_text(project.to_json(cmd_args.pretty_json())?))
 }
 
 //a List as a whole
@@ -38,8 +46,6 @@ pub fn list_cmd() -> CommandBuilder<CmdArgs> {
         .about("Operate on a list as a whole")
         .long_about(LIST_LONG_HELP);
 
-    
-
     CommandBuilder::new(command, Some(Box::new(list_fn)))
 }
 
@@ -56,7 +62,412 @@ fn list_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         "{}",
         serde_json::to_string_pretty(cmd_args.project()).unwrap()
     );
-    Ok("".into())
+    cmd_ok()
+}
+
+//a Check
+//hi CHECK_LONG_HELP
+const CHECK_LONG_HELP: &str = "\
+Check the consistency of the project, reporting errors and warnings.
+
+Errors are: files that are missing or fail to load; point mappings of
+names that are not in the named point set (which are dropped when the
+project is loaded); and images whose size does not match the sensor
+of their camera body.
+
+Warnings are: named points mapped more than once in a CIP; CIPs that map
+too few distinct named points with model positions to locate their
+camera; named points that are not mapped in any CIP; and, if the
+project has a frame, control points that are not named points with
+model positions, and a frame that has not been fitted.
+
+If a file is given then it is checked instead of the current project;
+it may be a project descriptor or a complete project, and it is
+checked even if it fails to load.
+
+With --json the result is the list of issues, each with its severity,
+kind, CIP, file and named point as appropriate.";
+
+//fp check_cmd
+pub fn check_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("check")
+        .about("Check the project for missing files and inconsistencies")
+        .long_about(CHECK_LONG_HELP);
+
+    let mut build = CommandBuilder::new(command, Some(Box::new(check_fn)));
+
+    CmdArgs::add_arg_positional_string(
+        &mut build,
+        "file",
+        "Project descriptor or project file to check",
+        Some(0),
+        None,
+    );
+
+    build
+}
+
+//fi check_fn
+fn check_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let check = {
+        if let Some(filename) = cmd_args.get_string_arg(0) {
+            ProjectCheck::of_file(cmd_args.path_set(), filename)
+        } else {
+            cmd_args.project().check(cmd_args.path_set())
+        }
+    };
+    Ok(CmdValue::new(&check)?.with_text(check.to_string()))
+}
+
+//a History
+//hi UNDO_LONG_HELP
+const UNDO_LONG_HELP: &str = "\
+Undo the most recent edit of the project, such as adding or moving a
+named point or mapping, or locating a camera.
+
+The history of edits is kept in a file next to the project file (for
+'proj.json' this is 'proj.history.json'); it is loaded with the
+project, and written with the project by '--write_project'.";
+
+//hi REDO_LONG_HELP
+const REDO_LONG_HELP: &str = "\
+Redo the most recently undone edit of the project.
+
+Making any other edit of the project discards the edits that may be
+redone.";
+
+//hi HISTORY_LONG_HELP
+const HISTORY_LONG_HELP: &str = "\
+List the edits of the project that may be undone, most recent first,
+each numbered by its position in the history; above them are listed
+any edits that have been undone and may be redone (marked '(redo)').
+
+With --json the history is returned with the edits themselves, as
+stored in the history file next to the project file.";
+
+//fp undo_cmd
+pub fn undo_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("undo")
+        .about("Undo the most recent edit of the project")
+        .long_about(UNDO_LONG_HELP);
+
+    CommandBuilder::new(command, Some(Box::new(undo_fn)))
+}
+
+//fi undo_fn
+fn undo_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let edit = cmd_args.project().undo()?;
+    undo_redo_result(cmd_args, edit, "undo")
+}
+
+//fp redo_cmd
+pub fn redo_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("redo")
+        .about("Redo the most recently undone edit of the project")
+        .long_about(REDO_LONG_HELP);
+
+    CommandBuilder::new(command, Some(Box::new(redo_fn)))
+}
+
+//fi redo_fn
+fn redo_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let edit = cmd_args.project().redo()?;
+    undo_redo_result(cmd_args, edit, "redo")
+}
+
+//fi undo_redo_result
+/// Complete an undo or redo of an edit, updating the current camera
+/// (in case the edit changed it) and writing any outputs
+fn undo_redo_result(cmd_args: &mut CmdArgs, edit: Option<ProjectEdit>, what: &str) -> CmdResult {
+    let Some(edit) = edit else {
+        return Err(format!("Nothing to {what}").into());
+    };
+    if cmd_args.project_cip().is_some() {
+        let camera = cmd_args.cip().borrow().camera().borrow().clone();
+        *cmd_args.camera_mut() = camera;
+    }
+    cmd_args.write_outputs()?;
+    Ok(CmdValue::new(&edit)?.with_text(format!("{what}: {edit}")))
+}
+
+//fp history_cmd
+pub fn history_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("history")
+        .about("List the edits of the project that may be undone and redone")
+        .long_about(HISTORY_LONG_HELP);
+
+    CommandBuilder::new(command, Some(Box::new(history_fn)))
+}
+
+//fi history_fn
+fn history_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let history = cmd_args.project().history();
+    let mut lines: Vec<_> = history
+        .redo_edits()
+        .iter()
+        .map(|edit| format!("  (redo) {edit}"))
+        .collect();
+    for (n, edit) in history.undo_edits().iter().enumerate().rev() {
+        lines.push(format!("{n:>6} {edit}"));
+    }
+    Ok(CmdValue::new(&*history)?.with_text(lines.join("\n")))
+}
+
+//a Archives
+//hi EXPORT_LONG_HELP
+const EXPORT_LONG_HELP: &str = "\
+Export the project as a single-file project archive (a tar file), for
+sharing the project.
+
+The archive contains a manifest (with the archive format version, the
+tool that created it, and SHA-256 hashes of its contents) and the
+complete project, including the camera database, named point set, and
+the cameras and point mappings of the CIPs; file names in the project
+are rewritten to be relative.
+
+With --include_images the images of the CIPs are included in the
+archive (and they must all be found); otherwise the CIPs refer to the
+images by their file names without directories, and the manifest
+records the hashes of those that are found so that they can be
+verified when the archive is used.
+
+An archive may be used directly with '--project_file'; it must have a
+'.tar' extension.";
+
+//fp export_cmd
+pub fn export_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("export")
+        .about("Export the project as a single-file project archive")
+        .long_about(EXPORT_LONG_HELP);
+
+    let mut build = CommandBuilder::new(command, Some(Box::new(export_fn)));
+
+    CmdArgs::add_arg_include_images(&mut build);
+    CmdArgs::add_arg_positional_string(
+        &mut build,
+        "archive",
+        "Project archive file ('.tar') to write",
+        Some(1),
+        None,
+    );
+
+    build
+}
+
+//fi export_fn
+fn export_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let filename = cmd_args.get_string_arg(0).unwrap().to_owned();
+    if !ProjectArchive::is_archive(&filename) {
+        return Err(format!("Project archive '{filename}' must have a '.tar' extension").into());
+    }
+    let created = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+    let tool = format!("photogram {}", env!("CARGO_PKG_VERSION"));
+    let options = ArchiveOptions::new(tool, created).with_images(cmd_args.include_images());
+    let archive = ProjectArchive::of_project(cmd_args.project(), cmd_args.path_set(), &options)?;
+    let mut data = vec![];
+    archive.write(&mut data)?;
+    cmd_args.write_binary_output(&filename, &data)?;
+    let manifest = archive.manifest();
+    let included = manifest.images().iter().filter(|i| i.included()).count();
+    let text = format!(
+        "Exported project to '{filename}' with {} files, {included} of {} images included",
+        manifest.files().len(),
+        manifest.images().len()
+    );
+    Ok(CmdValue::new(manifest)?.with_text(text))
+}
+
+//hi IMPORT_LONG_HELP
+const IMPORT_LONG_HELP: &str = "\
+Import a project archive, extracting the project (as 'project.json')
+and any images included in it to a directory.
+
+The archive is checked to be of a supported format version, and the
+files in it are checked against the hashes in its manifest. Images
+that were not included in the archive are looked for using the
+search path, and warnings are given for those that are not found or
+that do not match the images the archive was created with.";
+
+//fp import_cmd
+pub fn import_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("import")
+        .about("Extract a project archive to a directory")
+        .long_about(IMPORT_LONG_HELP);
+
+    let mut build = CommandBuilder::new(command, Some(Box::new(import_fn)));
+
+    CmdArgs::add_arg_positional_string(
+        &mut build,
+        "archive",
+        "Project archive file ('.tar') to import",
+        Some(1),
+        None,
+    );
+    CmdArgs::add_arg_positional_string(
+        &mut build,
+        "dir",
+        "Directory to extract the project to",
+        Some(1),
+        None,
+    );
+
+    build
+}
+
+//fi import_fn
+fn import_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let filename = cmd_args.get_string_arg(0).unwrap();
+    let dir = cmd_args.get_string_arg(1).unwrap();
+    let Some(path) = cmd_args.path_set().find_file(filename) else {
+        return Err(format!("Failed to find project archive '{filename}'").into());
+    };
+    let archive = ProjectArchive::read_file(path.display().to_string())?;
+    let warnings = archive.verify_images(cmd_args.path_set());
+    for warning in &warnings {
+        eprintln!("Warning: {warning}");
+    }
+    let project_path = {
+        if cmd_args.dry_run() {
+            eprintln!(
+                "Would extract {} files to '{dir}'",
+                archive.manifest().files().len()
+            );
+            std::path::Path::new(dir).join(archive.manifest().project())
+        } else {
+            archive.extract(dir)?
+        }
+    };
+    let project_path = project_path.display().to_string();
+    let value = serde_json::json!({
+        "project": project_path,
+        "manifest": archive.manifest(),
+        "warnings": warnings,
+    });
+    Ok(CmdValue::new(&value)?.with_text(project_path))
+}
+
+//a Schemas
+//hi SCHEMA_LONG_HELP
+const SCHEMA_LONG_HELP: &str = "\
+Output the JSON Schema document (draft 2020-12) for a type of JSON
+file, for validating files with other tools.
+
+Every JSON file written by photogram carries the version of its
+schema as a 'version' key; files without one were written before
+versions were added. Files of older versions are upgraded when they
+are read, and are written back in the current version.
+
+Without a name the schemas are listed with their current versions.";
+
+//hi WRITE_SCHEMAS_LONG_HELP
+const WRITE_SCHEMAS_LONG_HELP: &str = "\
+Write the JSON Schema document of every type of JSON file to a
+directory, as '<name>.schema.json'.";
+
+//tp SchemaDesc
+/// The name, current version and JSON Schema document generator of a
+/// type of versioned JSON file
+type SchemaDesc = (&'static str, usize, fn() -> serde_json::Value);
+
+//fi schemas
+/// The [SchemaDesc] of every type of versioned JSON file
+fn schemas() -> Vec<SchemaDesc> {
+    fn schema_of<T: Versioned>() -> SchemaDesc {
+        (T::SCHEMA_NAME, T::SCHEMA_VERSION, schema::json_schema::<T>)
+    }
+    vec![
+        schema_of::<Project>(),
+        schema_of::<ProjectFileDesc>(),
+        schema_of::<CipFileDesc>(),
+        schema_of::<ProjectHistory>(),
+        schema_of::<ProvenanceLog>(),
+        schema_of::<CameraDatabase>(),
+        schema_of::<CameraInstanceDesc>(),
+        schema_of::<LensPolys>(),
+        schema_of::<NamedPointSet>(),
+        schema_of::<PointMappingSet>(),
+        schema_of::<CalibrationMapping>(),
+        schema_of::<StarMapping>(),
+    ]
+}
+
+//fp schema_cmd
+pub fn schema_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("schema")
+        .about("Output the JSON Schema of a type of JSON file")
+        .long_about(SCHEMA_LONG_HELP);
+
+    let mut build = CommandBuilder::new(command, Some(Box::new(schema_fn)));
+
+    CmdArgs::add_arg_positional_string(
+        &mut build,
+        "name",
+        "Name of the schema (such as 'project' or 'named_point_set')",
+        Some(0),
+        None,
+    );
+
+    build
+}
+
+//fi schema_fn
+fn schema_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let schemas = schemas();
+    let Some(name) = cmd_args.get_string_arg(0) else {
+        let list: Vec<_> = schemas
+            .iter()
+            .map(|(name, version, _)| serde_json::json!({"name": name, "version": version}))
+            .collect();
+        let text: Vec<_> = schemas
+            .iter()
+            .map(|(name, version, _)| format!("{name} (version {version})"))
+            .collect();
+        return Ok(CmdValue::new(&list)?.with_text(text.join("\n")));
+    };
+    let Some((_, _, json_schema)) = schemas.iter().find(|(n, _, _)| *n == name) else {
+        let names: Vec<_> = schemas.iter().map(|(n, _, _)| *n).collect();
+        return Err(format!(
+            "Unknown schema '{name}'; the schemas are: {}",
+            names.join(", ")
+        )
+        .into());
+    };
+    CmdValue::new(&json_schema())
+}
+
+//fp write_schemas_cmd
+pub fn write_schemas_cmd() -> CommandBuilder<CmdArgs> {
+    let command = Command::new("write_schemas")
+        .about("Write the JSON Schema of every type of JSON file to a directory")
+        .long_about(WRITE_SCHEMAS_LONG_HELP);
+
+    let mut build = CommandBuilder::new(command, Some(Box::new(write_schemas_fn)));
+
+    CmdArgs::add_arg_positional_string(
+        &mut build,
+        "dir",
+        "Directory to write the schemas to",
+        Some(1),
+        None,
+    );
+
+    build
+}
+
+//fi write_schemas_fn
+fn write_schemas_fn(cmd_args: &mut CmdArgs) -> CmdResult {
+    let dir = std::path::Path::new(cmd_args.get_string_arg(0).unwrap()).to_owned();
+    let mut written = vec![];
+    for (name, _, json_schema) in schemas() {
+        let filename = dir
+            .join(format!("{name}.schema.json"))
+            .display()
+            .to_string();
+        let json = serde_json::to_string_pretty(&json_schema())?;
+        cmd_args.write_binary_output(&filename, json.as_bytes())?;
+        written.push(filename);
+    }
+    Ok(CmdValue::new(&written)?.with_text(written.join("\n")))
 }
 
 //a project command
@@ -70,6 +481,14 @@ pub fn project_cmd() -> CommandBuilder<CmdArgs> {
 
     build.add_subcommand(list_cmd());
     build.add_subcommand(as_json_cmd());
+    build.add_subcommand(check_cmd());
+    build.add_subcommand(undo_cmd());
+    build.add_subcommand(redo_cmd());
+    build.add_subcommand(history_cmd());
+    build.add_subcommand(export_cmd());
+    build.add_subcommand(import_cmd());
+    build.add_subcommand(schema_cmd());
+    build.add_subcommand(write_schemas_cmd());
 
     build
 }
@@ -6,7 +6,7 @@ use thunderclap::CommandBuilder;
 use ic_base::RollYaw;
 use ic_camera::CameraProjection;
 
-use crate::cmd::{cmd_ok, CmdArgs, CmdResult};
+use crate::cmd::{cmd_ok, CmdArgs, CmdResult, CmdValue};
 
 //a Help messages
 //hi STAR_LONG_HELP
@@ -149,6 +149,7 @@ fn star_find_stars_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         cmd_args.triangle_closeness(),
     )?;
     let mut best_match = (angle_orientations[0].1, angle_orientations[0].0, usize::MAX);
+    let mut candidates = vec![];
     for (i, (x, q)) in angle_orientations.iter().enumerate() {
         cmd_args.camera_mut().set_orientation(q);
         let _ = cmd_args.update_star_mappings();
@@ -167,6 +168,11 @@ fn star_find_stars_fn(cmd_args: &mut CmdArgs) -> CmdResult {
         if num_unmapped < best_match.2 {
             best_match = (orientation, *x, num_unmapped);
         }
+        candidates.push(serde_json::json!({
+            "angle_error": x.to_degrees(),
+            "unmapped": num_unmapped,
+            "total_error": total_error,
+        }));
         eprintln!(
             "Candidate {i} {} unmapped {num_unmapped} total_error {total_error} {q}",
             x.to_degrees()
@@ -180,10 +186,18 @@ fn star_find_stars_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     );
 
     cmd_args.camera_mut().set_orientation(&best_match.0);
-    let _ = cmd_args.update_star_mappings();
+    let (num_unmapped, total_error) = cmd_args.update_star_mappings();
 
     cmd_args.write_outputs()?;
-    cmd_args.output_camera()
+    let value = serde_json::json!({
+        "camera": cmd_args.camera(),
+        "angle_error": best_match.1.to_degrees(),
+        "unmapped": num_unmapped,
+        "mapped": cmd_args.star_mapping().mappings().len() - num_unmapped,
+        "total_error": total_error,
+        "candidates": candidates,
+    });
+    Ok(CmdValue::new(&value)?.with_text(cmd_args.camera().to_json(true)?))
 }
 
 //a Star orient
@@ -242,7 +256,12 @@ fn star_update_mapping_fn(cmd_args: &mut CmdArgs) -> CmdResult {
     );
 
     cmd_args.write_outputs()?;
-    cmd_args.output_star_mapping()
+    let value = serde_json::json!({
+        "star_mapping": cmd_args.star_mapping(),
+        "unmapped": num_unmapped,
+        "total_error": total_error,
+    });
+    Ok(CmdValue::new(&value)?.with_text(cmd_args.star_mapping().to_json(true)?))
 }
 
 //a Star show_star_mapping
@@ -20,6 +20,10 @@ struct KernelArgs {
     src_width: u32,
     /// Height of the source 'image'
     src_height: u32,
+    /// Standard deviation for Gaussian-derived kernels
+    sigma: f32,
+    /// Second standard deviation (difference-of-Gaussians)
+    sigma_b: f32,
 }
 
 struct ResultPair {
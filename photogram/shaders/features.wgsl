@@ -0,0 +1,261 @@
+// -*- rustic-analyzer-command: echo; rustic-format-on-save-method: none; -*-
+struct KernelArgs {
+    /// Width of the 'image'
+    width: u32,
+    /// Height of the 'image'
+    height: u32,
+    /// Center (or other) X coordinate if not in the work group
+    cx: u32,
+    /// Center (or other) Y coordinate if not in the work group
+    cy: u32,
+    /// Radius of a circle, window size, etc
+    size: u32,
+    /// Scale factor to apply (depends on kernel)
+    scale: f32,
+    /// Rotated cos_a
+    cos_a: f32,
+    /// Rotated dy
+    sin_a: f32,
+    /// Width of the source 'image'
+    src_width: u32,
+    /// Height of the source 'image'
+    src_height: u32,
+    /// Standard deviation for Gaussian-derived kernels
+    sigma: f32,
+    /// Second standard deviation (difference-of-Gaussians)
+    sigma_b: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> kernel_args: KernelArgs;
+
+@group(0) @binding(1)
+var<storage, read_write> out_data: array<f32>; // this is used as both input and output for convenience
+
+@group(0) @binding(2)
+var<storage, read> in_data: array<f32>; // this is used as input only
+
+@group(0) @binding(3)
+var<storage, read> in_data_b: array<f32>; // this is used as input only
+
+// Read in_data at x,y, clamping to the edges of the image
+fn in_at(x: i32, y: i32) -> f32 {
+    let cx = u32(clamp(x, 0, i32(kernel_args.width) - 1));
+    let cy = u32(clamp(y, 0, i32(kernel_args.height) - 1));
+    return in_data[cx + cy * kernel_args.width];
+}
+
+// Read in_data_b at x,y, clamping to the edges of the image
+fn in_b_at(x: i32, y: i32) -> f32 {
+    let cx = u32(clamp(x, 0, i32(kernel_args.width) - 1));
+    let cy = u32(clamp(y, 0, i32(kernel_args.height) - 1));
+    return in_data_b[cx + cy * kernel_args.width];
+}
+
+// The second standard deviation; if not positive then 1.6 times sigma
+fn second_sigma() -> f32 {
+    return select(kernel_args.sigma * 1.6, kernel_args.sigma_b, kernel_args.sigma_b > 0.0);
+}
+
+// Radius of a Gaussian-derived kernel - size if nonzero, else 3 sigma
+fn radius_for_sigma(sigma: f32) -> i32 {
+    return select(i32(ceil(3.0 * sigma)), i32(kernel_args.size), kernel_args.size > 0u);
+}
+
+// Unnormalized Gaussian weight at a squared distance of d2
+fn gaussian_weight(d2: f32, sigma: f32) -> f32 {
+    let s = max(sigma, 0.01);
+    return exp(-d2 / (2.0 * s * s));
+}
+
+// Gradient at x,y using a 3x3 kernel with the given edge and centre
+// weights (1,2 for Sobel, 3,10 for Scharr), normalized so that a
+// unit ramp has a unit gradient
+fn gradient(x: i32, y: i32, edge: f32, centre: f32) -> vec2<f32> {
+    let tl = in_at(x - 1, y - 1);
+    let t  = in_at(x,     y - 1);
+    let tr = in_at(x + 1, y - 1);
+    let l  = in_at(x - 1, y);
+    let r  = in_at(x + 1, y);
+    let bl = in_at(x - 1, y + 1);
+    let b  = in_at(x,     y + 1);
+    let br = in_at(x + 1, y + 1);
+    let gx = (tr + br - tl - bl) * edge + (r - l) * centre;
+    let gy = (bl + br - tl - tr) * edge + (b - t) * centre;
+    return vec2<f32>(gx, gy) / (2.0 * (2.0 * edge + centre));
+}
+
+// Separable Gaussian blur in X, normalized over the window
+@compute
+@workgroup_size(256,1)
+fn compute_gaussian_x(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = i32(global_id.x % kernel_args.width);
+    let y = i32(global_id.x / kernel_args.width);
+    let r = radius_for_sigma(kernel_args.sigma);
+    var sum = 0.0;
+    var total = 0.0;
+    for ( var i: i32 = -r; i <= r; i++ ) {
+        let w = gaussian_weight(f32(i * i), kernel_args.sigma);
+        sum += w * in_at(x + i, y);
+        total += w;
+    }
+    out_data[global_id.x] = sum / total * kernel_args.scale;
+}
+
+// Separable Gaussian blur in Y, normalized over the window
+@compute
+@workgroup_size(256,1)
+fn compute_gaussian_y(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = i32(global_id.x % kernel_args.width);
+    let y = i32(global_id.x / kernel_args.width);
+    let r = radius_for_sigma(kernel_args.sigma);
+    var sum = 0.0;
+    var total = 0.0;
+    for ( var i: i32 = -r; i <= r; i++ ) {
+        let w = gaussian_weight(f32(i * i), kernel_args.sigma);
+        sum += w * in_at(x, y + i);
+        total += w;
+    }
+    out_data[global_id.x] = sum / total * kernel_args.scale;
+}
+
+@compute
+@workgroup_size(256,1)
+fn compute_sobel_mag(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = i32(global_id.x % kernel_args.width);
+    let y = i32(global_id.x / kernel_args.width);
+    out_data[global_id.x] = length(gradient(x, y, 1.0, 2.0)) * kernel_args.scale;
+}
+
+@compute
+@workgroup_size(256,1)
+fn compute_sobel_angle(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = i32(global_id.x % kernel_args.width);
+    let y = i32(global_id.x / kernel_args.width);
+    let g = gradient(x, y, 1.0, 2.0);
+    out_data[global_id.x] = atan2(g.y, g.x) * kernel_args.scale;
+}
+
+@compute
+@workgroup_size(256,1)
+fn compute_scharr_mag(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = i32(global_id.x % kernel_args.width);
+    let y = i32(global_id.x / kernel_args.width);
+    out_data[global_id.x] = length(gradient(x, y, 3.0, 10.0)) * kernel_args.scale;
+}
+
+@compute
+@workgroup_size(256,1)
+fn compute_scharr_angle(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = i32(global_id.x % kernel_args.width);
+    let y = i32(global_id.x / kernel_args.width);
+    let g = gradient(x, y, 3.0, 10.0);
+    out_data[global_id.x] = atan2(g.y, g.x) * kernel_args.scale;
+}
+
+// Scale-normalized negated Laplacian of Gaussian, so that bright
+// blobs of about sigma radius give a positive peak
+//
+// The mean of the (truncated) kernel is removed so that flat regions
+// give zero
+@compute
+@workgroup_size(256,1)
+fn compute_log(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = i32(global_id.x % kernel_args.width);
+    let y = i32(global_id.x / kernel_args.width);
+    let sigma = max(kernel_args.sigma, 0.01);
+    let s2 = sigma * sigma;
+    let r = radius_for_sigma(sigma);
+    var sum_wi = 0.0;
+    var sum_w = 0.0;
+    var sum_i = 0.0;
+    for ( var dy: i32 = -r; dy <= r; dy++ ) {
+        for ( var dx: i32 = -r; dx <= r; dx++ ) {
+            let d2 = f32(dx * dx + dy * dy);
+            let w = (1.0 - d2 / (2.0 * s2)) * gaussian_weight(d2, sigma) / (3.14159265 * s2);
+            let i = in_at(x + dx, y + dy);
+            sum_wi += w * i;
+            sum_w += w;
+            sum_i += i;
+        }
+    }
+    let n = f32((2 * r + 1) * (2 * r + 1));
+    out_data[global_id.x] = (sum_wi - sum_w / n * sum_i) * kernel_args.scale;
+}
+
+// Difference of Gaussians (sigma minus sigma_b), each normalized
+// over the window; with sigma < sigma_b bright blobs are positive
+@compute
+@workgroup_size(256,1)
+fn compute_dog(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = i32(global_id.x % kernel_args.width);
+    let y = i32(global_id.x / kernel_args.width);
+    let sigma_a = kernel_args.sigma;
+    let sigma_b = second_sigma();
+    let r = radius_for_sigma(max(sigma_a, sigma_b));
+    var sum_a = 0.0;
+    var sum_b = 0.0;
+    var total_a = 0.0;
+    var total_b = 0.0;
+    for ( var dy: i32 = -r; dy <= r; dy++ ) {
+        for ( var dx: i32 = -r; dx <= r; dx++ ) {
+            let d2 = f32(dx * dx + dy * dy);
+            let w_a = gaussian_weight(d2, sigma_a);
+            let w_b = gaussian_weight(d2, sigma_b);
+            let i = in_at(x + dx, y + dy);
+            sum_a += w_a * i;
+            sum_b += w_b * i;
+            total_a += w_a;
+            total_b += w_b;
+        }
+    }
+    out_data[global_id.x] = (sum_a / total_a - sum_b / total_b) * kernel_args.scale;
+}
+
+// Non-maximum suppression in a window of radius size (at least 1)
+//
+// A value is kept if it is positive and no other value in the window
+// is larger; otherwise it is set to zero
+@compute
+@workgroup_size(256,1)
+fn compute_nms(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = i32(global_id.x % kernel_args.width);
+    let y = i32(global_id.x / kernel_args.width);
+    let r = i32(max(kernel_args.size, 1u));
+    let v = in_data[global_id.x];
+    var is_max = v > 0.0;
+    for ( var dy: i32 = -r; dy <= r; dy++ ) {
+        for ( var dx: i32 = -r; dx <= r; dx++ ) {
+            is_max = is_max && (in_at(x + dx, y + dy) <= v);
+        }
+    }
+    out_data[global_id.x] = select(0.0, v * kernel_args.scale, is_max);
+}
+
+// Non-maximum suppression of an edge magnitude along the gradient direction
+//
+// This is a binary kernel: in_data is the gradient angle (as produced
+// by sobel_angle or scharr_angle with a scale of 1), and in_data_b is
+// the gradient magnitude
+@compute
+@workgroup_size(256,1)
+fn compute_nms_edge(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = i32(global_id.x % kernel_args.width);
+    let y = i32(global_id.x / kernel_args.width);
+    let angle = in_data[global_id.x];
+    let dx = i32(round(cos(angle)));
+    let dy = i32(round(sin(angle)));
+    let v = in_data_b[global_id.x];
+    let is_max = v >= in_b_at(x + dx, y + dy) && v >= in_b_at(x - dx, y - dy);
+    out_data[global_id.x] = select(0.0, v * kernel_args.scale, is_max);
+}
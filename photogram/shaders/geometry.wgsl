@@ -0,0 +1,70 @@
+// -*- rustic-analyzer-command: echo; rustic-format-on-save-method: none; -*-
+struct KernelArgs {
+    /// Width of the 'image'
+    width: u32,
+    /// Height of the 'image'
+    height: u32,
+    /// Center (or other) X coordinate if not in the work group
+    cx: u32,
+    /// Center (or other) Y coordinate if not in the work group
+    cy: u32,
+    /// Radius of a circle, window size, etc
+    size: u32,
+    /// Scale factor to apply (depends on kernel)
+    scale: f32,
+    /// Rotated cos_a
+    cos_a: f32,
+    /// Rotated dy
+    sin_a: f32,
+    /// Width of the source 'image'
+    src_width: u32,
+    /// Height of the source 'image'
+    src_height: u32,
+    /// Standard deviation for Gaussian-derived kernels
+    sigma: f32,
+    /// Second standard deviation (difference-of-Gaussians)
+    sigma_b: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> kernel_args: KernelArgs;
+
+@group(0) @binding(1)
+var<storage, read_write> out_data: array<f32>; // this is used as both input and output for convenience
+
+@group(0) @binding(2)
+var<storage, read> in_data: array<f32>; // this is used as input only
+
+@group(0) @binding(3)
+var<storage, read> in_data_b: array<f32>; // this is used as input only
+
+// The subtended-angle error of a set of points against a set of lines
+//
+// in_data holds 8 values per line: p0.xyz, the angle the line
+// subtends, p1.xyz, and an unused value
+//
+// out_data holds 4 values per point: p.xyz, and the sum of the squared
+// errors in the angles subtended is written to the fourth
+//
+// kernel_args.width is the number of points, and kernel_args.size the
+// number of lines
+//
+// The angle is derived from atan2 of the cross and dot products
+// rather than the arc-cosine, as the latter loses precision for
+// small angles
+@compute
+@workgroup_size(256,1)
+fn compute_subtended_err2(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width { return; }
+    let i = global_id.x * 4u;
+    let p = vec3<f32>(out_data[i], out_data[i + 1u], out_data[i + 2u]);
+    var err2 = 0.0;
+    for (var l = 0u; l < kernel_args.size; l++) {
+        let j = l * 8u;
+        let a = vec3<f32>(in_data[j], in_data[j + 1u], in_data[j + 2u]) - p;
+        let b = vec3<f32>(in_data[j + 4u], in_data[j + 5u], in_data[j + 6u]) - p;
+        let err = atan2(length(cross(a, b)), dot(a, b)) - in_data[j + 3u];
+        err2 += err * err;
+    }
+    out_data[i + 3u] = err2;
+}
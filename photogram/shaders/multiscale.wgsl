@@ -0,0 +1,139 @@
+// -*- rustic-analyzer-command: echo; rustic-format-on-save-method: none; -*-
+struct KernelArgs {
+    /// Width of the 'image'
+    width: u32,
+    /// Height of the 'image'
+    height: u32,
+    /// Center (or other) X coordinate if not in the work group
+    cx: u32,
+    /// Center (or other) Y coordinate if not in the work group
+    cy: u32,
+    /// Radius of a circle, window size, etc
+    size: u32,
+    /// Scale factor to apply (depends on kernel)
+    scale: f32,
+    /// Rotated cos_a
+    cos_a: f32,
+    /// Rotated dy
+    sin_a: f32,
+    /// Width of the source 'image'
+    src_width: u32,
+    /// Height of the source 'image'
+    src_height: u32,
+    /// Standard deviation for Gaussian-derived kernels
+    sigma: f32,
+    /// Second standard deviation (difference-of-Gaussians)
+    sigma_b: f32,
+}
+
+@group(0) @binding(0)
+var<uniform> kernel_args: KernelArgs;
+
+@group(0) @binding(1)
+var<storage, read_write> out_data: array<f32>; // this is used as both input and output for convenience
+
+@group(0) @binding(2)
+var<storage, read> in_data: array<f32>; // this is used as input only
+
+@group(0) @binding(3)
+var<storage, read> in_data_b: array<f32>; // this is used as input only
+
+// Read the source image (of src_width by src_height) at x,y, clamping to the edges
+fn src_at(x: i32, y: i32) -> f32 {
+    let cx = u32(clamp(x, 0, i32(kernel_args.src_width) - 1));
+    let cy = u32(clamp(y, 0, i32(kernel_args.src_height) - 1));
+    return in_data[cx + cy * kernel_args.src_width];
+}
+
+// Sum of a summed-area table in_data over x0 < x <= x1, y0 < y <= y1
+//
+// x0 and y0 may be -1 to indicate the left or top edge of the image
+fn sat_sum(x0: i32, y0: i32, x1: i32, y1: i32) -> f32 {
+    let w = i32(kernel_args.width);
+    let cx0 = max(x0, 0);
+    let cy0 = max(y0, 0);
+    let d = in_data[x1 + y1 * w];
+    let b = select(0.0, in_data[x1 + cy0 * w], y0 >= 0);
+    let c = select(0.0, in_data[cx0 + y1 * w], x0 >= 0);
+    let a = select(0.0, in_data[cx0 + cy0 * w], x0 >= 0 && y0 >= 0);
+    return d - b - c + a;
+}
+
+// Invoke with one work item per *row* of the image
+//
+// Each invocation accumulates the running sum along its row
+@compute
+@workgroup_size(64,1)
+fn compute_integral_x(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let y = global_id.x;
+    if y >= kernel_args.height { return; }
+    var sum = 0.0;
+    let ofs = y * kernel_args.width;
+    for ( var x: u32 = 0; x < kernel_args.width; x++ ) {
+        sum += in_data[ofs + x];
+        out_data[ofs + x] = sum * kernel_args.scale;
+    }
+}
+
+// Invoke with one work item per *column* of the image
+//
+// Each invocation accumulates the running sum down its column
+@compute
+@workgroup_size(64,1)
+fn compute_integral_y(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    let x = global_id.x;
+    if x >= kernel_args.width { return; }
+    var sum = 0.0;
+    for ( var y: u32 = 0; y < kernel_args.height; y++ ) {
+        sum += in_data[x + y * kernel_args.width];
+        out_data[x + y * kernel_args.width] = sum * kernel_args.scale;
+    }
+}
+
+// Mean over a window of size*size centred on each pixel, using a
+// summed-area table as the input; the window is clipped to the image
+@compute
+@workgroup_size(256,1)
+fn compute_window_mean_sat(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = i32(global_id.x % kernel_args.width);
+    let y = i32(global_id.x / kernel_args.width);
+    let half_ws = i32(kernel_args.size / 2);
+    let x0 = max(x - half_ws - 1, -1);
+    let y0 = max(y - half_ws - 1, -1);
+    let x1 = min(x + half_ws, i32(kernel_args.width) - 1);
+    let y1 = min(y + half_ws, i32(kernel_args.height) - 1);
+    let n = f32((x1 - x0) * (y1 - y0));
+    out_data[global_id.x] = sat_sum(x0, y0, x1, y1) / n * kernel_args.scale;
+}
+
+// Halve a source image (src_width by src_height) by averaging 2x2 blocks
+//
+// This should be used after a Gaussian blur to build a pyramid level
+@compute
+@workgroup_size(256,1)
+fn compute_downsample(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = i32(global_id.x % kernel_args.width) * 2;
+    let y = i32(global_id.x / kernel_args.width) * 2;
+    let sum = src_at(x, y) + src_at(x + 1, y) + src_at(x, y + 1) + src_at(x + 1, y + 1);
+    out_data[global_id.x] = sum * 0.25 * kernel_args.scale;
+}
+
+// Bilinearly resample a source image (src_width by src_height) to width by height
+@compute
+@workgroup_size(256,1)
+fn compute_upsample(@builtin(global_invocation_id) global_id: vec3<u32>) {
+    if global_id.x >= kernel_args.width * kernel_args.height { return; }
+    let x = f32(global_id.x % kernel_args.width);
+    let y = f32(global_id.x / kernel_args.width);
+    let sx = max((x + 0.5) * f32(kernel_args.src_width) / f32(kernel_args.width) - 0.5, 0.0);
+    let sy = max((y + 0.5) * f32(kernel_args.src_height) / f32(kernel_args.height) - 0.5, 0.0);
+    let x0 = i32(floor(sx));
+    let y0 = i32(floor(sy));
+    let fx = sx - f32(x0);
+    let fy = sy - f32(y0);
+    let top = mix(src_at(x0, y0), src_at(x0 + 1, y0), fx);
+    let bottom = mix(src_at(x0, y0 + 1), src_at(x0 + 1, y0 + 1), fx);
+    out_data[global_id.x] = mix(top, bottom, fy) * kernel_args.scale;
+}
@@ -19,6 +19,10 @@ struct KernelArgs {
      src_width: u32,
     /// Height of the source 'image'
      src_height: u32,
+    /// Standard deviation for Gaussian-derived kernels
+     sigma: f32,
+    /// Second standard deviation (difference-of-Gaussians)
+     sigma_b: f32,
 }
 
 struct ResultPair {
@@ -187,6 +191,22 @@ fn compute_vec_square(@builtin(global_invocation_id) global_id: vec3<u32>) {
    out_data[global_id.x] = in_data[global_id.x]*in_data[global_id.x]* kernel_args.scale;
 }
 
+// Binary: out = (in_data_b + in_data) * scale
+@compute
+@workgroup_size(256,1)
+fn compute_vec_add_scaled(@builtin(global_invocation_id) global_id: vec3<u32>) {
+   if global_id.x >= kernel_args.width * kernel_args.height { return; }
+   out_data[global_id.x] = (in_data_b[global_id.x] + in_data[global_id.x]) * kernel_args.scale;
+}
+
+// Binary: out = (in_data_b - in_data) * scale
+@compute
+@workgroup_size(256,1)
+fn compute_vec_sub_scaled(@builtin(global_invocation_id) global_id: vec3<u32>) {
+   if global_id.x >= kernel_args.width * kernel_args.height { return; }
+   out_data[global_id.x] = (in_data_b[global_id.x] - in_data[global_id.x]) * kernel_args.scale;
+}
+
 @compute
 @workgroup_size(256,1)
 fn compute_window_sum_x(@builtin(global_invocation_id) global_id: vec3<u32>) {
@@ -0,0 +1,276 @@
+//a Documentation
+//! Control flow for batch files
+//!
+//! A batch file is a sequence of command lines; in addition to
+//! commands (and the builtins) it may contain the following
+//! directives, each of which must be the first word of its line:
+//!
+//! ```text
+//! for <var> in <value>*             repeat the body with ${var} set to each value
+//! for <var> from <a> to <b> [step <s>]
+//!                                   repeat the body for a numeric range (inclusive)
+//! if <lhs> <op> <rhs>               execute the body if the comparison is true
+//! if <value>                        true unless empty, '0' or 'false'
+//! else                              optional, between an 'if' and its 'end'
+//! end                               end a 'for', 'if' or 'macro'
+//! include <file>                    execute another batch file
+//! macro <name> <param>*             define a macro; its body is not executed
+//! call <name> <arg>*                execute a macro with ${param} set to each arg
+//! ```
+//!
+//! The comparison operators are `==`, `!=`, `<`, `<=`, `>` and
+//! `>=`; if both sides are numbers then they are compared as numbers,
+//! otherwise as strings.
+//!
+//! The directive lines are parsed (with variable substitution) when
+//! they are executed, as are the lines of their bodies; hence the
+//! body of a loop sees the loop variable, and `if ${0} > 0.5` tests
+//! the last result.
+
+//a Imports
+use std::rc::Rc;
+
+//a Constants
+//ci MAX_FOR_VALUES
+/// Maximum number of values of a numeric range in a 'for' loop
+const MAX_FOR_VALUES: usize = 1_000_000;
+
+//a BatchStmt
+//tp BatchStmt
+/// A statement of a batch file, with the (1-based) line number it
+/// starts at
+#[derive(Debug)]
+pub(crate) enum BatchStmt {
+    /// A command line (or an include or call)
+    Line(usize, String),
+    /// A 'for' loop, with the text of its header
+    For(usize, String, Vec<BatchStmt>),
+    /// An 'if', with the text of its header and the two branches
+    If(usize, String, Vec<BatchStmt>, Vec<BatchStmt>),
+    /// A macro definition, with the text of its header
+    Macro(usize, String, Rc<[BatchStmt]>),
+}
+
+//ip BatchStmt
+impl BatchStmt {
+    //fp parse
+    /// Parse the text of a batch file into its statements
+    ///
+    /// Only the structure is determined here; directives are parsed
+    /// fully when they are executed
+    pub(crate) fn parse(s: &str) -> Result<Vec<Self>, String> {
+        // Stack of the blocks being parsed: the header line, the
+        // directive, the header text, the statements so far, and the
+        // 'then' statements if an 'else' has been seen
+        type Block = (
+            usize,
+            String,
+            String,
+            Vec<BatchStmt>,
+            Option<Vec<BatchStmt>>,
+        );
+        let mut blocks: Vec<Block> = vec![];
+        let mut stmts = vec![];
+        for (i, l) in s.lines().enumerate() {
+            let line = i + 1;
+            let l = l.trim();
+            let directive = l.split_whitespace().next().unwrap_or("");
+            if directive.is_empty() || directive.starts_with('#') {
+                continue;
+            }
+            match directive {
+                "for" | "if" | "macro" => {
+                    blocks.push((
+                        line,
+                        directive.into(),
+                        l.into(),
+                        std::mem::take(&mut stmts),
+                        None,
+                    ));
+                }
+                "else" => {
+                    let Some(block) = blocks.last_mut() else {
+                        return Err(format!("line {line}: 'else' outside of an 'if'"));
+                    };
+                    if block.1 != "if" || block.4.is_some() {
+                        return Err(format!("line {line}: unexpected 'else'"));
+                    }
+                    block.4 = Some(std::mem::take(&mut stmts));
+                }
+                "end" => {
+                    let Some((start, directive, header, outer, then_stmts)) = blocks.pop() else {
+                        return Err(format!(
+                            "line {line}: 'end' without a 'for', 'if' or 'macro'"
+                        ));
+                    };
+                    let body = std::mem::replace(&mut stmts, outer);
+                    let stmt = match directive.as_str() {
+                        "for" => Self::For(start, header, body),
+                        "macro" => Self::Macro(start, header, body.into()),
+                        _ => match then_stmts {
+                            Some(then_stmts) => Self::If(start, header, then_stmts, body),
+                            None => Self::If(start, header, body, vec![]),
+                        },
+                    };
+                    stmts.push(stmt);
+                }
+                _ => {
+                    stmts.push(Self::Line(line, l.into()));
+                }
+            }
+        }
+        if let Some((line, directive, ..)) = blocks.last() {
+            return Err(format!("line {line}: '{directive}' has no matching 'end'"));
+        }
+        Ok(stmts)
+    }
+
+    //fp is_incomplete
+    /// Return true if the text of a batch file has a 'for', 'if' or
+    /// 'macro' without its 'end', so that more lines are required
+    pub(crate) fn is_incomplete(s: &str) -> bool {
+        let mut depth = 0_isize;
+        for l in s.lines() {
+            match l.split_whitespace().next() {
+                Some("for") | Some("if") | Some("macro") => depth += 1,
+                Some("end") => depth -= 1,
+                _ => (),
+            }
+        }
+        depth > 0
+    }
+
+    //ap line
+    /// The line number the statement starts at
+    pub(crate) fn line(&self) -> usize {
+        match self {
+            Self::Line(line, _) => *line,
+            Self::For(line, ..) => *line,
+            Self::If(line, ..) => *line,
+            Self::Macro(line, ..) => *line,
+        }
+    }
+}
+
+//a BatchMacro
+//tp BatchMacro
+/// A macro defined in a batch file
+#[derive(Debug, Clone)]
+pub(crate) struct BatchMacro {
+    /// The name of the file the macro was defined in
+    pub file: String,
+    /// The names of the parameters
+    pub params: Vec<String>,
+    /// The statements of the body
+    pub body: Rc<[BatchStmt]>,
+}
+
+//ip BatchMacro
+impl BatchMacro {
+    //fp of_header
+    /// Create a macro from its parsed header ('macro', name, and
+    /// parameter names) and its body
+    pub(crate) fn of_header(
+        file: &str,
+        header: &[String],
+        body: Rc<[BatchStmt]>,
+    ) -> Result<(String, Self), String> {
+        let Some(name) = header.get(1) else {
+            return Err("'macro' requires a name".into());
+        };
+        let params = header[2..].to_vec();
+        let m = Self {
+            file: file.into(),
+            params,
+            body,
+        };
+        Ok((name.clone(), m))
+    }
+}
+
+//a Directives
+//fi parse_number
+/// Parse a number for a comparison or range, returning None if it is
+/// not a number
+fn parse_number(s: &str) -> Option<f64> {
+    s.trim().parse::<f64>().ok().filter(|f| f.is_finite())
+}
+
+//fp for_values
+/// Evaluate the parsed header of a 'for' loop, returning the loop
+/// variable and the values it takes
+pub(crate) fn for_values(header: &[String]) -> Result<(String, Vec<String>), String> {
+    let Some(var) = header.get(1) else {
+        return Err("'for' requires a variable name".into());
+    };
+    match header.get(2).map(|s| s.as_str()) {
+        Some("in") => Ok((var.clone(), header[3..].to_vec())),
+        Some("from") => {
+            let (from, to, step) = match &header[3..] {
+                [a, to, b] if to == "to" => (a, b, None),
+                [a, to, b, step, s] if to == "to" && step == "step" => (a, b, Some(s)),
+                _ => {
+                    return Err("'for' range must be '<var> from <a> to <b> [step <s>]'".into());
+                }
+            };
+            let ints = [Some(from), Some(to), step]
+                .into_iter()
+                .flatten()
+                .all(|s| s.parse::<i64>().is_ok());
+            let number = |s: &String| {
+                parse_number(s).ok_or_else(|| format!("'for' range value '{s}' is not a number"))
+            };
+            let a = number(from)?;
+            let b = number(to)?;
+            let s = step.map(number).transpose()?.unwrap_or(1.0);
+            if s == 0.0 {
+                return Err("'for' range step must be non-zero".into());
+            }
+            let n = ((b - a) / s + 1E-9).floor();
+            if n < 0.0 {
+                return Ok((var.clone(), vec![]));
+            }
+            if n >= MAX_FOR_VALUES as f64 {
+                return Err(format!(
+                    "'for' range has too many values (more than {MAX_FOR_VALUES})"
+                ));
+            }
+            let values = (0..=(n as usize))
+                .map(|i| {
+                    let v = a + (i as f64) * s;
+                    if ints {
+                        format!("{}", v as i64)
+                    } else {
+                        format!("{v}")
+                    }
+                })
+                .collect();
+            Ok((var.clone(), values))
+        }
+        _ => Err("'for' must be '<var> in <values>' or '<var> from <a> to <b>'".into()),
+    }
+}
+
+//fp condition
+/// Evaluate the parsed header of an 'if'
+pub(crate) fn condition(header: &[String]) -> Result<bool, String> {
+    match &header[1..] {
+        [value] => Ok(!(value.is_empty() || value == "0" || value == "false")),
+        [lhs, op, rhs] => {
+            let ordering = match (parse_number(lhs), parse_number(rhs)) {
+                (Some(l), Some(r)) => l.partial_cmp(&r).unwrap(),
+                _ => lhs.cmp(rhs),
+            };
+            match op.as_str() {
+                "==" => Ok(ordering.is_eq()),
+                "!=" => Ok(ordering.is_ne()),
+                "<" => Ok(ordering.is_lt()),
+                "<=" => Ok(ordering.is_le()),
+                ">" => Ok(ordering.is_gt()),
+                ">=" => Ok(ordering.is_ge()),
+                _ => Err(format!("unknown comparison '{op}' in 'if'")),
+            }
+        }
+        _ => Err("'if' must be '<value>' or '<lhs> <op> <rhs>'".into()),
+    }
+}
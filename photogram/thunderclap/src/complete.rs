@@ -0,0 +1,150 @@
+//a Imports
+use std::path::Path;
+
+use clap::{Arg, ArgAction, Command, ValueHint};
+
+use crate::docs::{built, possible_values, takes_value, visible_args, visible_subcommands};
+
+//a Constants
+//ci DIRECTIVES
+/// The batch directives (and REPL commands) that may start a line
+const DIRECTIVES: &[&str] = &[
+    "call", "else", "end", "exit", "for", "if", "include", "macro", "quit",
+];
+
+//a Completion
+//fi find_option
+/// Find the option of a command for a word such as '--name' or '-n'
+fn find_option<'a>(command: &'a Command, word: &str) -> Option<&'a Arg> {
+    if let Some(long) = word.strip_prefix("--") {
+        command.get_arguments().find(|a| a.get_long() == Some(long))
+    } else {
+        let mut chars = word.strip_prefix('-')?.chars();
+        let short = chars.next()?;
+        if chars.next().is_some() {
+            return None;
+        }
+        command
+            .get_arguments()
+            .find(|a| a.get_short() == Some(short))
+    }
+}
+
+//fi complete_path
+/// Complete a partial file path, with directories having a trailing
+/// '/'; hidden files are only included if the partial name starts
+/// with '.'
+fn complete_path(word: &str, dirs_only: bool) -> Vec<String> {
+    let (dir, prefix) = match word.rfind('/') {
+        Some(n) => (&word[..n + 1], &word[n + 1..]),
+        None => ("", word),
+    };
+    let read_dir = if dir.is_empty() {
+        Path::new(".")
+    } else {
+        Path::new(dir)
+    };
+    let Ok(entries) = std::fs::read_dir(read_dir) else {
+        return vec![];
+    };
+    let mut result = vec![];
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+            continue;
+        }
+        let is_dir = entry.path().is_dir();
+        if is_dir {
+            result.push(format!("{dir}{name}/"));
+        } else if !dirs_only {
+            result.push(format!("{dir}{name}"));
+        }
+    }
+    result
+}
+
+//fi complete_value
+/// Complete the value of an argument
+fn complete_value(arg: &Arg, word: &str) -> Vec<String> {
+    let values = possible_values(arg);
+    if values.is_empty() {
+        complete_path(word, arg.get_value_hint() == ValueHint::DirPath)
+    } else {
+        values
+    }
+}
+
+//fp complete
+/// Determine the completions for the word of a command line that ends
+/// at 'line'
+///
+/// The command line is walked through the tree of subcommands of
+/// 'command' to determine whether the word is an option, the value of
+/// an option, a subcommand or a positional argument; file paths are
+/// completed for values that are not restricted to a set of possible
+/// values.
+///
+/// Returns the byte offset of the start of the word within the line
+/// and the (sorted) candidates to replace it with
+pub fn complete(command: &Command, line: &str) -> (usize, Vec<String>) {
+    let command = built(command);
+    let start = line.rfind(char::is_whitespace).map(|n| n + 1).unwrap_or(0);
+    let word = &line[start..];
+    let mut cmd = &command;
+    let mut value_of: Option<&Arg> = None;
+    let mut positionals = 0;
+    let mut first = true;
+    for token in line[..start].split_whitespace() {
+        first = false;
+        if value_of.take().is_some() {
+            continue;
+        }
+        if token.starts_with('-') {
+            if !token.contains('=') {
+                value_of = find_option(cmd, token).filter(|a| takes_value(a));
+            }
+        } else if let Some(sc) = cmd.find_subcommand(token) {
+            cmd = sc;
+            positionals = 0;
+        } else {
+            positionals += 1;
+        }
+    }
+
+    let mut candidates = vec![];
+    if let Some(arg) = value_of {
+        candidates = complete_value(arg, word);
+    } else if word.starts_with('-') {
+        for arg in visible_args(cmd) {
+            if let Some(long) = arg.get_long() {
+                candidates.push(format!("--{long}"));
+            }
+            if let Some(short) = arg.get_short().filter(|_| !word.starts_with("--")) {
+                candidates.push(format!("-{short}"));
+            }
+        }
+    } else {
+        if first {
+            candidates.extend(DIRECTIVES.iter().map(|d| d.to_string()));
+        }
+        for sc in visible_subcommands(cmd) {
+            candidates.push(sc.get_name().to_string());
+        }
+        let positional = cmd
+            .get_positionals()
+            .filter(|a| !a.is_hide_set())
+            .nth(positionals)
+            .or_else(|| {
+                cmd.get_positionals()
+                    .last()
+                    .filter(|a| matches!(a.get_action(), ArgAction::Append))
+            });
+        if let Some(arg) = positional {
+            candidates.extend(complete_value(arg, word));
+        }
+    }
+    candidates.retain(|c| c.starts_with(word));
+    candidates.sort();
+    candidates.dedup();
+    (start, candidates)
+}
@@ -0,0 +1,664 @@
+//a Imports
+use std::io::Write;
+
+use clap::{Arg, Command, ValueHint};
+
+//a Shell
+//tp Shell
+/// The shells for which completion scripts may be generated
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+//ip Shell
+impl Shell {
+    //ci NAMES
+    /// The names of the shells, as accepted by [std::str::FromStr]
+    pub const NAMES: [&'static str; 3] = ["bash", "zsh", "fish"];
+}
+
+//ip FromStr for Shell
+impl std::str::FromStr for Shell {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "bash" => Ok(Self::Bash),
+            "zsh" => Ok(Self::Zsh),
+            "fish" => Ok(Self::Fish),
+            _ => Err(format!(
+                "Unknown shell '{s}', expected one of {}",
+                Self::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+//ip Display for Shell
+impl std::fmt::Display for Shell {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = match self {
+            Self::Bash => "bash",
+            Self::Zsh => "zsh",
+            Self::Fish => "fish",
+        };
+        fmt.write_str(s)
+    }
+}
+
+//a Command tree support
+//fi built
+/// Build a copy of a command, so that the automatically generated
+/// arguments (such as '--help') and subcommands are present
+pub(crate) fn built(command: &Command) -> Command {
+    let mut command = command.clone();
+    command.build();
+    command
+}
+
+//fi visible_subcommands
+/// The visible subcommands of a command, sorted by name so that the
+/// output does not depend on the order they were added in
+pub(crate) fn visible_subcommands(command: &Command) -> impl Iterator<Item = &Command> {
+    let mut subcommands: Vec<_> = command
+        .get_subcommands()
+        .filter(|c| !c.is_hide_set())
+        .collect();
+    subcommands.sort_by_key(|c| c.get_name());
+    subcommands.into_iter()
+}
+
+//fi visible_args
+pub(crate) fn visible_args(command: &Command) -> impl Iterator<Item = &Arg> {
+    command.get_arguments().filter(|a| !a.is_hide_set())
+}
+
+//fi takes_value
+pub(crate) fn takes_value(arg: &Arg) -> bool {
+    arg.get_action().takes_values()
+}
+
+//fi possible_values
+/// The possible values of an argument that takes a value, if they are
+/// restricted
+pub(crate) fn possible_values(arg: &Arg) -> Vec<String> {
+    if !takes_value(arg) {
+        return vec![];
+    }
+    arg.get_possible_values()
+        .iter()
+        .filter(|v| !v.is_hide_set())
+        .map(|v| v.get_name().to_string())
+        .collect()
+}
+
+//fi value_name
+fn value_name(arg: &Arg) -> String {
+    match arg.get_value_names() {
+        Some(names) if !names.is_empty() => names[0].to_string(),
+        _ => arg.get_id().as_str().to_uppercase(),
+    }
+}
+
+//fi first_line
+/// The first line of the short help for an argument or command
+fn first_line<S: ToString>(help: Option<S>) -> String {
+    help.map(|h| h.to_string())
+        .and_then(|h| h.lines().next().map(|l| l.trim().to_string()))
+        .unwrap_or_default()
+}
+
+//fi for_each_command
+/// Invoke a function for a command and each of its visible
+/// subcommands, recursively, with the path of names to the command
+///
+/// The 'help' subcommands generated by clap are not included
+fn for_each_command<'a, F>(command: &'a Command, path: &mut Vec<&'a str>, f: &mut F)
+where
+    F: FnMut(&[&'a str], &'a Command),
+{
+    path.push(command.get_name());
+    f(path, command);
+    for sc in visible_subcommands(command) {
+        if sc.get_name() != "help" {
+            for_each_command(sc, path, f);
+        }
+    }
+    path.pop();
+}
+
+//a Completions
+//fp write_completions
+/// Write a completion script for a command (and all its subcommands)
+/// for a shell
+///
+/// The script completes the command name given by the (top-level)
+/// command's name
+pub fn write_completions<W: Write>(
+    command: &Command,
+    shell: Shell,
+    w: &mut W,
+) -> std::io::Result<()> {
+    let command = built(command);
+    match shell {
+        Shell::Bash => write_bash(&command, w),
+        Shell::Zsh => write_zsh(&command, w),
+        Shell::Fish => write_fish(&command, w),
+    }
+}
+
+//fi shell_fn_name
+/// The name of a shell function or case label for a path of commands
+fn shell_fn_name(path: &[&str]) -> String {
+    path.iter()
+        .map(|s| s.replace(|c: char| !c.is_ascii_alphanumeric() && c != '_', "_"))
+        .collect::<Vec<_>>()
+        .join("__")
+}
+
+//fi single_quote
+/// Quote a string for a shell using single quotes
+fn single_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+//fi write_bash
+fn write_bash<W: Write>(command: &Command, w: &mut W) -> std::io::Result<()> {
+    let name = command.get_name();
+    let root = shell_fn_name(&[name]);
+    writeln!(w, "_{root}() {{")?;
+    writeln!(w, "    local cur prev path i")?;
+    writeln!(w, "    COMPREPLY=()")?;
+    writeln!(w, "    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"")?;
+    writeln!(w, "    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"")?;
+    writeln!(w, "    path=\"{root}\"")?;
+    writeln!(w, "    for ((i = 1; i < COMP_CWORD; i++)); do")?;
+    writeln!(w, "        case \"${{path}}__${{COMP_WORDS[i]}}\" in")?;
+    let mut result = Ok(());
+    for_each_command(command, &mut vec![], &mut |path, c| {
+        if result.is_err() || path.len() < 2 {
+            return;
+        }
+        let parent = shell_fn_name(&path[..path.len() - 1]);
+        let this = shell_fn_name(path);
+        result = writeln!(
+            w,
+            "            {parent}__{}) path=\"{this}\" ;;",
+            c.get_name()
+        );
+    });
+    result?;
+    writeln!(w, "        esac")?;
+    writeln!(w, "    done")?;
+    writeln!(w, "    case \"${{path}}\" in")?;
+    let mut result = Ok(());
+    for_each_command(command, &mut vec![], &mut |path, c| {
+        if result.is_ok() {
+            result = write_bash_command(w, &shell_fn_name(path), c);
+        }
+    });
+    result?;
+    writeln!(w, "    esac")?;
+    writeln!(w, "}}")?;
+    writeln!(w, "complete -F _{root} -o bashdefault -o default {name}")
+}
+
+//fi write_bash_command
+fn write_bash_command<W: Write>(w: &mut W, path: &str, command: &Command) -> std::io::Result<()> {
+    let mut words = vec![];
+    writeln!(w, "        {path})")?;
+    writeln!(w, "            case \"${{prev}}\" in")?;
+    for arg in visible_args(command) {
+        if arg.is_positional() {
+            words.extend(possible_values(arg));
+            continue;
+        }
+        let mut flags = vec![];
+        if let Some(long) = arg.get_long() {
+            flags.push(format!("--{long}"));
+        }
+        if let Some(short) = arg.get_short() {
+            flags.push(format!("-{short}"));
+        }
+        words.extend(flags.iter().cloned());
+        if !takes_value(arg) || flags.is_empty() {
+            continue;
+        }
+        let values = possible_values(arg);
+        let reply = {
+            if !values.is_empty() {
+                format!(
+                    "compgen -W {} -- \"${{cur}}\"",
+                    single_quote(&values.join(" "))
+                )
+            } else if arg.get_value_hint() == ValueHint::DirPath {
+                "compgen -d -- \"${cur}\"".to_string()
+            } else {
+                "compgen -f -- \"${cur}\"".to_string()
+            }
+        };
+        writeln!(
+            w,
+            "                {}) COMPREPLY=($({reply})); return 0 ;;",
+            flags.join("|")
+        )?;
+    }
+    writeln!(w, "            esac")?;
+    for sc in visible_subcommands(command) {
+        words.push(sc.get_name().to_string());
+    }
+    writeln!(
+        w,
+        "            COMPREPLY=($(compgen -W {} -- \"${{cur}}\"))",
+        single_quote(&words.join(" "))
+    )?;
+    writeln!(w, "            ;;")
+}
+
+//fi zsh_escape
+/// Escape help text for use within the brackets of a zsh
+/// '_arguments' specification (which is itself single-quoted)
+fn zsh_escape(s: &str) -> String {
+    s.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+//fi zsh_action
+fn zsh_action(arg: &Arg) -> String {
+    let values = possible_values(arg);
+    if !values.is_empty() {
+        format!("({})", values.join(" "))
+    } else if arg.get_value_hint() == ValueHint::DirPath {
+        "_files -/".into()
+    } else {
+        "_files".into()
+    }
+}
+
+//fi write_zsh
+fn write_zsh<W: Write>(command: &Command, w: &mut W) -> std::io::Result<()> {
+    let name = command.get_name();
+    writeln!(w, "#compdef {name}")?;
+    let mut result = Ok(());
+    for_each_command(command, &mut vec![], &mut |path, c| {
+        if result.is_ok() {
+            result = write_zsh_command(w, path, c);
+        }
+    });
+    result?;
+    writeln!(w)?;
+    writeln!(
+        w,
+        "if [ \"$funcstack[1]\" = \"_{}\" ]; then",
+        shell_fn_name(&[name])
+    )?;
+    writeln!(w, "    _{} \"$@\"", shell_fn_name(&[name]))?;
+    writeln!(w, "else")?;
+    writeln!(w, "    compdef _{} {name}", shell_fn_name(&[name]))?;
+    writeln!(w, "fi")
+}
+
+//fi write_zsh_command
+fn write_zsh_command<W: Write>(w: &mut W, path: &[&str], command: &Command) -> std::io::Result<()> {
+    let fn_name = shell_fn_name(path);
+    let has_subcommands = visible_subcommands(command).next().is_some();
+    writeln!(w)?;
+    writeln!(w, "_{fn_name}() {{")?;
+    writeln!(w, "    local context state state_descr line")?;
+    writeln!(w, "    typeset -A opt_args")?;
+    writeln!(w, "    _arguments -s -S -C \\")?;
+    for arg in visible_args(command) {
+        let help = zsh_escape(&first_line(arg.get_help()));
+        let repeat = {
+            if matches!(
+                arg.get_action(),
+                clap::ArgAction::Append | clap::ArgAction::Count
+            ) {
+                "*"
+            } else {
+                ""
+            }
+        };
+        if arg.is_positional() {
+            let value = zsh_escape(&value_name(arg));
+            if repeat.is_empty() {
+                writeln!(w, "        ':{value} -- {help}:{}' \\", zsh_action(arg))?;
+            } else {
+                writeln!(w, "        '*::{value} -- {help}:{}' \\", zsh_action(arg))?;
+            }
+            continue;
+        }
+        let value = {
+            if takes_value(arg) {
+                format!(":{}:{}", zsh_escape(&value_name(arg)), zsh_action(arg))
+            } else {
+                String::new()
+            }
+        };
+        let mut flags = vec![];
+        if let Some(short) = arg.get_short() {
+            flags.push(format!(
+                "-{short}{}",
+                if value.is_empty() { "" } else { "+" }
+            ));
+        }
+        if let Some(long) = arg.get_long() {
+            flags.push(format!(
+                "--{long}{}",
+                if value.is_empty() { "" } else { "=" }
+            ));
+        }
+        for f in flags {
+            writeln!(w, "        '{repeat}{f}[{help}]{value}' \\")?;
+        }
+    }
+    if has_subcommands {
+        writeln!(w, "        ': :_{fn_name}_commands' \\")?;
+        writeln!(w, "        '*:: :->subcommand' \\")?;
+    }
+    writeln!(w, "        && return 0")?;
+    if has_subcommands {
+        writeln!(w, "    case $state in")?;
+        writeln!(w, "        subcommand)")?;
+        writeln!(w, "            case $line[1] in")?;
+        for sc in visible_subcommands(command).filter(|c| c.get_name() != "help") {
+            let mut sc_path = path.to_vec();
+            sc_path.push(sc.get_name());
+            writeln!(
+                w,
+                "                ({}) _{} ;;",
+                sc.get_name(),
+                shell_fn_name(&sc_path)
+            )?;
+        }
+        writeln!(w, "            esac")?;
+        writeln!(w, "            ;;")?;
+        writeln!(w, "    esac")?;
+    }
+    writeln!(w, "}}")?;
+    if has_subcommands {
+        writeln!(w)?;
+        writeln!(w, "_{fn_name}_commands() {{")?;
+        writeln!(w, "    local commands; commands=(")?;
+        for sc in visible_subcommands(command) {
+            let help = first_line(sc.get_about()).replace('\'', "'\\''");
+            let name = sc.get_name().replace(':', "\\:");
+            writeln!(w, "        '{name}:{help}'")?;
+        }
+        writeln!(w, "    )")?;
+        writeln!(
+            w,
+            "    _describe -t commands '{} commands' commands",
+            path.join(" ")
+        )?;
+        writeln!(w, "}}")?;
+    }
+    Ok(())
+}
+
+//fi write_fish
+fn write_fish<W: Write>(command: &Command, w: &mut W) -> std::io::Result<()> {
+    let name = command.get_name();
+    let mut result = Ok(());
+    for_each_command(command, &mut vec![], &mut |path, c| {
+        if result.is_ok() {
+            result = write_fish_command(w, name, path, c);
+        }
+    });
+    result
+}
+
+//fi write_fish_command
+fn write_fish_command<W: Write>(
+    w: &mut W,
+    name: &str,
+    path: &[&str],
+    command: &Command,
+) -> std::io::Result<()> {
+    let subcommands: Vec<&str> = visible_subcommands(command).map(|c| c.get_name()).collect();
+    let mut conditions = vec![];
+    if path.len() == 1 {
+        conditions.push("__fish_use_subcommand".to_string());
+    } else {
+        for p in &path[1..] {
+            conditions.push(format!("__fish_seen_subcommand_from {p}"));
+        }
+        if !subcommands.is_empty() {
+            conditions.push(format!(
+                "not __fish_seen_subcommand_from {}",
+                subcommands.join(" ")
+            ));
+        }
+    }
+    let condition = single_quote(&conditions.join("; and "));
+    for arg in visible_args(command) {
+        if arg.is_positional() {
+            continue;
+        }
+        let mut line = format!("complete -c {name} -n {condition}");
+        if let Some(short) = arg.get_short() {
+            line += &format!(" -s {short}");
+        }
+        if let Some(long) = arg.get_long() {
+            line += &format!(" -l {long}");
+        }
+        if takes_value(arg) {
+            let values = possible_values(arg);
+            if !values.is_empty() {
+                line += &format!(" -r -f -a {}", single_quote(&values.join(" ")));
+            } else {
+                line += " -r -F";
+            }
+        }
+        let help = first_line(arg.get_help());
+        if !help.is_empty() {
+            line += &format!(" -d {}", single_quote(&help));
+        }
+        writeln!(w, "{line}")?;
+    }
+    for sc in visible_subcommands(command) {
+        let mut line = format!(
+            "complete -c {name} -n {condition} -f -a {}",
+            single_quote(sc.get_name())
+        );
+        let help = first_line(sc.get_about());
+        if !help.is_empty() {
+            line += &format!(" -d {}", single_quote(&help));
+        }
+        writeln!(w, "{line}")?;
+    }
+    Ok(())
+}
+
+//a Man pages
+//fp man_pages
+/// Generate roff man pages (in section 1) for a command and all of its
+/// subcommands, returning (file name, contents) pairs
+///
+/// The page for a subcommand is named after its path, such as
+/// 'photogram-star-find_stars.1'; the long help of commands and
+/// arguments is used where it is given
+pub fn man_pages(command: &Command) -> Vec<(String, String)> {
+    let command = built(command);
+    let version = command.get_version().map(|v| v.to_string());
+    let mut pages = vec![];
+    for_each_command(&command, &mut vec![], &mut |path, c| {
+        let page = man_page(path, c, version.as_deref());
+        pages.push((format!("{}.1", path.join("-")), page));
+    });
+    pages
+}
+
+//fi roff_escape
+/// Escape text for roff, ensuring that lines cannot be mistaken for
+/// requests
+fn roff_escape(s: &str) -> String {
+    let s = s.replace('\\', "\\e").replace('-', "\\-");
+    let mut result = String::new();
+    for l in s.lines() {
+        if l.starts_with('.') || l.starts_with('\'') {
+            result += "\\&";
+        }
+        result += l;
+        result.push('\n');
+    }
+    result
+}
+
+//fi roff_paragraphs
+/// Convert text with paragraphs separated by blank lines into roff
+fn roff_paragraphs(s: &str) -> String {
+    let mut result = String::new();
+    for (i, p) in s.split("\n\n").enumerate() {
+        let p = p.trim_matches('\n');
+        if p.is_empty() {
+            continue;
+        }
+        if i > 0 {
+            result += ".PP\n";
+        }
+        result += &roff_escape(p);
+    }
+    result
+}
+
+//fi man_arg_name
+/// The name of an argument as shown in a man page, such as
+/// '\fB\-p\fR, \fB\-\-project\fR \fIPROJECT\fR'
+fn man_arg_name(arg: &Arg) -> String {
+    let value = roff_escape(&value_name(arg));
+    let value = value.trim_end();
+    if arg.is_positional() {
+        return format!("<\\fI{value}\\fR>");
+    }
+    let mut flags = vec![];
+    if let Some(short) = arg.get_short() {
+        flags.push(format!(
+            "\\fB\\-{}\\fR",
+            roff_escape(&short.to_string()).trim_end()
+        ));
+    }
+    if let Some(long) = arg.get_long() {
+        flags.push(format!("\\fB\\-\\-{}\\fR", roff_escape(long).trim_end()));
+    }
+    let mut name = flags.join(", ");
+    if takes_value(arg) {
+        name += &format!(" \\fI{value}\\fR");
+    }
+    name
+}
+
+//fi man_arg_help
+fn man_arg_help(arg: &Arg) -> String {
+    let help = arg
+        .get_long_help()
+        .or(arg.get_help())
+        .map(|h| h.to_string())
+        .unwrap_or_default();
+    let mut result = roff_paragraphs(&help);
+    let defaults: Vec<_> = arg
+        .get_default_values()
+        .iter()
+        .map(|v| v.to_string_lossy())
+        .collect();
+    if takes_value(arg) && !defaults.is_empty() && !arg.is_hide_default_value_set() {
+        result += &roff_escape(&format!("[default: {}]", defaults.join(", ")));
+    }
+    let values = possible_values(arg);
+    if !values.is_empty() && !arg.is_hide_possible_values_set() {
+        result += &roff_escape(&format!("[possible values: {}]", values.join(", ")));
+    }
+    result
+}
+
+//fi man_page
+fn man_page(path: &[&str], command: &Command, version: Option<&str>) -> String {
+    let title = path.join("-");
+    let mut page = String::new();
+    page += &format!(
+        ".TH {} 1 \"\" \"{}\"\n",
+        roff_escape(&title.to_uppercase()).trim_end(),
+        roff_escape(&format!("{} {}", path[0], version.unwrap_or(""))).trim_end()
+    );
+
+    page += ".SH NAME\n";
+    let about = first_line(command.get_about());
+    if about.is_empty() {
+        page += &roff_escape(&title);
+    } else {
+        page += &roff_escape(&format!("{title} - {about}"));
+    }
+
+    page += ".SH SYNOPSIS\n";
+    page += &format!("\\fB{}\\fR", roff_escape(&path.join(" ")).trim_end());
+    if visible_args(command).any(|a| !a.is_positional()) {
+        page += " [\\fIOPTIONS\\fR]";
+    }
+    for arg in visible_args(command).filter(|a| a.is_positional()) {
+        if arg.is_required_set() {
+            page += &format!(" {}", man_arg_name(arg));
+        } else {
+            page += &format!(" [{}]", man_arg_name(arg));
+        }
+    }
+    if visible_subcommands(command).next().is_some() {
+        page += " <\\fICOMMAND\\fR>";
+    }
+    page += "\n";
+
+    let description = command
+        .get_long_about()
+        .or(command.get_about())
+        .map(|a| a.to_string())
+        .unwrap_or_default();
+    if !description.is_empty() {
+        page += ".SH DESCRIPTION\n";
+        page += &roff_paragraphs(&description);
+    }
+
+    for (heading, positional) in [("ARGUMENTS", true), ("OPTIONS", false)] {
+        let args: Vec<_> = visible_args(command)
+            .filter(|a| a.is_positional() == positional)
+            .collect();
+        if args.is_empty() {
+            continue;
+        }
+        page += &format!(".SH {heading}\n");
+        for arg in args {
+            page += ".TP\n";
+            page += &man_arg_name(arg);
+            page += "\n";
+            page += &man_arg_help(arg);
+        }
+    }
+
+    let subcommands: Vec<_> = visible_subcommands(command)
+        .filter(|c| c.get_name() != "help")
+        .collect();
+    if !subcommands.is_empty() {
+        page += ".SH COMMANDS\n";
+        for sc in subcommands {
+            page += ".TP\n";
+            page += &format!("\\fB{}\\fR\n", roff_escape(sc.get_name()).trim_end());
+            let about = first_line(sc.get_about());
+            if !about.is_empty() {
+                page += &roff_escape(&about);
+            }
+            page += &format!(
+                "See \\fB{}\\-{}\\fR(1)\n",
+                roff_escape(&title).trim_end(),
+                roff_escape(sc.get_name()).trim_end()
+            );
+        }
+    }
+
+    if let Some(version) = version {
+        page += ".SH VERSION\n";
+        page += &roff_escape(&format!("v{version}"));
+    }
+    page
+}
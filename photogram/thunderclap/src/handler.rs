@@ -2,11 +2,25 @@
 use std::collections::HashMap;
 use std::ffi::OsString;
 use std::io::Write;
+use std::path::PathBuf;
 use std::rc::Rc;
 
 use clap::{Arg, ArgAction, ArgMatches, Command};
 
-use crate::{ArgFn, ArgResetFn, CommandArgs, CommandBuilder, CommandFn};
+use crate::batch::{condition, for_values, BatchMacro, BatchStmt};
+use crate::repl::LineEditor;
+use crate::value::ResultValue;
+use crate::{ArgFn, ArgResetFn, CommandArgs, CommandBuilder, CommandFn, Shell};
+
+//a Constants
+//ci MAX_BATCH_DEPTH
+/// The maximum depth of nested batch files and macro calls
+const MAX_BATCH_DEPTH: usize = 32;
+
+//ci MAX_PROMPT_VALUE
+/// The maximum length of a value of the command arguments to show in
+/// the interactive prompt
+const MAX_PROMPT_VALUE: usize = 32;
 
 //a CommandHandlerSet
 //tp CommandHandlerSet
@@ -107,7 +121,7 @@ impl<C: CommandArgs> CommandHandlerSet<C> {
         subcommand: &str,
         cmd_args: &mut C,
         sub_matches: &ArgMatches,
-    ) -> Result<String, C::Error> {
+    ) -> Result<ResultValue, C::Error> {
         let Some(sub_handler_set) = self.sub_cmds.get(subcommand) else {
             panic!("Subcommand was added to clap so there should be a match in the table");
         };
@@ -117,12 +131,15 @@ impl<C: CommandArgs> CommandHandlerSet<C> {
 
     //mi execute_cmd
     /// Execute the command function of this handler
-    fn execute_cmd(&self, cmd_args: &mut C) -> Result<String, C::Error> {
+    ///
+    /// The result is kept in both its text and structured forms
+    fn execute_cmd(&self, cmd_args: &mut C) -> Result<ResultValue, C::Error> {
         if self.handler.is_none() {
-            Ok("".to_string())
+            Ok(ResultValue::default())
         } else {
             let result = self.handler.as_ref().unwrap()(cmd_args)?;
-            Ok(result.to_string())
+            let json = cmd_args.value_json(&result);
+            Ok(ResultValue::new(result.to_string(), json))
         }
     }
 
@@ -131,7 +148,7 @@ impl<C: CommandArgs> CommandHandlerSet<C> {
     ///
     /// Either a subcommand of the handler is invoked, or if none
     /// is provided then the function for this handler is invoked
-    fn handle_cmd(&self, cmd_args: &mut C, matches: &ArgMatches) -> Result<String, C::Error> {
+    fn handle_cmd(&self, cmd_args: &mut C, matches: &ArgMatches) -> Result<ResultValue, C::Error> {
         if let Some((subcommand, submatches)) = matches.subcommand() {
             self.execute_sub_cmd(subcommand, cmd_args, submatches)
         } else {
@@ -151,10 +168,20 @@ pub struct CommandSet<C: CommandArgs> {
     command: Command,
     handler_set: CommandHandlerSet<C>,
     cmd_stack: Vec<(String, Option<usize>)>,
-    variables: HashMap<String, Rc<String>>,
-    result_history: Vec<Rc<String>>,
+    variables: HashMap<String, Rc<ResultValue>>,
+    macros: HashMap<String, BatchMacro>,
+    result_history: Vec<Rc<ResultValue>>,
     use_builtins: bool,
+    /// The documentation builtins ('completions' and 'man') that have
+    /// been added; those that the command has itself are not
+    doc_builtins: Vec<&'static str>,
+    /// True if the '--json' argument has been added (it is not if the
+    /// command has its own)
+    json_arg: bool,
     show_result: bool,
+    json_output: bool,
+    interactive: bool,
+    history_file: Option<PathBuf>,
 }
 
 //ip CommandSet
@@ -171,9 +198,15 @@ impl<C: CommandArgs> CommandSet<C> {
             handler_set,
             cmd_stack: vec![],
             variables: HashMap::default(),
+            macros: HashMap::default(),
             result_history: vec![],
             use_builtins,
+            doc_builtins: vec![],
+            json_arg: false,
             show_result: true,
+            json_output: false,
+            interactive: false,
+            history_file: None,
         }
     }
 
@@ -187,7 +220,24 @@ impl<C: CommandArgs> CommandSet<C> {
         allow_interactive: bool,
     ) -> Self {
         let (command, handler_set) = builder.take();
-        let mut command = command.no_binary_name(true);
+        // The builtins do not replace subcommands or arguments of the
+        // command with the same names
+        let doc_builtins: Vec<_> = ["completions", "man"]
+            .into_iter()
+            .filter(|name| command.find_subcommand(name).is_none())
+            .collect();
+        let json_arg = !command
+            .get_arguments()
+            .any(|a| a.get_id() == "json" || a.get_long() == Some("json"));
+        let mut command = Self::add_doc_builtins(command.no_binary_name(true), &doc_builtins);
+        if json_arg {
+            command = command.arg(
+                Arg::new("json")
+                    .long("json")
+                    .help("Print the results of commands as JSON")
+                    .action(ArgAction::SetTrue),
+            );
+        }
         let mut use_builtins = false;
         if allow_interactive || allow_batch {
             command = Self::add_builtins(command);
@@ -199,10 +249,78 @@ impl<C: CommandArgs> CommandSet<C> {
                 Arg::new("batch")
                     .long("batch")
                     .help("Execute a batch set of commands")
+                    .long_help(
+                        "Execute a batch set of commands, one per line; batch files may also \
+use 'for', 'if'/'else', 'end', 'include', 'macro' and 'call' directives",
+                    )
                     .action(ArgAction::Append),
             );
         }
-        Self::new(command, handler_set, use_builtins)
+        if allow_interactive {
+            command = command.subcommand_required(false);
+            command = command.arg(
+                Arg::new("interactive")
+                    .long("interactive")
+                    .help(
+                        "Execute commands interactively, with line editing, history and completion",
+                    )
+                    .action(ArgAction::SetTrue),
+            );
+        }
+        let mut command_set = Self::new(command, handler_set, use_builtins);
+        command_set.doc_builtins = doc_builtins;
+        command_set.json_arg = json_arg;
+        command_set.history_file = std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home).join(format!(".{}_history", command_set.command.get_name()))
+        });
+        command_set
+    }
+
+    //mp set_history_file
+    /// Set the file that the history of interactive operation is kept
+    /// in; by default this is '.<name>_history' in the home directory
+    pub fn set_history_file(&mut self, history_file: Option<PathBuf>) -> &mut Self {
+        self.history_file = history_file;
+        self
+    }
+
+    //mi add_doc_builtins
+    /// Add the builtins that document the command; these are provided
+    /// whether or not batch and interactive operation is permitted, but
+    /// only those named
+    fn add_doc_builtins(mut command: Command, names: &[&str]) -> Command {
+        if names.contains(&"completions") {
+            command = command.subcommand(
+                Command::new("completions")
+                    .about("Write a shell completion script to stdout")
+                    .arg(
+                        Arg::new("shell")
+                            .help("Shell to generate the completion script for")
+                            .required(true)
+                            .value_parser(Shell::NAMES),
+                    ),
+            );
+        }
+        if names.contains(&"man") {
+            command = command.subcommand(
+                Command::new("man")
+                    .about("Write man pages for the command and its subcommands")
+                    .arg(
+                        Arg::new("dir")
+                            .long("dir")
+                            .short('d')
+                            .help("Directory to write all of the man pages to")
+                            .required(false),
+                    )
+                    .arg(
+                        Arg::new("command")
+                            .help("Subcommand whose man page is written to stdout if no directory is given")
+                            .required(false)
+                            .action(ArgAction::Append),
+                    ),
+            );
+        }
+        command
     }
 
     //mi add_builtins
@@ -262,6 +380,17 @@ impl<C: CommandArgs> CommandSet<C> {
                             .action(ArgAction::Append),
                     ),
             )
+            .subcommand(
+                Command::new("store")
+                    .about("Store a value from the value history stack in a variable, keeping its fields")
+                    .arg(Arg::new("key").help("Variable name to store the value in").required(true))
+                    .arg(
+                        Arg::new("n")
+                            .help("Position of the value on the stack; 0 is the last result")
+                            .default_value("0")
+                            .action(ArgAction::Set),
+                    ),
+            )
             .subcommand(
                 Command::new("stack_clear")
                     .about("Clear the value history stack")
@@ -313,6 +442,54 @@ impl<C: CommandArgs> CommandSet<C> {
         Ok("".into())
     }
 
+    //mi handle_builtin_completions
+    fn handle_builtin_completions(&self, matches: &ArgMatches) -> Result<String, C::Error> {
+        let shell: Shell = matches.get_one::<String>("shell").unwrap().parse()?;
+        let mut stdout = std::io::stdout().lock();
+        self.write_completions(shell, &mut stdout)
+            .map_err(|e| format!("Failed to write completions ({e})"))?;
+        Ok("".into())
+    }
+
+    //mi handle_builtin_man
+    fn handle_builtin_man(&self, matches: &ArgMatches) -> Result<String, C::Error> {
+        let pages = self.man_pages();
+        if let Some(dir) = matches.get_one::<String>("dir") {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create man page directory '{dir}' ({e})"))?;
+            for (name, page) in pages {
+                let path = std::path::Path::new(dir).join(name);
+                std::fs::write(&path, page)
+                    .map_err(|e| format!("Failed to write man page '{}' ({e})", path.display()))?;
+            }
+            return Ok("".into());
+        }
+        let mut name = self.command.get_name().to_string();
+        if let Some(path) = matches.get_many::<String>("command") {
+            for p in path {
+                name = format!("{name}-{p}");
+            }
+        }
+        name += ".1";
+        let Some((_, page)) = pages.into_iter().find(|(n, _)| *n == name) else {
+            return Err(format!("No such command for a man page ({name})").into());
+        };
+        print!("{page}");
+        Ok("".into())
+    }
+
+    //mi handle_doc_builtins
+    fn handle_doc_builtins(&self, matches: &ArgMatches) -> Result<Option<String>, C::Error> {
+        match matches.subcommand() {
+            Some((name, _)) if !self.doc_builtins.contains(&name) => Ok(None),
+            Some(("completions", sub_matches)) => {
+                self.handle_builtin_completions(sub_matches).map(Some)
+            }
+            Some(("man", sub_matches)) => self.handle_builtin_man(sub_matches).map(Some),
+            _ => Ok(None),
+        }
+    }
+
     //mi handle_builtin_set
     fn handle_builtin_set(
         &mut self,
@@ -322,7 +499,8 @@ impl<C: CommandArgs> CommandSet<C> {
         let k = matches.get_one::<String>("key").unwrap();
         let v = matches.get_one::<String>("value").unwrap();
         if !cmd_args.value_set(k, v)? {
-            self.variables.insert(k.into(), Rc::new(v.into()));
+            self.variables
+                .insert(k.into(), Rc::new(v.to_string().into()));
         }
         Ok("".into())
     }
@@ -377,14 +555,34 @@ impl<C: CommandArgs> CommandSet<C> {
         Ok("".into())
     }
 
+    //mi handle_builtin_store
+    fn handle_builtin_store(
+        &mut self,
+        _cmd_args: &mut C,
+        matches: &ArgMatches,
+    ) -> Result<String, C::Error> {
+        let k = matches.get_one::<String>("key").unwrap();
+        let n = matches.get_one::<String>("n").unwrap();
+        let Ok(n) = n.parse::<usize>() else {
+            return Err(format!("Stack position '{n}' must be a number").into());
+        };
+        let len = self.result_history.len();
+        if n >= len {
+            return Err(format!("Value stack has no value at position {n}").into());
+        }
+        let v = self.result_history[len - 1 - n].clone();
+        self.variables.insert(k.into(), v);
+        Ok("".into())
+    }
+
     //mi handle_builtin_stack_pop
     fn handle_builtin_stack_pop(
         &mut self,
         _cmd_args: &mut C,
         _matches: &ArgMatches,
-    ) -> Result<String, C::Error> {
+    ) -> Result<ResultValue, C::Error> {
         if self.result_history.len() > 1 {
-            Ok(Rc::into_inner(self.result_history.remove(1)).unwrap())
+            Ok(Rc::unwrap_or_clone(self.result_history.remove(1)))
         } else {
             Err("Value stack underflow in pop".to_owned().into())
         }
@@ -401,7 +599,7 @@ impl<C: CommandArgs> CommandSet<C> {
                 self.result_history.pop();
             }
             for v in values {
-                self.result_history.push(Rc::new(v.to_owned()));
+                self.result_history.push(Rc::new(v.to_string().into()));
             }
             if !self.result_history.is_empty() {
                 self.result_history
@@ -419,33 +617,49 @@ impl<C: CommandArgs> CommandSet<C> {
         &mut self,
         cmd_args: &mut C,
         matches: &ArgMatches,
-    ) -> Result<Option<String>, C::Error> {
+    ) -> Result<Option<ResultValue>, C::Error> {
         match matches.subcommand_name() {
             Some("echo") => self
                 .handle_builtin_echo(cmd_args, matches.subcommand().unwrap().1)
-                .map(Some),
+                .map(|r| Some(r.into())),
             Some("show") => self
                 .handle_builtin_show(cmd_args, matches.subcommand().unwrap().1)
-                .map(Some),
+                .map(|r| Some(r.into())),
             Some("set") => self
                 .handle_builtin_set(cmd_args, matches.subcommand().unwrap().1)
-                .map(Some),
+                .map(|r| Some(r.into())),
             Some("stack_show") => self
                 .handle_builtin_stack_show(cmd_args, matches.subcommand().unwrap().1)
-                .map(Some),
+                .map(|r| Some(r.into())),
             Some("stack_push") => self
                 .handle_builtin_stack_push(cmd_args, matches.subcommand().unwrap().1)
-                .map(Some),
+                .map(|r| Some(r.into())),
+            Some("store") => self
+                .handle_builtin_store(cmd_args, matches.subcommand().unwrap().1)
+                .map(|r| Some(r.into())),
             Some("stack_pop") => self
                 .handle_builtin_stack_pop(cmd_args, matches.subcommand().unwrap().1)
                 .map(Some),
             Some("stack_clear") => self
                 .handle_builtin_stack_clear(cmd_args, matches.subcommand().unwrap().1)
-                .map(Some),
+                .map(|r| Some(r.into())),
             _ => Ok(None),
         }
     }
 
+    //mi field
+    /// Find a field of a variable or of a value on the value history
+    /// stack, for '${name.field}' or '${0.field}'
+    fn field(&self, name: &str) -> Option<String> {
+        let (name, path) = name.split_once('.')?;
+        if let Some(v) = self.variables.get(name) {
+            return v.field(path);
+        }
+        let n = self.result_history.len();
+        let v = name.parse::<usize>().ok().filter(|v| *v < n)?;
+        self.result_history[n - 1 - v].field(path)
+    }
+
     //mi substitute
     /// Substitute variables etc
     fn substitute(&self, cmd_args: &C, s: String) -> Result<String, C::Error> {
@@ -469,14 +683,16 @@ impl<C: CommandArgs> CommandSet<C> {
             }
             if let Some((name, rest)) = chars.as_str().split_once('}') {
                 if let Some(v) = self.variables.get(name) {
-                    result += v;
+                    result += v.text();
                 } else if let Some(v) = cmd_args.value_str(name) {
                     result += &v;
                 } else if let Ok(v) = name.parse::<usize>() {
                     let n = self.result_history.len();
                     if v < n {
-                        result += &self.result_history[n - 1 - v];
+                        result += self.result_history[n - 1 - v].text();
                     }
+                } else if let Some(v) = self.field(name) {
+                    result += &v;
                 } else {
                     return Err(format!("Failed to evaulate ${{{name}}}").into());
                 }
@@ -556,20 +772,242 @@ impl<C: CommandArgs> CommandSet<C> {
         Ok(())
     }
 
+    //mi batch_error
+    /// Create an error for the current line of the batch file being
+    /// executed
+    fn batch_error(&self, e: String) -> C::Error {
+        match self.cmd_stack.last() {
+            Some((name, Some(line))) => format!("{name} line {line}: {e}").into(),
+            Some((name, None)) => format!("{name}: {e}").into(),
+            None => e.into(),
+        }
+    }
+
     //mi execute_str
-    /// Execute commands from a [str]
-    fn execute_str(&mut self, cmd_args: &mut C, s: &str) -> Result<(), C::Error> {
-        for l in s.lines() {
-            if let Some(c_l) = self.cmd_stack.last_mut() {
-                c_l.1 = c_l.1.map(|x| x + 1);
+    /// Execute the text of a batch file, named 'name'
+    fn execute_str(&mut self, cmd_args: &mut C, name: &str, s: &str) -> Result<(), C::Error> {
+        if self.cmd_stack.len() > MAX_BATCH_DEPTH {
+            return Err(self.batch_error(format!(
+                "batch files and macros nested too deeply including {name}"
+            )));
+        }
+        let stmts = BatchStmt::parse(s).map_err(|e| format!("{name} {e}"))?;
+        self.cmd_stack.push((name.into(), Some(0)));
+        let result = self.execute_stmts(cmd_args, &stmts);
+        self.cmd_stack.pop();
+        result
+    }
+
+    //mi execute_include
+    /// Execute an 'include' of a batch file
+    fn execute_include(&mut self, cmd_args: &mut C, args: &[String]) -> Result<(), C::Error> {
+        let [filename] = args else {
+            return Err(self.batch_error("'include' requires a single filename".into()));
+        };
+        let s = std::fs::read_to_string(filename)
+            .map_err(|e| self.batch_error(format!("failed to load batch file {filename}: {e}")))?;
+        self.execute_str(cmd_args, filename, &s)
+    }
+
+    //mi execute_call
+    /// Execute a 'call' of a macro
+    ///
+    /// The parameters of the macro are set as variables for the
+    /// duration of the call, and restored afterwards
+    fn execute_call(&mut self, cmd_args: &mut C, args: &[String]) -> Result<(), C::Error> {
+        let Some(name) = args.first() else {
+            return Err(self.batch_error("'call' requires a macro name".into()));
+        };
+        let Some(m) = self.macros.get(name).cloned() else {
+            return Err(self.batch_error(format!("macro '{name}' is not defined")));
+        };
+        if args.len() != m.params.len() + 1 {
+            return Err(self.batch_error(format!(
+                "macro '{name}' takes {} arguments but {} were given",
+                m.params.len(),
+                args.len() - 1
+            )));
+        }
+        if self.cmd_stack.len() > MAX_BATCH_DEPTH {
+            return Err(self.batch_error(format!("macros nested too deeply calling '{name}'")));
+        }
+        let mut saved = vec![];
+        for (p, v) in m.params.iter().zip(args[1..].iter()) {
+            let v = Rc::new(v.clone().into());
+            saved.push((p, self.variables.insert(p.clone(), v)));
+        }
+        self.cmd_stack.push((m.file.clone(), Some(0)));
+        let result = self.execute_stmts(cmd_args, &m.body);
+        self.cmd_stack.pop();
+        for (p, v) in saved {
+            if let Some(v) = v {
+                self.variables.insert(p.clone(), v);
+            } else {
+                self.variables.remove(p);
+            }
+        }
+        result
+    }
+
+    //mi execute_stmts
+    /// Execute the statements of a batch file (or of the body of a
+    /// loop, branch or macro)
+    fn execute_stmts(&mut self, cmd_args: &mut C, stmts: &[BatchStmt]) -> Result<(), C::Error> {
+        for stmt in stmts {
+            if let Some((_, Some(line))) = self.cmd_stack.last_mut() {
+                *line = stmt.line();
+            }
+            match stmt {
+                BatchStmt::Line(_, l) => match l.split_whitespace().next() {
+                    Some("include") => {
+                        let args = self.parse_str(cmd_args, l)?;
+                        self.execute_include(cmd_args, &args[1..])?;
+                    }
+                    Some("call") => {
+                        let args = self.parse_str(cmd_args, l)?;
+                        self.execute_call(cmd_args, &args[1..])?;
+                    }
+                    _ => {
+                        self.execute_str_line(cmd_args, l)?;
+                    }
+                },
+                BatchStmt::For(_, header, body) => {
+                    let header = self.parse_str(cmd_args, header)?;
+                    let (var, values) = for_values(&header).map_err(|e| self.batch_error(e))?;
+                    for v in values {
+                        self.variables.insert(var.clone(), Rc::new(v.into()));
+                        self.execute_stmts(cmd_args, body)?;
+                    }
+                }
+                BatchStmt::If(_, header, then_stmts, else_stmts) => {
+                    let header = self.parse_str(cmd_args, header)?;
+                    if condition(&header).map_err(|e| self.batch_error(e))? {
+                        self.execute_stmts(cmd_args, then_stmts)?;
+                    } else {
+                        self.execute_stmts(cmd_args, else_stmts)?;
+                    }
+                }
+                BatchStmt::Macro(_, header, body) => {
+                    let header = self.parse_str(cmd_args, header)?;
+                    let file = &self.cmd_stack.last().unwrap().0;
+                    let (name, m) = BatchMacro::of_header(file, &header, body.clone())
+                        .map_err(|e| self.batch_error(e))?;
+                    self.macros.insert(name, m);
+                }
             }
-            self.execute_str_line(cmd_args, l)?;
         }
         Ok(())
     }
 
+    //mi arg_values
+    /// The values of all the keys of the command arguments
+    fn arg_values(cmd_args: &C) -> HashMap<String, String> {
+        cmd_args
+            .keys()
+            .filter_map(|k| cmd_args.value_str(k).map(|v| (k.to_string(), v)))
+            .collect()
+    }
+
+    //mi prompt
+    /// The prompt for interactive operation, showing the (short)
+    /// values of the command arguments that differ from 'initial'
+    fn prompt(&self, cmd_args: &C, initial: &HashMap<String, String>) -> String {
+        let mut prompt = String::new();
+        for k in cmd_args.keys() {
+            let Some(v) = cmd_args.value_str(k) else {
+                continue;
+            };
+            if initial.get(k) == Some(&v) || v.contains('\n') || v.len() > MAX_PROMPT_VALUE {
+                continue;
+            }
+            prompt += if prompt.is_empty() { "[" } else { " " };
+            prompt += &format!("{k}={v}");
+        }
+        if !prompt.is_empty() {
+            prompt += "] ";
+        }
+        prompt + self.command.get_name() + "> "
+    }
+
+    //mi execute_interactive
+    /// Execute commands interactively until 'exit', 'quit' or the end
+    /// of the input
+    ///
+    /// The prompt shows the values of the command arguments that have
+    /// changed since the start of the session.
+    ///
+    /// Each line (or block of lines, for 'for', 'if' and 'macro') is
+    /// executed as for a batch file; errors are reported, and do not
+    /// end the session. The result of each command is printed.
+    fn execute_interactive(&mut self, cmd_args: &mut C) -> Result<(), C::Error> {
+        if self.interactive {
+            return Err("Already executing interactively".to_string().into());
+        }
+        self.interactive = true;
+        self.show_result = false;
+        let name = self.command.get_name().to_string();
+        let depth = self.cmd_stack.len();
+        let mut editor = LineEditor::new(self.history_file.clone());
+        let initial = Self::arg_values(cmd_args);
+        let mut text = String::new();
+        loop {
+            let prompt = if text.is_empty() {
+                self.prompt(cmd_args, &initial)
+            } else {
+                "... ".into()
+            };
+            let command = &self.command;
+            let Some(line) = editor
+                .read_line(&prompt, |l| crate::complete(command, l))
+                .map_err(|e| format!("Failed to read input: {e}"))?
+            else {
+                break;
+            };
+            if text.is_empty() {
+                match line.trim() {
+                    "" => continue,
+                    "exit" | "quit" => break,
+                    _ => (),
+                }
+            }
+            editor.add_history(&line);
+            text += &line;
+            text.push('\n');
+            if BatchStmt::is_incomplete(&text) {
+                continue;
+            }
+            let text = std::mem::take(&mut text);
+            let last_result = self.result_history.last().cloned();
+            self.cmd_stack.push((name.clone(), None));
+            let result = BatchStmt::parse(&text)
+                .map_err(C::Error::from)
+                .and_then(|stmts| self.execute_stmts(cmd_args, &stmts));
+            self.cmd_stack.truncate(depth);
+            match result {
+                Err(e) => {
+                    eprintln!("{e}");
+                }
+                Ok(()) => {
+                    let new_result = self
+                        .result_history
+                        .last()
+                        .filter(|r| !last_result.as_ref().is_some_and(|l| Rc::ptr_eq(l, r)));
+                    if let Some(r) = new_result {
+                        if self.json_output {
+                            println!("{}", r.to_json(true));
+                        } else {
+                            println!("{r}");
+                        }
+                    }
+                }
+            }
+        }
+        self.interactive = false;
+        Ok(())
+    }
+
     //mi executed_result
-    fn executed_result(&mut self, result: String) {
+    fn executed_result(&mut self, result: ResultValue) {
         if !result.is_empty() {
             if !self.result_history.is_empty() {
                 self.result_history.pop();
@@ -589,19 +1027,40 @@ impl<C: CommandArgs> CommandSet<C> {
     {
         cmd_args.reset_args();
         let mut cmd = self.command.clone();
-        if let Some((name, opt_line)) = self.cmd_stack.last() {
-            if let Some(line) = opt_line {
-                cmd = cmd.bin_name(format!("{name} line {line}"));
-            } else {
-                cmd = cmd.bin_name(name);
-            }
-        }
-        match cmd.try_get_matches_from_mut(itr) {
+        let bin_name = match self.cmd_stack.last() {
+            Some((name, Some(line))) => format!("{name} line {line}"),
+            Some((name, None)) => name.clone(),
+            None => cmd.get_name().to_string(),
+        };
+        cmd = cmd.bin_name(&bin_name);
+        let args: Vec<OsString> = itr.into_iter().map(|a| a.into()).collect();
+        let mut command_line = vec![bin_name];
+        command_line.extend(args.iter().map(|a| a.to_string_lossy().into_owned()));
+        cmd_args.set_command_line(&command_line);
+        match cmd.try_get_matches_from_mut(args) {
             Err(e) => {
-                e.exit();
+                if !self.interactive {
+                    e.exit();
+                }
+                // Interactively, help and version are displayed, and
+                // errors are returned rather than exiting
+                if e.use_stderr() {
+                    return Err(e.render().to_string().into());
+                }
+                let _ = e.print();
+                Ok(())
             }
             Ok(matches) => {
                 self.handler_set.handle_args(cmd_args, &matches)?;
+                // '--json' applies only to this command, and not to
+                // later ones in a batch or session
+                let json_output =
+                    self.json_arg && matches!(matches.try_get_one::<bool>("json"), Ok(Some(true)));
+                self.json_output = json_output;
+                if let Some(result) = self.handle_doc_builtins(&matches)? {
+                    self.executed_result(result.into());
+                    return Ok(());
+                }
                 if self.use_builtins {
                     if let Some(result) = self.handle_builtins(cmd_args, &matches)? {
                         self.executed_result(result);
@@ -628,11 +1087,13 @@ impl<C: CommandArgs> CommandSet<C> {
                         }
                     }
                     for (filename, s) in batches {
-                        self.cmd_stack.push((filename, Some(0)));
-                        self.execute_str(cmd_args, &s.unwrap())?;
-                        self.cmd_stack.pop();
+                        self.execute_str(cmd_args, &filename, &s.unwrap())?;
                     }
                 }
+                if let Ok(Some(true)) = matches.try_get_one::<bool>("interactive") {
+                    self.execute_interactive(cmd_args)?;
+                }
+                self.json_output = json_output;
                 let result = self.handler_set.handle_cmd(cmd_args, &matches)?;
                 self.executed_result(result);
                 Ok(())
@@ -640,6 +1101,40 @@ impl<C: CommandArgs> CommandSet<C> {
         }
     }
 
+    //mp write_completions
+    /// Write a completion script for the command, including its
+    /// subcommands and the builtins, for a shell
+    pub fn write_completions<W: Write>(&self, shell: Shell, w: &mut W) -> std::io::Result<()> {
+        crate::write_completions(&self.command, shell, w)
+    }
+
+    //mp man_pages
+    /// Generate the man pages for the command and its subcommands, as
+    /// (file name, contents) pairs
+    pub fn man_pages(&self) -> Vec<(String, String)> {
+        crate::man_pages(&self.command)
+    }
+
+    //mp execute_batch
+    /// Execute the text of a batch file, named 'name' (for errors),
+    /// returning the last result
+    ///
+    /// Variables and macros set by the batch file remain set for later
+    /// commands
+    pub fn execute_batch(
+        &mut self,
+        cmd_args: &mut C,
+        name: &str,
+        s: &str,
+    ) -> Result<String, C::Error> {
+        self.execute_str(cmd_args, name, s)?;
+        Ok(self
+            .result_history
+            .last()
+            .map(|r| r.text().to_string())
+            .unwrap_or_default())
+    }
+
     //mp execute_env
     pub fn execute_env(&mut self, cmd_args: &mut C) -> Result<String, C::Error> {
         let mut iter = std::env::args_os();
@@ -648,7 +1143,7 @@ impl<C: CommandArgs> CommandSet<C> {
             .push((cmd_name.to_str().unwrap().into(), None));
         self.variables.clear();
         for (k, v) in std::env::vars() {
-            self.variables.insert(k, Rc::new(v));
+            self.variables.insert(k, Rc::new(v.into()));
         }
         match self.execute(cmd_args, iter) {
             Err(e) => {
@@ -658,15 +1153,17 @@ impl<C: CommandArgs> CommandSet<C> {
             _x => {
                 let result = {
                     if self.result_history.is_empty() {
-                        "".into()
+                        ResultValue::default()
                     } else {
-                        Rc::into_inner(self.result_history.remove(0)).unwrap()
+                        Rc::unwrap_or_clone(self.result_history.remove(0))
                     }
                 };
-                if self.show_result {
+                if self.json_output {
+                    println!("{}", result.to_json(true));
+                } else if self.show_result {
                     println!("{result}");
                 }
-                Ok(result)
+                Ok(result.text().to_string())
             }
         }
     }
@@ -1,11 +1,18 @@
 //a Modules
 mod arg_count;
+mod batch;
 mod builder;
+mod complete;
+mod docs;
 mod handler;
+mod repl;
 mod traits;
+mod value;
 
 pub use arg_count::ArgCount;
 pub use builder::CommandBuilder;
+pub use complete::complete;
+pub use docs::{man_pages, write_completions, Shell};
 pub use traits::CommandArgs;
 
 pub(crate) use handler::{CommandHandlerSet, CommandSet};
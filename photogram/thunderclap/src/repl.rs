@@ -0,0 +1,455 @@
+//a Imports
+use std::io::{BufRead, Read, Write};
+use std::path::PathBuf;
+
+//a Constants
+//ci MAX_HISTORY
+/// The maximum number of lines kept in the history (and its file)
+const MAX_HISTORY: usize = 1000;
+
+//ci TERMINAL_WIDTH
+/// The width assumed for the terminal when listing completions
+const TERMINAL_WIDTH: usize = 80;
+
+//a RawMode
+//tp RawMode
+/// The terminal in raw mode, with the original mode restored on drop
+#[cfg(unix)]
+struct RawMode(libc::termios);
+
+//ip RawMode
+#[cfg(unix)]
+impl RawMode {
+    //cp enable
+    /// Put the terminal into raw mode, if stdin and stdout are both a
+    /// terminal
+    fn enable() -> Option<Self> {
+        // SAFETY: the termios structure is plain data, and is only
+        // used with the functions that fill it in and apply it
+        unsafe {
+            if libc::isatty(0) == 0 || libc::isatty(1) == 0 {
+                return None;
+            }
+            let mut termios = std::mem::zeroed::<libc::termios>();
+            if libc::tcgetattr(0, &mut termios) != 0 {
+                return None;
+            }
+            let original = termios;
+            termios.c_iflag &= !(libc::ICRNL | libc::IXON);
+            termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+            termios.c_cc[libc::VMIN] = 1;
+            termios.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(0, libc::TCSAFLUSH, &termios) != 0 {
+                return None;
+            }
+            Some(Self(original))
+        }
+    }
+}
+
+//ip Drop for RawMode
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // SAFETY: restores the mode read by 'enable'
+        unsafe {
+            libc::tcsetattr(0, libc::TCSAFLUSH, &self.0);
+        }
+    }
+}
+
+//tp RawMode
+/// Raw mode is not supported, so lines are read without editing
+#[cfg(not(unix))]
+struct RawMode;
+
+//ip RawMode
+#[cfg(not(unix))]
+impl RawMode {
+    //cp enable
+    fn enable() -> Option<Self> {
+        None
+    }
+}
+
+//a Key
+//ti Key
+/// A key press decoded from the terminal input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+    Interrupt,
+    EndOfFile,
+    KillToEnd,
+    KillToStart,
+    KillWord,
+    Redraw,
+    Other,
+}
+
+//fi read_byte
+fn read_byte<R: Read>(input: &mut R) -> std::io::Result<Option<u8>> {
+    let mut buf = [0_u8];
+    match input.read(&mut buf)? {
+        0 => Ok(None),
+        _ => Ok(Some(buf[0])),
+    }
+}
+
+//fi read_key
+/// Read a key from the terminal, decoding escape sequences and UTF-8;
+/// return None at the end of the input
+fn read_key<R: Read>(input: &mut R) -> std::io::Result<Option<Key>> {
+    let Some(b) = read_byte(input)? else {
+        return Ok(None);
+    };
+    let key = match b {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        0x01 => Key::Home,
+        0x02 => Key::Left,
+        0x03 => Key::Interrupt,
+        0x04 => Key::EndOfFile,
+        0x05 => Key::End,
+        0x06 => Key::Right,
+        0x0b => Key::KillToEnd,
+        0x0c => Key::Redraw,
+        0x0e => Key::Down,
+        0x10 => Key::Up,
+        0x15 => Key::KillToStart,
+        0x17 => Key::KillWord,
+        0x1b => match read_byte(input)? {
+            Some(b'[') | Some(b'O') => match read_byte(input)? {
+                Some(b'A') => Key::Up,
+                Some(b'B') => Key::Down,
+                Some(b'C') => Key::Right,
+                Some(b'D') => Key::Left,
+                Some(b'H') => Key::Home,
+                Some(b'F') => Key::End,
+                Some(d) if d.is_ascii_digit() => {
+                    let mut code = vec![d];
+                    while let Some(b) = read_byte(input)? {
+                        if b == b'~' || !b.is_ascii_digit() {
+                            break;
+                        }
+                        code.push(b);
+                    }
+                    match code.as_slice() {
+                        b"1" | b"7" => Key::Home,
+                        b"3" => Key::Delete,
+                        b"4" | b"8" => Key::End,
+                        _ => Key::Other,
+                    }
+                }
+                _ => Key::Other,
+            },
+            _ => Key::Other,
+        },
+        b if b < 0x20 => Key::Other,
+        b if b < 0x80 => Key::Char(b as char),
+        b => {
+            let n = if b >= 0xf0 {
+                3
+            } else if b >= 0xe0 {
+                2
+            } else {
+                1
+            };
+            let mut bytes = vec![b];
+            for _ in 0..n {
+                match read_byte(input)? {
+                    Some(b) => bytes.push(b),
+                    None => return Ok(None),
+                }
+            }
+            match std::str::from_utf8(&bytes) {
+                Ok(s) => Key::Char(s.chars().next().unwrap()),
+                Err(_) => Key::Other,
+            }
+        }
+    };
+    Ok(Some(key))
+}
+
+//fi common_prefix
+/// The longest common prefix of a set of strings
+fn common_prefix(strings: &[String]) -> String {
+    let mut prefix: Vec<char> = strings[0].chars().collect();
+    for s in &strings[1..] {
+        let n = prefix
+            .iter()
+            .zip(s.chars())
+            .take_while(|(a, b)| **a == *b)
+            .count();
+        prefix.truncate(n);
+    }
+    prefix.into_iter().collect()
+}
+
+//a LineEditor
+//tp LineEditor
+/// A line editor for interactive operation, with a history that may
+/// be kept in a file between sessions
+///
+/// If the input is not a terminal then lines are read without
+/// editing.
+///
+/// The keys supported are the arrows, home, end, delete and backspace;
+/// Tab for completion; and the Emacs-style control keys for the same
+/// operations, along with ^K, ^U and ^W to delete to the end of the
+/// line, to the start of the line and the previous word.
+pub(crate) struct LineEditor {
+    history: Vec<String>,
+    history_file: Option<PathBuf>,
+}
+
+//ip LineEditor
+impl LineEditor {
+    //cp new
+    /// Create a new line editor, loading the history file (if any)
+    pub(crate) fn new(history_file: Option<PathBuf>) -> Self {
+        let mut history = vec![];
+        if let Some((path, Ok(s))) = history_file
+            .as_ref()
+            .map(|p| (p, std::fs::read_to_string(p)))
+        {
+            history = s.lines().map(|l| l.to_string()).collect();
+            if history.len() > MAX_HISTORY {
+                history.drain(0..history.len() - MAX_HISTORY);
+                let mut s = history.join("\n");
+                s.push('\n');
+                let _ = std::fs::write(path, s);
+            }
+        }
+        Self {
+            history,
+            history_file,
+        }
+    }
+
+    //mp add_history
+    /// Add a line to the history, appending it to the history file
+    ///
+    /// Empty lines and repeats of the previous line are not added
+    pub(crate) fn add_history(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() || self.history.last().is_some_and(|l| l == line) {
+            return;
+        }
+        self.history.push(line.to_string());
+        if self.history.len() > MAX_HISTORY {
+            self.history.remove(0);
+        }
+        let Some(path) = &self.history_file else {
+            return;
+        };
+        if let Ok(mut f) = std::fs::File::options()
+            .append(true)
+            .create(true)
+            .open(path)
+        {
+            let _ = writeln!(f, "{line}");
+        }
+    }
+
+    //mp read_line
+    /// Read a line, using 'complete' to find the completions for Tab
+    ///
+    /// 'complete' is given the line up to the cursor, and returns the
+    /// byte offset of the start of the word being completed and the
+    /// candidates for it
+    ///
+    /// Returns None at the end of the input
+    pub(crate) fn read_line<F>(
+        &mut self,
+        prompt: &str,
+        complete: F,
+    ) -> std::io::Result<Option<String>>
+    where
+        F: Fn(&str) -> (usize, Vec<String>),
+    {
+        let Some(_raw_mode) = RawMode::enable() else {
+            return Self::read_line_unedited(prompt);
+        };
+        let mut input = std::io::stdin().lock();
+        let mut output = std::io::stdout().lock();
+        let mut line: Vec<char> = vec![];
+        let mut cursor = 0;
+        let mut history_index = self.history.len();
+        let mut edited = String::new();
+        Self::redraw(&mut output, prompt, &line, cursor)?;
+        while let Some(key) = read_key(&mut input)? {
+            match key {
+                Key::Enter => {
+                    write!(output, "\r\n")?;
+                    output.flush()?;
+                    return Ok(Some(line.into_iter().collect()));
+                }
+                Key::Interrupt => {
+                    write!(output, "^C\r\n")?;
+                    output.flush()?;
+                    return Ok(Some(String::new()));
+                }
+                Key::EndOfFile if line.is_empty() => {
+                    write!(output, "\r\n")?;
+                    output.flush()?;
+                    return Ok(None);
+                }
+                Key::Char(c) => {
+                    line.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Backspace if cursor > 0 => {
+                    cursor -= 1;
+                    line.remove(cursor);
+                }
+                Key::Delete | Key::EndOfFile if cursor < line.len() => {
+                    line.remove(cursor);
+                }
+                Key::Left if cursor > 0 => {
+                    cursor -= 1;
+                }
+                Key::Right if cursor < line.len() => {
+                    cursor += 1;
+                }
+                Key::Home => {
+                    cursor = 0;
+                }
+                Key::End => {
+                    cursor = line.len();
+                }
+                Key::KillToEnd => {
+                    line.truncate(cursor);
+                }
+                Key::KillToStart => {
+                    line.drain(0..cursor);
+                    cursor = 0;
+                }
+                Key::KillWord => {
+                    let mut start = cursor;
+                    while start > 0 && line[start - 1].is_whitespace() {
+                        start -= 1;
+                    }
+                    while start > 0 && !line[start - 1].is_whitespace() {
+                        start -= 1;
+                    }
+                    line.drain(start..cursor);
+                    cursor = start;
+                }
+                Key::Up if history_index > 0 => {
+                    if history_index == self.history.len() {
+                        edited = line.iter().collect();
+                    }
+                    history_index -= 1;
+                    line = self.history[history_index].chars().collect();
+                    cursor = line.len();
+                }
+                Key::Down if history_index < self.history.len() => {
+                    history_index += 1;
+                    if history_index == self.history.len() {
+                        line = edited.chars().collect();
+                    } else {
+                        line = self.history[history_index].chars().collect();
+                    }
+                    cursor = line.len();
+                }
+                Key::Redraw => {
+                    write!(output, "\x1b[2J\x1b[H")?;
+                }
+                Key::Tab => {
+                    let before: String = line[0..cursor].iter().collect();
+                    let (start, candidates) = complete(&before);
+                    let start = before[..start].chars().count();
+                    let word: String = line[start..cursor].iter().collect();
+                    let replacement = match candidates.len() {
+                        0 => None,
+                        1 if candidates[0].ends_with('/') => Some(candidates[0].clone()),
+                        1 => Some(format!("{} ", candidates[0])),
+                        _ => {
+                            let prefix = common_prefix(&candidates);
+                            if prefix.len() > word.len() {
+                                Some(prefix)
+                            } else {
+                                Self::list(&mut output, &candidates)?;
+                                None
+                            }
+                        }
+                    };
+                    if let Some(replacement) = replacement {
+                        let replacement: Vec<char> = replacement.chars().collect();
+                        cursor = start + replacement.len();
+                        line.splice(start..start + word.chars().count(), replacement);
+                    } else if candidates.is_empty() {
+                        write!(output, "\x07")?;
+                    }
+                }
+                _ => {
+                    write!(output, "\x07")?;
+                }
+            }
+            Self::redraw(&mut output, prompt, &line, cursor)?;
+        }
+        Ok(None)
+    }
+
+    //fi read_line_unedited
+    /// Read a line without editing, for when the input is not a
+    /// terminal
+    fn read_line_unedited(prompt: &str) -> std::io::Result<Option<String>> {
+        let mut output = std::io::stdout();
+        write!(output, "{prompt}")?;
+        output.flush()?;
+        let mut line = String::new();
+        if std::io::stdin().lock().read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\r', '\n']).to_string()))
+    }
+
+    //fi redraw
+    /// Redraw the prompt and line, and place the cursor
+    fn redraw<W: Write>(
+        output: &mut W,
+        prompt: &str,
+        line: &[char],
+        cursor: usize,
+    ) -> std::io::Result<()> {
+        let s: String = line.iter().collect();
+        write!(output, "\r{prompt}{s}\x1b[K")?;
+        if cursor < line.len() {
+            write!(output, "\x1b[{}D", line.len() - cursor)?;
+        }
+        output.flush()
+    }
+
+    //fi list
+    /// List completion candidates in columns below the line
+    fn list<W: Write>(output: &mut W, candidates: &[String]) -> std::io::Result<()> {
+        let width = candidates
+            .iter()
+            .map(|c| c.chars().count())
+            .max()
+            .unwrap_or(0)
+            + 2;
+        let columns = (TERMINAL_WIDTH / width).max(1);
+        write!(output, "\r\n")?;
+        for (i, c) in candidates.iter().enumerate() {
+            write!(output, "{c:width$}")?;
+            if (i + 1) % columns == 0 || i + 1 == candidates.len() {
+                write!(output, "\r\n")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -23,6 +23,15 @@ pub trait CommandArgs: 'static {
     /// Function invoked before every batch or interactive command to reset temporary options
     fn reset_args(&mut self) {}
 
+    /// Function invoked before every command (at the top level, in
+    /// batch mode and interactively) with its command line, after
+    /// [CommandArgs::reset_args]
+    ///
+    /// The first element is the name it is executed from (such as
+    /// the program name, or a batch file name and line number); this
+    /// can be used to record how results were produced
+    fn set_command_line(&mut self, _command_line: &[String]) {}
+
     /// Get the keys (elements) of the arguments - used in batch and interactive only
     fn keys(&self) -> Box<dyn Iterator<Item = &str>> {
         const KEYS: [String; 0] = [];
@@ -36,6 +45,19 @@ pub trait CommandArgs: 'static {
         None
     }
 
+    /// Convert a value returned by a command into JSON - used in batch
+    /// and interactive only, and for '--json'
+    ///
+    /// This is invoked immediately after the command, so it may
+    /// include information from the arguments (such as files
+    /// written); the fields of the JSON are available in batch mode
+    /// as `${0.field}` etc
+    ///
+    /// Return None if the value has no structure beyond its text
+    fn value_json(&self, _value: &Self::Value) -> Option<serde_json::Value> {
+        None
+    }
+
     /// Set the value to a value from a string
     ///
     /// Return Ok(false) if the key is not provided by the args
@@ -0,0 +1,89 @@
+//a Imports
+use serde_json::Value;
+
+//a ResultValue
+//tp ResultValue
+/// The result of a command (or the value of a variable) in batch and
+/// interactive operation
+///
+/// This has a text form, which is used for `${name}`, and an optional
+/// structured form whose fields are used for `${name.field.0}` (and
+/// which is printed with `--json`)
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ResultValue {
+    text: String,
+    json: Option<Value>,
+}
+
+//ip ResultValue
+impl ResultValue {
+    //cp new
+    /// Create a new result value
+    pub(crate) fn new(text: String, json: Option<Value>) -> Self {
+        Self { text, json }
+    }
+
+    //ap text
+    /// The text form of the value
+    pub(crate) fn text(&self) -> &str {
+        &self.text
+    }
+
+    //ap is_empty
+    /// Return true if the value is empty, i.e. a command did not
+    /// return anything
+    pub(crate) fn is_empty(&self) -> bool {
+        self.text.is_empty() && self.json.as_ref().is_none_or(|j| j.is_null())
+    }
+
+    //mp to_json
+    /// The value as JSON; if there is no structured form then this is
+    /// the text as a JSON string
+    pub(crate) fn to_json(&self, pretty: bool) -> String {
+        let json = match &self.json {
+            Some(json) => json.clone(),
+            None => Value::String(self.text.clone()),
+        };
+        if pretty {
+            serde_json::to_string_pretty(&json).unwrap()
+        } else {
+            json.to_string()
+        }
+    }
+
+    //mp field
+    /// Find a field of the structured form of the value, given a
+    /// path of object keys and array indices separated by '.'
+    ///
+    /// Strings are returned without quotes, and null as an empty
+    /// string; objects and arrays are returned as JSON
+    pub(crate) fn field(&self, path: &str) -> Option<String> {
+        let mut json = self.json.as_ref()?;
+        for key in path.split('.') {
+            json = match json {
+                Value::Object(map) => map.get(key)?,
+                Value::Array(array) => array.get(key.parse::<usize>().ok()?)?,
+                _ => return None,
+            };
+        }
+        match json {
+            Value::Null => Some("".into()),
+            Value::String(s) => Some(s.clone()),
+            json => Some(json.to_string()),
+        }
+    }
+}
+
+//ip From<String> for ResultValue
+impl From<String> for ResultValue {
+    fn from(text: String) -> Self {
+        Self::new(text, None)
+    }
+}
+
+//ip Display for ResultValue
+impl std::fmt::Display for ResultValue {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.write_str(&self.text)
+    }
+}
@@ -0,0 +1,186 @@
+//a Imports
+use clap::Command;
+
+use thunderclap::{ArgCount, CommandArgs, CommandBuilder};
+
+//a Log
+//tp Log
+/// Command arguments that record the values given to the 'log' command
+#[derive(Default)]
+struct Log {
+    value: String,
+    log: Vec<String>,
+}
+
+//ip CommandArgs for Log
+impl CommandArgs for Log {
+    type Error = String;
+    type Value = String;
+    fn cmd_ok() -> Result<String, String> {
+        Ok("".into())
+    }
+    fn value_json(&self, value: &String) -> Option<serde_json::Value> {
+        Some(
+            serde_json::json!({"value": value, "len": value.len(), "words": value.split('_').collect::<Vec<_>>()}),
+        )
+    }
+}
+
+//fi log_fn
+fn log_fn(log: &mut Log) -> Result<String, String> {
+    let value = log.value.clone();
+    log.log.push(value.clone());
+    Ok(value)
+}
+
+//fi build
+/// Build the 'test' command, with a 'log' subcommand
+fn build() -> CommandBuilder<Log> {
+    let mut log_cmd = CommandBuilder::new(
+        Command::new("log").about("Log a value"),
+        Some(Box::new(log_fn)),
+    );
+    log_cmd.add_arg_string(
+        "value",
+        None,
+        "Value to log",
+        ArgCount::PositionalRequired(1),
+        None,
+        |log: &mut Log, v| {
+            log.value = v.into();
+            Ok(())
+        },
+    );
+    let mut build = CommandBuilder::new(Command::new("test"), None);
+    build.add_subcommand(log_cmd);
+    build
+}
+
+//fi run
+/// Run a batch file, returning the values logged
+fn run(batch: &str) -> Result<Vec<String>, String> {
+    let mut command = build().main(true, false);
+    let mut log = Log::default();
+    command.execute_batch(&mut log, "test.bat", batch)?;
+    Ok(log.log)
+}
+
+//a Tests
+//ti loops
+#[test]
+fn loops() {
+    let log = run("for x in a b 'c d'\nlog ${x}\nend").unwrap();
+    assert_eq!(log, ["a", "b", "c d"]);
+    let log =
+        run("for i from 1 to 3\nfor j from 0 to 1 step 0.5\nlog ${i}:${j}\nend\nend").unwrap();
+    assert_eq!(log.len(), 9);
+    assert_eq!(log[0], "1:0");
+    assert_eq!(log[4], "2:0.5");
+    let log = run("for i from 10 to 0 step -5\nlog ${i}\nend").unwrap();
+    assert_eq!(log, ["10", "5", "0"]);
+    let log = run("for i from 1 to 0\nlog ${i}\nend").unwrap();
+    assert!(log.is_empty());
+}
+
+//ti conditionals
+#[test]
+fn conditionals() {
+    let batch = "for x in 5 10 20 abc\nif ${x} == abc\nlog abc\nelse\nif ${x} > 9\nlog big\nelse\nlog small\nend\nend\nend";
+    let log = run(batch).unwrap();
+    assert_eq!(log, ["small", "big", "big", "abc"]);
+    // Results of commands are available as ${0}
+    let log = run("log 3\nif ${0} <= 3\nlog yes\nend").unwrap();
+    assert_eq!(log, ["3", "yes"]);
+    let log = run("set flag 0\nif ${flag}\nlog yes\nend").unwrap();
+    assert!(log.is_empty());
+}
+
+//ti macros
+#[test]
+fn macros() {
+    let batch = "set a outer\nmacro twice a b\nlog ${a}\nlog ${b}\nend\ncall twice 1 2\nlog ${a}";
+    let log = run(batch).unwrap();
+    assert_eq!(log, ["1", "2", "outer"]);
+    assert!(run("call missing").is_err());
+    assert!(run("macro m a\nend\ncall m").is_err());
+    assert!(run("macro m\ncall m\nend\ncall m").is_err());
+}
+
+//ti includes
+#[test]
+fn includes() {
+    let dir = std::env::temp_dir().join(format!("thunderclap_batch_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("inc.bat");
+    std::fs::write(&file, "macro show v\nlog included:${v}\nend\n").unwrap();
+    let batch = format!("include '{}'\ncall show 1", file.display());
+    let log = run(&batch).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(log, ["included:1"]);
+    assert!(run("include /nonexistent/file.bat").is_err());
+}
+
+//ti structure
+#[test]
+fn structure() {
+    assert!(run("for x in a\nlog ${x}").is_err());
+    assert!(run("end").is_err());
+    assert!(run("else").is_err());
+    assert!(run("if 1\nelse\nelse\nend").is_err());
+    assert!(run("if 1 ~ 2\nend").is_err());
+    assert!(run("for x from 1 to 2 step 0\nend").is_err());
+    assert!(run("for x from 0 to 1e300\nend").is_err());
+    assert!(run("for x from 0 to 1 step 1e-300\nend").is_err());
+}
+
+//ti fields
+#[test]
+fn fields() {
+    // Fields of the structured form of results are ${0.<path>}
+    let log = run("log abc_de\nlog ${0.len}:${0.words.1}").unwrap();
+    assert_eq!(log, ["abc_de", "6:de"]);
+    // and stored results keep their structured form
+    let log = run("log x_y\nstore r\nlog ${r.words.0}\nlog ${r}").unwrap();
+    assert_eq!(log, ["x_y", "x", "x_y"]);
+    assert!(run("store r 3").is_err());
+}
+
+//ti errors
+#[test]
+fn errors() {
+    // Errors give the location in the batch file, and do not leave it
+    // on the stack for later batch files
+    let mut command = build().main(true, false);
+    let mut log = Log::default();
+    for _ in 0..40 {
+        let e = command
+            .execute_batch(&mut log, "first.bat", "log a\ncall missing")
+            .unwrap_err();
+        assert!(e.starts_with("first.bat line 2:"), "{e}");
+    }
+    let e = command
+        .execute_batch(&mut log, "second.bat", "call missing")
+        .unwrap_err();
+    assert!(e.starts_with("second.bat line 1:"), "{e}");
+}
+
+//ti clashes
+#[test]
+fn clashes() {
+    // A subcommand or argument of the application with the name of a
+    // builtin is used instead of the builtin
+    let mut build = build();
+    build.add_subcommand(CommandBuilder::new(
+        Command::new("man").about("The application's man"),
+        Some(Box::new(|log: &mut Log| {
+            log.log.push("app man".into());
+            Ok("".into())
+        })),
+    ));
+    let mut command = build.main(true, false);
+    let mut log = Log::default();
+    command
+        .execute_batch(&mut log, "test.bat", "man\ncompletions bash")
+        .unwrap();
+    assert_eq!(log.log, ["app man"]);
+}
@@ -0,0 +1,67 @@
+//a Imports
+use clap::{Arg, ArgAction, Command, ValueHint};
+
+use thunderclap::complete;
+
+//fi command
+fn command() -> Command {
+    Command::new("tool")
+        .no_binary_name(true)
+        .arg(
+            Arg::new("verbose")
+                .long("verbose")
+                .short('v')
+                .action(ArgAction::SetTrue),
+        )
+        .arg(Arg::new("dir").long("dir").value_hint(ValueHint::DirPath))
+        .subcommand(
+            Command::new("fetch")
+                .arg(Arg::new("mode").long("mode").value_parser(["fast", "slow"]))
+                .arg(Arg::new("what").value_parser(["apple", "banana"])),
+        )
+        .subcommand(Command::new("fix"))
+}
+
+//fi candidates
+fn candidates(line: &str) -> Vec<String> {
+    complete(&command(), line).1
+}
+
+//ti subcommands
+#[test]
+fn subcommands() {
+    assert_eq!(candidates("f"), ["fetch", "fix", "for"]);
+    assert_eq!(candidates("--verbose fe"), ["fetch"]);
+    assert_eq!(complete(&command(), "--verbose fe").0, 10);
+    assert_eq!(candidates("fetch b"), ["banana"]);
+    assert!(candidates("").contains(&"help".to_string()));
+    assert!(!candidates("fetch ").contains(&"help".to_string()));
+}
+
+//ti options
+#[test]
+fn options() {
+    assert_eq!(candidates("--v"), ["--verbose"]);
+    assert_eq!(
+        candidates("-"),
+        ["--dir", "--help", "--verbose", "-h", "-v"]
+    );
+    assert_eq!(candidates("fetch --m"), ["--mode"]);
+    assert_eq!(candidates("fetch --mode "), ["fast", "slow"]);
+    assert_eq!(candidates("fetch --mode s"), ["slow"]);
+    assert_eq!(candidates("fetch --mode slow a"), ["apple"]);
+}
+
+//ti paths
+#[test]
+fn paths() {
+    let dir = std::env::temp_dir().join(format!("thunderclap_complete_{}", std::process::id()));
+    std::fs::create_dir_all(dir.join("subdir")).unwrap();
+    std::fs::write(dir.join("file.txt"), "").unwrap();
+    let prefix = format!("{}/", dir.display());
+    let files = candidates(&format!("--dir {prefix}"));
+    let all = candidates(&format!("fetch --mode fast apple {prefix}"));
+    std::fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(files, [format!("{prefix}subdir/")]);
+    assert!(all.is_empty());
+}
@@ -0,0 +1,66 @@
+//a Imports
+use clap::{Arg, ArgAction, Command};
+
+use thunderclap::{man_pages, write_completions, Shell};
+
+//fi command
+fn command() -> Command {
+    Command::new("tool")
+        .version("1.0")
+        .about("A test tool")
+        .arg(
+            Arg::new("verbose")
+                .long("verbose")
+                .short('v')
+                .action(ArgAction::SetTrue)
+                .help("Enable verbose output"),
+        )
+        .subcommand(
+            Command::new("fetch")
+                .about("Fetch a thing")
+                .long_about("Fetch a thing\n\nFetching is done with great care.")
+                .arg(
+                    Arg::new("mode")
+                        .long("mode")
+                        .value_parser(["fast", "slow"])
+                        .help("Speed of fetching"),
+                ),
+        )
+        .subcommand(Command::new("hidden").hide(true))
+}
+
+//fi completions
+fn completions(shell: Shell) -> String {
+    let mut buffer = Vec::new();
+    write_completions(&command(), shell, &mut buffer).unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+//ti shells
+#[test]
+fn shells() {
+    for name in Shell::NAMES {
+        let shell: Shell = name.parse().unwrap();
+        assert_eq!(shell.to_string(), *name);
+        let script = completions(shell);
+        assert!(script.contains("fetch"), "{name} completes subcommands");
+        assert!(script.contains("verbose"), "{name} completes options");
+        assert!(script.contains("slow"), "{name} completes possible values");
+        assert!(!script.contains("hidden"), "{name} omits hidden commands");
+    }
+    assert!("csh".parse::<Shell>().is_err());
+}
+
+//ti man
+#[test]
+fn man() {
+    let pages = man_pages(&command());
+    let names: Vec<_> = pages.iter().map(|(n, _)| n.as_str()).collect();
+    assert!(names.contains(&"tool.1"));
+    assert!(names.contains(&"tool-fetch.1"));
+    assert!(!names.iter().any(|n| n.contains("hidden")));
+    let fetch = &pages.iter().find(|(n, _)| n == "tool-fetch.1").unwrap().1;
+    assert!(fetch.starts_with(".TH"));
+    assert!(fetch.contains("Fetching is done with great care."));
+    assert!(fetch.contains("\\-\\-mode"));
+}